@@ -0,0 +1,62 @@
+//! Embed build information reported by `GET /version`.
+
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| ".".to_string());
+
+    // Builds without a .git directory (e.g. Docker) can pass the SHA in.
+    let git_sha = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|sha| sha.trim().to_string())
+        .filter(|sha| !sha.is_empty())
+        .or_else(|| std::env::var("TOON_GIT_SHA").ok())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    // SOURCE_DATE_EPOCH keeps reproducible builds reproducible.
+    let timestamp = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0)
+        });
+
+    let toon_format = locked_version(&Path::new(&manifest_dir).join("Cargo.lock"), "toon-format")
+        .unwrap_or_else(|| "unknown".to_string());
+
+    println!("cargo:rustc-env=TOON_BUILD_GIT_SHA={}", git_sha);
+    println!("cargo:rustc-env=TOON_BUILD_TIMESTAMP={}", timestamp);
+    println!("cargo:rustc-env=TOON_BUILD_TOON_FORMAT={}", toon_format);
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
+    println!("cargo:rerun-if-changed=Cargo.lock");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    println!("cargo:rerun-if-env-changed=TOON_GIT_SHA");
+}
+
+/// Version of `package` recorded in the lockfile.
+fn locked_version(lockfile: &Path, package: &str) -> Option<String> {
+    let lock = std::fs::read_to_string(lockfile).ok()?;
+    let name_line = format!("name = \"{}\"", package);
+    let mut lines = lock.lines();
+    while let Some(line) = lines.next() {
+        if line.trim() == name_line {
+            return lines
+                .next()?
+                .trim()
+                .strip_prefix("version = \"")?
+                .strip_suffix('"')
+                .map(str::to_string);
+        }
+    }
+    None
+}
@@ -0,0 +1,161 @@
+//! Validation of TOON files for pre-commit hooks (`toon-mcp check`).
+//!
+//! Every `.toon` file under the given paths is decoded in strict mode, and
+//! errors are reported as `path:line:column: message`. With `format`, valid
+//! files not laid out the way the encoder writes them are rewritten, so a
+//! repository keeps its stored data in one layout.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::core::{self, DecodeRequest, EncodeOptionsInput, ValidationError};
+
+/// Result of checking one file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Valid,
+    Invalid,
+    /// Valid, and rewritten in the encoder's layout
+    Formatted,
+}
+
+/// Outcome of checking one file.
+#[derive(Debug, Serialize)]
+pub struct FileCheck {
+    pub path: PathBuf,
+    pub outcome: Outcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ValidationError>,
+}
+
+impl FileCheck {
+    /// `path:line:column: message`, for editors and CI logs.
+    pub fn describe(&self) -> String {
+        let path = self.path.display();
+        match (&self.error, self.outcome) {
+            (Some(error), _) => match (error.line, error.column) {
+                (Some(line), Some(column)) => {
+                    format!("{}:{}:{}: {}", path, line, column, error.message)
+                }
+                (Some(line), None) => format!("{}:{}: {}", path, line, error.message),
+                _ => format!("{}: {}", path, error.message),
+            },
+            (None, Outcome::Formatted) => format!("{}: reformatted", path),
+            (None, _) => format!("{}: ok", path),
+        }
+    }
+}
+
+/// Files to check: files as given, and the `.toon` files below directories,
+/// skipping hidden directories such as `.git`. Sorted.
+pub fn files(paths: &[PathBuf]) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            collect_toon_files(path, &mut files)?;
+        } else {
+            anyhow::ensure!(path.is_file(), "{}: no such file", path.display());
+            files.push(path.clone());
+        }
+    }
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+fn collect_toon_files(dir: &Path, out: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let hidden = path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+        if path.is_dir() {
+            if !hidden {
+                collect_toon_files(&path, out)?;
+            }
+        } else if path.extension().is_some_and(|ext| ext == "toon") {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Validate the file at `path` and, with `format`, rewrite it in the
+/// encoder's layout when it differs.
+pub fn check(path: &Path, format: bool) -> anyhow::Result<FileCheck> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) => {
+            return Ok(FileCheck {
+                path: path.to_path_buf(),
+                outcome: Outcome::Invalid,
+                error: Some(ValidationError {
+                    message: e.to_string(),
+                    line: None,
+                    column: None,
+                    suggestion: None,
+                    row: None,
+                    snippet: None,
+                }),
+            })
+        }
+    };
+    let validation = core::validate_toon(&text, Some(true));
+    let mut outcome = if validation.valid {
+        Outcome::Valid
+    } else {
+        Outcome::Invalid
+    };
+    if format && outcome == Outcome::Valid {
+        let value = core::decode_toon(&text, &DecodeRequest::default())?;
+        let formatted = core::encode_json(&value, &EncodeOptionsInput::default())? + "\n";
+        if formatted != text {
+            fs::write(path, formatted)?;
+            outcome = Outcome::Formatted;
+        }
+    }
+    Ok(FileCheck {
+        path: path.to_path_buf(),
+        outcome,
+        error: validation.error,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_reports_and_formats() {
+        let dir = std::env::temp_dir().join(format!("toon-check-{}", std::process::id()));
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::create_dir_all(dir.join(".hidden")).unwrap();
+        fs::write(dir.join("ok.toon"), "a: 1\n").unwrap();
+        fs::write(dir.join("nested/bad.toon"), "a[2]: 1\n").unwrap();
+        fs::write(dir.join("nested/quoted.toon"), "a: \"x\"\n").unwrap();
+        fs::write(dir.join(".hidden/skipped.toon"), "a[2]: 1\n").unwrap();
+        fs::write(dir.join("notes.txt"), "not toon").unwrap();
+
+        let files = files(std::slice::from_ref(&dir)).unwrap();
+        assert_eq!(files.len(), 3);
+        let outcomes: Vec<Outcome> = files
+            .iter()
+            .map(|file| check(file, true).unwrap().outcome)
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![Outcome::Invalid, Outcome::Formatted, Outcome::Valid]
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("nested/quoted.toon")).unwrap(),
+            "a: x\n"
+        );
+        let bad = check(&dir.join("nested/bad.toon"), false).unwrap();
+        assert!(bad.error.is_some());
+        assert!(bad.describe().starts_with(&bad.path.display().to_string()));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
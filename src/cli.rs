@@ -1,6 +1,12 @@
 //! Command-line interface for toon-mcp server.
 
-use clap::{Parser, ValueEnum};
+use std::fmt;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use clap::{Parser, Subcommand, ValueEnum};
 
 /// Server mode selection.
 #[derive(Debug, Clone, Copy, ValueEnum, Default)]
@@ -10,6 +16,183 @@ pub enum ServerMode {
     Mcp,
     /// HTTP REST API mode
     Http,
+    /// HTTP server answering with canned responses from --fixtures
+    Mock,
+}
+
+/// How subcommands print their results.
+#[derive(Debug, Clone, Copy, ValueEnum, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// One JSON object with the result, stats, and warnings
+    Json,
+}
+
+/// Subcommands, run instead of a server.
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Rewrite the golden-file snapshots from this build's encoder
+    RegenGolden {
+        /// Directory holding the golden payloads and snapshots
+        #[arg(default_value = crate::golden::DEFAULT_DIR)]
+        dir: PathBuf,
+    },
+    /// Run the official TOON spec test vectors and report each case
+    Conformance {
+        /// The `tests/fixtures` directory of a toon-format/spec checkout
+        #[arg(env = "TOON_SPEC_DIR")]
+        dir: PathBuf,
+    },
+    /// Compare encodings with the reference TypeScript implementation
+    Differential {
+        /// JSON payloads, or directories of them
+        #[arg(default_value = crate::golden::DEFAULT_DIR)]
+        paths: Vec<PathBuf>,
+
+        /// Reference CLI to compare against; without a value, `npx @toon-format/cli`.
+        /// When omitted, only this server's round trip is checked
+        #[arg(
+            long,
+            env = "TOON_REFERENCE_CMD",
+            num_args = 0..=1,
+            default_missing_value = crate::differential::DEFAULT_REFERENCE
+        )]
+        reference: Option<String>,
+    },
+    /// Re-run the requests in a recording made with TOON_RECORD and report changed responses
+    Replay {
+        /// Recording file (JSON lines)
+        file: PathBuf,
+    },
+    /// Convert files matching glob patterns between JSON and TOON, including the
+    /// entries of .zip and .tar.gz archives
+    Convert {
+        /// Files or glob patterns such as 'data/**/*.json' (quote them so the shell does not
+        /// expand them); without any, or with `-`, stdin is converted to stdout
+        patterns: Vec<String>,
+
+        /// Output format; by default `.toon` files become JSON and other files TOON, and
+        /// stdin is converted to the format it is not
+        #[arg(long, value_enum)]
+        to: Option<crate::convert::Format>,
+
+        /// Write outputs under this directory, keeping their paths below each pattern's
+        /// first wildcard; by default each output is written next to its input
+        #[arg(long)]
+        out_dir: Option<PathBuf>,
+
+        /// Files converted at once (default: available CPUs)
+        #[arg(short, long)]
+        jobs: Option<usize>,
+
+        /// Skip files whose output is up to date: newer than the input (`mtime`, the default)
+        /// or converted from identical content (`hash`)
+        #[arg(long, value_enum, num_args = 0..=1, default_missing_value = "mtime")]
+        skip_unchanged: Option<crate::convert::SkipUnchanged>,
+    },
+    /// Show the structural differences between two data files, JSON or TOON
+    Diff {
+        /// Earlier document (`-` for stdin)
+        before: PathBuf,
+        /// Later document (`-` for stdin)
+        after: PathBuf,
+        /// Color the differences: auto (when stdout is a terminal), always, or never
+        #[arg(long, value_enum, default_value_t = clap::ColorChoice::Auto)]
+        color: clap::ColorChoice,
+    },
+    /// Print a data file as canonical JSON, for git's `diff.<driver>.textconv`
+    Textconv {
+        /// TOON or JSON file (`-` for stdin)
+        file: PathBuf,
+    },
+    /// Three-way merge driver for git: merge the changes from BASE to THEIRS into OURS
+    /// structurally and write the result to OURS, exiting with 1 on conflicts
+    Merge {
+        /// Common ancestor (%O)
+        base: PathBuf,
+        /// Our version, overwritten with the result (%A)
+        ours: PathBuf,
+        /// Their version (%B)
+        theirs: PathBuf,
+        /// Path of the file in the repository (%P); its extension picks the output format
+        path: Option<PathBuf>,
+    },
+    /// Validate .toon files, for pre-commit hooks; exits with 1 when any is invalid or
+    /// was reformatted
+    Check {
+        /// Files, or directories searched for .toon files
+        #[arg(default_value = ".")]
+        paths: Vec<PathBuf>,
+
+        /// Rewrite valid files that are not laid out the way the encoder writes them
+        #[arg(long, default_value_t = false)]
+        format: bool,
+    },
+    /// Print a shell completion script to stdout
+    Completions {
+        /// Shell to generate the script for
+        shell: clap_complete::Shell,
+    },
+    /// Print the man page (roff) to stdout
+    Man,
+}
+
+impl Command {
+    /// Name of the subcommand, as typed.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::RegenGolden { .. } => "regen-golden",
+            Command::Conformance { .. } => "conformance",
+            Command::Differential { .. } => "differential",
+            Command::Replay { .. } => "replay",
+            Command::Convert { .. } => "convert",
+            Command::Diff { .. } => "diff",
+            Command::Textconv { .. } => "textconv",
+            Command::Merge { .. } => "merge",
+            Command::Check { .. } => "check",
+            Command::Completions { .. } => "completions",
+            Command::Man => "man",
+        }
+    }
+}
+
+/// An address the HTTP server listens on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListenAddr {
+    /// TCP socket address
+    Tcp(SocketAddr),
+    /// Unix domain socket path
+    Unix(PathBuf),
+}
+
+impl FromStr for ListenAddr {
+    type Err = String;
+
+    /// Parse `unix:/path`, an absolute or `./` path, or `host:port`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(path) = s.strip_prefix("unix:") {
+            return Ok(ListenAddr::Unix(PathBuf::from(path)));
+        }
+        if s.starts_with('/') || s.starts_with("./") {
+            return Ok(ListenAddr::Unix(PathBuf::from(s)));
+        }
+        s.to_socket_addrs()
+            .map_err(|e| format!("invalid listen address '{}': {}", s, e))?
+            .next()
+            .map(ListenAddr::Tcp)
+            .ok_or_else(|| format!("listen address '{}' did not resolve", s))
+    }
+}
+
+impl fmt::Display for ListenAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListenAddr::Tcp(addr) => write!(f, "http://{}", addr),
+            ListenAddr::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
 }
 
 /// TOON MCP Server - Token-efficient JSON encoding for LLM prompts.
@@ -23,17 +206,72 @@ pub struct Args {
     #[arg(short, long, value_enum, default_value_t = ServerMode::Mcp, env = "TOON_MODE")]
     pub mode: ServerMode,
 
-    /// HTTP server host address
+    /// HTTP server host address (IPv4, IPv6 such as [::], or hostname)
     #[arg(long, default_value = "0.0.0.0", env = "TOON_HOST")]
     pub host: String,
 
-    /// HTTP server port
+    /// HTTP server port (0 picks a free port, printed on startup)
     #[arg(short, long, default_value_t = 8080, env = "TOON_PORT")]
     pub port: u16,
 
+    /// HTTP listen address, repeatable: host:port or a Unix socket path (overrides --host/--port)
+    #[arg(long, env = "TOON_LISTEN", value_delimiter = ',')]
+    pub listen: Vec<ListenAddr>,
+
     /// Enable verbose logging
     #[arg(short, long, default_value_t = false, env = "TOON_VERBOSE")]
     pub verbose: bool,
+
+    /// Exercise the encoder, decoder, and caches on built-in fixtures before serving
+    #[arg(long, default_value_t = false, env = "TOON_WARMUP")]
+    pub warmup: bool,
+
+    /// MCP mode: serve over this Windows named pipe instead of stdio (e.g. \\.\pipe\toon-mcp)
+    #[arg(long, env = "TOON_PIPE")]
+    pub pipe: Option<String>,
+
+    /// MCP mode: ping the client every N seconds and exit if it stops answering (0 disables)
+    #[arg(long, default_value_t = 0, env = "TOON_KEEPALIVE")]
+    pub keepalive: u64,
+
+    /// HTTP mode (development): randomly delay, fail, or truncate API responses
+    #[arg(long, default_value_t = false, env = "TOON_CHAOS")]
+    pub chaos: bool,
+
+    /// With --chaos: percentage of requests delayed
+    #[arg(long, default_value_t = 10, value_parser = percent(), env = "TOON_CHAOS_LATENCY")]
+    pub chaos_latency: u8,
+
+    /// With --chaos: delay added to a delayed request, in milliseconds
+    #[arg(long, default_value_t = 2000, env = "TOON_CHAOS_LATENCY_MS")]
+    pub chaos_latency_ms: u64,
+
+    /// With --chaos: percentage of requests answered with 500
+    #[arg(long, default_value_t = 5, value_parser = percent(), env = "TOON_CHAOS_ERRORS")]
+    pub chaos_errors: u8,
+
+    /// With --chaos: percentage of responses cut short
+    #[arg(long, default_value_t = 5, value_parser = percent(), env = "TOON_CHAOS_TRUNCATE")]
+    pub chaos_truncate: u8,
+
+    /// With --mode mock: directory of fixture files and recordings to serve
+    #[arg(long, default_value = "fixtures", env = "TOON_MOCK_FIXTURES")]
+    pub fixtures: PathBuf,
+
+    /// Subcommands: print results as text or as one JSON object
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+
+    /// Subcommands: print nothing but errors; check the exit code
+    #[arg(short, long, global = true, default_value_t = false)]
+    pub quiet: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+fn percent() -> clap::builder::RangedI64ValueParser<u8> {
+    clap::value_parser!(u8).range(0..=100)
 }
 
 impl Args {
@@ -42,8 +280,183 @@ impl Args {
         Args::parse()
     }
 
+    /// Keepalive interval for MCP mode, if enabled.
+    pub fn keepalive_interval(&self) -> Option<std::time::Duration> {
+        (self.keepalive > 0).then(|| std::time::Duration::from_secs(self.keepalive))
+    }
+
+    /// Fault injection settings, when `--chaos` is set.
+    #[cfg(feature = "http")]
+    pub fn chaos_config(&self) -> Option<crate::server::chaos::ChaosConfig> {
+        self.chaos.then(|| crate::server::chaos::ChaosConfig {
+            latency_percent: self.chaos_latency,
+            latency: std::time::Duration::from_millis(self.chaos_latency_ms),
+            error_percent: self.chaos_errors,
+            truncate_percent: self.chaos_truncate,
+        })
+    }
+
+    /// Addresses for HTTP mode: every `--listen`, or `--host`/`--port` when none is given.
+    pub fn listen_addrs(&self) -> anyhow::Result<Vec<ListenAddr>> {
+        if self.listen.is_empty() {
+            Ok(vec![ListenAddr::Tcp(self.socket_addr()?)])
+        } else {
+            Ok(self.listen.clone())
+        }
+    }
+
     /// Get the socket address for HTTP mode.
-    pub fn socket_addr(&self) -> String {
-        format!("{}:{}", self.host, self.port)
+    ///
+    /// Accepts IP literals (IPv6 with or without brackets) and resolvable
+    /// hostnames; anything else is an error.
+    pub fn socket_addr(&self) -> anyhow::Result<SocketAddr> {
+        let host = self.host.trim();
+        let literal = host
+            .strip_prefix('[')
+            .and_then(|h| h.strip_suffix(']'))
+            .unwrap_or(host);
+        if let Ok(ip) = literal.parse::<IpAddr>() {
+            return Ok(SocketAddr::new(ip, self.port));
+        }
+
+        (host, self.port)
+            .to_socket_addrs()
+            .map_err(|e| anyhow!("invalid host '{}': {}", self.host, e))?
+            .next()
+            .ok_or_else(|| anyhow!("host '{}' did not resolve to any address", self.host))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args_default() -> Args {
+        Args::try_parse_from(["toon-mcp"]).unwrap()
+    }
+
+    fn args(host: &str, port: &str) -> Args {
+        Args::try_parse_from(["toon-mcp", "--host", host, "--port", port]).unwrap()
+    }
+
+    #[test]
+    fn test_socket_addr_ipv4_and_ipv6() {
+        assert_eq!(
+            args("127.0.0.1", "8080").socket_addr().unwrap(),
+            "127.0.0.1:8080".parse().unwrap()
+        );
+        assert_eq!(
+            args("[::]", "0").socket_addr().unwrap(),
+            "[::]:0".parse().unwrap()
+        );
+        assert_eq!(
+            args("::1", "9000").socket_addr().unwrap(),
+            "[::1]:9000".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_listen_addrs_repeatable() {
+        let args = Args::try_parse_from([
+            "toon-mcp",
+            "--listen",
+            "127.0.0.1:8080",
+            "--listen",
+            "unix:/tmp/toon.sock",
+        ])
+        .unwrap();
+        assert_eq!(
+            args.listen_addrs().unwrap(),
+            vec![
+                ListenAddr::Tcp("127.0.0.1:8080".parse().unwrap()),
+                ListenAddr::Unix(PathBuf::from("/tmp/toon.sock")),
+            ]
+        );
+
+        let default = args_default();
+        assert_eq!(
+            default.listen_addrs().unwrap(),
+            vec![ListenAddr::Tcp("0.0.0.0:8080".parse().unwrap())]
+        );
+    }
+
+    #[test]
+    fn test_regen_golden_subcommand() {
+        let args = Args::try_parse_from(["toon-mcp", "regen-golden"]).unwrap();
+        assert!(matches!(
+            args.command,
+            Some(Command::RegenGolden { ref dir }) if dir.as_path() == std::path::Path::new("tests/golden")
+        ));
+    }
+
+    #[test]
+    fn test_completions_subcommand() {
+        let args = Args::try_parse_from(["toon-mcp", "completions", "zsh"]).unwrap();
+        assert!(matches!(
+            args.command,
+            Some(Command::Completions {
+                shell: clap_complete::Shell::Zsh
+            })
+        ));
+        assert!(Args::try_parse_from(["toon-mcp", "completions", "tcsh"]).is_err());
+    }
+
+    #[test]
+    fn test_output_flags_after_subcommand() {
+        let args = Args::try_parse_from([
+            "toon-mcp",
+            "replay",
+            "session.jsonl",
+            "--output",
+            "json",
+            "-q",
+        ])
+        .unwrap();
+        assert_eq!(args.output, OutputFormat::Json);
+        assert!(args.quiet);
+        assert_eq!(args.command.unwrap().name(), "replay");
+        assert_eq!(args_default().output, OutputFormat::Text);
+    }
+
+    #[test]
+    fn test_convert_subcommand() {
+        let args = Args::try_parse_from([
+            "toon-mcp",
+            "convert",
+            "data/**/*.json",
+            "--to",
+            "toon",
+            "-j",
+            "8",
+            "--skip-unchanged",
+        ])
+        .unwrap();
+        let Some(Command::Convert {
+            patterns,
+            to,
+            jobs,
+            skip_unchanged,
+            ..
+        }) = args.command
+        else {
+            panic!("expected convert");
+        };
+        assert_eq!(patterns, vec!["data/**/*.json"]);
+        assert_eq!(to, Some(crate::convert::Format::Toon));
+        assert_eq!(jobs, Some(8));
+        assert_eq!(skip_unchanged, Some(crate::convert::SkipUnchanged::Mtime));
+    }
+
+    #[test]
+    fn test_warmup_flag() {
+        assert!(!args_default().warmup);
+        let args = Args::try_parse_from(["toon-mcp", "--mode", "http", "--warmup"]).unwrap();
+        assert!(args.warmup);
+    }
+
+    #[test]
+    fn test_socket_addr_rejects_invalid_host() {
+        let err = args("not a host!", "8080").socket_addr().unwrap_err();
+        assert!(err.to_string().contains("not a host!"));
     }
 }
@@ -0,0 +1,274 @@
+//! Runner for the official TOON specification test vectors.
+//!
+//! The spec repository (<https://github.com/toon-format/spec>) publishes
+//! fixtures under `tests/fixtures/{encode,decode}/*.json`. Each file lists
+//! cases with an input, the expected output (or `shouldError`), and encoder
+//! or decoder options. [`run`] feeds every case through
+//! [`core::encode_json`] or [`core::decode_toon`] and reports the outcome of
+//! each, so spec compliance can be shown case by case.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::core::{self, DecodeRequest, EncodeOptionsInput};
+
+/// Environment variable naming a fixtures directory for the test suite.
+pub const SPEC_DIR_ENV: &str = "TOON_SPEC_DIR";
+
+#[derive(Deserialize)]
+struct FixtureFile {
+    #[serde(default)]
+    category: Option<String>,
+    tests: Vec<Case>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Case {
+    name: String,
+    input: Value,
+    #[serde(default)]
+    expected: Value,
+    #[serde(default)]
+    should_error: bool,
+    #[serde(default)]
+    options: Map<String, Value>,
+}
+
+/// Result of one case.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Pass,
+    Fail,
+    /// The case uses an option this server does not expose
+    Skip,
+}
+
+/// Outcome of one case, with the reason when it did not pass.
+#[derive(Debug, Serialize)]
+pub struct CaseResult {
+    /// Fixture file, relative to the fixtures directory
+    pub file: String,
+    pub name: String,
+    pub outcome: Outcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Outcomes of every case in a fixtures directory.
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    pub passed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub cases: Vec<CaseResult>,
+}
+
+/// Run every fixture file under `dir`.
+///
+/// A file's category (`encode` or `decode`) comes from its `category` field,
+/// or else from the name of the directory it is in.
+pub fn run(dir: &Path) -> anyhow::Result<Report> {
+    let mut files = Vec::new();
+    collect_json_files(dir, &mut files)?;
+    files.sort();
+    anyhow::ensure!(!files.is_empty(), "no fixture files in {}", dir.display());
+
+    let mut report = Report::default();
+    for path in files {
+        let fixture: FixtureFile = serde_json::from_str(&fs::read_to_string(&path)?)
+            .map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+        let category = fixture.category.clone().unwrap_or_else(|| {
+            let parent = path.parent().and_then(Path::file_name);
+            parent.unwrap_or_default().to_string_lossy().into_owned()
+        });
+        let file = path
+            .strip_prefix(dir)
+            .unwrap_or(&path)
+            .display()
+            .to_string();
+
+        for case in &fixture.tests {
+            let (outcome, message) = match run_case(&category, case) {
+                Ok(()) => (Outcome::Pass, None),
+                Err((outcome, message)) => (outcome, Some(message)),
+            };
+            match outcome {
+                Outcome::Pass => report.passed += 1,
+                Outcome::Fail => report.failed += 1,
+                Outcome::Skip => report.skipped += 1,
+            }
+            report.cases.push(CaseResult {
+                file: file.clone(),
+                name: case.name.clone(),
+                outcome,
+                message,
+            });
+        }
+    }
+    Ok(report)
+}
+
+fn collect_json_files(dir: &Path, files: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_json_files(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "json") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+type CaseError = (Outcome, String);
+
+fn fail(message: String) -> CaseError {
+    (Outcome::Fail, message)
+}
+
+fn skip(message: String) -> CaseError {
+    (Outcome::Skip, message)
+}
+
+fn run_case(category: &str, case: &Case) -> Result<(), CaseError> {
+    match category {
+        "encode" => {
+            let options = encode_options(&case.options).map_err(skip)?;
+            let result = core::encode_json(&case.input, &options);
+            match (result, case.should_error) {
+                (Err(_), true) => Ok(()),
+                (Ok(toon), true) => Err(fail(format!("expected an error, got {:?}", toon))),
+                (Err(e), false) => Err(fail(format!("unexpected error: {}", e))),
+                (Ok(toon), false) if Some(toon.as_str()) == case.expected.as_str() => Ok(()),
+                (Ok(toon), false) => Err(fail(format!(
+                    "expected {:?}, got {:?}",
+                    case.expected.as_str().unwrap_or_default(),
+                    toon
+                ))),
+            }
+        }
+        "decode" => {
+            let Some(toon) = case.input.as_str() else {
+                return Err(skip("decode input is not a string".to_string()));
+            };
+            let request = decode_request(toon, &case.options).map_err(skip)?;
+            let result = core::decode_toon(toon, &request);
+            match (result, case.should_error) {
+                (Err(_), true) => Ok(()),
+                (Ok(json), true) => Err(fail(format!("expected an error, got {}", json))),
+                (Err(e), false) => Err(fail(format!("unexpected error: {}", e))),
+                (Ok(json), false) if same_json(&json, &case.expected) => Ok(()),
+                (Ok(json), false) => Err(fail(format!("expected {}, got {}", case.expected, json))),
+            }
+        }
+        other => Err(skip(format!("unknown category '{}'", other))),
+    }
+}
+
+fn encode_options(options: &Map<String, Value>) -> Result<EncodeOptionsInput, String> {
+    let mut input = EncodeOptionsInput::default();
+    for (key, value) in options {
+        match (key.as_str(), value) {
+            ("delimiter", Value::String(d)) => {
+                input.delimiter = Some(
+                    match d.as_str() {
+                        "," => "comma",
+                        "\t" => "tab",
+                        "|" => "pipe",
+                        _ => return Err(format!("unsupported delimiter {:?}", d)),
+                    }
+                    .to_string(),
+                );
+            }
+            ("indent", Value::Number(n)) => {
+                input.indent = n
+                    .as_u64()
+                    .and_then(|n| u8::try_from(n).ok())
+                    .filter(|n| *n <= 8);
+                if input.indent.is_none() {
+                    return Err(format!("unsupported indent {}", n));
+                }
+            }
+            ("keyFolding", Value::String(mode)) => input.fold_keys = Some(mode == "safe"),
+            ("flattenDepth", Value::Number(n)) => {
+                input.flatten_depth = n.as_u64().map(|n| n as usize);
+            }
+            _ => return Err(format!("unsupported encode option {}={}", key, value)),
+        }
+    }
+    Ok(input)
+}
+
+fn decode_request(toon: &str, options: &Map<String, Value>) -> Result<DecodeRequest, String> {
+    let mut request = DecodeRequest {
+        toon: toon.to_string(),
+        ..Default::default()
+    };
+    for (key, value) in options {
+        match (key.as_str(), value) {
+            ("strict", Value::Bool(strict)) => request.strict = Some(*strict),
+            ("expandPaths", Value::String(mode)) => request.expand_paths = Some(mode == "safe"),
+            // Indentation is detected from the document; only the default is assumed.
+            ("indent", Value::Number(n)) if n.as_u64() == Some(2) => {}
+            _ => return Err(format!("unsupported decode option {}={}", key, value)),
+        }
+    }
+    Ok(request)
+}
+
+/// JSON equality that treats numbers by value, so `1` matches `1.0`.
+pub fn same_json(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x == y || x.as_f64() == y.as_f64(),
+        (Value::Array(x), Value::Array(y)) => {
+            x.len() == y.len() && x.iter().zip(y).all(|(a, b)| same_json(a, b))
+        }
+        (Value::Object(x), Value::Object(y)) => {
+            x.len() == y.len()
+                && x.iter()
+                    .all(|(k, v)| y.get(k).is_some_and(|w| same_json(v, w)))
+        }
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_run_reports_each_case() {
+        let dir = std::env::temp_dir().join(format!("toon-conformance-{}", std::process::id()));
+        fs::create_dir_all(dir.join("encode")).unwrap();
+        let fixture = json!({
+            "version": "1.5",
+            "tests": [
+                {"name": "object", "input": {"a": 1}, "expected": "a: 1"},
+                {"name": "wrong", "input": {"a": 1}, "expected": "a: 2"},
+                {"name": "option", "input": {}, "expected": "", "options": {"lengthMarker": "#"}}
+            ]
+        });
+        fs::write(dir.join("encode/objects.json"), fixture.to_string()).unwrap();
+
+        let report = run(&dir).unwrap();
+        assert_eq!((report.passed, report.failed, report.skipped), (1, 1, 1));
+        assert_eq!(report.cases[0].file, "encode/objects.json");
+        assert_eq!(report.cases[1].outcome, Outcome::Fail);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_same_json_compares_numbers_by_value() {
+        assert!(same_json(
+            &json!({"a": [1, 2.5]}),
+            &json!({"a": [1.0, 2.5]})
+        ));
+        assert!(!same_json(&json!({"a": 1}), &json!({"a": "1"})));
+    }
+}
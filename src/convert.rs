@@ -0,0 +1,508 @@
+//! Batch conversion of files between JSON and TOON (`toon-mcp convert`).
+//!
+//! Inputs are glob patterns such as `data/**/*.json`, or plain paths. Each
+//! matched file is converted on one of `jobs` threads and written next to
+//! the input or, with an output directory, to its path below the pattern's
+//! base directory under it. A file that fails is reported and the others
+//! carry on.
+//!
+//! With the `archive` feature, matched `.zip`, `.tar.gz`, and `.tgz` files
+//! are converted entry by entry into an archive of the same kind, named
+//! after the output format (`drop.zip` becomes `drop.toon.zip`).
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::core::{self, DecodeRequest, EncodeOptionsInput, ToonCoreError};
+
+#[cfg(feature = "archive")]
+mod archive;
+
+/// File in the output directory recording input hashes for
+/// `--skip-unchanged hash`.
+pub const MANIFEST: &str = ".toon-convert.json";
+
+/// A document format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Json,
+    Toon,
+}
+
+impl Format {
+    /// File extension for the format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Toon => "toon",
+        }
+    }
+
+    /// The format files at `path` are read as: `.toon` files are TOON,
+    /// anything else JSON.
+    pub fn of(path: &Path) -> Self {
+        match path.extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("toon") => Format::Toon,
+            _ => Format::Json,
+        }
+    }
+
+    /// The format a document in this one is converted to.
+    pub fn other(self) -> Self {
+        match self {
+            Format::Json => Format::Toon,
+            Format::Toon => Format::Json,
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Format::Json => "JSON",
+            Format::Toon => "TOON",
+        })
+    }
+}
+
+/// Guess the format of a document with no file name: JSON if it parses as
+/// JSON, TOON otherwise. A TOON document holding a single number, string,
+/// or literal is valid JSON too, and is taken as JSON. Returns the format
+/// with the reason, for the user.
+pub fn detect(text: &str) -> (Format, String) {
+    match serde_json::from_str::<serde::de::IgnoredAny>(text) {
+        Ok(_) => (Format::Json, "parses as JSON".to_string()),
+        Err(e) => (Format::Toon, format!("is not JSON ({})", e)),
+    }
+}
+
+/// How to tell that an input's output is already up to date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SkipUnchanged {
+    /// The output is newer than the input
+    Mtime,
+    /// The input's SHA-256 matches the one recorded when it was last converted
+    Hash,
+}
+
+/// An archive whose entries are converted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    TarGz,
+}
+
+impl ArchiveKind {
+    /// File name suffixes of the kind; outputs get the first.
+    fn suffixes(self) -> &'static [&'static str] {
+        match self {
+            ArchiveKind::Zip => &[".zip"],
+            ArchiveKind::TarGz => &[".tar.gz", ".tgz"],
+        }
+    }
+
+    /// The kind of archive at `path`, from its name, with the name's stem.
+    pub fn of(path: &Path) -> Option<(Self, String)> {
+        let name = path.file_name()?.to_string_lossy();
+        let lower = name.to_ascii_lowercase();
+        [ArchiveKind::Zip, ArchiveKind::TarGz]
+            .into_iter()
+            .find_map(|kind| {
+                let suffix = kind.suffixes().iter().find(|s| lower.ends_with(*s))?;
+                Some((kind, name[..name.len() - suffix.len()].to_string()))
+            })
+    }
+}
+
+/// Settings for a batch.
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    /// Output format; `None` converts each file to the other format than its
+    /// extension says
+    pub to: Option<Format>,
+    /// Directory the outputs are written under; `None` writes next to inputs
+    pub out_dir: Option<PathBuf>,
+    /// Files converted at once
+    pub jobs: usize,
+    pub skip_unchanged: Option<SkipUnchanged>,
+}
+
+/// What happened to one file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Converted,
+    /// The output was up to date
+    Skipped,
+    Failed,
+}
+
+/// Outcome of converting one file.
+#[derive(Debug, Serialize)]
+pub struct FileResult {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub status: Status,
+    /// Entries converted, for archives
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entries: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A matched file and its path below its pattern's base directory.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Input {
+    pub path: PathBuf,
+    pub relative: PathBuf,
+}
+
+/// Files matching `patterns`, sorted and without duplicates. A pattern
+/// without wildcards names a file; one matching nothing is an error.
+pub fn inputs(patterns: &[String]) -> anyhow::Result<Vec<Input>> {
+    let mut inputs = BTreeSet::new();
+    for pattern in patterns {
+        let base = base_dir(pattern);
+        let mut matched = false;
+        for entry in glob::glob(pattern)
+            .map_err(|e| anyhow::anyhow!("invalid pattern '{}': {}", pattern, e))?
+        {
+            let path = entry?;
+            if !path.is_file() {
+                continue;
+            }
+            matched = true;
+            let relative = path.strip_prefix(&base).unwrap_or(&path).to_path_buf();
+            inputs.insert(Input { path, relative });
+        }
+        anyhow::ensure!(matched, "no files match '{}'", pattern);
+    }
+    Ok(inputs.into_iter().collect())
+}
+
+/// The leading components of `pattern` without wildcards; for a plain path,
+/// its parent directory.
+fn base_dir(pattern: &str) -> PathBuf {
+    let path = Path::new(pattern);
+    let literal: PathBuf = path
+        .components()
+        .take_while(|c| !c.as_os_str().to_string_lossy().contains(['*', '?', '[']))
+        .collect();
+    if literal == path {
+        literal.parent().map(Path::to_path_buf).unwrap_or_default()
+    } else {
+        literal
+    }
+}
+
+/// Convert a document between formats. Output ends with a newline.
+pub fn convert_text(text: &str, from: Format, to: Format) -> Result<String, ToonCoreError> {
+    let json: Value = match from {
+        Format::Json => {
+            serde_json::from_str(text).map_err(|e| ToonCoreError::InvalidJson(e.to_string()))?
+        }
+        Format::Toon => core::decode_toon(text, &DecodeRequest::default())?,
+    };
+    render(&json, to)
+}
+
+/// Write `value` in `format` with default options, ending with a newline.
+pub fn render(value: &Value, format: Format) -> Result<String, ToonCoreError> {
+    let mut out = match format {
+        Format::Json => serde_json::to_string_pretty(value)
+            .map_err(|e| ToonCoreError::SerializationError(e.to_string()))?,
+        Format::Toon => core::encode_json(value, &EncodeOptionsInput::default())?,
+    };
+    out.push('\n');
+    Ok(out)
+}
+
+/// Convert every input on `options.jobs` threads. Results are in input
+/// order.
+pub fn run(inputs: &[Input], options: &Options) -> anyhow::Result<Vec<FileResult>> {
+    let manifest_path = options
+        .out_dir
+        .as_deref()
+        .unwrap_or(Path::new("."))
+        .join(MANIFEST);
+    let hashing = options.skip_unchanged == Some(SkipUnchanged::Hash);
+    let manifest: BTreeMap<String, String> = match fs::read_to_string(&manifest_path) {
+        Ok(text) if hashing => serde_json::from_str(&text).unwrap_or_default(),
+        _ => BTreeMap::new(),
+    };
+
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<Converted>>> = Mutex::new(inputs.iter().map(|_| None).collect());
+    std::thread::scope(|scope| {
+        for _ in 0..options.jobs.clamp(1, inputs.len().max(1)) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(input) = inputs.get(index) else {
+                    break;
+                };
+                let result = convert_file(input, options, &manifest);
+                results.lock().unwrap_or_else(|e| e.into_inner())[index] = Some(result);
+            });
+        }
+    });
+    let results = results.into_inner().unwrap_or_else(|e| e.into_inner());
+
+    let mut updated = manifest.clone();
+    let results: Vec<FileResult> = results
+        .into_iter()
+        .flatten()
+        .map(|(result, hash)| {
+            if let Some(hash) = hash {
+                updated.insert(result.output.display().to_string(), hash);
+            }
+            result
+        })
+        .collect();
+    if hashing && updated != manifest {
+        if let Some(dir) = &options.out_dir {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(
+            &manifest_path,
+            serde_json::to_string_pretty(&updated)? + "\n",
+        )?;
+    }
+    Ok(results)
+}
+
+/// A file's result, with the input's hash when hashing, for the manifest.
+type Converted = (FileResult, Option<String>);
+
+/// Convert one file.
+fn convert_file(
+    input: &Input,
+    options: &Options,
+    manifest: &BTreeMap<String, String>,
+) -> Converted {
+    let from = options
+        .to
+        .map(Format::other)
+        .unwrap_or_else(|| Format::of(&input.path));
+    let to = from.other();
+    let archive = ArchiveKind::of(&input.path);
+    let target = match &archive {
+        Some((kind, stem)) => {
+            let name = format!("{}.{}{}", stem, to.extension(), kind.suffixes()[0]);
+            input.relative.with_file_name(name)
+        }
+        None => input.relative.with_extension(to.extension()),
+    };
+    let output = match &options.out_dir {
+        Some(dir) => dir.join(target),
+        None => input
+            .path
+            .with_file_name(target.file_name().unwrap_or_default()),
+    };
+    let mut result = FileResult {
+        input: input.path.clone(),
+        output,
+        status: Status::Converted,
+        entries: None,
+        error: None,
+    };
+    let archive = archive.map(|(kind, _)| kind);
+    let hash = match write_output(&mut result, archive, (from, to), options, manifest) {
+        Ok(hash) => hash,
+        Err(e) => {
+            result.status = Status::Failed;
+            result.error = Some(format!("{:#}", e));
+            None
+        }
+    };
+    (result, hash)
+}
+
+/// Convert `result.input` into `result.output` unless it is up to date,
+/// recording the outcome in `result`. Returns the input's hash when hashing.
+fn write_output(
+    result: &mut FileResult,
+    archive: Option<ArchiveKind>,
+    (from, to): (Format, Format),
+    options: &Options,
+    manifest: &BTreeMap<String, String>,
+) -> anyhow::Result<Option<String>> {
+    let (input, output) = (&result.input, &result.output);
+    anyhow::ensure!(output != input, "output would overwrite the input");
+    let mut hash = None;
+    match options.skip_unchanged {
+        Some(SkipUnchanged::Mtime) if is_newer(output, input) => {
+            result.status = Status::Skipped;
+            return Ok(None);
+        }
+        Some(SkipUnchanged::Hash) => {
+            let digest = hex(&Sha256::digest(fs::read(input)?));
+            let recorded = manifest.get(&output.display().to_string()) == Some(&digest);
+            if recorded && output.is_file() {
+                result.status = Status::Skipped;
+                return Ok(Some(digest));
+            }
+            hash = Some(digest);
+        }
+        _ => {}
+    }
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    match archive {
+        Some(kind) => {
+            anyhow::ensure!(options.to.is_some(), "converting an archive needs --to");
+            result.entries = Some(convert_archive(input, output, kind, from, to)?);
+        }
+        None => {
+            let converted = convert_text(&fs::read_to_string(input)?, from, to)?;
+            fs::write(output, converted)?;
+        }
+    }
+    Ok(hash)
+}
+
+#[cfg(feature = "archive")]
+fn convert_archive(
+    input: &Path,
+    output: &Path,
+    kind: ArchiveKind,
+    from: Format,
+    to: Format,
+) -> anyhow::Result<usize> {
+    archive::convert(input, output, kind, from, to)
+}
+
+#[cfg(not(feature = "archive"))]
+fn convert_archive(
+    _input: &Path,
+    _output: &Path,
+    _kind: ArchiveKind,
+    _from: Format,
+    _to: Format,
+) -> anyhow::Result<usize> {
+    anyhow::bail!("converting archives requires a build with the archive feature")
+}
+
+/// Whether `output` exists and was modified no earlier than `input`.
+fn is_newer(output: &Path, input: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+    match (modified(output), modified(input)) {
+        (Some(output), Some(input)) => output >= input,
+        _ => false,
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("toon-convert-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("data/nested")).unwrap();
+        fs::write(dir.join("data/a.json"), r#"{"a": 1}"#).unwrap();
+        fs::write(dir.join("data/nested/b.json"), r#"{"b": [1, 2]}"#).unwrap();
+        fs::write(dir.join("data/nested/bad.json"), "{").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_base_dir() {
+        assert_eq!(base_dir("data/**/*.json"), Path::new("data"));
+        assert_eq!(base_dir("*.json"), Path::new(""));
+        assert_eq!(base_dir("data/a.json"), Path::new("data"));
+    }
+
+    #[test]
+    fn test_batch_mirrors_tree_and_reports_failures() {
+        let dir = scratch("batch");
+        let pattern = format!("{}/data/**/*.json", dir.display());
+        let inputs = inputs(&[pattern]).unwrap();
+        assert_eq!(inputs.len(), 3);
+        let options = Options {
+            to: Some(Format::Toon),
+            out_dir: Some(dir.join("out")),
+            jobs: 2,
+            skip_unchanged: None,
+        };
+
+        let results = run(&inputs, &options).unwrap();
+        let statuses: Vec<Status> = results.iter().map(|r| r.status).collect();
+        assert_eq!(
+            statuses,
+            vec![Status::Converted, Status::Converted, Status::Failed]
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("out/nested/b.toon")).unwrap(),
+            "b[2]: 1,2\n"
+        );
+        assert!(results[2].error.is_some());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_skip_unchanged_by_hash() {
+        let dir = scratch("hash");
+        let inputs = inputs(&[format!("{}/data/a.json", dir.display())]).unwrap();
+        let options = Options {
+            out_dir: Some(dir.join("out")),
+            jobs: 1,
+            skip_unchanged: Some(SkipUnchanged::Hash),
+            ..Default::default()
+        };
+
+        assert_eq!(run(&inputs, &options).unwrap()[0].status, Status::Converted);
+        assert_eq!(run(&inputs, &options).unwrap()[0].status, Status::Skipped);
+        fs::write(dir.join("data/a.json"), r#"{"a": 2}"#).unwrap();
+        assert_eq!(run(&inputs, &options).unwrap()[0].status, Status::Converted);
+        assert_eq!(
+            fs::read_to_string(dir.join("out/a.toon")).unwrap(),
+            "a: 2\n"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_archive_kind() {
+        assert_eq!(
+            ArchiveKind::of(Path::new("in/drop.tar.gz")),
+            Some((ArchiveKind::TarGz, "drop".to_string()))
+        );
+        assert_eq!(
+            ArchiveKind::of(Path::new("Drop.ZIP")),
+            Some((ArchiveKind::Zip, "Drop".to_string()))
+        );
+        assert_eq!(ArchiveKind::of(Path::new("a.json")), None);
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect("{\"a\": [1, 2]}\n").0, Format::Json);
+        assert_eq!(detect("a[2]: 1,2").0, Format::Toon);
+        assert_eq!(detect("[2]: 1,2").0, Format::Toon);
+        assert_eq!(detect("[1, 2]").0, Format::Json);
+        let (_, reason) = detect("a: 1");
+        assert!(reason.starts_with("is not JSON"), "{}", reason);
+    }
+
+    #[test]
+    fn test_convert_text_round_trip() {
+        let toon = convert_text(r#"{"a": [1, 2]}"#, Format::Json, Format::Toon).unwrap();
+        let json = convert_text(&toon, Format::Toon, Format::Json).unwrap();
+        assert_eq!(json, "{\n  \"a\": [\n    1,\n    2\n  ]\n}\n");
+    }
+}
@@ -0,0 +1,152 @@
+//! Conversion of the entries inside zip and tar.gz archives.
+//!
+//! Entries with the source format's extension are converted and renamed;
+//! every other entry, directories included, is copied into the output
+//! archive unchanged. The output is written next to its final path and
+//! renamed into place once complete, so a failed entry never leaves a
+//! partial archive behind.
+
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use super::{convert_text, ArchiveKind, Format};
+
+/// Convert the archive at `input` into `output`. Returns the number of
+/// entries converted.
+pub fn convert(
+    input: &Path,
+    output: &Path,
+    kind: ArchiveKind,
+    from: Format,
+    to: Format,
+) -> anyhow::Result<usize> {
+    let partial = PathBuf::from(format!("{}.partial", output.display()));
+    let converted = match kind {
+        ArchiveKind::Zip => convert_zip(input, &partial, from, to),
+        ArchiveKind::TarGz => convert_tar_gz(input, &partial, from, to),
+    };
+    match converted {
+        Ok(count) => {
+            fs::rename(&partial, output)?;
+            Ok(count)
+        }
+        Err(e) => {
+            let _ = fs::remove_file(&partial);
+            Err(e)
+        }
+    }
+}
+
+/// The converted name of an entry in `from` format, or `None` to copy it.
+fn renamed(name: &str, from: Format, to: Format) -> Option<String> {
+    let stem = name.strip_suffix(from.extension())?.strip_suffix('.')?;
+    Some(format!("{}.{}", stem, to.extension()))
+}
+
+fn convert_entry(name: &str, bytes: Vec<u8>, from: Format, to: Format) -> anyhow::Result<String> {
+    let text = String::from_utf8(bytes).map_err(|e| anyhow::anyhow!("entry {}: {}", name, e))?;
+    convert_text(&text, from, to).map_err(|e| anyhow::anyhow!("entry {}: {}", name, e))
+}
+
+fn convert_zip(input: &Path, output: &Path, from: Format, to: Format) -> anyhow::Result<usize> {
+    let mut archive = zip::ZipArchive::new(File::open(input)?)?;
+    let mut writer = zip::ZipWriter::new(File::create(output)?);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    let mut count = 0;
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index)?;
+        let name = entry.name().to_string();
+        match renamed(&name, from, to).filter(|_| entry.is_file()) {
+            Some(new_name) => {
+                let mut bytes = Vec::new();
+                entry.read_to_end(&mut bytes)?;
+                let converted = convert_entry(&name, bytes, from, to)?;
+                writer.start_file(new_name, options)?;
+                writer.write_all(converted.as_bytes())?;
+                count += 1;
+            }
+            None => writer.raw_copy_file(entry)?,
+        }
+    }
+    writer.finish()?;
+    Ok(count)
+}
+
+fn convert_tar_gz(input: &Path, output: &Path, from: Format, to: Format) -> anyhow::Result<usize> {
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(File::open(input)?));
+    let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+        File::create(output)?,
+        flate2::Compression::default(),
+    ));
+    let mut count = 0;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_string_lossy().into_owned();
+        let mut header = entry.header().clone();
+        match renamed(&path, from, to).filter(|_| header.entry_type().is_file()) {
+            Some(new_path) => {
+                let mut bytes = Vec::new();
+                entry.read_to_end(&mut bytes)?;
+                let converted = convert_entry(&path, bytes, from, to)?;
+                header.set_size(converted.len() as u64);
+                builder.append_data(&mut header, new_path, converted.as_bytes())?;
+                count += 1;
+            }
+            None => builder.append_data(&mut header, &path, &mut entry)?,
+        }
+    }
+    builder.into_inner()?.finish()?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_renamed() {
+        assert_eq!(
+            renamed("data/a.json", Format::Json, Format::Toon).as_deref(),
+            Some("data/a.toon")
+        );
+        assert_eq!(renamed("README.md", Format::Json, Format::Toon), None);
+        assert_eq!(renamed("json", Format::Json, Format::Toon), None);
+    }
+
+    #[test]
+    fn test_zip_round_trip() {
+        let dir = std::env::temp_dir().join(format!("toon-archive-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("drop.zip");
+        let mut writer = zip::ZipWriter::new(File::create(&input).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        writer.start_file("rows/a.json", options).unwrap();
+        writer.write_all(br#"{"a": [1, 2]}"#).unwrap();
+        writer.start_file("README.md", options).unwrap();
+        writer.write_all(b"notes").unwrap();
+        writer.finish().unwrap();
+
+        let output = dir.join("drop.toon.zip");
+        let count = convert(
+            &input,
+            &output,
+            ArchiveKind::Zip,
+            Format::Json,
+            Format::Toon,
+        )
+        .unwrap();
+        assert_eq!(count, 1);
+        let mut archive = zip::ZipArchive::new(File::open(&output).unwrap()).unwrap();
+        let mut toon = String::new();
+        archive
+            .by_name("rows/a.toon")
+            .unwrap()
+            .read_to_string(&mut toon)
+            .unwrap();
+        assert_eq!(toon, "a[2]: 1,2\n");
+        assert!(archive.by_name("README.md").is_ok());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
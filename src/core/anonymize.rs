@@ -0,0 +1,382 @@
+//! PII detection and anonymization.
+//!
+//! String values are scanned with regex detectors (emails, phone numbers,
+//! credit cards, IP addresses, plus caller-supplied patterns). Matches are
+//! replaced according to the selected [`Mode`] before the document is encoded.
+//!
+//! Token-producing modes can also record a token -> original mapping, sealed
+//! with AES-256-GCM under the caller's key, so LLM output that echoes the
+//! tokens can be de-anonymized later.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::OnceLock;
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use hmac::{Hmac, Mac};
+use regex::Regex;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+
+use super::{path, AnonymizeReport, PiiPattern, ToonCoreError};
+
+/// How detected values are replaced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Replace with a type label such as `[EMAIL]`
+    Mask,
+    /// Replace with a truncated SHA-256 digest such as `email_3fa9c1d2e4b7`
+    Hash,
+    /// Replace with a sequential token such as `email_1`, stable within one call
+    Pseudonymize,
+    /// Replace with a keyed HMAC token such as `email_9c2f6a01b3d4e5f6`, stable across calls
+    Hmac,
+}
+
+impl Mode {
+    /// Parse a mode name ("mask", "hash", "pseudonymize", or "hmac").
+    pub fn parse(name: Option<&str>) -> Result<Self, ToonCoreError> {
+        match name {
+            None | Some("mask") => Ok(Mode::Mask),
+            Some("hash") => Ok(Mode::Hash),
+            Some("pseudonymize") => Ok(Mode::Pseudonymize),
+            Some("hmac") => Ok(Mode::Hmac),
+            Some(other) => Err(ToonCoreError::InvalidOption(format!(
+                "unknown anonymization mode '{}' (expected mask, hash, pseudonymize, or hmac)",
+                other
+            ))),
+        }
+    }
+}
+
+/// A named pattern that identifies one kind of PII.
+#[derive(Debug, Clone)]
+pub struct Detector {
+    pub name: String,
+    regex: Regex,
+    check: Option<fn(&str) -> bool>,
+}
+
+impl Detector {
+    fn new(name: &str, pattern: &str, check: Option<fn(&str) -> bool>) -> Self {
+        Self {
+            name: name.to_string(),
+            regex: Regex::new(pattern).expect("built-in PII pattern must compile"),
+            check,
+        }
+    }
+}
+
+/// Names of the built-in detectors, in matching priority order.
+pub const BUILTIN_DETECTORS: &[&str] = &["email", "credit_card", "ip", "phone"];
+
+fn builtins() -> &'static [Detector] {
+    static BUILTINS: OnceLock<Vec<Detector>> = OnceLock::new();
+    BUILTINS.get_or_init(|| {
+        vec![
+            Detector::new(
+                "email",
+                r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}",
+                None,
+            ),
+            Detector::new("credit_card", r"\b(?:\d[ -]?){12,18}\d\b", Some(luhn_valid)),
+            Detector::new(
+                "ip",
+                r"\b(?:(?:25[0-5]|2[0-4]\d|1?\d?\d)\.){3}(?:25[0-5]|2[0-4]\d|1?\d?\d)\b|\b(?:[0-9A-Fa-f]{1,4}:){7}[0-9A-Fa-f]{1,4}\b",
+                None,
+            ),
+            Detector::new(
+                "phone",
+                r"(?:\+\d{1,3}[ .-]?)?\(?\b\d{3}\)?[ .-]?\d{3}[ .-]?\d{4}\b",
+                None,
+            ),
+        ]
+    })
+}
+
+/// Luhn checksum, used to keep arbitrary digit runs from matching as cards.
+fn luhn_valid(candidate: &str) -> bool {
+    let digits: Vec<u32> = candidate.chars().filter_map(|c| c.to_digit(10)).collect();
+    if digits.len() < 13 {
+        return false;
+    }
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| {
+            if i % 2 == 1 {
+                let doubled = d * 2;
+                if doubled > 9 {
+                    doubled - 9
+                } else {
+                    doubled
+                }
+            } else {
+                d
+            }
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+/// Resolve the detectors to run: the selected built-ins followed by custom patterns.
+pub fn detectors(
+    selected: Option<&[String]>,
+    patterns: &[PiiPattern],
+) -> Result<Vec<Detector>, ToonCoreError> {
+    let mut out = Vec::new();
+    for detector in builtins() {
+        let enabled = match selected {
+            Some(names) => names.contains(&detector.name),
+            None => true,
+        };
+        if enabled {
+            out.push(detector.clone());
+        }
+    }
+    if let Some(names) = selected {
+        for name in names {
+            if !BUILTIN_DETECTORS.contains(&name.as_str()) {
+                return Err(ToonCoreError::InvalidOption(format!(
+                    "unknown detector '{}' (expected one of: {})",
+                    name,
+                    BUILTIN_DETECTORS.join(", ")
+                )));
+            }
+        }
+    }
+    for pattern in patterns {
+        let regex = Regex::new(&pattern.regex).map_err(|e| {
+            ToonCoreError::InvalidOption(format!("invalid pattern '{}': {}", pattern.name, e))
+        })?;
+        out.push(Detector {
+            name: pattern.name.clone(),
+            regex,
+            check: None,
+        });
+    }
+    Ok(out)
+}
+
+/// Walks a document replacing detected values, keeping replacements consistent.
+pub struct Anonymizer<'a> {
+    detectors: &'a [Detector],
+    mode: Mode,
+    key: Option<&'a [u8]>,
+    tokens: HashMap<(String, String), String>,
+    counters: HashMap<String, usize>,
+    mapping: BTreeMap<String, String>,
+    report: AnonymizeReport,
+}
+
+impl<'a> Anonymizer<'a> {
+    /// Create an anonymizer. [`Mode::Hmac`] requires a `key`.
+    pub fn new(
+        detectors: &'a [Detector],
+        mode: Mode,
+        key: Option<&'a [u8]>,
+    ) -> Result<Self, ToonCoreError> {
+        if mode == Mode::Hmac && key.is_none() {
+            return Err(ToonCoreError::InvalidOption(
+                "hmac mode requires a key (pass `key` or set TOON_PSEUDONYM_KEY)".to_string(),
+            ));
+        }
+        Ok(Self {
+            detectors,
+            mode,
+            key,
+            tokens: HashMap::new(),
+            counters: HashMap::new(),
+            mapping: BTreeMap::new(),
+            report: AnonymizeReport {
+                total: 0,
+                by_type: BTreeMap::new(),
+                paths: Vec::new(),
+            },
+        })
+    }
+
+    /// Token -> original value for every replacement made so far.
+    ///
+    /// Empty in [`Mode::Mask`], where replacements are not unique.
+    pub fn mapping(&self) -> &BTreeMap<String, String> {
+        &self.mapping
+    }
+
+    /// Anonymize every string value in `value`.
+    pub fn run(&mut self, value: &Value) -> Value {
+        self.walk(value, "")
+    }
+
+    /// Consume the anonymizer and return its report.
+    pub fn into_report(self) -> AnonymizeReport {
+        self.report
+    }
+
+    fn walk(&mut self, value: &Value, at: &str) -> Value {
+        match value {
+            Value::Object(map) => {
+                let mut out = Map::with_capacity(map.len());
+                for (k, v) in map {
+                    out.insert(k.clone(), self.walk(v, &path::key(at, k)));
+                }
+                Value::Object(out)
+            }
+            Value::Array(items) => Value::Array(
+                items
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| self.walk(v, &path::index(at, i)))
+                    .collect(),
+            ),
+            Value::String(s) => Value::String(self.scan(s, at)),
+            other => other.clone(),
+        }
+    }
+
+    /// Replace all detector matches in `text`; earlier detectors win overlaps.
+    fn scan(&mut self, text: &str, at: &str) -> String {
+        let mut spans: Vec<(usize, usize, usize)> = Vec::new();
+        for (d, detector) in self.detectors.iter().enumerate() {
+            for m in detector.regex.find_iter(text) {
+                if detector.check.is_some_and(|check| !check(m.as_str())) {
+                    continue;
+                }
+                let overlaps = spans
+                    .iter()
+                    .any(|&(start, end, _)| m.start() < end && start < m.end());
+                if !overlaps {
+                    spans.push((m.start(), m.end(), d));
+                }
+            }
+        }
+        if spans.is_empty() {
+            return text.to_string();
+        }
+        spans.sort_unstable();
+
+        let mut out = String::with_capacity(text.len());
+        let mut cursor = 0;
+        for (start, end, d) in spans {
+            out.push_str(&text[cursor..start]);
+            let replacement = self.replacement(d, &text[start..end]);
+            out.push_str(&replacement);
+            cursor = end;
+        }
+        out.push_str(&text[cursor..]);
+
+        self.report.paths.push(at.to_string());
+        out
+    }
+
+    fn replacement(&mut self, detector: usize, original: &str) -> String {
+        let name = self.detectors[detector].name.clone();
+        self.report.total += 1;
+        *self.report.by_type.entry(name.clone()).or_insert(0) += 1;
+
+        let token = match self.mode {
+            Mode::Mask => return format!("[{}]", name.to_uppercase()),
+            Mode::Hash => format!("{}_{}", name, &hex_digest(original.as_bytes())[..12]),
+            Mode::Pseudonymize => {
+                let key = (name.clone(), original.to_string());
+                if let Some(token) = self.tokens.get(&key) {
+                    return token.clone();
+                }
+                let counter = self.counters.entry(name.clone()).or_insert(0);
+                *counter += 1;
+                let token = format!("{}_{}", name, counter);
+                self.tokens.insert(key, token.clone());
+                token
+            }
+            Mode::Hmac => {
+                let key = self.key.unwrap_or_default();
+                let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key)
+                    .expect("HMAC accepts keys of any length");
+                mac.update(name.as_bytes());
+                mac.update(b":");
+                mac.update(original.as_bytes());
+                let digest = mac.finalize().into_bytes();
+                format!("{}_{}", name, &hex(&digest)[..16])
+            }
+        };
+        self.mapping.insert(token.clone(), original.to_string());
+        token
+    }
+}
+
+/// Lowercase hex SHA-256 digest of `bytes`.
+pub fn hex_digest(bytes: &[u8]) -> String {
+    hex(&Sha256::digest(bytes))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Derive the AES-256 key for mapping blobs from the caller's secret.
+fn mapping_cipher(key: &[u8]) -> Aes256Gcm {
+    let mut hasher = Sha256::new();
+    hasher.update(b"toon-mcp mapping v1:");
+    hasher.update(key);
+    Aes256Gcm::new_from_slice(&hasher.finalize()).expect("SHA-256 output is a valid AES-256 key")
+}
+
+/// Encrypt a token mapping into a base64 blob (`nonce || ciphertext`).
+pub fn seal_mapping(
+    mapping: &BTreeMap<String, String>,
+    key: &[u8],
+) -> Result<String, ToonCoreError> {
+    let plaintext = serde_json::to_vec(mapping)
+        .map_err(|e| ToonCoreError::SerializationError(e.to_string()))?;
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = mapping_cipher(key)
+        .encrypt(&nonce, plaintext.as_slice())
+        .map_err(|_| ToonCoreError::EncodeError("failed to encrypt mapping".to_string()))?;
+
+    let mut blob = nonce.to_vec();
+    blob.extend_from_slice(&ciphertext);
+    Ok(BASE64.encode(blob))
+}
+
+/// Decrypt a blob produced by [`seal_mapping`].
+pub fn open_mapping(blob: &str, key: &[u8]) -> Result<BTreeMap<String, String>, ToonCoreError> {
+    let bytes = BASE64
+        .decode(blob.trim())
+        .map_err(|e| ToonCoreError::InvalidOption(format!("mapping is not valid base64: {}", e)))?;
+    if bytes.len() < 12 {
+        return Err(ToonCoreError::InvalidOption(
+            "mapping blob is truncated".to_string(),
+        ));
+    }
+    let (nonce, ciphertext) = bytes.split_at(12);
+    let plaintext = mapping_cipher(key)
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| {
+            ToonCoreError::InvalidOption(
+                "mapping could not be decrypted (wrong key or corrupted blob)".to_string(),
+            )
+        })?;
+    serde_json::from_slice(&plaintext).map_err(ToonCoreError::from)
+}
+
+/// Replace every token from `mapping` found in `text` with its original value.
+///
+/// Longer tokens are replaced first so `email_1` never clobbers `email_10`.
+/// Returns the restored text and the number of replacements.
+pub fn deanonymize_text(text: &str, mapping: &BTreeMap<String, String>) -> (String, usize) {
+    let mut tokens: Vec<(&String, &String)> = mapping.iter().collect();
+    tokens.sort_by_key(|t| std::cmp::Reverse(t.0.len()));
+
+    let mut out = text.to_string();
+    let mut replaced = 0;
+    for (token, original) in tokens {
+        let count = out.matches(token.as_str()).count();
+        if count > 0 {
+            replaced += count;
+            out = out.replace(token.as_str(), original);
+        }
+    }
+    (out, replaced)
+}
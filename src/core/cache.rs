@@ -0,0 +1,100 @@
+//! Prompt-cache reuse between successive versions of a payload.
+//!
+//! Provider prompt caches match on an exact prefix of the prompt, so what
+//! matters is how far two encodings agree from the start, not how similar
+//! they are overall.
+
+use super::{estimate_tokens, CacheAnalysisResponse};
+
+/// Smallest prefix, in tokens, that common providers cache.
+pub const DEFAULT_MIN_CACHEABLE_TOKENS: usize = 1024;
+
+/// Length in bytes of the longest common prefix of `a` and `b`, on a char boundary.
+pub fn common_prefix(a: &str, b: &str) -> usize {
+    a.char_indices()
+        .zip(b.chars())
+        .find(|((_, x), y)| x != y)
+        .map(|((i, _), _)| i)
+        .unwrap_or_else(|| a.len().min(b.len()))
+}
+
+/// Compare the encodings of two payload versions.
+pub fn analyze(before: &str, after: &str, min_cacheable_tokens: usize) -> CacheAnalysisResponse {
+    let prefix = common_prefix(before, after);
+    let shared = &after[..prefix];
+    let prefix_tokens = estimate_tokens(shared);
+    let after_tokens = estimate_tokens(after);
+    let reusable_tokens = if prefix_tokens >= min_cacheable_tokens {
+        prefix_tokens
+    } else {
+        0
+    };
+
+    let identical = before == after;
+    let first_difference_line = (!identical).then(|| shared.matches('\n').count() + 1);
+
+    let mut suggestions = Vec::new();
+    if let Some(line) = first_difference_line {
+        let old_line = before[prefix..].lines().next().unwrap_or("");
+        let new_line = after[prefix..].lines().next().unwrap_or("");
+        let old_line = format!("{}{}", line_start(shared), old_line);
+        let new_line = format!("{}{}", line_start(shared), new_line);
+        if old_line != new_line && without_lengths(&old_line) == without_lengths(&new_line) {
+            suggestions.push(format!(
+                "Line {} is an array header whose length changed; every row added or removed \
+                 rewrites it, so place growing arrays at the end of the payload.",
+                line
+            ));
+        } else if line == 1 {
+            suggestions.push(
+                "The versions differ from the first line; put fields that stay the same \
+                 (instructions, reference data) before fields that change."
+                    .to_string(),
+            );
+        }
+    }
+    if after_tokens >= min_cacheable_tokens && prefix_tokens < min_cacheable_tokens {
+        suggestions.push(format!(
+            "The shared prefix (~{} tokens) is below the {}-token minimum providers cache; \
+             move stable content earlier.",
+            prefix_tokens, min_cacheable_tokens
+        ));
+    }
+
+    CacheAnalysisResponse {
+        prefix_chars: shared.chars().count(),
+        prefix_lines: shared.matches('\n').count(),
+        prefix_tokens,
+        after_tokens,
+        reusable_tokens,
+        reuse_ratio: if after_tokens == 0 {
+            1.0
+        } else {
+            reusable_tokens as f64 / after_tokens as f64
+        },
+        first_difference_line,
+        suggestions,
+    }
+}
+
+/// Part of the last line of `prefix` (the shared start of the differing line).
+fn line_start(prefix: &str) -> &str {
+    prefix.rsplit('\n').next().unwrap_or("")
+}
+
+/// `line` with the digits inside `[...]` removed, so headers compare equal
+/// regardless of their array length.
+fn without_lengths(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut in_brackets = false;
+    for c in line.chars() {
+        match c {
+            '[' => in_brackets = true,
+            ']' => in_brackets = false,
+            c if in_brackets && c.is_ascii_digit() => continue,
+            _ => {}
+        }
+        out.push(c);
+    }
+    out
+}
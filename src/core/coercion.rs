@@ -0,0 +1,120 @@
+//! Coercion reports for decode (`report_coercions`).
+//!
+//! With `coerce_types` on, an unquoted `30` decodes as the number 30 and
+//! `true` as a boolean. That is usually wanted, but a zip code or an ID that
+//! happens to look like a number is silently changed too. [`report`] decodes the
+//! document a second time without coercion and lists every value whose type
+//! differs, so the caller can audit each decision.
+//!
+//! The decoder types unquoted scalars whether or not coercion is on, so the
+//! second decode is read back as text: every number, boolean, and null in it
+//! was an unquoted token. Its `from` is the value as written by the encoder,
+//! so `1.50` is reported as `1.5`.
+
+use serde_json::Value;
+
+use super::{decode_toon, path, Coercion, DecodeRequest, ToonCoreError};
+
+/// Every value type coercion changed in `decoded`, the result of decoding
+/// `request`, in document order; `None` unless `report_coercions` is set.
+pub fn report(
+    request: &DecodeRequest,
+    decoded: &Value,
+) -> Result<Option<Vec<Coercion>>, ToonCoreError> {
+    if request.report_coercions != Some(true) {
+        return Ok(None);
+    }
+    let mut coercions = Vec::new();
+    if request.coerce_types == Some(false) {
+        return Ok(Some(coercions));
+    }
+    let raw = decode_toon(
+        &request.toon,
+        &DecodeRequest {
+            strict: request.strict,
+            coerce_types: Some(false),
+            expand_paths: request.expand_paths,
+            metadata: request.metadata.clone(),
+            deterministic: request.deterministic,
+            type_hints: request.type_hints,
+            ..Default::default()
+        },
+    )?;
+    collect(&as_text(raw), decoded, "", &mut coercions);
+    Ok(Some(coercions))
+}
+
+/// `value` with every scalar as the string it was written as.
+fn as_text(value: Value) -> Value {
+    match value {
+        Value::String(_) => value,
+        Value::Array(items) => Value::Array(items.into_iter().map(as_text).collect()),
+        Value::Object(map) => {
+            Value::Object(map.into_iter().map(|(k, v)| (k, as_text(v))).collect())
+        }
+        scalar => Value::String(scalar.to_string()),
+    }
+}
+
+fn collect(raw: &Value, coerced: &Value, at: &str, coercions: &mut Vec<Coercion>) {
+    match (raw, coerced) {
+        (Value::String(_), Value::String(_)) => {}
+        (Value::String(text), to) => coercions.push(Coercion {
+            path: at.to_string(),
+            from: text.clone(),
+            to: to.clone(),
+        }),
+        (Value::Array(raw), Value::Array(coerced)) => {
+            for (index, (raw, coerced)) in raw.iter().zip(coerced).enumerate() {
+                collect(raw, coerced, &path::index(at, index), coercions);
+            }
+        }
+        (Value::Object(raw), Value::Object(coerced)) => {
+            for (key, coerced) in coerced {
+                if let Some(raw) = raw.get(key) {
+                    collect(raw, coerced, &path::key(at, key), coercions);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_collect_reports_changed_types_by_path() {
+        let raw = json!({"users": [{"id": "1", "score": "4.5", "name": "Alice", "admin": "true"}]});
+        let coerced = json!({"users": [{"id": 1, "score": 4.5, "name": "Alice", "admin": true}]});
+        let mut coercions = Vec::new();
+        collect(&raw, &coerced, "", &mut coercions);
+
+        let found: Vec<(&str, &str, &Value)> = coercions
+            .iter()
+            .map(|c| (c.path.as_str(), c.from.as_str(), &c.to))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("users[0].id", "1", &json!(1)),
+                ("users[0].score", "4.5", &json!(4.5)),
+                ("users[0].admin", "true", &json!(true)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_report_only_when_asked() {
+        let mut request = DecodeRequest {
+            toon: "a: 1".to_string(),
+            coerce_types: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(report(&request, &json!({"a": "1"})).unwrap(), None);
+        request.report_coercions = Some(true);
+        assert_eq!(report(&request, &json!({"a": "1"})).unwrap(), Some(vec![]));
+    }
+}
@@ -0,0 +1,455 @@
+//! Reversible value compaction for tables.
+//!
+//! These transforms shorten repetitive cell values before encoding and
+//! record what they did in [`EncodeMetadata`](super::EncodeMetadata), so
+//! decoding with that metadata restores the original document exactly.
+//! Columns are addressed as `<table path>[].<column>`, e.g. `orders[].status`.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde_json::Value;
+
+use super::{path, Literals, ToonCoreError};
+
+/// Most distinct values a column may have to be treated as an enum.
+pub const MAX_ENUM_VALUES: usize = 16;
+
+/// Path of `column` in the table at `table`.
+pub fn column_path(table: &str, column: &str) -> String {
+    format!("{}[].{}", table, column)
+}
+
+/// Call `f` with the path and rows of every array of objects in `value`,
+/// outermost first.
+pub fn for_each_table(value: &mut Value, at: &str, f: &mut impl FnMut(&str, &mut [Value])) {
+    match value {
+        Value::Array(items) => {
+            if !items.is_empty() && items.iter().all(Value::is_object) {
+                f(at, items);
+                let rows = format!("{}[]", at);
+                for item in items.iter_mut() {
+                    if let Value::Object(row) = item {
+                        for (key, child) in row.iter_mut() {
+                            for_each_table(child, &path::key(&rows, key), f);
+                        }
+                    }
+                }
+            } else {
+                for (i, item) in items.iter_mut().enumerate() {
+                    for_each_table(item, &path::index(at, i), f);
+                }
+            }
+        }
+        Value::Object(map) => {
+            for (key, child) in map.iter_mut() {
+                for_each_table(child, &path::key(at, key), f);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Replace the values of low-cardinality string columns with short codes.
+///
+/// Returns the legend for each coded column (code -> value). A column
+/// qualifies when every non-null cell is a string, it has at most
+/// [`MAX_ENUM_VALUES`] distinct values, each value repeats on average, and
+/// the codes are shorter overall.
+pub fn encode_enums(value: &mut Value) -> BTreeMap<String, BTreeMap<String, String>> {
+    let mut legends = BTreeMap::new();
+    for_each_table(value, "", &mut |table, rows| {
+        for column in columns(rows) {
+            let Some(values) = enum_values(rows, &column) else {
+                continue;
+            };
+            let codes = short_codes(&values);
+            let saved: isize = rows
+                .iter()
+                .filter_map(|row| row.get(&column).and_then(Value::as_str))
+                .map(|v| v.chars().count() as isize - codes[v].chars().count() as isize)
+                .sum();
+            if saved <= 0 {
+                continue;
+            }
+
+            for row in rows.iter_mut() {
+                if let Some(cell) = row.get_mut(&column) {
+                    if let Some(code) = cell.as_str().map(|v| codes[v].clone()) {
+                        *cell = Value::String(code);
+                    }
+                }
+            }
+            let legend = codes.into_iter().map(|(v, code)| (code, v)).collect();
+            legends.insert(column_path(table, &column), legend);
+        }
+    });
+    legends
+}
+
+/// Keys of all rows, in first-seen order.
+fn columns(rows: &[Value]) -> Vec<String> {
+    let mut columns: Vec<String> = Vec::new();
+    for row in rows.iter().filter_map(Value::as_object) {
+        for key in row.keys() {
+            if !columns.contains(key) {
+                columns.push(key.clone());
+            }
+        }
+    }
+    columns
+}
+
+/// Distinct values of `column` when it looks like an enum.
+fn enum_values(rows: &[Value], column: &str) -> Option<Vec<String>> {
+    let mut values: Vec<String> = Vec::new();
+    let mut count = 0;
+    for row in rows {
+        match row.get(column) {
+            None | Some(Value::Null) => {}
+            Some(Value::String(s)) => {
+                count += 1;
+                if !values.contains(s) {
+                    if values.len() == MAX_ENUM_VALUES {
+                        return None;
+                    }
+                    values.push(s.clone());
+                }
+            }
+            Some(_) => return None,
+        }
+    }
+    (!values.is_empty() && count >= values.len() * 2).then_some(values)
+}
+
+/// Longest value prefix used as a code.
+const MAX_PREFIX_CODE: usize = 3;
+
+/// Distinct short code for each value: the shortest prefix length (up to
+/// three characters) that tells all values apart, or `a`, `b`, ... when no
+/// such prefix does.
+fn short_codes(values: &[String]) -> BTreeMap<String, String> {
+    for len in 1..=MAX_PREFIX_CODE {
+        let codes: Vec<String> = values
+            .iter()
+            .map(|v| v.chars().take(len).collect())
+            .collect();
+        let mut unique = codes.clone();
+        unique.sort();
+        unique.dedup();
+        if unique.len() == values.len() && codes.iter().all(|c| !c.is_empty()) {
+            return values.iter().cloned().zip(codes).collect();
+        }
+    }
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, v)| (v.clone(), letters(i)))
+        .collect()
+}
+
+/// `a`, `b`, ..., `z`, `aa`, `ab`, ...
+fn letters(mut i: usize) -> String {
+    let mut out = Vec::new();
+    loop {
+        out.push(b'a' + (i % 26) as u8);
+        if i < 26 {
+            break;
+        }
+        i = i / 26 - 1;
+    }
+    out.reverse();
+    String::from_utf8(out).unwrap_or_default()
+}
+
+/// Expand enum codes using the legends returned by [`encode_enums`].
+pub fn decode_enums(value: &mut Value, legends: &BTreeMap<String, BTreeMap<String, String>>) {
+    for_each_table(value, "", &mut |table, rows| {
+        for row in rows.iter_mut() {
+            let Value::Object(row) = row else {
+                continue;
+            };
+            for (column, cell) in row.iter_mut() {
+                let Some(legend) = legends.get(&column_path(table, column)) else {
+                    continue;
+                };
+                if let Some(original) = cell.as_str().and_then(|code| legend.get(code)) {
+                    *cell = Value::String(original.clone());
+                }
+            }
+        }
+    });
+}
+
+/// Shortest shared prefix worth moving out of a column.
+pub const MIN_PREFIX_LENGTH: usize = 8;
+
+/// Strip the prefix shared by every cell of URL and path columns.
+///
+/// Returns the prefix removed from each column. A column qualifies when
+/// every non-null cell is a string, at least two cells are present, and the
+/// cells share a prefix of at least [`MIN_PREFIX_LENGTH`] characters ending
+/// in `/`. The prefix is cut back to that last `/` so cells keep whole path
+/// segments.
+pub fn encode_prefixes(value: &mut Value) -> BTreeMap<String, String> {
+    let mut prefixes = BTreeMap::new();
+    for_each_table(value, "", &mut |table, rows| {
+        for column in columns(rows) {
+            let Some(prefix) = shared_prefix(rows, &column) else {
+                continue;
+            };
+            for row in rows.iter_mut() {
+                if let Some(Value::String(cell)) = row.get_mut(&column) {
+                    cell.replace_range(..prefix.len(), "");
+                }
+            }
+            prefixes.insert(column_path(table, &column), prefix);
+        }
+    });
+    prefixes
+}
+
+/// Prefix shared by all cells of `column`, when it qualifies.
+fn shared_prefix(rows: &[Value], column: &str) -> Option<String> {
+    let mut shared: Option<&str> = None;
+    let mut count = 0;
+    for row in rows {
+        match row.get(column) {
+            None | Some(Value::Null) => {}
+            Some(Value::String(s)) => {
+                count += 1;
+                shared = Some(match shared {
+                    None => s.as_str(),
+                    Some(prefix) => &prefix[..super::cache::common_prefix(prefix, s)],
+                });
+            }
+            Some(_) => return None,
+        }
+    }
+    let shared = shared?;
+    let prefix = &shared[..shared.rfind('/')? + 1];
+    (count >= 2 && prefix.chars().count() >= MIN_PREFIX_LENGTH).then(|| prefix.to_string())
+}
+
+/// Put back the prefixes returned by [`encode_prefixes`].
+pub fn decode_prefixes(value: &mut Value, prefixes: &BTreeMap<String, String>) {
+    for_each_table(value, "", &mut |table, rows| {
+        for row in rows.iter_mut() {
+            let Value::Object(row) = row else {
+                continue;
+            };
+            for (column, cell) in row.iter_mut() {
+                if let (Some(prefix), Value::String(s)) =
+                    (prefixes.get(&column_path(table, column)), cell)
+                {
+                    s.insert_str(0, prefix);
+                }
+            }
+        }
+    });
+}
+
+/// Fewest occurrences for a string to enter the dictionary.
+pub const MIN_DICTIONARY_REPEATS: usize = 3;
+
+/// Replace string values repeated across the document with tokens `@0`,
+/// `@1`, ...
+///
+/// Returns the legend (token -> value). A value is tokenized when it occurs
+/// at least [`MIN_DICTIONARY_REPEATS`] times and the characters saved in
+/// the cells outweigh its legend entry. The most profitable values get the
+/// shortest tokens, and tokens that already occur as strings are skipped.
+pub fn encode_dictionary(value: &mut Value) -> BTreeMap<String, String> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    count_strings(value, &mut counts);
+
+    let mut candidates: Vec<(String, usize)> = counts
+        .iter()
+        .filter(|(_, count)| **count >= MIN_DICTIONARY_REPEATS)
+        .map(|(s, count)| (s.clone(), *count))
+        .collect();
+    candidates.sort_by(|(a, a_count), (b, b_count)| {
+        let saving = |s: &str, count: usize| s.chars().count() * count;
+        saving(b, *b_count).cmp(&saving(a, *a_count)).then(a.cmp(b))
+    });
+
+    let mut by_value: HashMap<String, String> = HashMap::new();
+    let mut next = 0;
+    for (s, count) in candidates {
+        let token = loop {
+            let token = format!("@{}", next);
+            if !counts.contains_key(&token) {
+                break token;
+            }
+            next += 1;
+        };
+        let (len, token_len) = (s.chars().count(), token.chars().count());
+        // The legend repeats the value and the token once more.
+        if count * len <= count * token_len + len + token_len {
+            continue;
+        }
+        next += 1;
+        by_value.insert(s, token);
+    }
+
+    replace_strings(value, &by_value);
+    by_value.into_iter().map(|(s, token)| (token, s)).collect()
+}
+
+fn count_strings(value: &Value, counts: &mut HashMap<String, usize>) {
+    match value {
+        Value::String(s) => *counts.entry(s.clone()).or_default() += 1,
+        Value::Array(items) => items.iter().for_each(|v| count_strings(v, counts)),
+        Value::Object(map) => map.values().for_each(|v| count_strings(v, counts)),
+        _ => {}
+    }
+}
+
+fn replace_strings(value: &mut Value, replacements: &HashMap<String, String>) {
+    match value {
+        Value::String(s) => {
+            if let Some(replacement) = replacements.get(s.as_str()) {
+                *s = replacement.clone();
+            }
+        }
+        Value::Array(items) => items
+            .iter_mut()
+            .for_each(|v| replace_strings(v, replacements)),
+        Value::Object(map) => map
+            .values_mut()
+            .for_each(|v| replace_strings(v, replacements)),
+        _ => {}
+    }
+}
+
+/// Expand the tokens written by [`encode_dictionary`].
+pub fn decode_dictionary(value: &mut Value, legend: &BTreeMap<String, String>) {
+    let replacements = legend.iter().map(|(t, s)| (t.clone(), s.clone())).collect();
+    replace_strings(value, &replacements);
+}
+
+/// Write booleans and nulls as the strings given in `literals`.
+///
+/// Fails when a literal is empty, two literals are the same, or the document
+/// already contains a string equal to one, since decoding could not tell
+/// them apart.
+pub fn encode_literals(value: &mut Value, literals: &Literals) -> Result<(), ToonCoreError> {
+    let spellings: Vec<&str> = [&literals.true_as, &literals.false_as, &literals.null_as]
+        .into_iter()
+        .flatten()
+        .map(String::as_str)
+        .collect();
+    for (i, spelling) in spellings.iter().enumerate() {
+        if spelling.is_empty() || spellings[..i].contains(spelling) {
+            return Err(ToonCoreError::InvalidOption(
+                "literals must be non-empty and distinct".to_string(),
+            ));
+        }
+    }
+    if let Some(clash) = find_string(value, &spellings) {
+        return Err(ToonCoreError::InvalidOption(format!(
+            "literal '{}' also appears as a string in the document",
+            clash
+        )));
+    }
+    replace_literals(value, literals);
+    Ok(())
+}
+
+fn find_string<'a>(value: &Value, spellings: &[&'a str]) -> Option<&'a str> {
+    match value {
+        Value::String(s) => spellings.iter().find(|l| **l == s.as_str()).copied(),
+        Value::Array(items) => items.iter().find_map(|v| find_string(v, spellings)),
+        Value::Object(map) => map.values().find_map(|v| find_string(v, spellings)),
+        _ => None,
+    }
+}
+
+fn replace_literals(value: &mut Value, literals: &Literals) {
+    let spelling = match value {
+        Value::Bool(true) => &literals.true_as,
+        Value::Bool(false) => &literals.false_as,
+        Value::Null => &literals.null_as,
+        Value::Array(items) => {
+            items.iter_mut().for_each(|v| replace_literals(v, literals));
+            return;
+        }
+        Value::Object(map) => {
+            map.values_mut().for_each(|v| replace_literals(v, literals));
+            return;
+        }
+        _ => return,
+    };
+    if let Some(spelling) = spelling {
+        *value = Value::String(spelling.clone());
+    }
+}
+
+/// Turn the strings written by [`encode_literals`] back into booleans and nulls.
+pub fn decode_literals(value: &mut Value, literals: &Literals) {
+    match value {
+        Value::String(s) => {
+            let s = Some(s.as_str());
+            let restored = if literals.true_as.as_deref() == s {
+                Value::Bool(true)
+            } else if literals.false_as.as_deref() == s {
+                Value::Bool(false)
+            } else if literals.null_as.as_deref() == s {
+                Value::Null
+            } else {
+                return;
+            };
+            *value = restored;
+        }
+        Value::Array(items) => items.iter_mut().for_each(|v| decode_literals(v, literals)),
+        Value::Object(map) => map.values_mut().for_each(|v| decode_literals(v, literals)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_codes_use_distinguishing_prefixes() {
+        let values = vec!["active".to_string(), "archived".to_string()];
+        let codes = short_codes(&values);
+        assert_eq!(codes["active"], "ac");
+        assert_eq!(codes["archived"], "ar");
+
+        // No prefix of up to three characters tells these apart.
+        let values = vec!["alpha-1".to_string(), "alpha-2".to_string()];
+        let codes = short_codes(&values);
+        assert_eq!(codes["alpha-1"], "a");
+        assert_eq!(codes["alpha-2"], "b");
+    }
+
+    #[test]
+    fn test_letters() {
+        assert_eq!(letters(0), "a");
+        assert_eq!(letters(25), "z");
+        assert_eq!(letters(26), "aa");
+        assert_eq!(letters(27), "ab");
+    }
+
+    #[test]
+    fn test_shared_prefix_ends_at_path_segment() {
+        let rows = vec![
+            serde_json::json!({"url": "https://api.example.com/v1/users/1"}),
+            serde_json::json!({"url": "https://api.example.com/v1/orders"}),
+            serde_json::json!({"url": null}),
+        ];
+        assert_eq!(
+            shared_prefix(&rows, "url").as_deref(),
+            Some("https://api.example.com/v1/")
+        );
+
+        // A single value or a number in the column disqualifies it.
+        assert_eq!(shared_prefix(&rows[..1], "url"), None);
+        let mixed = vec![
+            serde_json::json!({"url": "https://api.example.com/a"}),
+            serde_json::json!({"url": 7}),
+        ];
+        assert_eq!(shared_prefix(&mixed, "url"), None);
+    }
+}
@@ -0,0 +1,200 @@
+//! Output contracts: a small rule set for checking model-produced tables.
+//!
+//! Full JSON Schema is more than most output checks need. A [`Contract`]
+//! lists the keys every row must have and, per column, a type, a numeric
+//! range, and a pattern. [`check`] applies it to each row of the decoded
+//! document and reports every violation with its path.
+
+use std::collections::HashMap;
+
+use regex::Regex;
+use serde_json::Value;
+
+use super::{diff::type_name, path, ColumnContract, Contract, SchemaViolation, ToonCoreError};
+
+/// Types a column can require.
+const TYPES: &[&str] = &[
+    "string", "number", "integer", "boolean", "null", "array", "object",
+];
+
+/// Check every row of `value` against `contract`.
+///
+/// Rows are those of a root array or of the only field of a root object
+/// holding an array; any other document is checked as a single row. Fails
+/// only when the contract itself is invalid.
+pub fn check(value: &Value, contract: &Contract) -> Result<Vec<SchemaViolation>, ToonCoreError> {
+    let patterns = compile(contract)?;
+    // The table's name, or `None` when the document is a single row.
+    let (table, rows) = match value {
+        Value::Array(items) => (Some(String::new()), items.as_slice()),
+        Value::Object(map) if map.len() == 1 => match map.iter().next() {
+            Some((key, Value::Array(items))) => (Some(key.clone()), items.as_slice()),
+            _ => (None, std::slice::from_ref(value)),
+        },
+        _ => (None, std::slice::from_ref(value)),
+    };
+
+    let mut violations = Vec::new();
+    for (index, row) in rows.iter().enumerate() {
+        let at = match &table {
+            Some(name) => path::index(name, index),
+            None => String::new(),
+        };
+        let Value::Object(row) = row else {
+            violations.push(violation(&at, "row is not an object".to_string()));
+            continue;
+        };
+        for key in &contract.required {
+            if !row.contains_key(key) {
+                violations.push(violation(
+                    &path::key(&at, key),
+                    "missing required key".to_string(),
+                ));
+            }
+        }
+        for (column, cell) in row {
+            let Some(rule) = contract.columns.get(column) else {
+                continue;
+            };
+            if let Some(message) = check_cell(cell, rule, patterns.get(column.as_str())) {
+                violations.push(violation(&path::key(&at, column), message));
+            }
+        }
+    }
+    Ok(violations)
+}
+
+fn compile(contract: &Contract) -> Result<HashMap<&str, Regex>, ToonCoreError> {
+    let mut patterns = HashMap::new();
+    for (column, rule) in &contract.columns {
+        if let Some(kind) = &rule.kind {
+            if !TYPES.contains(&kind.as_str()) {
+                return Err(ToonCoreError::InvalidOption(format!(
+                    "contract type '{}' for column '{}' (expected one of: {})",
+                    kind,
+                    column,
+                    TYPES.join(", ")
+                )));
+            }
+        }
+        if let Some(pattern) = &rule.pattern {
+            let regex = Regex::new(pattern).map_err(|e| {
+                ToonCoreError::InvalidOption(format!(
+                    "contract pattern for column '{}': {}",
+                    column, e
+                ))
+            })?;
+            patterns.insert(column.as_str(), regex);
+        }
+    }
+    Ok(patterns)
+}
+
+/// Why `cell` breaks `rule`, if it does.
+fn check_cell(cell: &Value, rule: &ColumnContract, pattern: Option<&Regex>) -> Option<String> {
+    if cell.is_null() {
+        let allowed = rule.nullable.unwrap_or(false) || rule.kind.as_deref() == Some("null");
+        return (!allowed).then(|| "value is null".to_string());
+    }
+    if let Some(kind) = rule.kind.as_deref() {
+        let matches = match kind {
+            "integer" => cell.as_i64().is_some() || cell.as_u64().is_some(),
+            kind => type_name(cell) == kind,
+        };
+        if !matches {
+            return Some(format!("expected {}, found {}", kind, type_name(cell)));
+        }
+    }
+    if let Some(number) = cell.as_f64() {
+        if let Some(min) = rule.min.filter(|min| number < *min) {
+            return Some(format!("{} is below the minimum {}", cell, min));
+        }
+        if let Some(max) = rule.max.filter(|max| number > *max) {
+            return Some(format!("{} is above the maximum {}", cell, max));
+        }
+    }
+    if let Some(pattern) = pattern {
+        let text = match cell {
+            Value::String(text) => text.clone(),
+            other => other.to_string(),
+        };
+        if !pattern.is_match(&text) {
+            return Some(format!("{} does not match /{}/", cell, pattern.as_str()));
+        }
+    }
+    None
+}
+
+fn violation(path: &str, message: String) -> SchemaViolation {
+    SchemaViolation {
+        path: path.to_string(),
+        message,
+        suggestion: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn contract(json: Value) -> Contract {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn test_check_reports_each_violation_by_path() {
+        let rows = json!({"users": [
+            {"id": 1, "email": "alice@example.com", "age": 30},
+            {"id": "2", "email": "bob", "age": 130},
+            {"email": null}
+        ]});
+        let contract = contract(json!({
+            "required": ["id", "email"],
+            "columns": {
+                "id": {"type": "integer"},
+                "email": {"type": "string", "pattern": "^[^@]+@[^@]+$"},
+                "age": {"type": "integer", "min": 0, "max": 120}
+            }
+        }));
+
+        let violations: Vec<(String, String)> = check(&rows, &contract)
+            .unwrap()
+            .into_iter()
+            .map(|v| (v.path, v.message))
+            .collect();
+        assert_eq!(
+            violations,
+            vec![
+                (
+                    "users[1].id".to_string(),
+                    "expected integer, found string".to_string()
+                ),
+                (
+                    "users[1].email".to_string(),
+                    "\"bob\" does not match /^[^@]+@[^@]+$/".to_string()
+                ),
+                (
+                    "users[1].age".to_string(),
+                    "130 is above the maximum 120".to_string()
+                ),
+                (
+                    "users[2].id".to_string(),
+                    "missing required key".to_string()
+                ),
+                ("users[2].email".to_string(), "value is null".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_single_object_and_invalid_contracts() {
+        let nullable = contract(json!({"columns": {"name": {"nullable": true}}}));
+        assert!(check(&json!({"name": null}), &nullable).unwrap().is_empty());
+
+        let bad_type = contract(json!({"columns": {"a": {"type": "float"}}}));
+        assert!(check(&json!([]), &bad_type).is_err());
+        let bad_pattern = contract(json!({"columns": {"a": {"pattern": "("}}}));
+        assert!(check(&json!([]), &bad_pattern).is_err());
+    }
+}
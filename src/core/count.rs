@@ -0,0 +1,138 @@
+//! Byte and token counts of text that is never stored.
+//!
+//! Measuring a document's JSON rendering used to mean serializing it into a
+//! string first, which for a large payload costs as much memory as the
+//! payload itself. [`CountingWriter`] receives the serializer's output and
+//! keeps only the counts.
+
+use std::io::{self, Write};
+
+use serde_json::Value;
+
+use super::ToonCoreError;
+
+/// Incremental form of [`estimate_tokens`](super::estimate_tokens): words of
+/// alphanumerics and underscores count once, every other non-whitespace
+/// character counts once.
+#[derive(Debug, Default, Clone)]
+pub struct TokenCounter {
+    count: usize,
+    in_word: bool,
+}
+
+impl TokenCounter {
+    /// Count the tokens in `text`, continuing a word from the previous call.
+    pub fn push_str(&mut self, text: &str) {
+        for c in text.chars() {
+            if c.is_alphanumeric() || c == '_' {
+                if !self.in_word {
+                    self.count += 1;
+                    self.in_word = true;
+                }
+            } else {
+                self.in_word = false;
+                if !c.is_whitespace() {
+                    self.count += 1;
+                }
+            }
+        }
+    }
+
+    /// Tokens counted so far.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+/// Writer counting the bytes and tokens of the UTF-8 text written to it.
+#[derive(Debug, Default)]
+pub struct CountingWriter {
+    bytes: usize,
+    tokens: TokenCounter,
+    /// Start of a character split across writes
+    pending: Vec<u8>,
+}
+
+impl CountingWriter {
+    /// Bytes and tokens written so far.
+    pub fn counts(&self) -> (usize, usize) {
+        (self.bytes, self.tokens.count())
+    }
+}
+
+impl Write for CountingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.bytes += buf.len();
+        let joined;
+        let mut text = buf;
+        if !self.pending.is_empty() {
+            joined = [std::mem::take(&mut self.pending).as_slice(), buf].concat();
+            text = &joined;
+        }
+        match std::str::from_utf8(text) {
+            Ok(valid) => self.tokens.push_str(valid),
+            Err(e) if e.error_len().is_none() => {
+                let (valid, rest) = text.split_at(e.valid_up_to());
+                // Checked by `valid_up_to`.
+                self.tokens
+                    .push_str(std::str::from_utf8(valid).unwrap_or_default());
+                self.pending = rest.to_vec();
+            }
+            Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Bytes and tokens of `text`.
+pub fn text(text: &str) -> (usize, usize) {
+    let mut tokens = TokenCounter::default();
+    tokens.push_str(text);
+    (text.len(), tokens.count())
+}
+
+/// Bytes and tokens of `value` serialized as compact or pretty JSON, without
+/// building the string.
+pub fn json(value: &Value, pretty: bool) -> Result<(usize, usize), ToonCoreError> {
+    let mut writer = CountingWriter::default();
+    let result = if pretty {
+        serde_json::to_writer_pretty(&mut writer, value)
+    } else {
+        serde_json::to_writer(&mut writer, value)
+    };
+    result.map_err(|e| ToonCoreError::SerializationError(e.to_string()))?;
+    Ok(writer.counts())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_counts_match_serialized_strings() {
+        let value = json!({"users": [{"id": 1, "name": "Zoë", "tags": ["a b", "日本"]}]});
+        for pretty in [false, true] {
+            let rendered = if pretty {
+                serde_json::to_string_pretty(&value).unwrap()
+            } else {
+                serde_json::to_string(&value).unwrap()
+            };
+            assert_eq!(json(&value, pretty).unwrap(), text(&rendered));
+        }
+    }
+
+    #[test]
+    fn test_characters_split_across_writes() {
+        let mut writer = CountingWriter::default();
+        let bytes = "ab日本".as_bytes();
+        for byte in bytes {
+            writer.write_all(std::slice::from_ref(byte)).unwrap();
+        }
+        assert_eq!(writer.counts(), (bytes.len(), 1));
+    }
+}
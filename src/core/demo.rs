@@ -0,0 +1,151 @@
+//! Built-in sample datasets (`toon_demo_data`, `GET /api/v1/demo/{name}`).
+//!
+//! A few small files from `src/core/demo/` are compiled into the binary, so
+//! new users and the playground can try conversions without hunting for
+//! data: a CSV table, a table with list columns, and nested records.
+
+use serde_json::{Map, Value};
+
+use super::{
+    encode_json, DemoDataRequest, DemoDataResponse, DemoDataset, DemoListResponse, ToonCoreError,
+};
+
+struct Entry {
+    name: &'static str,
+    description: &'static str,
+    format: &'static str,
+    source: &'static str,
+    raw: &'static str,
+}
+
+const DATASETS: &[Entry] = &[
+    Entry {
+        name: "iris",
+        description: "Flower measurements, 10 per species: a flat table of numbers, TOON's best case",
+        format: "csv",
+        source: "Fisher's Iris data set (1936), first 10 rows of each species; public domain",
+        raw: include_str!("demo/iris.csv"),
+    },
+    Entry {
+        name: "countries",
+        description: "Countries with capitals, areas, and languages: a table whose list column keeps it from being tabular",
+        format: "json",
+        source: "ISO 3166-1 codes and public reference data",
+        raw: include_str!("demo/countries.json"),
+    },
+    Entry {
+        name: "orders",
+        description: "Store orders with a customer and line items each: nested records, try nested_tables and fold_keys",
+        format: "json",
+        source: "Synthetic",
+        raw: include_str!("demo/orders.json"),
+    },
+];
+
+/// Dataset names, in listing order.
+pub const NAMES: &[&str] = &["iris", "countries", "orders"];
+
+/// Describe every dataset.
+pub fn list() -> DemoListResponse {
+    DemoListResponse {
+        datasets: DATASETS.iter().map(describe).collect(),
+    }
+}
+
+/// Load a dataset and encode it.
+pub fn load(request: &DemoDataRequest) -> Result<DemoDataResponse, ToonCoreError> {
+    let entry = DATASETS
+        .iter()
+        .find(|entry| entry.name == request.dataset.trim())
+        .ok_or_else(|| {
+            ToonCoreError::InvalidOption(format!(
+                "unknown demo dataset '{}' (expected {})",
+                request.dataset,
+                NAMES.join(", ")
+            ))
+        })?;
+    let json = match entry.format {
+        "csv" => csv_rows(entry.raw),
+        _ => serde_json::from_str(entry.raw)
+            .map_err(|e| ToonCoreError::InvalidJson(e.to_string()))?,
+    };
+    Ok(DemoDataResponse {
+        dataset: describe(entry),
+        raw: entry.raw.to_string(),
+        toon: encode_json(&json, &request.encode_options)?,
+        json,
+    })
+}
+
+fn describe(entry: &Entry) -> DemoDataset {
+    DemoDataset {
+        name: entry.name.to_string(),
+        description: entry.description.to_string(),
+        format: entry.format.to_string(),
+        source: entry.source.to_string(),
+    }
+}
+
+/// Rows of a CSV file without quoted fields, as objects keyed by the header.
+/// Cells that parse as JSON numbers become numbers.
+fn csv_rows(text: &str) -> Value {
+    let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+    let header: Vec<&str> = lines.next().unwrap_or_default().split(',').collect();
+    let rows = lines
+        .map(|line| {
+            let row: Map<String, Value> = header
+                .iter()
+                .zip(line.split(','))
+                .map(|(column, cell)| {
+                    let value = match serde_json::from_str::<serde_json::Number>(cell) {
+                        Ok(number) => Value::Number(number),
+                        Err(_) => Value::String(cell.to_string()),
+                    };
+                    (column.to_string(), value)
+                })
+                .collect();
+            Value::Object(row)
+        })
+        .collect();
+    Value::Array(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn request(dataset: &str) -> DemoDataRequest {
+        DemoDataRequest {
+            dataset: dataset.to_string(),
+            encode_options: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_every_dataset_loads() {
+        let names: Vec<&str> = DATASETS.iter().map(|entry| entry.name).collect();
+        assert_eq!(names, NAMES);
+        for name in NAMES {
+            let response = load(&request(name)).unwrap();
+            assert!(!response.toon.is_empty(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_csv_becomes_rows() {
+        let iris = load(&request("iris")).unwrap();
+        assert_eq!(iris.json.as_array().unwrap().len(), 30);
+        assert_eq!(
+            iris.json[0],
+            json!({"sepal_length": 5.1, "sepal_width": 3.5, "petal_length": 1.4, "petal_width": 0.2, "species": "setosa"})
+        );
+        assert!(iris.toon.contains("[30]{sepal_length,"));
+    }
+
+    #[test]
+    fn test_unknown_dataset() {
+        let err = load(&request("titanic")).unwrap_err();
+        assert!(err.to_string().contains("iris, countries, orders"));
+    }
+}
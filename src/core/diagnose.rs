@@ -0,0 +1,207 @@
+//! Reasons a document gains little from TOON.
+//!
+//! TOON saves the most on uniform arrays of flat objects, where keys are
+//! written once per table instead of once per row. [`diagnose`] looks for the
+//! shapes that defeat this and estimates how many tokens each one costs.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use super::{estimate_tokens, Diagnosis};
+
+/// Nesting depth from which indentation starts to add up.
+const DEEP_NESTING: usize = 4;
+
+/// Length in characters from which a string counts as long text.
+const LONG_TEXT_CHARS: usize = 200;
+
+/// Number of keys used only once from which a document counts as key-heavy.
+const MANY_UNIQUE_KEYS: usize = 50;
+
+#[derive(Default)]
+struct Findings<'a> {
+    non_uniform_arrays: usize,
+    non_uniform_rows: usize,
+    non_uniform_tokens: usize,
+    deep_values: usize,
+    deep_tokens: usize,
+    max_depth: usize,
+    long_texts: usize,
+    opaque_texts: usize,
+    long_text_tokens: usize,
+    keys: HashMap<&'a str, usize>,
+}
+
+/// Causes of poor savings found in `json`, costliest first.
+pub fn diagnose(json: &Value) -> Vec<Diagnosis> {
+    let mut findings = Findings::default();
+    walk(json, 0, &mut findings);
+
+    let mut out = Vec::new();
+    if findings.non_uniform_arrays > 0 {
+        out.push(Diagnosis {
+            cause: "non_uniform_rows".to_string(),
+            detail: format!(
+                "{} arrays of objects ({} rows) cannot use the tabular layout because their \
+                 rows have different keys or nested values, so every row repeats its keys",
+                findings.non_uniform_arrays, findings.non_uniform_rows
+            ),
+            estimated_tokens: findings.non_uniform_tokens,
+            suggestion: "Give every row the same keys (null for missing values) and move nested \
+                         objects and arrays into tables of their own."
+                .to_string(),
+            options: Vec::new(),
+        });
+    }
+    if findings.deep_values > 0 {
+        out.push(Diagnosis {
+            cause: "deep_nesting".to_string(),
+            detail: format!(
+                "{} values are nested {} or more levels deep (up to {}); each level adds \
+                 indentation to every line below it",
+                findings.deep_values, DEEP_NESTING, findings.max_depth
+            ),
+            estimated_tokens: findings.deep_tokens,
+            suggestion: "Flatten single-key wrapper objects, or fold them into dotted keys."
+                .to_string(),
+            options: vec!["fold_keys".to_string()],
+        });
+    }
+    if findings.long_texts > 0 {
+        let mut options = Vec::new();
+        if findings.opaque_texts > 0 {
+            options.push("blob_policy".to_string());
+        }
+        out.push(Diagnosis {
+            cause: "long_text".to_string(),
+            detail: format!(
+                "{} strings of {} or more characters ({} without whitespace, such as base64) \
+                 cost the same in any format",
+                findings.long_texts, LONG_TEXT_CHARS, findings.opaque_texts
+            ),
+            estimated_tokens: findings.long_text_tokens,
+            suggestion: "Leave out, shorten, or summarize long text the model does not need."
+                .to_string(),
+            options,
+        });
+    }
+    let unique: Vec<&str> = findings
+        .keys
+        .iter()
+        .filter(|(_, count)| **count == 1)
+        .map(|(key, _)| *key)
+        .collect();
+    if unique.len() >= MANY_UNIQUE_KEYS {
+        out.push(Diagnosis {
+            cause: "many_unique_keys".to_string(),
+            detail: format!(
+                "{} keys are used only once, as in objects keyed by ID; none of them can be \
+                 shared by a table header",
+                unique.len()
+            ),
+            estimated_tokens: unique.iter().map(|key| estimate_tokens(key) + 1).sum(),
+            suggestion: "Turn objects keyed by ID into arrays of rows with the ID as a column."
+                .to_string(),
+            options: Vec::new(),
+        });
+    }
+
+    out.sort_by_key(|d| Reverse(d.estimated_tokens));
+    out
+}
+
+fn walk<'a>(value: &'a Value, depth: usize, findings: &mut Findings<'a>) {
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map {
+                *findings.keys.entry(key.as_str()).or_default() += 1;
+                let child_depth = depth + 1;
+                if child_depth >= DEEP_NESTING && !is_container(child) {
+                    findings.deep_values += 1;
+                    // About one token of indentation per level past the threshold.
+                    findings.deep_tokens += child_depth - DEEP_NESTING + 1;
+                    findings.max_depth = findings.max_depth.max(child_depth);
+                }
+                walk(child, child_depth, findings);
+            }
+        }
+        Value::Array(items) => {
+            if items.len() >= 2 && items.iter().all(Value::is_object) && !is_tabular(items) {
+                findings.non_uniform_arrays += 1;
+                findings.non_uniform_rows += items.len();
+                // A table would write the keys once instead of once per row.
+                findings.non_uniform_tokens += items[1..]
+                    .iter()
+                    .filter_map(Value::as_object)
+                    .flat_map(|row| row.keys())
+                    .map(|key| estimate_tokens(key) + 1)
+                    .sum::<usize>();
+            }
+            for item in items {
+                walk(item, depth + 1, findings);
+            }
+        }
+        Value::String(s) if s.chars().count() >= LONG_TEXT_CHARS => {
+            findings.long_texts += 1;
+            findings.long_text_tokens += estimate_tokens(s);
+            if !s.chars().any(char::is_whitespace) {
+                findings.opaque_texts += 1;
+            }
+        }
+        _ => {}
+    }
+}
+
+fn is_container(value: &Value) -> bool {
+    value.is_object() || value.is_array()
+}
+
+/// Whether `rows` share one key set and hold only primitive values.
+fn is_tabular(rows: &[Value]) -> bool {
+    let Some(first) = rows.first().and_then(Value::as_object) else {
+        return false;
+    };
+    rows.iter().filter_map(Value::as_object).all(|row| {
+        row.len() == first.len()
+            && row.keys().all(|key| first.contains_key(key))
+            && !row.values().any(is_container)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_uniform_tables_need_no_diagnosis() {
+        let json = json!({"users": [{"id": 1, "name": "a"}, {"name": "b", "id": 2}]});
+        assert!(diagnose(&json).is_empty());
+    }
+
+    #[test]
+    fn test_non_uniform_rows() {
+        let json = json!({"events": [
+            {"id": 1, "type": "click"},
+            {"id": 2, "type": "view", "extra": "x"},
+            {"id": 3, "type": "click", "tags": ["x"]}
+        ]});
+        let found = diagnose(&json);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].cause, "non_uniform_rows");
+        // "id:", "type:", "extra:" in row 2 and "id:", "type:", "tags:" in row 3.
+        assert_eq!(found[0].estimated_tokens, 12);
+    }
+
+    #[test]
+    fn test_deep_nesting_and_long_text() {
+        let json = json!({"a": {"b": {"c": {"d": 1, "e": "x".repeat(300)}}}});
+        let found = diagnose(&json);
+        let causes: Vec<&str> = found.iter().map(|d| d.cause.as_str()).collect();
+        assert!(causes.contains(&"deep_nesting"));
+        let long_text = found.iter().find(|d| d.cause == "long_text").unwrap();
+        assert_eq!(long_text.options, vec!["blob_policy"]);
+    }
+}
@@ -0,0 +1,119 @@
+//! Structural diff of JSON documents.
+//!
+//! Objects are compared by key and arrays by index, producing one
+//! [`Difference`] per leaf that was changed, added, or removed.
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use super::{path, suggest, Difference, SchemaViolation};
+
+/// Compare `before` against `after` and list every difference.
+pub fn diff_values(before: &Value, after: &Value) -> Vec<Difference> {
+    let mut out = Vec::new();
+    walk(before, after, "", &mut out);
+    out
+}
+
+fn walk(before: &Value, after: &Value, at: &str, out: &mut Vec<Difference>) {
+    match (before, after) {
+        (Value::Object(a), Value::Object(b)) => {
+            for (key, left) in a {
+                let child = path::key(at, key);
+                match b.get(key) {
+                    Some(right) => walk(left, right, &child, out),
+                    None => out.push(Difference::removed(child, left)),
+                }
+            }
+            for (key, right) in b {
+                if !a.contains_key(key) {
+                    out.push(Difference::added(path::key(at, key), right));
+                }
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            for (i, left) in a.iter().enumerate() {
+                let child = path::index(at, i);
+                match b.get(i) {
+                    Some(right) => walk(left, right, &child, out),
+                    None => out.push(Difference::removed(child, left)),
+                }
+            }
+            for (i, right) in b.iter().enumerate().skip(a.len()) {
+                out.push(Difference::added(path::index(at, i), right));
+            }
+        }
+        (a, b) if a == b => {}
+        (a, b) => out.push(Difference::changed(at.to_string(), a, b)),
+    }
+}
+
+/// JSON type name of a value, as used in violation messages.
+pub fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Derive shape violations from a diff against the original document.
+///
+/// Value edits are expected when a model modifies data; changing a field's
+/// type, inventing keys, or dropping keys is not. Added or removed array
+/// elements are not violations. An unexpected key close to a missing key of
+/// the same object, as left by a typo, suggests the missing one.
+pub fn shape_violations(differences: &[Difference]) -> Vec<SchemaViolation> {
+    let mut missing: HashMap<&str, Vec<&str>> = HashMap::new();
+    for d in differences {
+        if d.kind == "removed" && !d.path.ends_with(']') && !d.path.is_empty() {
+            let (parent, key) = split_key(&d.path);
+            missing.entry(parent).or_default().push(key);
+        }
+    }
+
+    differences
+        .iter()
+        .filter_map(|d| {
+            let is_key = !d.path.ends_with(']') && !d.path.is_empty();
+            let message = match (d.kind.as_str(), &d.before, &d.after) {
+                ("changed", Some(before), Some(after))
+                    if type_name(before) != type_name(after) && !after.is_null() =>
+                {
+                    format!(
+                        "type changed from {} to {}",
+                        type_name(before),
+                        type_name(after)
+                    )
+                }
+                ("added", _, _) if is_key => "unexpected key".to_string(),
+                ("removed", _, _) if is_key => "missing key".to_string(),
+                _ => return None,
+            };
+            let suggestion = match d.kind.as_str() {
+                "added" => {
+                    let (parent, key) = split_key(&d.path);
+                    missing
+                        .get(parent)
+                        .and_then(|keys| suggest::did_you_mean(key, keys.iter().copied()))
+                }
+                _ => None,
+            };
+            Some(SchemaViolation {
+                path: d.path.clone(),
+                message,
+                suggestion,
+            })
+        })
+        .collect()
+}
+
+/// Split a key path into its parent's path and the key. Keys containing a
+/// dot are split at their last dot.
+fn split_key(path: &str) -> (&str, &str) {
+    path.rsplit_once('.').unwrap_or(("", path))
+}
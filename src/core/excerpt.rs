@@ -0,0 +1,126 @@
+//! Source excerpts for parse errors.
+//!
+//! "line 48213 column 7" is hard to act on in a document too big to open.
+//! [`locate`] adds the row of the enclosing table the line belongs to and a
+//! snippet quoting the offending line, and the one before it, with a caret
+//! under the column.
+
+use super::ToonCoreError;
+
+/// Add the table row and a source snippet to a parse error in `source`.
+///
+/// Other errors, and positions outside `source`, are returned unchanged.
+pub fn locate(error: ToonCoreError, source: &str) -> ToonCoreError {
+    let ToonCoreError::ParseError {
+        message,
+        line,
+        column,
+        suggestion,
+        row,
+        snippet,
+    } = error
+    else {
+        return error;
+    };
+    let lines: Vec<&str> = source.lines().collect();
+    if line == 0 || line > lines.len() {
+        return ToonCoreError::ParseError {
+            message,
+            line,
+            column,
+            suggestion,
+            row,
+            snippet,
+        };
+    }
+
+    let start = line.saturating_sub(1).max(1);
+    let context: Vec<(usize, &str)> = (start..=line).map(|n| (n, lines[n - 1])).collect();
+    ToonCoreError::ParseError {
+        message,
+        line,
+        column,
+        suggestion,
+        row: row.or_else(|| table_row(&lines, line - 1)),
+        snippet: Some(render(&context, column)),
+    }
+}
+
+/// Number `lines` in a gutter and put a caret under `column` (1-based) of
+/// the last one.
+pub fn render(lines: &[(usize, &str)], column: usize) -> String {
+    let width = lines.last().map_or(1, |(n, _)| n.to_string().len());
+    let mut out = String::new();
+    for (number, text) in lines {
+        out.push_str(&format!("{:>width$} | {}\n", number, text, width = width));
+    }
+    out.push_str(&format!(
+        "{:>width$} | {}^",
+        "",
+        " ".repeat(column.saturating_sub(1)),
+        width = width
+    ));
+    out
+}
+
+/// 1-based row of the table whose header precedes `index`, when the line at
+/// `index` is one of that table's rows.
+fn table_row(lines: &[&str], index: usize) -> Option<usize> {
+    let indent = |line: &str| line.len() - line.trim_start().len();
+    let depth = indent(lines[index]);
+    let header = (0..index)
+        .rev()
+        .find(|&i| !lines[i].trim().is_empty() && indent(lines[i]) < depth)?;
+    let text = lines[header].trim();
+    if !text.ends_with("}:") || !text.contains("]{") {
+        return None;
+    }
+    let rows = lines[header + 1..=index]
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .count();
+    Some(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_error(line: usize, column: usize) -> ToonCoreError {
+        ToonCoreError::ParseError {
+            message: "bad value".to_string(),
+            line,
+            column,
+            suggestion: None,
+            row: None,
+            snippet: None,
+        }
+    }
+
+    #[test]
+    fn test_locate_reports_row_and_snippet() {
+        let source = "meta: 1\nusers[3]{id,name}:\n  1,Alice\n  2,\"Bob\n  3,Carol";
+        let ToonCoreError::ParseError { row, snippet, .. } = locate(parse_error(4, 5), source)
+        else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(row, Some(2));
+        assert_eq!(snippet.unwrap(), "3 |   1,Alice\n4 |   2,\"Bob\n  |     ^");
+    }
+
+    #[test]
+    fn test_locate_outside_tables() {
+        let ToonCoreError::ParseError { row, snippet, .. } =
+            locate(parse_error(1, 1), "name: \"Alice")
+        else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(row, None);
+        assert_eq!(snippet.unwrap(), "1 | name: \"Alice\n  | ^");
+
+        let ToonCoreError::ParseError { snippet, .. } = locate(parse_error(9, 1), "a: 1") else {
+            panic!("expected a parse error");
+        };
+        assert!(snippet.is_none());
+    }
+}
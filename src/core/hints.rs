@@ -0,0 +1,231 @@
+//! Column type hints for tabular TOON.
+//!
+//! With hints enabled, every table header is followed by a row naming the
+//! type of each column, e.g. `# int,str,ts`. Models see which columns hold
+//! numbers and which hold text, and decoding with hints uses the row to
+//! quote or unquote cells the model wrote with the wrong type.
+//!
+//! Hint rows are not part of the TOON format: other decoders reject them
+//! (the row count no longer matches), so only decode hinted output here
+//! with `type_hints` enabled.
+
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+/// Prefix marking a hint row.
+const HINT_PREFIX: &str = "# ";
+
+/// Tabular header: optional list marker and key, `[N<delimiter>]{fields}:`.
+fn header_regex() -> &'static Regex {
+    static HEADER: OnceLock<Regex> = OnceLock::new();
+    HEADER.get_or_init(|| {
+        Regex::new(r#"^\s*(?:- )?(?:"(?:[^"\\]|\\.)*"|[^\[\s"]*)\[(\d+)([\t|]?)\]\{.*\}:$"#)
+            .expect("table header pattern must compile")
+    })
+}
+
+fn timestamp_regex() -> &'static Regex {
+    static TIMESTAMP: OnceLock<Regex> = OnceLock::new();
+    TIMESTAMP.get_or_init(|| {
+        Regex::new(
+            r"^\d{4}-\d{2}-\d{2}(?:[T ]\d{2}:\d{2}(?::\d{2}(?:\.\d+)?)?(?:Z|[+-]\d{2}:?\d{2})?)?$",
+        )
+        .expect("timestamp pattern must compile")
+    })
+}
+
+/// A table header: its row count and cell delimiter.
+fn table_header(line: &str) -> Option<(usize, char)> {
+    let caps = header_regex().captures(line)?;
+    let rows = caps[1].parse().ok()?;
+    let delimiter = caps[2].chars().next().unwrap_or(',');
+    Some((rows, delimiter))
+}
+
+/// Split a row into cells on `delimiter`, leaving quoted cells intact.
+fn cells(row: &str, delimiter: char) -> Vec<&str> {
+    let mut out = Vec::new();
+    let (mut start, mut quoted, mut escaped) = (0, false, false);
+    for (i, c) in row.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            c if c == delimiter && !quoted => {
+                out.push(&row[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    out.push(&row[start..]);
+    out
+}
+
+fn unquote(cell: &str) -> Option<&str> {
+    cell.strip_prefix('"')?.strip_suffix('"')
+}
+
+/// Type of one cell as written; `None` for null.
+fn cell_type(cell: &str) -> Option<&'static str> {
+    let cell = cell.trim();
+    if cell == "null" {
+        return None;
+    }
+    let (text, quoted) = match unquote(cell) {
+        Some(inner) => (inner, true),
+        None => (cell, false),
+    };
+    Some(if timestamp_regex().is_match(text) {
+        "ts"
+    } else if quoted {
+        "str"
+    } else if cell == "true" || cell == "false" {
+        "bool"
+    } else if cell.parse::<i64>().is_ok() {
+        "int"
+    } else if cell.starts_with(|c: char| c.is_ascii_digit() || c == '-')
+        && cell.parse::<f64>().is_ok()
+    {
+        "float"
+    } else {
+        "str"
+    })
+}
+
+/// Combined type of a column's cells.
+fn column_type(types: impl Iterator<Item = Option<&'static str>>) -> &'static str {
+    let mut column: Option<&'static str> = None;
+    for ty in types.flatten() {
+        column = Some(match (column, ty) {
+            (None, ty) => ty,
+            (Some(a), b) if a == b => a,
+            (Some("int"), "float") | (Some("float"), "int") => "float",
+            _ => return "any",
+        });
+    }
+    column.unwrap_or("null")
+}
+
+/// Insert a type hint row after every table header in `toon`.
+pub fn add(toon: &str) -> String {
+    let lines: Vec<&str> = toon.lines().collect();
+    let mut out = Vec::with_capacity(lines.len());
+    for (i, line) in lines.iter().enumerate() {
+        out.push(line.to_string());
+        if let Some((count, delimiter)) = table_header(line) {
+            let rows: Vec<&str> = lines[i + 1..].iter().take(count).copied().collect();
+            if let Some(first) = rows.first() {
+                let indent = &first[..first.len() - first.trim_start().len()];
+                let split: Vec<Vec<&str>> = rows
+                    .iter()
+                    .map(|row| cells(row.trim_start(), delimiter))
+                    .collect();
+                let width = split[0].len();
+                let types: Vec<&str> = (0..width)
+                    .map(|col| {
+                        column_type(
+                            split
+                                .iter()
+                                .map(|r| r.get(col).copied().and_then(cell_type)),
+                        )
+                    })
+                    .collect();
+                out.push(format!(
+                    "{}{}{}",
+                    indent,
+                    HINT_PREFIX,
+                    types.join(&delimiter.to_string())
+                ));
+            }
+        }
+    }
+    out.join("\n")
+}
+
+/// Remove hint rows from `toon`, rewriting each cell to match its column's
+/// hinted type: `str`/`ts` cells are quoted, and quoted numbers and booleans
+/// in `int`/`float`/`bool` columns are unquoted.
+pub fn apply(toon: &str) -> String {
+    let lines: Vec<&str> = toon.lines().collect();
+    let mut out = Vec::with_capacity(lines.len());
+    let mut i = 0;
+    while i < lines.len() {
+        out.push(lines[i].to_string());
+        let header = table_header(lines[i]);
+        i += 1;
+        let Some((count, delimiter)) = header else {
+            continue;
+        };
+        let Some(hint) = lines
+            .get(i)
+            .and_then(|line| line.trim_start().strip_prefix(HINT_PREFIX))
+        else {
+            continue;
+        };
+        let types: Vec<&str> = hint.split(delimiter).map(str::trim).collect();
+        i += 1;
+
+        for row in lines[i..].iter().take(count) {
+            let indent = &row[..row.len() - row.trim_start().len()];
+            let fixed: Vec<String> = cells(row.trim_start(), delimiter)
+                .into_iter()
+                .enumerate()
+                .map(|(col, cell)| coerce(cell, types.get(col).copied().unwrap_or("any")))
+                .collect();
+            out.push(format!("{}{}", indent, fixed.join(&delimiter.to_string())));
+            i += 1;
+        }
+    }
+    out.join("\n")
+}
+
+/// Rewrite one cell so it decodes as `ty`.
+fn coerce(cell: &str, ty: &str) -> String {
+    let trimmed = cell.trim();
+    if trimmed == "null" {
+        return trimmed.to_string();
+    }
+    match (ty, unquote(trimmed)) {
+        ("str" | "ts", None) => {
+            format!("\"{}\"", trimmed.replace('\\', "\\\\").replace('"', "\\\""))
+        }
+        ("int", Some(inner)) if inner.trim().parse::<i64>().is_ok() => inner.trim().to_string(),
+        ("float", Some(inner)) if inner.trim().parse::<f64>().is_ok() => inner.trim().to_string(),
+        ("bool", Some(inner)) if matches!(inner.trim(), "true" | "false") => {
+            inner.trim().to_string()
+        }
+        _ => cell.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cells_respect_quotes() {
+        assert_eq!(cells(r#"1,"a,b",x"#, ','), vec!["1", r#""a,b""#, "x"]);
+        assert_eq!(
+            cells(r#""say \"hi\"",2"#, ','),
+            vec![r#""say \"hi\"""#, "2"]
+        );
+    }
+
+    #[test]
+    fn test_column_types() {
+        assert_eq!(
+            column_type([Some("int"), None, Some("int")].into_iter()),
+            "int"
+        );
+        assert_eq!(
+            column_type([Some("int"), Some("float")].into_iter()),
+            "float"
+        );
+        assert_eq!(column_type([Some("int"), Some("str")].into_iter()), "any");
+        assert_eq!(column_type([None].into_iter()), "null");
+        assert_eq!(cell_type("2024-05-01T10:00:00Z"), Some("ts"));
+        assert_eq!(cell_type("\"42\""), Some("str"));
+    }
+}
@@ -0,0 +1,320 @@
+//! Ready-to-run HTTP API snippets for an operation (`toon_howto`).
+//!
+//! Given a tool name or route and the arguments to send, this renders the
+//! equivalent request as a curl command, a Python `requests` call, and a
+//! TypeScript `fetch` call. Without arguments, the route's entry from the
+//! API examples (`api_examples.json`, next to this file) is used, so the
+//! snippet works as written.
+
+use serde_json::Value;
+
+use super::{ApiExample, HowtoRequest, HowtoResponse, ToonCoreError};
+
+/// Example requests, with paths relative to the API version prefix.
+const EXAMPLES: &str = include_str!("api_examples.json");
+
+/// Server address used when the caller gives none.
+pub const DEFAULT_BASE_URL: &str = "http://localhost:8080";
+
+/// Version prefix snippets target; v1 is deprecated.
+const API_PREFIX: &str = "/api/v2";
+
+/// Routes served outside the version prefix.
+const UNVERSIONED: &[&str] = &["/rpc"];
+
+/// API routes: path below the version prefix, method, and matching MCP tool.
+const ROUTES: &[(&str, &str, Option<&str>)] = &[
+    ("capabilities", "GET", None),
+    ("examples", "GET", None),
+    ("demo", "GET", None),
+    ("demo/{name}", "GET", None),
+    ("encode", "POST", Some("toon_encode")),
+    ("decode", "POST", Some("toon_decode")),
+    ("decode/stream", "POST", None),
+    ("validate", "POST", Some("toon_validate")),
+    ("format/range", "POST", None),
+    ("stats", "POST", Some("toon_stats")),
+    ("stats/decode", "POST", Some("toon_stats_decode")),
+    ("assert_savings", "POST", None),
+    ("hash", "POST", Some("toon_hash")),
+    ("cache_analysis", "POST", Some("toon_cache_analysis")),
+    ("verify_roundtrip", "POST", Some("toon_verify_roundtrip")),
+    ("diff_validate", "POST", Some("toon_diff_validate")),
+    ("schema/example", "POST", Some("toon_example_from_schema")),
+    ("schema/template", "POST", Some("toon_schema_template")),
+    ("aggregate", "POST", Some("toon_aggregate")),
+    ("join", "POST", Some("toon_join")),
+    ("pivot", "POST", Some("toon_pivot")),
+    ("anonymize", "POST", Some("toon_anonymize")),
+    ("deanonymize", "POST", Some("toon_deanonymize")),
+    ("sql", "POST", Some("toon_to_sql")),
+    ("sqlite/query", "POST", Some("toon_from_sqlite")),
+    ("postgres/query", "POST", Some("toon_from_postgres")),
+    ("admin/usage", "GET", None),
+    ("admin/latency", "GET", None),
+    ("rpc", "POST", None),
+];
+
+/// API routes as (path below the version prefix, method).
+pub fn routes() -> impl Iterator<Item = (&'static str, &'static str)> {
+    ROUTES.iter().map(|(path, method, _)| (*path, *method))
+}
+
+/// Full path of a route such as "/encode" under `prefix`; unversioned
+/// routes like "/rpc" keep their path.
+pub fn route_path(prefix: &str, route: &str) -> String {
+    if UNVERSIONED.contains(&route) {
+        route.to_string()
+    } else {
+        format!("{}{}", prefix, route)
+    }
+}
+
+/// The API examples, with paths relative to the version prefix.
+pub fn examples() -> Vec<ApiExample> {
+    serde_json::from_str(EXAMPLES).expect("api_examples.json is valid")
+}
+
+/// Render the HTTP request for an operation in curl, Python, and TypeScript.
+pub fn howto(request: &HowtoRequest) -> Result<HowtoResponse, ToonCoreError> {
+    let (mut route, method) = resolve(&request.operation)?;
+    if route.contains('{') {
+        // Fill in path parameters from the route's example.
+        if let Some(example) = examples()
+            .into_iter()
+            .find(|example| matches(&route, example.path.trim_start_matches('/')))
+        {
+            route = example.path.trim_start_matches('/').to_string();
+        }
+    }
+    let base_url = request
+        .base_url
+        .as_deref()
+        .unwrap_or(DEFAULT_BASE_URL)
+        .trim_end_matches('/');
+    let url = format!(
+        "{}{}",
+        base_url,
+        route_path(API_PREFIX, &format!("/{}", route))
+    );
+
+    let body = match method {
+        "GET" => None,
+        _ => Some(match &request.arguments {
+            Some(arguments) => arguments.clone(),
+            None => examples()
+                .into_iter()
+                .find(|example| example.path.trim_start_matches('/') == route)
+                .and_then(|example| example.request)
+                .unwrap_or_else(|| Value::Object(Default::default())),
+        }),
+    };
+
+    Ok(HowtoResponse {
+        curl: curl(method, &url, body.as_ref()),
+        python: python(method, &url, body.as_ref()),
+        typescript: typescript(method, &url, body.as_ref()),
+        method: method.to_string(),
+        url,
+        body,
+    })
+}
+
+/// Find the route for a tool name (`toon_encode`), route (`schema/example`),
+/// or path (`/api/v1/encode`).
+fn resolve(operation: &str) -> Result<(String, &'static str), ToonCoreError> {
+    let name = operation.trim();
+    let route = name
+        .strip_prefix("/api/v1/")
+        .or_else(|| name.strip_prefix("/api/v2/"))
+        .unwrap_or(name)
+        .trim_matches('/');
+    ROUTES
+        .iter()
+        .find(|(path, _, tool)| matches(path, route) || *tool == Some(name))
+        .or_else(|| {
+            // Tool-style names for routes without a tool, e.g. "toon_format_range".
+            let route = route.strip_prefix("toon_").unwrap_or(route);
+            ROUTES
+                .iter()
+                .find(|(path, _, _)| path.replace('/', "_") == route)
+        })
+        .map(|(path, method, _)| match matches(path, route) {
+            true => (route.to_string(), *method),
+            false => (path.to_string(), *method),
+        })
+        .ok_or_else(|| {
+            let known: Vec<&str> = ROUTES.iter().map(|(path, _, _)| *path).collect();
+            ToonCoreError::InvalidOption(format!(
+                "unknown operation '{}' (expected a tool name or one of: {})",
+                operation,
+                known.join(", ")
+            ))
+        })
+}
+
+/// Whether `route` (e.g. "demo/iris") is an instance of `path` (e.g. "demo/{name}").
+fn matches(path: &str, route: &str) -> bool {
+    let path: Vec<&str> = path.split('/').collect();
+    let route: Vec<&str> = route.split('/').collect();
+    path.len() == route.len()
+        && path
+            .iter()
+            .zip(&route)
+            .all(|(p, r)| p == r || (p.starts_with('{') && !r.is_empty()))
+}
+
+fn curl(method: &str, url: &str, body: Option<&Value>) -> String {
+    match body {
+        None => format!("curl {}", url),
+        Some(body) => format!(
+            "curl -X {} {} \\\n  -H 'Content-Type: application/json' \\\n  -d '{}'",
+            method,
+            url,
+            body.to_string().replace('\'', "'\\''")
+        ),
+    }
+}
+
+fn python(method: &str, url: &str, body: Option<&Value>) -> String {
+    let call = match body {
+        None => format!("requests.{}({:?})", method.to_lowercase(), url),
+        Some(body) => format!(
+            "requests.{}(\n    {:?},\n    json={},\n)",
+            method.to_lowercase(),
+            url,
+            python_literal(body, 1)
+        ),
+    };
+    format!(
+        "import requests\n\nresponse = {}\nresponse.raise_for_status()\nprint(response.json())",
+        call
+    )
+}
+
+/// Render JSON as a Python literal, indented by four spaces per level.
+fn python_literal(value: &Value, level: usize) -> String {
+    let indent = "    ".repeat(level + 1);
+    let close = "    ".repeat(level);
+    match value {
+        Value::Null => "None".to_string(),
+        Value::Bool(true) => "True".to_string(),
+        Value::Bool(false) => "False".to_string(),
+        Value::Array(items) if !items.is_empty() => {
+            let items: Vec<String> = items
+                .iter()
+                .map(|item| format!("{}{},\n", indent, python_literal(item, level + 1)))
+                .collect();
+            format!("[\n{}{}]", items.concat(), close)
+        }
+        Value::Object(map) if !map.is_empty() => {
+            let entries: Vec<String> = map
+                .iter()
+                .map(|(key, value)| {
+                    format!(
+                        "{}{}: {},\n",
+                        indent,
+                        Value::String(key.clone()),
+                        python_literal(value, level + 1)
+                    )
+                })
+                .collect();
+            format!("{{\n{}{}}}", entries.concat(), close)
+        }
+        // Strings, numbers, and empty containers read the same in Python as in JSON.
+        other => other.to_string(),
+    }
+}
+
+fn typescript(method: &str, url: &str, body: Option<&Value>) -> String {
+    let init = match body {
+        None => String::new(),
+        Some(body) => {
+            let json = serde_json::to_string_pretty(body)
+                .unwrap_or_default()
+                .replace('\n', "\n  ");
+            format!(
+                ", {{\n  method: \"{}\",\n  headers: {{ \"Content-Type\": \"application/json\" }},\n  body: JSON.stringify({}),\n}}",
+                method, json
+            )
+        }
+    };
+    format!(
+        "const response = await fetch({}{});\nif (!response.ok) throw new Error(`HTTP ${{response.status}}`);\nconsole.log(await response.json());",
+        Value::String(url.to_string()),
+        init
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn howto_for(operation: &str, arguments: Option<Value>) -> HowtoResponse {
+        howto(&HowtoRequest {
+            operation: operation.to_string(),
+            arguments,
+            base_url: None,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_resolves_tools_routes_and_paths() {
+        for operation in ["toon_encode", "encode", "/api/v1/encode"] {
+            assert_eq!(
+                howto_for(operation, None).url,
+                "http://localhost:8080/api/v2/encode"
+            );
+        }
+        assert_eq!(
+            howto_for("toon_example_from_schema", None).url,
+            "http://localhost:8080/api/v2/schema/example"
+        );
+        assert_eq!(howto_for("format_range", None).method, "POST");
+        assert_eq!(
+            howto_for("demo/{name}", None).url,
+            "http://localhost:8080/api/v2/demo/iris"
+        );
+        assert_eq!(
+            howto_for("/api/v1/demo/orders", None).url,
+            "http://localhost:8080/api/v2/demo/orders"
+        );
+        assert_eq!(howto_for("rpc", None).url, "http://localhost:8080/rpc");
+        assert!(howto(&HowtoRequest {
+            operation: "toon_teleport".to_string(),
+            arguments: None,
+            base_url: None,
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_snippets_carry_the_arguments() {
+        let response = howto_for("toon_encode", Some(json!({"json": {"it's": true}})));
+        assert_eq!(
+            response.curl,
+            "curl -X POST http://localhost:8080/api/v2/encode \\\n  -H 'Content-Type: application/json' \\\n  -d '{\"json\":{\"it'\\''s\":true}}'"
+        );
+        assert!(response.python.contains(
+            "json={\n        \"json\": {\n            \"it's\": True,\n        },\n    },"
+        ));
+        assert!(response.typescript.contains("\"it's\": true"));
+    }
+
+    #[test]
+    fn test_defaults_to_the_route_example() {
+        let response = howto_for("decode", None);
+        assert_eq!(
+            response.body.unwrap()["toon"],
+            "users[2]{id,name}:\n  1,Alice\n  2,Bob"
+        );
+        let response = howto_for("capabilities", Some(json!({"ignored": 1})));
+        assert!(response.body.is_none());
+        assert_eq!(
+            response.curl,
+            "curl http://localhost:8080/api/v2/capabilities"
+        );
+    }
+}
@@ -0,0 +1,129 @@
+//! Line-level diff of text documents.
+//!
+//! Turns two versions of a document into the smallest list of whole-line
+//! [`TextEdit`]s that rewrites the first into the second, so editors can
+//! apply a change without replacing the whole buffer.
+
+use super::TextEdit;
+
+/// Largest number of line pairs compared exactly; bigger changed regions are
+/// replaced with a single edit.
+const MAX_COMPARISONS: usize = 4_000_000;
+
+/// Edits turning `before` into `after`, in document order.
+pub fn edits(before: &str, after: &str) -> Vec<TextEdit> {
+    let old: Vec<&str> = before.lines().collect();
+    let new: Vec<&str> = after.lines().collect();
+
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    if old_mid.len().saturating_mul(new_mid.len()) > MAX_COMPARISONS {
+        return vec![edit(prefix, old_mid.len(), new_mid)];
+    }
+
+    // lcs[i][j]: longest common subsequence of old_mid[i..] and new_mid[j..].
+    let mut lcs = vec![vec![0u32; new_mid.len() + 1]; old_mid.len() + 1];
+    for i in (0..old_mid.len()).rev() {
+        for j in (0..new_mid.len()).rev() {
+            lcs[i][j] = if old_mid[i] == new_mid[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    let (mut hunk_i, mut hunk_j) = (0, 0);
+    while i < old_mid.len() || j < new_mid.len() {
+        if i < old_mid.len() && j < new_mid.len() && old_mid[i] == new_mid[j] {
+            if hunk_i < i || hunk_j < j {
+                out.push(edit(prefix + hunk_i, i - hunk_i, &new_mid[hunk_j..j]));
+            }
+            i += 1;
+            j += 1;
+            (hunk_i, hunk_j) = (i, j);
+        } else if j < new_mid.len() && (i == old_mid.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            j += 1;
+        } else {
+            i += 1;
+        }
+    }
+    if hunk_i < i || hunk_j < j {
+        out.push(edit(prefix + hunk_i, i - hunk_i, &new_mid[hunk_j..j]));
+    }
+    out
+}
+
+/// Replace `count` lines from 0-based line `start` with `lines`.
+fn edit(start: usize, count: usize, lines: &[&str]) -> TextEdit {
+    TextEdit {
+        start_line: start + 1,
+        end_line: start + count + 1,
+        new_text: lines.iter().map(|line| format!("{}\n", line)).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Apply `edits` the way an editor would, last edit first.
+    fn apply(text: &str, edits: &[TextEdit]) -> String {
+        let mut lines: Vec<String> = text.lines().map(|l| format!("{}\n", l)).collect();
+        for edit in edits.iter().rev() {
+            let replacement = edit.new_text.lines().map(|l| format!("{}\n", l));
+            lines.splice(edit.start_line - 1..edit.end_line - 1, replacement);
+        }
+        lines.concat()
+    }
+
+    #[test]
+    fn test_edits_touch_only_changed_lines() {
+        let before = "a: 1\nb: 2\nc: 3\nd: 4\n";
+        let after = "a: 1\nb: 20\nc: 3\nd: 4\ne: 5\n";
+        let edits = edits(before, after);
+
+        assert_eq!(
+            edits,
+            vec![
+                TextEdit {
+                    start_line: 2,
+                    end_line: 3,
+                    new_text: "b: 20\n".to_string()
+                },
+                TextEdit {
+                    start_line: 5,
+                    end_line: 5,
+                    new_text: "e: 5\n".to_string()
+                },
+            ]
+        );
+        assert_eq!(apply(before, &edits), after);
+    }
+
+    #[test]
+    fn test_identical_documents_need_no_edits() {
+        assert!(edits("a: 1\nb: 2", "a: 1\nb: 2").is_empty());
+    }
+
+    #[test]
+    fn test_deletions() {
+        let before = "a\nb\nc\nd";
+        let after = "a\nd";
+        let edits = edits(before, after);
+        assert_eq!(edits.len(), 1);
+        assert_eq!((edits[0].start_line, edits[0].end_line), (2, 4));
+        assert_eq!(edits[0].new_text, "");
+        assert_eq!(apply(before, &edits), "a\nd\n");
+    }
+}
@@ -0,0 +1,166 @@
+//! Locale-formatted numbers (`locale_numbers`).
+//!
+//! Models often write numbers the way people read them: `1,234.56`,
+//! `1.234,56`, or `1 234,56`. Such values are strings to TOON, and in a
+//! comma-delimited table they must be quoted anyway. [`apply`] turns every
+//! string that is unambiguously a grouped or decimal-comma number into that
+//! number. Plain numeric strings such as `"30"` were quoted on purpose and are
+//! left alone.
+
+use serde_json::{Number, Value};
+
+/// Characters used to group thousands besides `,` and `.`: space, no-break
+/// space, narrow no-break space, thin space, and apostrophe.
+const GROUP_SEPARATORS: &[char] = &[' ', '\u{a0}', '\u{202f}', '\u{2009}', '\''];
+
+/// Replace every locale-formatted number in `value` with the number.
+pub fn apply(value: &mut Value) {
+    match value {
+        Value::String(text) => {
+            if let Some(number) = parse(text) {
+                *value = Value::Number(number);
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(apply),
+        Value::Object(map) => map.values_mut().for_each(apply),
+        _ => {}
+    }
+}
+
+/// Parse `text` as a locale-formatted number. Returns `None` for plain
+/// numbers, which need no locale handling, and for anything ambiguous or
+/// malformed.
+pub fn parse(text: &str) -> Option<Number> {
+    let text = text.trim();
+    let (sign, digits) = match text.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", text.strip_prefix('+').unwrap_or(text)),
+    };
+    if !digits.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let has_comma = digits.contains(',');
+    let has_dot = digits.contains('.');
+    let spaced = digits.contains(GROUP_SEPARATORS);
+    let (integer, fraction) = match (has_comma, has_dot) {
+        // Both: the last one is the decimal separator.
+        (true, true) => {
+            let decimal = if digits.rfind(',') > digits.rfind('.') {
+                ','
+            } else {
+                '.'
+            };
+            let (integer, fraction) = digits.rsplit_once(decimal)?;
+            (integer, Some(fraction))
+        }
+        (true, false) => match digits.split_once(',') {
+            // A single comma followed by other than three digits is decimal.
+            Some((integer, fraction)) if !fraction.contains(',') && fraction.len() != 3 => {
+                (integer, Some(fraction))
+            }
+            // `1,234` reads as a thousand and more, unless the integer part
+            // is grouped by spaces, as in `1 234,567`.
+            Some((integer, fraction)) if !fraction.contains(',') && spaced => {
+                (integer, Some(fraction))
+            }
+            _ => (digits, None),
+        },
+        (false, true) if digits.matches('.').count() > 1 => (digits, None),
+        (false, true) if spaced => {
+            let (integer, fraction) = digits.split_once('.')?;
+            (integer, Some(fraction))
+        }
+        (false, true) => return None,
+        (false, false) if spaced => (digits, None),
+        (false, false) => return None,
+    };
+
+    let integer = ungroup(integer)?;
+    let number = match fraction {
+        Some(fraction) if !fraction.is_empty() && fraction.chars().all(|c| c.is_ascii_digit()) => {
+            format!("{}{}.{}", sign, integer, fraction)
+        }
+        Some(_) => return None,
+        None => format!("{}{}", sign, integer),
+    };
+    serde_json::from_str(&number).ok()
+}
+
+/// The digits of an integer part grouped by one separator in threes, as in
+/// `1,234,567` or `12 345`.
+fn ungroup(integer: &str) -> Option<String> {
+    let separators: Vec<char> = integer.chars().filter(|c| !c.is_ascii_digit()).collect();
+    if separators.is_empty() {
+        return Some(integer.to_string());
+    }
+    let separator = separators[0];
+    let is_separator = |c: char| c == ',' || c == '.' || GROUP_SEPARATORS.contains(&c);
+    if !is_separator(separator) || separators.iter().any(|c| *c != separator) {
+        return None;
+    }
+    let mut groups = integer.split(separator);
+    let first = groups.next()?;
+    if first.is_empty() || first.len() > 3 {
+        return None;
+    }
+    let mut digits = first.to_string();
+    for group in groups {
+        if group.len() != 3 {
+            return None;
+        }
+        digits.push_str(group);
+    }
+    Some(digits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn number(text: &str) -> Option<Value> {
+        parse(text).map(Value::Number)
+    }
+
+    #[test]
+    fn test_parse_locale_numbers() {
+        assert_eq!(number("1,234.56"), Some(json!(1234.56)));
+        assert_eq!(number("1.234,56"), Some(json!(1234.56)));
+        assert_eq!(number("1 234,56"), Some(json!(1234.56)));
+        assert_eq!(number("1\u{a0}234\u{a0}567"), Some(json!(1234567)));
+        assert_eq!(number("1'234.5"), Some(json!(1234.5)));
+        assert_eq!(number("1,234,567"), Some(json!(1234567)));
+        assert_eq!(number("1.234.567"), Some(json!(1234567)));
+        assert_eq!(number("3,5"), Some(json!(3.5)));
+        assert_eq!(number("-12,75"), Some(json!(-12.75)));
+        assert_eq!(number("1,234"), Some(json!(1234)));
+    }
+
+    #[test]
+    fn test_parse_leaves_other_strings() {
+        for text in [
+            "30",
+            "1.5",
+            "",
+            "abc",
+            "1,23,456",
+            "12 34",
+            "1,234.5,6",
+            "v1,2",
+            "1,",
+        ] {
+            assert_eq!(number(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_apply_converts_nested_strings() {
+        let mut value = json!({"rows": [{"price": "1 234,56", "sku": "A-1", "qty": "30"}]});
+        apply(&mut value);
+        assert_eq!(
+            value,
+            json!({"rows": [{"price": 1234.56, "sku": "A-1", "qty": "30"}]})
+        );
+    }
+}
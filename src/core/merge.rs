@@ -0,0 +1,223 @@
+//! Three-way structural merge of JSON documents (`toon-mcp merge`).
+//!
+//! Objects are merged key by key. Arrays of the same length as the base
+//! are merged index by index, and arrays that only had rows appended on
+//! either side get both sides' rows. A value changed on one side only takes
+//! that side's version; a value changed differently on both sides is a
+//! [`Conflict`] and keeps ours.
+
+use std::collections::HashSet;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use super::path;
+
+/// A value both sides changed differently. Absent values were removed (or
+/// never there, for `base`).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Conflict {
+    pub path: String,
+    pub base: Option<Value>,
+    pub ours: Option<Value>,
+    pub theirs: Option<Value>,
+}
+
+/// Result of a merge.
+#[derive(Debug)]
+pub struct Merged {
+    /// The merged document, with our side at every conflict
+    pub value: Value,
+    pub conflicts: Vec<Conflict>,
+}
+
+/// Merge the changes from `base` to `theirs` into `ours`.
+pub fn merge3(base: &Value, ours: &Value, theirs: &Value) -> Merged {
+    let mut conflicts = Vec::new();
+    let value =
+        merge_at("", Some(base), Some(ours), Some(theirs), &mut conflicts).unwrap_or(Value::Null);
+    Merged { value, conflicts }
+}
+
+/// Merged value at `at`, or `None` when it was removed.
+fn merge_at(
+    at: &str,
+    base: Option<&Value>,
+    ours: Option<&Value>,
+    theirs: Option<&Value>,
+    conflicts: &mut Vec<Conflict>,
+) -> Option<Value> {
+    if ours == theirs || theirs == base {
+        return ours.cloned();
+    }
+    if ours == base {
+        return theirs.cloned();
+    }
+    match (base, ours, theirs) {
+        (None | Some(Value::Object(_)), Some(Value::Object(o)), Some(Value::Object(t))) => {
+            let empty = Map::new();
+            let b = match base {
+                Some(Value::Object(b)) => b,
+                _ => &empty,
+            };
+            Some(Value::Object(merge_objects(at, b, o, t, conflicts)))
+        }
+        (Some(Value::Array(b)), Some(Value::Array(o)), Some(Value::Array(t))) => {
+            match merge_arrays(at, b, o, t, conflicts) {
+                Some(items) => Some(Value::Array(items)),
+                None => conflict(at, base, ours, theirs, conflicts),
+            }
+        }
+        _ => conflict(at, base, ours, theirs, conflicts),
+    }
+}
+
+fn conflict(
+    at: &str,
+    base: Option<&Value>,
+    ours: Option<&Value>,
+    theirs: Option<&Value>,
+    conflicts: &mut Vec<Conflict>,
+) -> Option<Value> {
+    conflicts.push(Conflict {
+        path: at.to_string(),
+        base: base.cloned(),
+        ours: ours.cloned(),
+        theirs: theirs.cloned(),
+    });
+    ours.cloned()
+}
+
+/// Keys in our order, then the ones we removed, then the ones they added.
+fn merge_objects(
+    at: &str,
+    base: &Map<String, Value>,
+    ours: &Map<String, Value>,
+    theirs: &Map<String, Value>,
+    conflicts: &mut Vec<Conflict>,
+) -> Map<String, Value> {
+    let mut seen = HashSet::new();
+    let mut merged = Map::new();
+    for key in ours.keys().chain(base.keys()).chain(theirs.keys()) {
+        if !seen.insert(key) {
+            continue;
+        }
+        let child = path::key(at, key);
+        if let Some(value) = merge_at(
+            &child,
+            base.get(key),
+            ours.get(key),
+            theirs.get(key),
+            conflicts,
+        ) {
+            merged.insert(key.clone(), value);
+        }
+    }
+    merged
+}
+
+/// Index by index when neither side changed the length, or base followed
+/// by both sides' appended rows; `None` when the sides can't be lined up.
+///
+/// Appended rows both sides share are kept once: when one side's rows
+/// extend the other's, only the extra rows are added. Appended rows that
+/// start alike and then differ can't be lined up.
+fn merge_arrays(
+    at: &str,
+    base: &[Value],
+    ours: &[Value],
+    theirs: &[Value],
+    conflicts: &mut Vec<Conflict>,
+) -> Option<Vec<Value>> {
+    if ours.len() == base.len() && theirs.len() == base.len() {
+        let items = base
+            .iter()
+            .zip(ours)
+            .zip(theirs)
+            .enumerate()
+            .map(|(i, ((b, o), t))| {
+                merge_at(&path::index(at, i), Some(b), Some(o), Some(t), conflicts)
+                    .unwrap_or(Value::Null)
+            })
+            .collect();
+        return Some(items);
+    }
+    if !(ours.starts_with(base) && theirs.starts_with(base)) {
+        return None;
+    }
+    let (ours_added, theirs_added) = (&ours[base.len()..], &theirs[base.len()..]);
+    let shared = ours_added
+        .iter()
+        .zip(theirs_added)
+        .take_while(|(o, t)| o == t)
+        .count();
+    let extends = shared == ours_added.len() || shared == theirs_added.len();
+    if shared > 0 && !extends {
+        return None;
+    }
+    let mut items = ours.to_vec();
+    items.extend_from_slice(&theirs_added[shared..]);
+    Some(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_changes_on_different_keys_merge() {
+        let base = json!({"name": "api", "port": 80, "tags": ["a"]});
+        let ours = json!({"name": "api", "port": 8080, "tags": ["a"]});
+        let theirs = json!({"name": "web", "port": 80, "tags": ["a", "b"], "debug": true});
+        let merged = merge3(&base, &ours, &theirs);
+        assert!(merged.conflicts.is_empty());
+        assert_eq!(
+            merged.value,
+            json!({"name": "web", "port": 8080, "tags": ["a", "b"], "debug": true})
+        );
+    }
+
+    #[test]
+    fn test_appended_rows_from_both_sides() {
+        let base = json!({"rows": [{"id": 1}]});
+        let ours = json!({"rows": [{"id": 1}, {"id": 2}]});
+        let theirs = json!({"rows": [{"id": 1}, {"id": 3}]});
+        let merged = merge3(&base, &ours, &theirs);
+        assert!(merged.conflicts.is_empty());
+        assert_eq!(
+            merged.value,
+            json!({"rows": [{"id": 1}, {"id": 2}, {"id": 3}]})
+        );
+    }
+
+    #[test]
+    fn test_overlapping_appends_are_not_duplicated() {
+        let base = json!({"rows": [1]});
+        let merged = merge3(&base, &json!({"rows": [1, 2]}), &json!({"rows": [1, 2, 3]}));
+        assert!(merged.conflicts.is_empty());
+        assert_eq!(merged.value, json!({"rows": [1, 2, 3]}));
+
+        let merged = merge3(&base, &json!({"rows": [1, 2, 3]}), &json!({"rows": [1, 2]}));
+        assert!(merged.conflicts.is_empty());
+        assert_eq!(merged.value, json!({"rows": [1, 2, 3]}));
+
+        let ours = json!({"rows": [1, 2, 3]});
+        let merged = merge3(&base, &ours, &json!({"rows": [1, 2, 4]}));
+        assert_eq!(merged.value, ours);
+        assert_eq!(merged.conflicts.len(), 1);
+        assert_eq!(merged.conflicts[0].path, "rows");
+    }
+
+    #[test]
+    fn test_conflict_keeps_ours() {
+        let base = json!({"rows": [{"id": 1, "name": "a"}], "gone": 1});
+        let ours = json!({"rows": [{"id": 1, "name": "b"}]});
+        let theirs = json!({"rows": [{"id": 1, "name": "c"}], "gone": 2});
+        let merged = merge3(&base, &ours, &theirs);
+        assert_eq!(merged.value, ours);
+        let paths: Vec<&str> = merged.conflicts.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(paths, vec!["rows[0].name", "gone"]);
+        assert_eq!(merged.conflicts[1].ours, None);
+    }
+}
@@ -3,7 +3,41 @@
 //! This module contains pure functions that are shared between
 //! the MCP and HTTP transport layers.
 
+pub mod anonymize;
+pub mod cache;
+pub mod coercion;
+pub mod compact;
+pub mod contract;
+pub mod count;
+pub mod demo;
+pub mod diagnose;
+pub mod diff;
+pub mod excerpt;
+pub mod hints;
+pub mod howto;
+pub mod lines;
+pub mod locale;
+pub mod merge;
+pub mod nested;
+pub mod newline;
+pub mod path;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+pub mod rand;
+pub mod sample;
+pub mod schema;
+pub mod shape;
+pub mod snippets;
+pub mod sql;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod strategy;
+pub mod stream;
+pub mod suggest;
+pub mod table;
+pub mod transform;
 pub mod types;
+pub mod warmup;
 
 pub use types::*;
 
@@ -14,14 +48,126 @@ pub fn encode_json(
     json: &serde_json::Value,
     options: &EncodeOptionsInput,
 ) -> Result<String, ToonCoreError> {
+    encode_with_metadata(json, options).map(|response| response.toon)
+}
+
+/// Encode JSON value to TOON format, returning metadata for reversible transforms.
+pub fn encode_with_metadata(
+    json: &serde_json::Value,
+    options: &EncodeOptionsInput,
+) -> Result<EncodeResponse, ToonCoreError> {
+    let (value, metadata) = transform::apply(json, options)?;
+    let (options, decisions) = match options.strategy.as_deref() {
+        None | Some("manual") => (std::borrow::Cow::Borrowed(options), None),
+        Some("auto") => {
+            let (chosen, decisions) = strategy::choose(&value, options);
+            (std::borrow::Cow::Owned(chosen), Some(decisions))
+        }
+        Some(other) => {
+            return Err(ToonCoreError::InvalidOption(format!(
+                "unknown strategy '{}' (expected manual or auto)",
+                other
+            )))
+        }
+    };
+    let options = options.as_ref();
     let opts = build_encode_options(options);
-    encode(json, &opts).map_err(|e| ToonCoreError::EncodeError(e.to_string()))
+    let (value, markers) = match options.placeholders.as_ref().filter(|p| !p.is_empty()) {
+        Some(placeholders) => {
+            let (marked, markers) = transform::insert_placeholders(&value, placeholders)?;
+            (std::borrow::Cow::Owned(marked), markers)
+        }
+        None => (value, Vec::new()),
+    };
+    let mut toon = encode(value.as_ref(), &opts).map_err(|e| ToonCoreError::EncodeError(e.to_string()))?;
+    for (marker, variable) in markers {
+        if toon.matches(&marker).count() != 1 {
+            return Err(ToonCoreError::EncodeError(format!(
+                "document already contains the placeholder marker {}",
+                marker
+            )));
+        }
+        toon = toon.replacen(&marker, &variable, 1);
+    }
+    if options.type_hints.unwrap_or(false) {
+        toon = hints::add(&toon);
+    }
+    let toon = newline::apply(toon, options)?;
+
+    Ok(EncodeResponse {
+        toon,
+        metadata: (!metadata.is_empty()).then_some(metadata),
+        decisions,
+    })
+}
+
+/// Encode an [`EncodeRequest`].
+///
+/// A string-wrapped top-level array is encoded row by row without building
+/// the whole document in memory, unless a transform needs the full document.
+pub fn encode_request(request: &EncodeRequest) -> Result<EncodeResponse, ToonCoreError> {
+    if let serde_json::Value::String(s) = &request.json {
+        if !needs_whole_document(&request.options) && s.trim_start().starts_with('[') {
+            if let Some(mut toon) = stream::encode_array(s.as_bytes(), &request.options)? {
+                if request.options.type_hints.unwrap_or(false) {
+                    toon = hints::add(&toon);
+                }
+                return Ok(EncodeResponse {
+                    toon: newline::apply(toon, &request.options)?,
+                    metadata: None,
+                    decisions: None,
+                });
+            }
+        }
+    }
+
+    let json = parse_json_input(&request.json)?;
+    encode_with_metadata(&json, &request.options)
+}
+
+/// Whether `options` transform the document as a whole, which rules out
+/// encoding it row by row.
+fn needs_whole_document(options: &EncodeOptionsInput) -> bool {
+    options.rename.as_ref().is_some_and(|r| !r.is_empty())
+        || options.dedupe_rows.unwrap_or(false)
+        || options.fill_missing.unwrap_or(false)
+        || options.nested_tables.as_deref().is_some_and(|n| n != "keep")
+        || options.deterministic.unwrap_or(false)
+        || options.previous.is_some()
+        || options.placeholders.as_ref().is_some_and(|p| !p.is_empty())
+        || options.column_order.as_ref().is_some_and(|c| !c.is_empty())
+        || options.url_prefixes.unwrap_or(false)
+        || options.enum_codes.unwrap_or(false)
+        || options.dictionary.unwrap_or(false)
+        || options.literals.is_some()
+        || options.blob_policy.as_deref().is_some_and(|p| p != "keep")
+        || options.strategy.as_deref().is_some_and(|s| s != "manual")
 }
 
 /// Decode TOON string to JSON value.
 pub fn decode_toon(toon: &str, request: &DecodeRequest) -> Result<serde_json::Value, ToonCoreError> {
     let opts = build_decode_options(request);
-    decode(toon, &opts).map_err(ToonCoreError::from)
+    let toon = newline::normalize(toon);
+    let toon = match request.type_hints {
+        Some(true) => std::borrow::Cow::Owned(hints::apply(&toon)),
+        _ => toon,
+    };
+    let mut value: serde_json::Value =
+        decode(&toon, &opts).map_err(|e| excerpt::locate(e.into(), &toon))?;
+
+    if request.locale_numbers == Some(true) && request.coerce_types != Some(false) {
+        locale::apply(&mut value);
+    }
+
+    if let Some(ref metadata) = request.metadata {
+        transform::restore(&mut value, metadata)?;
+    }
+
+    if request.deterministic.unwrap_or(false) {
+        value = transform::canonicalize(&value);
+    }
+
+    Ok(value)
 }
 
 /// Validate TOON syntax without returning the decoded value.
@@ -31,38 +177,206 @@ pub fn validate_toon(toon: &str, strict: Option<bool>) -> ValidateResponse {
         opts = opts.with_strict(s);
     }
 
-    match decode::<serde_json::Value>(toon, &opts) {
+    let toon = newline::normalize(toon);
+    match decode::<serde_json::Value>(&toon, &opts) {
         Ok(_) => ValidateResponse {
             valid: true,
             error: None,
+            violations: Vec::new(),
         },
         Err(e) => {
-            let core_error: ToonCoreError = e.into();
+            let core_error = excerpt::locate(e.into(), &toon);
             ValidateResponse {
                 valid: false,
                 error: Some(core_error.into()),
+                violations: Vec::new(),
             }
         }
     }
 }
 
+/// Validate a request's TOON and, when asked, the shape of its table and
+/// its contract. Fails only when the contract itself is invalid.
+pub fn validate_request(request: &ValidateRequest) -> Result<ValidateResponse, ToonCoreError> {
+    if request.expected_rows.is_none()
+        && request.expected_columns.is_none()
+        && request.contract.is_none()
+    {
+        return Ok(validate_toon(&request.toon, request.strict));
+    }
+    let decode_request = DecodeRequest {
+        strict: request.strict,
+        ..Default::default()
+    };
+    let value = match decode_toon(&request.toon, &decode_request) {
+        Ok(value) => value,
+        Err(e) => {
+            return Ok(ValidateResponse {
+                valid: false,
+                error: Some(e.into()),
+                violations: Vec::new(),
+            })
+        }
+    };
+
+    let mut error = shape::check(
+        &value,
+        request.expected_rows,
+        request.expected_columns.as_deref(),
+    )
+    .err();
+    let violations = match &request.contract {
+        Some(contract) => contract::check(&value, contract)?,
+        None => Vec::new(),
+    };
+    if let (None, Some(first)) = (&error, violations.first()) {
+        error = Some(ValidationError {
+            message: format!(
+                "{} contract violation(s); first at {}: {}",
+                violations.len(),
+                first.path,
+                first.message
+            ),
+            line: None,
+            column: None,
+            suggestion: None,
+            row: None,
+            snippet: None,
+        });
+    }
+    Ok(ValidateResponse {
+        valid: error.is_none(),
+        error,
+        violations,
+    })
+}
+
+/// Format lines `start_line..=end_line` of a TOON document.
+///
+/// The whole document is re-encoded with the requested layout, and only the
+/// line edits overlapping the range are returned. The document must parse;
+/// reversible transforms such as `rename` are rejected since formatting never
+/// changes the data.
+pub fn format_range(request: &FormatRangeRequest) -> Result<FormatRangeResponse, ToonCoreError> {
+    if request.start_line == 0 || request.end_line < request.start_line {
+        return Err(ToonCoreError::InvalidOption(format!(
+            "invalid line range {}-{} (lines are 1-based)",
+            request.start_line, request.end_line
+        )));
+    }
+    let options = &request.options;
+    let decode_request = DecodeRequest {
+        strict: request.strict,
+        ..DecodeRequest::default()
+    };
+    let value = decode_toon(&request.toon, &decode_request)?;
+    let encoded = encode_with_metadata(&value, options)?;
+    // Every transform that changes the data records metadata; placeholders
+    // replace values without recording any.
+    let placeholders = options.placeholders.as_ref().is_some_and(|p| !p.is_empty());
+    if encoded.metadata.is_some() || placeholders {
+        return Err(ToonCoreError::InvalidOption(
+            "options that change the data cannot be used when formatting".to_string(),
+        ));
+    }
+    let formatted = encoded.toon;
+
+    let edits = lines::edits(&request.toon, &formatted)
+        .into_iter()
+        .filter(|edit| {
+            // Insertions sit between lines, so they count when touching the range.
+            let last = edit.end_line.max(edit.start_line + 1) - 1;
+            edit.start_line <= request.end_line && last >= request.start_line
+        })
+        .collect();
+    Ok(FormatRangeResponse { edits })
+}
+
 /// Compute statistics comparing JSON and TOON formats.
 pub fn compute_stats(
     json: &serde_json::Value,
     options: &EncodeOptionsInput,
+    stats_options: &StatsOptions,
 ) -> Result<StatsResponse, ToonCoreError> {
-    // Generate JSON string
-    let json_str =
-        serde_json::to_string(json).map_err(|e| ToonCoreError::SerializationError(e.to_string()))?;
+    let mut stats = match sample::estimate(json, options, stats_options.sample_rows)? {
+        Some(estimate) => {
+            let json_counts = estimate.json.counts();
+            let toon_counts = estimate.toon.counts();
+            let mut stats = compare_sizes(json_counts, toon_counts, stats_options);
+            estimate.annotate(&mut stats);
+            stats
+        }
+        None => {
+            // The JSON rendering is only counted, never built
+            let json_counts = count::json(json, false)?;
+            let toon_counts = count::text(&encode_json(json, options)?);
+            compare_sizes(json_counts, toon_counts, stats_options)
+        }
+    };
+    if stats_options.diagnose.unwrap_or(false) {
+        stats.diagnostics = Some(diagnose::diagnose(json));
+    }
+    Ok(stats)
+}
 
-    // Generate TOON string
-    let toon_str = encode_json(json, options)?;
+/// Check that encoding `request.json` saves at least `min_savings_percent`.
+///
+/// Meant for CI: a change in data shape that breaks tabular encoding (a new
+/// field on some rows, say) shows up as a failed check.
+pub fn assert_savings(
+    request: &AssertSavingsRequest,
+) -> Result<AssertSavingsResponse, ToonCoreError> {
+    let json = parse_json_input(&request.json)?;
+    let stats = compute_stats(&json, &request.encode_options, &StatsOptions::default())?;
+    let (metric, actual_percent) = match request.metric.as_deref() {
+        None | Some("tokens") => ("tokens", stats.savings.tokens_percent),
+        Some("bytes") => ("bytes", stats.savings.bytes_percent),
+        Some(other) => {
+            return Err(ToonCoreError::InvalidOption(format!(
+                "unknown savings metric '{}' (expected tokens or bytes)",
+                other
+            )))
+        }
+    };
+    Ok(AssertSavingsResponse {
+        passed: actual_percent >= request.min_savings_percent,
+        metric: metric.to_string(),
+        actual_percent,
+        min_savings_percent: request.min_savings_percent,
+        stats,
+    })
+}
+
+/// Compute statistics for a TOON document against the JSON it decodes to.
+///
+/// This measures the model-output side: how much larger the JSON would have
+/// been had the model emitted it directly. JSON is rendered per `output_format`.
+pub fn compute_decode_stats(request: &DecodeRequest) -> Result<StatsResponse, ToonCoreError> {
+    let json = decode_toon(&request.toon, request)?;
+    let json_counts = match request.output_format.as_deref() {
+        Some("ndjson") => count::text(&format_ndjson(&json)?),
+        format => count::json(&json, format == Some("json_pretty"))?,
+    };
+    Ok(compare_sizes(
+        json_counts,
+        count::text(&request.toon),
+        &StatsOptions::default(),
+    ))
+}
 
-    // Calculate stats
-    let json_bytes = json_str.len();
-    let toon_bytes = toon_str.len();
-    let json_tokens = estimate_tokens(&json_str);
-    let toon_tokens = estimate_tokens(&toon_str);
+/// Savings of TOON relative to JSON, given the `(bytes, tokens)` of each
+/// rendering.
+fn compare_sizes(
+    (json_bytes, json_tokens): (usize, usize),
+    (toon_bytes, toon_tokens): (usize, usize),
+    options: &StatsOptions,
+) -> StatsResponse {
+    let precision = options.precision.unwrap_or(2).min(6) as usize;
+    let human = options.human_units.unwrap_or(false);
+    let round = |n: f64| {
+        let scale = 10f64.powi(precision as i32);
+        (n * scale).round() / scale
+    };
 
     let bytes_pct = if json_bytes > 0 {
         ((json_bytes as f64 - toon_bytes as f64) / json_bytes as f64) * 100.0
@@ -76,19 +390,438 @@ pub fn compute_stats(
         0.0
     };
 
-    Ok(StatsResponse {
-        json: FormatStats {
-            bytes: json_bytes,
-            tokens_approx: json_tokens,
-        },
-        toon: FormatStats {
-            bytes: toon_bytes,
-            tokens_approx: toon_tokens,
-        },
+    let format_stats = |bytes: usize, tokens: usize| FormatStats {
+        bytes,
+        tokens_approx: tokens,
+        bytes_human: human.then(|| human_bytes(bytes, precision)),
+        tokens_human: human.then(|| human_tokens(tokens, precision)),
+        bytes_margin: None,
+        tokens_margin: None,
+    };
+
+    StatsResponse {
+        json: format_stats(json_bytes, json_tokens),
+        toon: format_stats(toon_bytes, toon_tokens),
         savings: SavingsStats {
-            bytes_percent: (bytes_pct * 100.0).round() / 100.0,
-            tokens_percent: (tokens_pct * 100.0).round() / 100.0,
+            bytes_percent: round(bytes_pct),
+            tokens_percent: round(tokens_pct),
+            bytes_saved: json_bytes as i64 - toon_bytes as i64,
+            tokens_saved: json_tokens as i64 - toon_tokens as i64,
+        },
+        diagnostics: None,
+        sample: None,
+    }
+}
+
+/// Format a byte count with binary units ("512 B", "1.50 KB", "2.00 MB").
+fn human_bytes(bytes: usize, precision: usize) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.*} {}", precision, size, UNITS[unit])
+}
+
+/// Format a token count ("950 tokens", "1.20k tokens", "3.40M tokens").
+fn human_tokens(tokens: usize, precision: usize) -> String {
+    match tokens {
+        0..=999 => format!("{} tokens", tokens),
+        1_000..=999_999 => format!("{:.*}k tokens", precision, tokens as f64 / 1e3),
+        _ => format!("{:.*}M tokens", precision, tokens as f64 / 1e6),
+    }
+}
+
+/// Group an array of objects and encode the per-group aggregates as TOON.
+pub fn aggregate(request: &AggregateRequest) -> Result<TableResponse, ToonCoreError> {
+    let json = parse_json_input(&request.json)?;
+    let rows = table::as_rows(&json, "json")?;
+    let summary = table::aggregate_rows(rows, &request.group_by, &request.aggregations)?;
+    encode_table(summary, &request.encode_options)
+}
+
+/// Default row cap for joins.
+pub const DEFAULT_JOIN_MAX_ROWS: usize = 10_000;
+
+/// Join two arrays of objects and encode the result as TOON.
+pub fn join(request: &JoinRequest) -> Result<TableResponse, ToonCoreError> {
+    let left = parse_json_input(&request.left)?;
+    let right = parse_json_input(&request.right)?;
+    let kind = table::JoinKind::parse(request.how.as_deref())?;
+    let rows = table::join_rows(
+        table::as_rows(&left, "left")?,
+        table::as_rows(&right, "right")?,
+        &request.on,
+        kind,
+        request.max_rows.unwrap_or(DEFAULT_JOIN_MAX_ROWS),
+    )?;
+    encode_table(rows, &request.encode_options)
+}
+
+/// Pivot an array of objects between long and wide layouts and encode it as TOON.
+pub fn pivot(request: &PivotRequest) -> Result<TableResponse, ToonCoreError> {
+    let json = parse_json_input(&request.json)?;
+    let rows = table::as_rows(&json, "json")?;
+    let columns = request.columns.as_deref().unwrap_or("key");
+    let values = request.values.as_deref().unwrap_or("value");
+
+    let (reshaped, skipped) = match request.direction.as_deref() {
+        None | Some("wide") => table::pivot_wider(rows, &request.index, columns, values)?,
+        Some("long") => (
+            table::pivot_longer(rows, &request.index, columns, values),
+            0,
+        ),
+        Some(other) => {
+            return Err(ToonCoreError::InvalidOption(format!(
+                "unknown pivot direction '{}' (expected wide or long)",
+                other
+            )))
+        }
+    };
+    let mut response = encode_table(reshaped, &request.encode_options)?;
+    response.rows_skipped = (skipped > 0).then_some(skipped);
+    Ok(response)
+}
+
+/// Detect and replace PII in a document, then encode it as TOON.
+pub fn anonymize(request: &AnonymizeRequest) -> Result<AnonymizeResponse, ToonCoreError> {
+    let json = parse_json_input(&request.json)?;
+    let mode = anonymize::Mode::parse(request.mode.as_deref())?;
+    let detectors = anonymize::detectors(request.detectors.as_deref(), &request.patterns)?;
+
+    let key = pseudonym_key(request.key.as_deref());
+    let key = key.as_deref().map(str::as_bytes);
+
+    let mut anonymizer = anonymize::Anonymizer::new(&detectors, mode, key)?;
+    let cleaned = anonymizer.run(&json);
+    let toon = encode_json(&cleaned, &request.encode_options)?;
+
+    let mapping = if request.return_mapping.unwrap_or(false) {
+        if mode == anonymize::Mode::Mask {
+            return Err(ToonCoreError::InvalidOption(
+                "mask mode is not reversible; use hash, pseudonymize, or hmac".to_string(),
+            ));
+        }
+        let key = key.ok_or_else(|| {
+            ToonCoreError::InvalidOption(
+                "return_mapping requires a key (pass `key` or set TOON_PSEUDONYM_KEY)".to_string(),
+            )
+        })?;
+        Some(anonymize::seal_mapping(anonymizer.mapping(), key)?)
+    } else {
+        None
+    };
+
+    Ok(AnonymizeResponse {
+        toon,
+        report: anonymizer.into_report(),
+        mapping,
+    })
+}
+
+/// Restore original values in text using an encrypted anonymization mapping.
+pub fn deanonymize(request: &DeanonymizeRequest) -> Result<DeanonymizeResponse, ToonCoreError> {
+    let key = pseudonym_key(request.key.as_deref()).ok_or_else(|| {
+        ToonCoreError::InvalidOption(
+            "a key is required (pass `key` or set TOON_PSEUDONYM_KEY)".to_string(),
+        )
+    })?;
+    let mapping = anonymize::open_mapping(&request.mapping, key.as_bytes())?;
+    let (text, replaced) = anonymize::deanonymize_text(&request.text, &mapping);
+    Ok(DeanonymizeResponse { text, replaced })
+}
+
+/// Compute SHA-256 digests of the canonical JSON and TOON forms of a document.
+///
+/// Canonical JSON is compact with recursively sorted keys, so digests are
+/// stable regardless of the key order a producer happened to emit.
+pub fn hash_document(request: &HashRequest) -> Result<HashResponse, ToonCoreError> {
+    let json = document_input(request.json.as_ref(), request.toon.as_deref())?;
+
+    let canonical = transform::sort_keys(&json);
+    let json_str = serde_json::to_string(&canonical)
+        .map_err(|e| ToonCoreError::SerializationError(e.to_string()))?;
+    let toon_str = encode_json(&canonical, &request.encode_options)?;
+
+    Ok(HashResponse {
+        json_sha256: anonymize::hex_digest(json_str.as_bytes()),
+        toon_sha256: anonymize::hex_digest(toon_str.as_bytes()),
+    })
+}
+
+/// Estimate how much of a provider's prompt cache survives going from
+/// `before` to `after`.
+pub fn cache_analysis(
+    request: &CacheAnalysisRequest,
+) -> Result<CacheAnalysisResponse, ToonCoreError> {
+    let options = &request.encode_options;
+    let before = encode_json(&parse_json_input(&request.before)?, options)?;
+    let after_json = parse_json_input(&request.after)?;
+    let after = encode_json(&after_json, options)?;
+    let min_tokens = request
+        .min_cacheable_tokens
+        .unwrap_or(cache::DEFAULT_MIN_CACHEABLE_TOKENS);
+
+    let mut report = cache::analyze(&before, &after, min_tokens);
+
+    // Key order alone can break the prefix; see whether `previous` helps.
+    if options.previous.is_none() && !options.deterministic.unwrap_or(false) {
+        let aligned_options = EncodeOptionsInput {
+            previous: Some(before.clone()),
+            ..options.clone()
+        };
+        let aligned = encode_json(&after_json, &aligned_options)?;
+        let aligned_prefix = cache::common_prefix(&before, &aligned);
+        if aligned_prefix > cache::common_prefix(&before, &after) {
+            report.suggestions.push(format!(
+                "Keys are ordered differently; encoding with `previous` set to the earlier \
+                 output shares ~{} tokens instead of ~{}.",
+                estimate_tokens(&aligned[..aligned_prefix]),
+                report.prefix_tokens
+            ));
+        }
+    }
+    Ok(report)
+}
+
+/// Encode then decode a document and report whether the round trip is lossless.
+///
+/// Metadata from reversible transforms is fed back into the decode, so only
+/// genuine losses (number formatting, dropped values, ...) are reported.
+pub fn verify_roundtrip(request: &RoundTripRequest) -> Result<RoundTripResponse, ToonCoreError> {
+    let json = parse_json_input(&request.json)?;
+    let encoded = encode_with_metadata(&json, &request.encode_options)?;
+
+    let decode_request = DecodeRequest {
+        metadata: encoded.metadata,
+        type_hints: request.encode_options.type_hints,
+        ..Default::default()
+    };
+    let decoded = decode_toon(&encoded.toon, &decode_request)?;
+    let differences = diff::diff_values(&json, &decoded);
+
+    Ok(RoundTripResponse {
+        lossless: differences.is_empty(),
+        toon: encoded.toon,
+        differences,
+    })
+}
+
+/// Decode LLM-returned TOON and compare it with the original JSON.
+///
+/// Decode failures are reported in the response rather than as an error, so
+/// agents get one uniform verdict for every model output.
+pub fn diff_validate(request: &DiffValidateRequest) -> Result<DiffValidateResponse, ToonCoreError> {
+    let original = parse_json_input(&request.original)?;
+    let decode_request = DecodeRequest {
+        strict: request.strict,
+        metadata: request.metadata.clone(),
+        ..Default::default()
+    };
+
+    let decoded = match decode_toon(&request.toon, &decode_request) {
+        Ok(decoded) => decoded,
+        Err(e) => {
+            return Ok(DiffValidateResponse {
+                valid: false,
+                error: Some(e.into()),
+                changed: Vec::new(),
+                added: Vec::new(),
+                removed: Vec::new(),
+                violations: Vec::new(),
+            })
+        }
+    };
+
+    let differences = diff::diff_values(&original, &decoded);
+    let violations = diff::shape_violations(&differences);
+
+    let (mut changed, mut added, mut removed) = (Vec::new(), Vec::new(), Vec::new());
+    for difference in differences {
+        match difference.kind.as_str() {
+            "added" => added.push(difference),
+            "removed" => removed.push(difference),
+            _ => changed.push(difference),
+        }
+    }
+
+    Ok(DiffValidateResponse {
+        valid: violations.is_empty(),
+        error: None,
+        changed,
+        added,
+        removed,
+        violations,
+    })
+}
+
+/// Generate a synthetic example document from a JSON Schema and encode it as TOON.
+pub fn example_from_schema(
+    request: &SchemaExampleRequest,
+) -> Result<SchemaExampleResponse, ToonCoreError> {
+    let schema = parse_json_input(&request.schema)?;
+    let generator = schema::ExampleGenerator::new(
+        &schema,
+        request.rows.unwrap_or(2),
+        request.include_optional.unwrap_or(true),
+    );
+    let json = generator.generate()?;
+    let toon = encode_json(&json, &request.encode_options)?;
+    Ok(SchemaExampleResponse { toon, json })
+}
+
+/// Build a TOON tabular header template from a JSON Schema.
+pub fn schema_template(
+    request: &SchemaTemplateRequest,
+) -> Result<SchemaTemplateResponse, ToonCoreError> {
+    let schema_value = parse_json_input(&request.schema)?;
+    let delimiter = match request.delimiter.as_deref() {
+        None | Some("comma") => ',',
+        Some("tab") => '\t',
+        Some("pipe") => '|',
+        Some(other) => {
+            return Err(ToonCoreError::InvalidOption(format!(
+                "unknown delimiter '{}' (expected comma, tab, or pipe)",
+                other
+            )))
+        }
+    };
+    let fields = schema::template_fields(&schema_value)?;
+    let template = schema::render_template(request.name.as_deref(), &fields, delimiter);
+    Ok(SchemaTemplateResponse { template, fields })
+}
+
+/// Default number of rows per generated INSERT statement.
+pub const DEFAULT_SQL_BATCH_SIZE: usize = 500;
+
+/// Convert a tabular JSON or TOON document into CREATE TABLE and INSERT statements.
+pub fn to_sql(request: &SqlRequest) -> Result<SqlResponse, ToonCoreError> {
+    let json = document_input(request.json.as_ref(), request.toon.as_deref())?;
+    let (name, value) = match &json {
+        serde_json::Value::Object(map) if map.len() == 1 => match map.iter().next() {
+            Some((name, value @ serde_json::Value::Array(_))) => (Some(name.as_str()), value),
+            _ => (None, &json),
         },
+        _ => (None, &json),
+    };
+    let rows = table::as_rows(value, "input")?;
+
+    let table = request.table.as_deref().or(name).unwrap_or("data");
+    let columns = sql::infer_columns(rows);
+    let batch_size = request.batch_size.unwrap_or(DEFAULT_SQL_BATCH_SIZE);
+    Ok(SqlResponse {
+        sql: sql::render(table, &columns, rows, batch_size),
+        columns,
+        rows: rows.len(),
+    })
+}
+
+/// Default row cap for database queries.
+pub const DEFAULT_QUERY_MAX_ROWS: usize = 1_000;
+
+/// Run a read-only query against a SQLite file under `TOON_SQLITE_ROOT`.
+#[cfg(feature = "sqlite")]
+pub fn query_sqlite(request: &SqliteQueryRequest) -> Result<QueryResponse, ToonCoreError> {
+    let root = std::env::var("TOON_SQLITE_ROOT")
+        .ok()
+        .filter(|r| !r.is_empty())
+        .ok_or_else(|| {
+            ToonCoreError::InvalidOption(
+                "SQLite access is disabled; set TOON_SQLITE_ROOT to a sandbox directory"
+                    .to_string(),
+            )
+        })?;
+    let path = sqlite::resolve_path(std::path::Path::new(&root), &request.path)?;
+    let max_rows = request.max_rows.unwrap_or(DEFAULT_QUERY_MAX_ROWS);
+    let (rows, truncated) = sqlite::query(&path, &request.query, &request.params, max_rows)?;
+    let table = encode_table(rows, &request.encode_options)?;
+    Ok(QueryResponse {
+        toon: table.toon,
+        rows: table.rows,
+        truncated,
+    })
+}
+
+/// Run a read-only query against a SQLite file under `TOON_SQLITE_ROOT`.
+#[cfg(not(feature = "sqlite"))]
+pub fn query_sqlite(_request: &SqliteQueryRequest) -> Result<QueryResponse, ToonCoreError> {
+    Err(ToonCoreError::InvalidOption(
+        "SQLite support not available. Build with --features sqlite".to_string(),
+    ))
+}
+
+/// Run a read-only query against the database at `TOON_POSTGRES_URL`.
+#[cfg(feature = "postgres")]
+pub async fn query_postgres(
+    request: &PostgresQueryRequest,
+) -> Result<QueryResponse, ToonCoreError> {
+    let url = std::env::var("TOON_POSTGRES_URL")
+        .ok()
+        .filter(|u| !u.is_empty())
+        .ok_or_else(|| {
+            ToonCoreError::InvalidOption(
+                "Postgres access is disabled; set TOON_POSTGRES_URL to a connection string"
+                    .to_string(),
+            )
+        })?;
+    let max_rows = request.max_rows.unwrap_or(DEFAULT_QUERY_MAX_ROWS);
+    let (rows, truncated) =
+        postgres::query(&url, &request.query, &request.params, max_rows).await?;
+    let table = encode_table(rows, &request.encode_options)?;
+    Ok(QueryResponse {
+        toon: table.toon,
+        rows: table.rows,
+        truncated,
+    })
+}
+
+/// Run a read-only query against the database at `TOON_POSTGRES_URL`.
+#[cfg(not(feature = "postgres"))]
+pub async fn query_postgres(
+    _request: &PostgresQueryRequest,
+) -> Result<QueryResponse, ToonCoreError> {
+    Err(ToonCoreError::InvalidOption(
+        "Postgres support not available. Build with --features postgres".to_string(),
+    ))
+}
+
+/// Parse a document supplied as exactly one of JSON or TOON.
+fn document_input(
+    json: Option<&serde_json::Value>,
+    toon: Option<&str>,
+) -> Result<serde_json::Value, ToonCoreError> {
+    match (json, toon) {
+        (Some(json), None) => parse_json_input(json),
+        (None, Some(toon)) => decode_toon(toon, &DecodeRequest::default()),
+        _ => Err(ToonCoreError::InvalidOption(
+            "provide exactly one of `json` or `toon`".to_string(),
+        )),
+    }
+}
+
+/// Resolve the pseudonymization secret from the request or `TOON_PSEUDONYM_KEY`.
+fn pseudonym_key(key: Option<&str>) -> Option<String> {
+    key.map(str::to_string)
+        .or_else(|| std::env::var("TOON_PSEUDONYM_KEY").ok())
+        .filter(|k| !k.is_empty())
+}
+
+/// Encode a list of result rows as a TOON table.
+fn encode_table(
+    rows: Vec<serde_json::Value>,
+    options: &EncodeOptionsInput,
+) -> Result<TableResponse, ToonCoreError> {
+    let count = rows.len();
+    let toon = encode_json(&serde_json::Value::Array(rows), options)?;
+    Ok(TableResponse {
+        toon,
+        rows: count,
+        rows_skipped: None,
     })
 }
 
@@ -102,25 +835,55 @@ pub fn parse_json_input(value: &serde_json::Value) -> Result<serde_json::Value,
     }
 }
 
+/// Rough serialized size of a JSON value in bytes, without serializing it.
+///
+/// Used to decide whether a conversion is large enough to move off the
+/// async runtime; it only needs to be the right order of magnitude.
+pub fn json_size_hint(value: &serde_json::Value) -> usize {
+    match value {
+        serde_json::Value::String(s) => s.len() + 2,
+        serde_json::Value::Array(items) => {
+            2 + items.iter().map(|v| json_size_hint(v) + 1).sum::<usize>()
+        }
+        serde_json::Value::Object(map) => {
+            2 + map
+                .iter()
+                .map(|(k, v)| k.len() + 4 + json_size_hint(v))
+                .sum::<usize>()
+        }
+        _ => 8,
+    }
+}
+
 /// Estimate token count for a string.
 /// Simple approximation: count alphanumeric words plus non-whitespace punctuation.
 pub fn estimate_tokens(text: &str) -> usize {
-    let mut count = 0;
-    let mut in_word = false;
-    for c in text.chars() {
-        if c.is_alphanumeric() || c == '_' {
-            if !in_word {
-                count += 1;
-                in_word = true;
-            }
-        } else {
-            in_word = false;
-            if !c.is_whitespace() {
-                count += 1;
-            }
-        }
+    count::text(text).1
+}
+
+/// Short identifier tying an internal-error response to its server log line.
+pub fn error_reference() -> String {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    let seq = COUNTER.fetch_add(1, Ordering::Relaxed) & 0xffff;
+    format!("{:x}-{:04x}", millis, seq)
+}
+
+/// Message carried by a caught panic payload.
+pub fn panic_message(payload: &(dyn std::any::Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
     }
-    count
 }
 
 /// Build EncodeOptions from EncodeOptionsInput.
@@ -151,6 +914,108 @@ pub fn build_encode_options(input: &EncodeOptionsInput) -> EncodeOptions {
     opts
 }
 
+/// TOON specification versions implemented by the bundled toon-format release.
+pub const SPEC_VERSIONS: &[&str] = &["1.5"];
+
+/// Cargo features this binary was compiled with.
+pub fn enabled_features() -> Vec<&'static str> {
+    [
+        ("mcp", cfg!(feature = "mcp")),
+        ("http", cfg!(feature = "http")),
+        ("tiktoken", cfg!(feature = "tiktoken")),
+        ("sqlite", cfg!(feature = "sqlite")),
+        ("postgres", cfg!(feature = "postgres")),
+        ("jwt", cfg!(feature = "jwt")),
+        ("redis", cfg!(feature = "redis")),
+        ("archive", cfg!(feature = "archive")),
+    ]
+    .into_iter()
+    .filter_map(|(name, enabled)| enabled.then_some(name))
+    .collect()
+}
+
+/// Version, commit, and capability information embedded at build time.
+pub fn build_info() -> VersionResponse {
+    VersionResponse {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_sha: option_env!("TOON_BUILD_GIT_SHA")
+            .unwrap_or("unknown")
+            .to_string(),
+        build_timestamp: option_env!("TOON_BUILD_TIMESTAMP")
+            .and_then(|ts| ts.parse().ok())
+            .unwrap_or(0),
+        features: enabled_features().into_iter().map(String::from).collect(),
+        toon_format_version: option_env!("TOON_BUILD_TOON_FORMAT")
+            .unwrap_or("unknown")
+            .to_string(),
+        spec_versions: SPEC_VERSIONS.iter().map(|v| v.to_string()).collect(),
+    }
+}
+
+/// Largest request body the HTTP server accepts (axum's default limit).
+pub const MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+/// Supported formats, options, limits, and compiled-in features.
+pub fn capabilities() -> CapabilitiesResponse {
+    let info = build_info();
+    let strings =
+        |values: &[&str]| -> Vec<String> { values.iter().map(|v| v.to_string()).collect() };
+
+    CapabilitiesResponse {
+        toon_format_version: info.toon_format_version,
+        spec_versions: info.spec_versions,
+        features: info.features,
+        input_formats: strings(&["json", "toon"]),
+        output_formats: strings(option_values("output_format")),
+        delimiters: strings(option_values("delimiter")),
+        tokenizers: strings(&["estimate"]),
+        limits: CapabilityLimits {
+            max_body_bytes: MAX_BODY_BYTES,
+            max_indent: 8,
+            join_max_rows: DEFAULT_JOIN_MAX_ROWS,
+            query_max_rows: DEFAULT_QUERY_MAX_ROWS,
+            sql_batch_size: DEFAULT_SQL_BATCH_SIZE,
+        },
+        defaults: OptionDefaults {
+            delimiter: "comma".to_string(),
+            indent: 2,
+            fold_keys: false,
+            strict: true,
+            coerce_types: true,
+            expand_paths: false,
+            output_format: "json".to_string(),
+        },
+    }
+}
+
+/// Accepted values of enumerated tool arguments, keyed by argument name.
+pub fn option_values(argument: &str) -> &'static [&'static str] {
+    match argument {
+        "delimiter" => &["comma", "tab", "pipe"],
+        "output_format" => &["json", "json_pretty", "ndjson"],
+        "mode" => &["mask", "hash", "pseudonymize", "hmac"],
+        "detectors" => anonymize::BUILTIN_DETECTORS,
+        "how" => &["inner", "left"],
+        "direction" => &["wide", "long"],
+        "op" => &["count", "sum", "avg", "min", "max"],
+        "blob_policy" => &["keep", "drop", "truncate", "hash"],
+        "line_ending" => &["lf", "crlf"],
+        "strategy" => &["manual", "auto"],
+        "nested_tables" => &["keep", "prefix", "link"],
+        "dataset" => demo::NAMES,
+        _ => &[],
+    }
+}
+
+/// Values of `argument` starting with `prefix`, for client autocompletion.
+pub fn complete_option(argument: &str, prefix: &str) -> Vec<String> {
+    option_values(argument)
+        .iter()
+        .filter(|value| value.starts_with(prefix))
+        .map(|value| value.to_string())
+        .collect()
+}
+
 /// Build DecodeOptions from DecodeRequest.
 pub fn build_decode_options(request: &DecodeRequest) -> DecodeOptions {
     let mut opts = DecodeOptions::new();
@@ -177,11 +1042,106 @@ pub fn format_json_output(
 ) -> Result<String, ToonCoreError> {
     match output_format {
         Some("json_pretty") => serde_json::to_string_pretty(value),
+        Some("ndjson") => return format_ndjson(value),
         _ => serde_json::to_string(value),
     }
     .map_err(|e| ToonCoreError::SerializationError(e.to_string()))
 }
 
+/// Render rows as newline-delimited JSON, one compact value per line.
+///
+/// Rows are the elements of a root array, or of the only field of a root
+/// object holding an array (the usual shape of a named TOON table). Any other
+/// value is written as a single line.
+pub fn format_ndjson(value: &serde_json::Value) -> Result<String, ToonCoreError> {
+    let mut out = String::new();
+    for row in ndjson_rows(value) {
+        let line = serde_json::to_string(row)
+            .map_err(|e| ToonCoreError::SerializationError(e.to_string()))?;
+        out.push_str(&line);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// The rows [`format_ndjson`] writes one per line.
+pub(crate) fn ndjson_rows(value: &serde_json::Value) -> &[serde_json::Value] {
+    match value {
+        serde_json::Value::Array(items) => items.as_slice(),
+        serde_json::Value::Object(map) if map.len() == 1 => match map.values().next() {
+            Some(serde_json::Value::Array(items)) => items.as_slice(),
+            _ => std::slice::from_ref(value),
+        },
+        _ => std::slice::from_ref(value),
+    }
+}
+
+/// Rows per page when `page` or `cursor` is given without `page_size`.
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+
+/// Cut a decoded document down to the page of rows selected by `request`.
+///
+/// Rows follow the same rule as [`format_ndjson`]: a root array, or the
+/// array held by the only field of a root object, which keeps its key.
+/// Returns `None`, leaving the value whole, when no paging option is set.
+/// `cursor` (from a previous page's `next_cursor`) takes precedence over
+/// the 1-based `page`.
+pub fn paginate(
+    value: &mut serde_json::Value,
+    request: &DecodeRequest,
+) -> Result<Option<PageInfo>, ToonCoreError> {
+    if request.page.is_none() && request.page_size.is_none() && request.cursor.is_none() {
+        return Ok(None);
+    }
+
+    let page_size = request.page_size.unwrap_or(DEFAULT_PAGE_SIZE);
+    if page_size == 0 {
+        return Err(ToonCoreError::InvalidOption(
+            "page_size must be at least 1".to_string(),
+        ));
+    }
+    let offset = match (&request.cursor, request.page) {
+        (Some(cursor), _) => cursor
+            .parse::<usize>()
+            .map_err(|_| ToonCoreError::InvalidOption(format!("invalid cursor '{}'", cursor)))?,
+        (None, Some(0)) => {
+            return Err(ToonCoreError::InvalidOption(
+                "page numbers start at 1".to_string(),
+            ))
+        }
+        (None, Some(page)) => (page - 1).saturating_mul(page_size),
+        (None, None) => 0,
+    };
+
+    let rows = match value {
+        serde_json::Value::Array(items) => items,
+        serde_json::Value::Object(map) if map.len() == 1 => match map.values_mut().next() {
+            Some(serde_json::Value::Array(items)) => items,
+            _ => return Err(not_paginable()),
+        },
+        _ => return Err(not_paginable()),
+    };
+
+    let total_rows = rows.len();
+    let start = offset.min(total_rows);
+    let end = start.saturating_add(page_size).min(total_rows);
+    rows.truncate(end);
+    rows.drain(..start);
+
+    Ok(Some(PageInfo {
+        total_rows,
+        offset: start,
+        returned: end - start,
+        next_cursor: (end < total_rows).then(|| end.to_string()),
+    }))
+}
+
+fn not_paginable() -> ToonCoreError {
+    ToonCoreError::InvalidOption(
+        "pagination needs a root array or an object with a single array field".to_string(),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -235,6 +1195,7 @@ mod tests {
             coerce_types: None,
             expand_paths: None,
             output_format: None,
+            ..Default::default()
         };
 
         let decoded = decode_toon(&decode_req.toon, &decode_req).unwrap();
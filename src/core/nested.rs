@@ -0,0 +1,342 @@
+//! Flattening of one-to-many data into tables (`nested_tables`).
+//!
+//! An array of orders that each hold an array of line items cannot be a TOON
+//! table, so the encoder writes every order as its own block and repeats
+//! the item keys in each one. Two layouts keep such data tabular:
+//!
+//! - `prefix`: one row per child, holding the parent's columns followed by
+//!   the child's columns prefixed with the array's key (`items.sku`).
+//!   Parents without children keep one row with null child columns.
+//! - `link`: the parent table without the nested array, plus a child table
+//!   named `<parent>_<child>` next to it whose first column refers to the
+//!   parent's key column (`id`, or the first column with unique values).
+//!   A root array becomes `{"rows": [...], "rows_<child>": [...]}`.
+//!
+//! An array qualifies when its rows are objects of primitives except for one
+//! key holding arrays of flat objects.
+
+use serde_json::{Map, Value};
+
+use super::ToonCoreError;
+
+/// Key given to a root array in the `link` layout.
+const ROOT_KEY: &str = "rows";
+
+/// How to lay out one-to-many data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NestedTables {
+    /// Leave nested arrays as they are
+    Keep,
+    /// One row per child with prefixed child columns
+    Prefix,
+    /// Separate child tables with a foreign-key column
+    Link,
+}
+
+impl NestedTables {
+    /// Parse a layout name ("keep", "prefix", or "link").
+    pub fn parse(name: Option<&str>) -> Result<Self, ToonCoreError> {
+        match name {
+            None | Some("keep") => Ok(NestedTables::Keep),
+            Some("prefix") => Ok(NestedTables::Prefix),
+            Some("link") => Ok(NestedTables::Link),
+            Some(other) => Err(ToonCoreError::InvalidOption(format!(
+                "unknown nested_tables layout '{}' (expected keep, prefix, or link)",
+                other
+            ))),
+        }
+    }
+}
+
+/// Flatten every qualifying array in `value`. Returns the number of arrays
+/// flattened.
+pub fn flatten(value: &mut Value, layout: NestedTables) -> usize {
+    if layout == NestedTables::Keep {
+        return 0;
+    }
+    if let Value::Array(rows) = value {
+        if let Some(flat) = flatten_rows(ROOT_KEY, rows, layout) {
+            *value = match flat {
+                Flat::Rows(rows) => Value::Array(rows),
+                Flat::Linked(parents, (child_key, children)) => {
+                    let mut map = Map::new();
+                    map.insert(ROOT_KEY.to_string(), Value::Array(parents));
+                    map.insert(child_key, Value::Array(children));
+                    Value::Object(map)
+                }
+            };
+            return 1;
+        }
+    }
+    flatten_below(value, layout)
+}
+
+fn flatten_below(value: &mut Value, layout: NestedTables) -> usize {
+    match value {
+        Value::Object(map) => {
+            let mut count = 0;
+            let taken: Vec<String> = map.keys().cloned().collect();
+            let mut rebuilt = Map::new();
+            for (key, mut child) in std::mem::take(map) {
+                let flat = match &child {
+                    Value::Array(rows) => flatten_rows(&key, rows, layout),
+                    _ => None,
+                };
+                // A child table must not replace a member of the same name.
+                let flat = flat.filter(|flat| {
+                    !matches!(flat, Flat::Linked(_, (child_key, _)) if taken.contains(child_key))
+                });
+                match flat {
+                    Some(Flat::Rows(rows)) => {
+                        count += 1;
+                        rebuilt.insert(key, Value::Array(rows));
+                    }
+                    Some(Flat::Linked(parents, (child_key, children))) => {
+                        count += 1;
+                        rebuilt.insert(key, Value::Array(parents));
+                        rebuilt.insert(child_key, Value::Array(children));
+                    }
+                    None => {
+                        count += flatten_below(&mut child, layout);
+                        rebuilt.insert(key, child);
+                    }
+                }
+            }
+            *map = rebuilt;
+            count
+        }
+        Value::Array(items) => items
+            .iter_mut()
+            .map(|item| flatten_below(item, layout))
+            .sum(),
+        _ => 0,
+    }
+}
+
+enum Flat {
+    /// Rows replacing the array
+    Rows(Vec<Value>),
+    /// Parent rows, and the child table's key and rows
+    Linked(Vec<Value>, (String, Vec<Value>)),
+}
+
+fn flatten_rows(key: &str, rows: &[Value], layout: NestedTables) -> Option<Flat> {
+    let parents: Vec<&Map<String, Value>> = rows.iter().filter_map(Value::as_object).collect();
+    if parents.is_empty() || parents.len() < rows.len() {
+        return None;
+    }
+    let nested = nested_key(&parents)?;
+    let parent_columns = columns(parents.iter().copied(), |k| k != nested);
+    let all_children: Vec<&Map<String, Value>> = parents
+        .iter()
+        .flat_map(|parent| children(parent, nested))
+        .collect();
+    let child_columns = columns(all_children.iter().copied(), |_| true);
+
+    match layout {
+        NestedTables::Keep => None,
+        NestedTables::Prefix => {
+            let prefixed: Vec<String> = child_columns
+                .iter()
+                .map(|column| format!("{}.{}", nested, column))
+                .collect();
+            if prefixed
+                .iter()
+                .any(|column| parent_columns.contains(column))
+            {
+                return None;
+            }
+            let mut flat = Vec::new();
+            for parent in &parents {
+                let base = cells(parent, &parent_columns);
+                let items = children(parent, nested);
+                if items.is_empty() {
+                    let mut flat_row = base.clone();
+                    for column in &prefixed {
+                        flat_row.insert(column.clone(), Value::Null);
+                    }
+                    flat.push(Value::Object(flat_row));
+                }
+                for child in items {
+                    let mut flat_row = base.clone();
+                    for (column, name) in child_columns.iter().zip(&prefixed) {
+                        let cell = child.get(column).cloned().unwrap_or(Value::Null);
+                        flat_row.insert(name.clone(), cell);
+                    }
+                    flat.push(Value::Object(flat_row));
+                }
+            }
+            Some(Flat::Rows(flat))
+        }
+        NestedTables::Link => {
+            let id = key_column(&parents, &parent_columns)?;
+            let foreign_key = format!("{}_{}", key, id);
+            if child_columns.contains(&foreign_key) {
+                return None;
+            }
+            let mut linked = Vec::new();
+            for parent in &parents {
+                for child in children(parent, nested) {
+                    let mut child_row = Map::new();
+                    child_row.insert(foreign_key.clone(), parent[id.as_str()].clone());
+                    child_row.extend(cells(child, &child_columns));
+                    linked.push(Value::Object(child_row));
+                }
+            }
+            let parents = parents
+                .iter()
+                .map(|parent| Value::Object(cells(parent, &parent_columns)))
+                .collect();
+            Some(Flat::Linked(
+                parents,
+                (format!("{}_{}", key, nested), linked),
+            ))
+        }
+    }
+}
+
+/// The one key whose values are arrays of flat objects, when every other
+/// value is a primitive.
+fn nested_key<'a>(parents: &[&'a Map<String, Value>]) -> Option<&'a str> {
+    let mut nested: Option<&str> = None;
+    for parent in parents {
+        for (key, value) in parent.iter() {
+            match value {
+                Value::Array(items) => {
+                    let flat_objects = items.iter().all(|item| {
+                        item.as_object()
+                            .is_some_and(|child| child.values().all(is_primitive))
+                    });
+                    if !flat_objects || nested.is_some_and(|n| n != key.as_str()) {
+                        return None;
+                    }
+                    nested = Some(key.as_str());
+                }
+                Value::Object(_) => return None,
+                _ => {}
+            }
+        }
+    }
+    nested
+}
+
+/// The rows held by `parent`'s nested array `key`.
+fn children<'a>(parent: &'a Map<String, Value>, key: &str) -> Vec<&'a Map<String, Value>> {
+    match parent.get(key) {
+        Some(Value::Array(items)) => items.iter().filter_map(Value::as_object).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Column names of `rows` accepted by `keep`, in order of first appearance.
+fn columns<'a>(
+    rows: impl Iterator<Item = &'a Map<String, Value>>,
+    keep: impl Fn(&str) -> bool,
+) -> Vec<String> {
+    let mut columns: Vec<String> = Vec::new();
+    for row in rows {
+        for key in row.keys() {
+            if keep(key) && !columns.contains(key) {
+                columns.push(key.clone());
+            }
+        }
+    }
+    columns
+}
+
+/// `source`'s values for `columns`, null where missing.
+fn cells(source: &Map<String, Value>, columns: &[String]) -> Map<String, Value> {
+    columns
+        .iter()
+        .map(|column| {
+            let cell = source.get(column).cloned().unwrap_or(Value::Null);
+            (column.clone(), cell)
+        })
+        .collect()
+}
+
+/// `id` if its values are present and unique, otherwise the first such column.
+fn key_column(parents: &[&Map<String, Value>], columns: &[String]) -> Option<String> {
+    let unique = |column: &String| {
+        let mut seen = std::collections::HashSet::new();
+        parents.iter().all(|parent| match parent.get(column) {
+            Some(value) if !value.is_null() => seen.insert(value.to_string()),
+            _ => false,
+        })
+    };
+    let id = "id".to_string();
+    if columns.contains(&id) && unique(&id) {
+        return Some(id);
+    }
+    columns.iter().find(|column| unique(column)).cloned()
+}
+
+fn is_primitive(value: &Value) -> bool {
+    !value.is_array() && !value.is_object()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn orders() -> Value {
+        json!({"orders": [
+            {"id": 7, "customer": "Alice", "items": [
+                {"sku": "A1", "qty": 2},
+                {"sku": "B2", "qty": 1}
+            ]},
+            {"id": 8, "customer": "Bob", "items": []}
+        ]})
+    }
+
+    #[test]
+    fn test_flatten_prefix() {
+        let mut value = orders();
+        assert_eq!(flatten(&mut value, NestedTables::Prefix), 1);
+        assert_eq!(
+            value,
+            json!({"orders": [
+                {"id": 7, "customer": "Alice", "items.sku": "A1", "items.qty": 2},
+                {"id": 7, "customer": "Alice", "items.sku": "B2", "items.qty": 1},
+                {"id": 8, "customer": "Bob", "items.sku": null, "items.qty": null}
+            ]})
+        );
+    }
+
+    #[test]
+    fn test_flatten_link() {
+        let mut value = orders();
+        assert_eq!(flatten(&mut value, NestedTables::Link), 1);
+        assert_eq!(
+            value,
+            json!({
+                "orders": [
+                    {"id": 7, "customer": "Alice"},
+                    {"id": 8, "customer": "Bob"}
+                ],
+                "orders_items": [
+                    {"orders_id": 7, "sku": "A1", "qty": 2},
+                    {"orders_id": 7, "sku": "B2", "qty": 1}
+                ]
+            })
+        );
+
+        let mut root = orders()["orders"].take();
+        assert_eq!(flatten(&mut root, NestedTables::Link), 1);
+        assert_eq!(root["rows"][1], json!({"id": 8, "customer": "Bob"}));
+        assert_eq!(
+            root["rows_items"][1],
+            json!({"rows_id": 7, "sku": "B2", "qty": 1})
+        );
+    }
+
+    #[test]
+    fn test_flatten_skips_other_shapes() {
+        let mut value = json!({"a": [{"x": [1, 2]}], "b": [{"p": [{"q": 1}], "r": [{"s": 2}]}]});
+        let before = value.clone();
+        assert_eq!(flatten(&mut value, NestedTables::Prefix), 0);
+        assert_eq!(value, before);
+        assert!(NestedTables::parse(Some("join")).is_err());
+    }
+}
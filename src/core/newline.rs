@@ -0,0 +1,88 @@
+//! Line endings and byte order marks.
+//!
+//! The encoder writes `\n` between lines and nothing after the last one.
+//! Files checked into a Windows repository, or tools that expect every line
+//! to be terminated, need `\r\n` or a final newline instead (`line_ending`,
+//! `final_newline`). In the other direction, documents saved by Windows
+//! tools often start with a UTF-8 byte order mark and end lines with `\r\n`;
+//! [`normalize`] removes both before decoding. TOON escapes line breaks
+//! inside strings, so every raw `\n` ends a line.
+
+use std::borrow::Cow;
+
+use super::{EncodeOptionsInput, ToonCoreError};
+
+/// The UTF-8 byte order mark some editors write at the start of a file.
+pub const BOM: char = '\u{feff}';
+
+/// `toon` without a leading byte order mark and with `\r\n` line endings
+/// replaced by `\n`.
+pub fn normalize(toon: &str) -> Cow<'_, str> {
+    let toon = toon.strip_prefix(BOM).unwrap_or(toon);
+    if toon.contains('\r') {
+        Cow::Owned(toon.replace("\r\n", "\n"))
+    } else {
+        Cow::Borrowed(toon)
+    }
+}
+
+/// Rewrite `toon`'s line endings as `options` asks.
+pub fn apply(toon: String, options: &EncodeOptionsInput) -> Result<String, ToonCoreError> {
+    let crlf = match options.line_ending.as_deref() {
+        None | Some("lf") => false,
+        Some("crlf") => true,
+        Some(other) => {
+            return Err(ToonCoreError::InvalidOption(format!(
+                "unknown line ending '{}' (expected lf or crlf)",
+                other
+            )))
+        }
+    };
+    let mut toon = if crlf {
+        toon.replace('\n', "\r\n")
+    } else {
+        toon
+    };
+    if options.final_newline.unwrap_or(false) && !toon.is_empty() && !toon.ends_with('\n') {
+        toon.push_str(if crlf { "\r\n" } else { "\n" });
+    }
+    Ok(toon)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(line_ending: Option<&str>, final_newline: Option<bool>) -> EncodeOptionsInput {
+        EncodeOptionsInput {
+            line_ending: line_ending.map(str::to_string),
+            final_newline,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_apply_line_endings() {
+        let toon = "a: 1\nb: 2".to_string();
+        assert_eq!(apply(toon.clone(), &options(None, None)).unwrap(), toon);
+        assert_eq!(
+            apply(toon.clone(), &options(Some("crlf"), None)).unwrap(),
+            "a: 1\r\nb: 2"
+        );
+        assert_eq!(
+            apply(toon.clone(), &options(Some("lf"), Some(true))).unwrap(),
+            "a: 1\nb: 2\n"
+        );
+        assert_eq!(
+            apply(toon.clone(), &options(Some("crlf"), Some(true))).unwrap(),
+            "a: 1\r\nb: 2\r\n"
+        );
+        assert!(apply(toon, &options(Some("cr"), None)).is_err());
+    }
+
+    #[test]
+    fn test_normalize_strips_bom_and_crlf() {
+        assert_eq!(normalize("\u{feff}a: 1\r\nb: 2\r\n"), "a: 1\nb: 2\n");
+        assert!(matches!(normalize("a: 1\nb: 2"), Cow::Borrowed(_)));
+    }
+}
@@ -0,0 +1,18 @@
+//! Helpers for addressing values inside a JSON document.
+//!
+//! Paths use dotted keys with bracketed array indices, e.g. `users[0].email`.
+//! The empty string addresses the document root.
+
+/// Path of an object member below `parent`.
+pub fn key(parent: &str, key: &str) -> String {
+    if parent.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", parent, key)
+    }
+}
+
+/// Path of an array element below `parent`.
+pub fn index(parent: &str, index: usize) -> String {
+    format!("{}[{}]", parent, index)
+}
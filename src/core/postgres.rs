@@ -0,0 +1,140 @@
+//! Read-only PostgreSQL queries (requires the `postgres` feature).
+//!
+//! Queries run inside a `READ ONLY` transaction with a statement timeout.
+//! Rows are converted server-side with `row_to_json`, so every column type
+//! Postgres can render as JSON is supported without client-side mapping.
+
+use serde_json::Value;
+use tokio_postgres::types::{ToSql, Type};
+use tokio_postgres::NoTls;
+
+use super::ToonCoreError;
+
+/// Per-statement timeout applied to every query.
+const STATEMENT_TIMEOUT: &str = "30s";
+
+type BoxedParam = Box<dyn ToSql + Sync + Send>;
+
+fn db_error(e: tokio_postgres::Error) -> ToonCoreError {
+    ToonCoreError::DataSource(e.to_string())
+}
+
+/// `None` for JSON null, otherwise the converted value (or an error if it does not fit).
+fn nullable<T>(value: &Value, convert: impl Fn(&Value) -> Option<T>) -> Option<Option<T>> {
+    if value.is_null() {
+        Some(None)
+    } else {
+        convert(value).map(Some)
+    }
+}
+
+/// Convert a JSON parameter to the type Postgres inferred for its placeholder.
+fn bind(position: usize, ty: &Type, value: &Value) -> Result<BoxedParam, ToonCoreError> {
+    let bound: Option<BoxedParam> = match *ty {
+        Type::BOOL => nullable(value, Value::as_bool).map(|v| Box::new(v) as BoxedParam),
+        Type::INT2 => nullable(value, |v| v.as_i64().and_then(|n| i16::try_from(n).ok()))
+            .map(|v| Box::new(v) as BoxedParam),
+        Type::INT4 => nullable(value, |v| v.as_i64().and_then(|n| i32::try_from(n).ok()))
+            .map(|v| Box::new(v) as BoxedParam),
+        Type::INT8 => nullable(value, Value::as_i64).map(|v| Box::new(v) as BoxedParam),
+        Type::FLOAT4 => {
+            nullable(value, |v| v.as_f64().map(|n| n as f32)).map(|v| Box::new(v) as BoxedParam)
+        }
+        Type::FLOAT8 => nullable(value, Value::as_f64).map(|v| Box::new(v) as BoxedParam),
+        Type::TEXT | Type::VARCHAR | Type::BPCHAR | Type::NAME | Type::UNKNOWN => {
+            nullable(value, |v| match v {
+                Value::String(s) => Some(s.clone()),
+                other => Some(other.to_string()),
+            })
+            .map(|v| Box::new(v) as BoxedParam)
+        }
+        Type::JSON | Type::JSONB => {
+            nullable(value, |v| Some(v.clone())).map(|v| Box::new(v) as BoxedParam)
+        }
+        _ => {
+            return Err(ToonCoreError::InvalidOption(format!(
+                "parameter ${} has unsupported type {}; cast the placeholder, e.g. ${}::text",
+                position, ty, position
+            )))
+        }
+    };
+    bound.ok_or_else(|| {
+        ToonCoreError::InvalidOption(format!(
+            "parameter ${} ({}) cannot be bound as {}",
+            position, value, ty
+        ))
+    })
+}
+
+/// Run a read-only query and return at most `max_rows` rows as objects.
+///
+/// The second element reports whether more rows were available.
+pub async fn query(
+    url: &str,
+    sql: &str,
+    params: &[Value],
+    max_rows: usize,
+) -> Result<(Vec<Value>, bool), ToonCoreError> {
+    let (mut client, connection) = tokio_postgres::connect(url, NoTls)
+        .await
+        .map_err(db_error)?;
+    tokio::spawn(async move {
+        let _ = connection.await;
+    });
+
+    let transaction = client
+        .build_transaction()
+        .read_only(true)
+        .start()
+        .await
+        .map_err(db_error)?;
+    transaction
+        .batch_execute(&format!(
+            "SET LOCAL statement_timeout = '{}'",
+            STATEMENT_TIMEOUT
+        ))
+        .await
+        .map_err(db_error)?;
+
+    let wrapped = format!(
+        "SELECT row_to_json(q)::text FROM ({}) q LIMIT {}",
+        sql.trim().trim_end_matches(';'),
+        max_rows.saturating_add(1)
+    );
+    let statement = transaction.prepare(&wrapped).await.map_err(db_error)?;
+    if statement.params().len() != params.len() {
+        return Err(ToonCoreError::InvalidOption(format!(
+            "query expects {} parameters, got {}",
+            statement.params().len(),
+            params.len()
+        )));
+    }
+    let bound = statement
+        .params()
+        .iter()
+        .zip(params)
+        .enumerate()
+        .map(|(i, (ty, value))| bind(i + 1, ty, value))
+        .collect::<Result<Vec<_>, _>>()?;
+    let refs: Vec<&(dyn ToSql + Sync)> = bound
+        .iter()
+        .map(|p| p.as_ref() as &(dyn ToSql + Sync))
+        .collect();
+
+    let rows = transaction
+        .query(&statement, &refs)
+        .await
+        .map_err(db_error)?;
+    let mut out = rows
+        .iter()
+        .map(|row| {
+            let text: String = row.try_get(0).map_err(db_error)?;
+            serde_json::from_str(&text).map_err(|e| ToonCoreError::DataSource(e.to_string()))
+        })
+        .collect::<Result<Vec<Value>, _>>()?;
+    transaction.rollback().await.map_err(db_error)?;
+
+    let truncated = out.len() > max_rows;
+    out.truncate(max_rows);
+    Ok((out, truncated))
+}
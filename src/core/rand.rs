@@ -0,0 +1,20 @@
+//! splitmix64, the small generator behind row sampling and chaos faults.
+//!
+//! Neither needs more than a fast, evenly spread stream of numbers, so a
+//! dependency on a full random number crate isn't worth it.
+
+/// Increment added to the state before each draw.
+pub const GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// Scramble an already advanced state into the next output.
+pub fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Advance `state` and return the next number.
+pub fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(GAMMA);
+    mix(*state)
+}
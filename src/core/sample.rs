@@ -0,0 +1,291 @@
+//! Sampled statistics for very large documents (`sample_rows`).
+//!
+//! Exact statistics encode the whole document, which for inputs of hundreds
+//! of megabytes takes minutes. Such documents are nearly always one long
+//! array with a little around it, so [`estimate`] measures the document with
+//! that array cut down to a stratified sample of its rows, then adds the
+//! unsampled rows at the sampled rows' mean size, with a margin at 95%
+//! confidence.
+//!
+//! Options that compare rows with each other, such as `dedupe_rows` or
+//! `dictionary`, only see the sample, so their effect is estimated poorly.
+
+use serde_json::{Map, Value};
+
+use super::{
+    count, encode_json, path, rand, EncodeOptionsInput, SampleInfo, StatsResponse, ToonCoreError,
+};
+
+/// Two-sided z-score for 95% confidence.
+const Z_95: f64 = 1.96;
+
+/// Seed for the offsets within strata, so a document is sampled the same
+/// way on every run.
+const SEED: u64 = 0x746F_6F6E_5F72_6F77;
+
+/// Estimated bytes and tokens of one rendering.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Measure {
+    pub bytes: usize,
+    pub tokens: usize,
+    pub bytes_margin: usize,
+    pub tokens_margin: usize,
+}
+
+impl Measure {
+    /// Estimated `(bytes, tokens)`.
+    pub fn counts(&self) -> (usize, usize) {
+        (self.bytes, self.tokens)
+    }
+}
+
+/// Sampled estimate of a document's JSON and TOON sizes.
+#[derive(Debug)]
+pub struct Estimate {
+    pub json: Measure,
+    pub toon: Measure,
+    pub sample: SampleInfo,
+}
+
+impl Estimate {
+    /// Add the margins and sample description to `stats`.
+    pub fn annotate(self, stats: &mut StatsResponse) {
+        stats.json.bytes_margin = Some(self.json.bytes_margin);
+        stats.json.tokens_margin = Some(self.json.tokens_margin);
+        stats.toon.bytes_margin = Some(self.toon.bytes_margin);
+        stats.toon.tokens_margin = Some(self.toon.tokens_margin);
+        stats.sample = Some(self.sample);
+    }
+}
+
+/// Estimate the sizes of `json` from `rows` rows of its largest array.
+/// Returns `None` when `rows` is unset or the array is no longer than that,
+/// in which case exact counting is cheap enough.
+pub fn estimate(
+    json: &Value,
+    options: &EncodeOptionsInput,
+    rows: Option<usize>,
+) -> Result<Option<Estimate>, ToonCoreError> {
+    let Some(wanted) = rows else {
+        return Ok(None);
+    };
+    if wanted < 2 {
+        return Err(ToonCoreError::InvalidOption(format!(
+            "sample_rows must be at least 2, got {}",
+            wanted
+        )));
+    }
+    let mut steps = Vec::new();
+    let mut largest = None;
+    find_largest(json, &mut steps, &mut largest);
+    let Some(Largest { steps, rows: all }) = largest.filter(|l| l.rows.len() > wanted) else {
+        return Ok(None);
+    };
+
+    let sample: Vec<Value> = strata(all.len(), wanted)
+        .map(|index| all[index].clone())
+        .collect();
+    let mut json_rows = Vec::with_capacity(sample.len());
+    let mut toon_rows = Vec::with_capacity(sample.len());
+    for row in &sample {
+        let (bytes, tokens) = count::json(row, false)?;
+        // Each row but the first is preceded by a comma.
+        json_rows.push((bytes + 1, tokens + 1));
+        toon_rows.push(toon_row(row, options)?);
+    }
+
+    let cut = with_rows(json, &steps, sample);
+    let json_base = count::json(&cut, false)?;
+    let toon_base = count::text(&encode_json(&cut, options)?);
+
+    Ok(Some(Estimate {
+        json: extrapolate(json_base, &json_rows, all.len()),
+        toon: extrapolate(toon_base, &toon_rows, all.len()),
+        sample: SampleInfo {
+            path: steps.iter().fold(String::new(), |at, step| match step {
+                Step::Key(key) => path::key(&at, key),
+                Step::Index(index) => path::index(&at, *index),
+            }),
+            rows_total: all.len(),
+            rows_sampled: wanted,
+            confidence: 0.95,
+        },
+    }))
+}
+
+#[derive(Clone)]
+enum Step {
+    Key(String),
+    Index(usize),
+}
+
+struct Largest<'a> {
+    steps: Vec<Step>,
+    rows: &'a [Value],
+}
+
+/// The longest array in `value`; the first found on ties.
+fn find_largest<'a>(value: &'a Value, steps: &mut Vec<Step>, largest: &mut Option<Largest<'a>>) {
+    match value {
+        Value::Array(items) => {
+            if largest.as_ref().is_none_or(|l| items.len() > l.rows.len()) {
+                *largest = Some(Largest {
+                    steps: steps.clone(),
+                    rows: items,
+                });
+            }
+            for (index, item) in items.iter().enumerate() {
+                steps.push(Step::Index(index));
+                find_largest(item, steps, largest);
+                steps.pop();
+            }
+        }
+        Value::Object(map) => {
+            for (key, child) in map {
+                steps.push(Step::Key(key.clone()));
+                find_largest(child, steps, largest);
+                steps.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// One index from each of `wanted` equal strata of `total` rows, at a
+/// pseudo-random offset within the stratum. The sample spans the whole
+/// array without lining up with data that repeats every few rows, as a
+/// fixed offset would.
+fn strata(total: usize, wanted: usize) -> impl Iterator<Item = usize> {
+    let mut state = SEED;
+    (0..wanted).map(move |stratum| {
+        let start = stratum * total / wanted;
+        let end = (stratum + 1) * total / wanted;
+        start + (rand::splitmix64(&mut state) % (end - start) as u64) as usize
+    })
+}
+
+/// Cost of one more `row` in a TOON array: the difference between encoding
+/// it twice and once, which cancels the array header whatever form the
+/// encoder picks (table, list, or inline).
+fn toon_row(row: &Value, options: &EncodeOptionsInput) -> Result<(usize, usize), ToonCoreError> {
+    let once = count::text(&encode_json(&Value::Array(vec![row.clone()]), options)?);
+    let twice = count::text(&encode_json(
+        &Value::Array(vec![row.clone(), row.clone()]),
+        options,
+    )?);
+    Ok((
+        twice.0.saturating_sub(once.0),
+        twice.1.saturating_sub(once.1),
+    ))
+}
+
+/// `value` with the array at `steps` replaced by `rows`. Only the values on
+/// the way to the array are copied, never the array itself.
+fn with_rows(value: &Value, steps: &[Step], mut rows: Vec<Value>) -> Value {
+    let Some((step, rest)) = steps.split_first() else {
+        return Value::Array(rows);
+    };
+    match (value, step) {
+        (Value::Object(map), Step::Key(key)) => {
+            let mut copy = Map::new();
+            for (name, child) in map {
+                let child = if name == key {
+                    with_rows(child, rest, std::mem::take(&mut rows))
+                } else {
+                    child.clone()
+                };
+                copy.insert(name.clone(), child);
+            }
+            Value::Object(copy)
+        }
+        (Value::Array(items), Step::Index(index)) => Value::Array(
+            items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| {
+                    if i == *index {
+                        with_rows(item, rest, std::mem::take(&mut rows))
+                    } else {
+                        item.clone()
+                    }
+                })
+                .collect(),
+        ),
+        _ => value.clone(),
+    }
+}
+
+/// `base`, the size with only the sampled rows, plus the unsampled rows at
+/// the mean of `sampled`, with margins from the sample's variance.
+fn extrapolate(base: (usize, usize), sampled: &[(usize, usize)], total_rows: usize) -> Measure {
+    let bytes: Vec<f64> = sampled.iter().map(|(bytes, _)| *bytes as f64).collect();
+    let tokens: Vec<f64> = sampled.iter().map(|(_, tokens)| *tokens as f64).collect();
+    let (extra_bytes, bytes_margin) = unsampled_total(&bytes, total_rows);
+    let (extra_tokens, tokens_margin) = unsampled_total(&tokens, total_rows);
+    Measure {
+        bytes: base.0 + extra_bytes.round() as usize,
+        tokens: base.1 + extra_tokens.round() as usize,
+        bytes_margin: bytes_margin.ceil() as usize,
+        tokens_margin: tokens_margin.ceil() as usize,
+    }
+}
+
+/// Estimated total of the rows not in `sample`, and its margin.
+fn unsampled_total(sample: &[f64], total_rows: usize) -> (f64, f64) {
+    let n = sample.len() as f64;
+    let mean = sample.iter().sum::<f64>() / n;
+    let variance = sample.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    let unsampled = total_rows as f64 - n;
+    // Finite population correction: the margin vanishes as the sample
+    // approaches the whole array.
+    let standard_error = (variance / n * (1.0 - n / total_rows as f64)).sqrt();
+    (unsampled * mean, Z_95 * unsampled * standard_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rows(count: usize) -> Value {
+        let rows: Vec<Value> = (0..count)
+            .map(|i| json!({"id": i, "name": format!("user{}", i % 7), "active": i % 2 == 0}))
+            .collect();
+        json!({"meta": {"source": "test"}, "users": rows})
+    }
+
+    #[test]
+    fn test_strata_span_the_array() {
+        let picked: Vec<usize> = strata(100, 4).collect();
+        for (stratum, index) in picked.iter().enumerate() {
+            assert!((stratum * 25..(stratum + 1) * 25).contains(index));
+        }
+        assert_eq!(strata(100, 4).collect::<Vec<_>>(), picked);
+        assert_eq!(strata(3, 3).collect::<Vec<_>>(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_estimate_is_within_margin() {
+        let json = rows(2000);
+        let options = EncodeOptionsInput::default();
+        let estimate = estimate(&json, &options, Some(50)).unwrap().unwrap();
+        assert_eq!(estimate.sample.path, "users");
+        assert_eq!(estimate.sample.rows_total, 2000);
+
+        let exact_json = count::json(&json, false).unwrap();
+        let exact_toon = count::text(&encode_json(&json, &options).unwrap());
+        for (measure, (bytes, tokens)) in [(estimate.json, exact_json), (estimate.toon, exact_toon)]
+        {
+            assert!(measure.bytes.abs_diff(bytes) <= measure.bytes_margin.max(bytes / 100));
+            assert!(measure.tokens.abs_diff(tokens) <= measure.tokens_margin.max(tokens / 100));
+        }
+    }
+
+    #[test]
+    fn test_estimate_only_for_long_arrays() {
+        let options = EncodeOptionsInput::default();
+        assert!(estimate(&rows(10), &options, Some(10)).unwrap().is_none());
+        assert!(estimate(&rows(10), &options, None).unwrap().is_none());
+        assert!(estimate(&rows(10), &options, Some(1)).is_err());
+    }
+}
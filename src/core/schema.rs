@@ -0,0 +1,320 @@
+//! Helpers driven by JSON Schema documents.
+//!
+//! Only the commonly used subset of JSON Schema is understood: `type`,
+//! `properties`, `required`, `items`, `enum`, `const`, `default`, `examples`,
+//! `format`, numeric bounds, `minItems`, local `$ref`s, and the first branch of
+//! `oneOf`/`anyOf`/`allOf`.
+
+use serde_json::{Map, Value};
+
+use super::{TemplateField, ToonCoreError};
+
+/// Maximum nesting depth followed before giving up (guards recursive `$ref`s).
+const MAX_DEPTH: usize = 16;
+
+/// Generates small synthetic documents that conform to a schema.
+pub struct ExampleGenerator<'a> {
+    root: &'a Value,
+    rows: usize,
+    include_optional: bool,
+}
+
+impl<'a> ExampleGenerator<'a> {
+    pub fn new(root: &'a Value, rows: usize, include_optional: bool) -> Self {
+        Self {
+            root,
+            rows: rows.max(1),
+            include_optional,
+        }
+    }
+
+    /// Generate an example document for the root schema.
+    pub fn generate(&self) -> Result<Value, ToonCoreError> {
+        if !self.root.is_object() {
+            return Err(ToonCoreError::InvalidJson(
+                "schema must be a JSON object".to_string(),
+            ));
+        }
+        Ok(self.example(self.root, "value", 1, 0))
+    }
+
+    /// Example value for `schema`; `name` and `ordinal` vary generated strings and numbers.
+    fn example(&self, schema: &Value, name: &str, ordinal: usize, depth: usize) -> Value {
+        if depth > MAX_DEPTH {
+            return Value::Null;
+        }
+        let schema = resolve(self.root, schema);
+
+        if let Some(value) = schema.get("const") {
+            return value.clone();
+        }
+        if let Some(Value::Array(options)) = schema.get("enum") {
+            if !options.is_empty() {
+                return options[(ordinal - 1) % options.len()].clone();
+            }
+        }
+        if let Some(Value::Array(examples)) = schema.get("examples") {
+            if let Some(first) = examples.first() {
+                return first.clone();
+            }
+        }
+        if let Some(value) = schema.get("default") {
+            return value.clone();
+        }
+        for combinator in ["oneOf", "anyOf"] {
+            if let Some(Value::Array(branches)) = schema.get(combinator) {
+                if let Some(first) = branches.first() {
+                    return self.example(first, name, ordinal, depth + 1);
+                }
+            }
+        }
+        if let Some(Value::Array(parts)) = schema.get("allOf") {
+            let merged = merge_all_of(self.root, parts);
+            return self.example(&merged, name, ordinal, depth + 1);
+        }
+
+        match schema_type(schema) {
+            Some("object") => self.object(schema, ordinal, depth),
+            Some("array") => {
+                let items = schema.get("items");
+                let count = schema
+                    .get("minItems")
+                    .and_then(Value::as_u64)
+                    .map(|n| n as usize)
+                    .unwrap_or(self.rows)
+                    .max(1);
+                Value::Array(
+                    (1..=count)
+                        .map(|i| match items {
+                            Some(items) => self.example(items, name, i, depth + 1),
+                            None => Value::String(format!("{}_{}", name, i)),
+                        })
+                        .collect(),
+                )
+            }
+            Some("integer") => {
+                let minimum = schema.get("minimum").and_then(Value::as_i64).unwrap_or(0);
+                let value = minimum + ordinal as i64;
+                match schema.get("maximum").and_then(Value::as_i64) {
+                    Some(maximum) => Value::from(value.min(maximum)),
+                    None => Value::from(value),
+                }
+            }
+            Some("number") => {
+                let minimum = schema.get("minimum").and_then(Value::as_f64).unwrap_or(0.0);
+                let value = minimum + ordinal as f64 + 0.5;
+                let value = match schema.get("maximum").and_then(Value::as_f64) {
+                    Some(maximum) => value.min(maximum),
+                    None => value,
+                };
+                serde_json::Number::from_f64(value)
+                    .map(Value::Number)
+                    .unwrap_or(Value::Null)
+            }
+            Some("boolean") => Value::Bool(ordinal % 2 == 1),
+            Some("null") => Value::Null,
+            Some("string") => Value::String(example_string(schema, name, ordinal)),
+            _ if schema.get("properties").is_some() => self.object(schema, ordinal, depth),
+            _ => Value::Null,
+        }
+    }
+
+    fn object(&self, schema: &Value, ordinal: usize, depth: usize) -> Value {
+        let required: Vec<&str> = schema
+            .get("required")
+            .and_then(Value::as_array)
+            .map(|r| r.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+
+        let mut out = Map::new();
+        if let Some(Value::Object(properties)) = schema.get("properties") {
+            for (key, property) in properties {
+                if self.include_optional || required.contains(&key.as_str()) {
+                    out.insert(key.clone(), self.example(property, key, ordinal, depth + 1));
+                }
+            }
+        }
+        Value::Object(out)
+    }
+}
+
+/// Follow a local `$ref` (`#/definitions/...` or `#/$defs/...`).
+pub fn resolve<'a>(root: &'a Value, schema: &'a Value) -> &'a Value {
+    let mut current = schema;
+    for _ in 0..MAX_DEPTH {
+        match current.get("$ref").and_then(Value::as_str) {
+            Some(reference) => match reference.strip_prefix('#') {
+                Some(pointer) => match root.pointer(pointer) {
+                    Some(target) => current = target,
+                    None => return current,
+                },
+                None => return current,
+            },
+            None => return current,
+        }
+    }
+    current
+}
+
+/// The first declared type of a schema (`"type": ["string", "null"]` yields "string").
+pub fn schema_type(schema: &Value) -> Option<&str> {
+    match schema.get("type") {
+        Some(Value::String(t)) => Some(t.as_str()),
+        Some(Value::Array(types)) => types
+            .iter()
+            .filter_map(Value::as_str)
+            .find(|t| *t != "null")
+            .or(Some("null")),
+        _ => None,
+    }
+}
+
+/// Merge the `properties` and `required` lists of `allOf` branches.
+fn merge_all_of(root: &Value, parts: &[Value]) -> Value {
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    let mut merged = Map::new();
+    for part in parts {
+        let part = resolve(root, part);
+        if let Some(Value::Object(p)) = part.get("properties") {
+            properties.extend(p.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+        if let Some(Value::Array(r)) = part.get("required") {
+            required.extend(r.iter().cloned());
+        }
+        if let Some(t) = part.get("type") {
+            merged.insert("type".to_string(), t.clone());
+        }
+    }
+    merged.insert("properties".to_string(), Value::Object(properties));
+    merged.insert("required".to_string(), Value::Array(required));
+    Value::Object(merged)
+}
+
+fn example_string(schema: &Value, name: &str, ordinal: usize) -> String {
+    match schema.get("format").and_then(Value::as_str) {
+        Some("email") => format!("user{}@example.com", ordinal),
+        Some("date-time") => format!("2024-01-{:02}T12:00:00Z", ordinal.min(28)),
+        Some("date") => format!("2024-01-{:02}", ordinal.min(28)),
+        Some("time") => "12:00:00".to_string(),
+        Some("uri") | Some("url") => format!("https://example.com/{}/{}", name, ordinal),
+        Some("uuid") => format!("00000000-0000-4000-8000-{:012}", ordinal),
+        Some("ipv4") => format!("192.0.2.{}", ordinal.min(254)),
+        _ => format!("{}_{}", name, ordinal),
+    }
+}
+
+/// Describe the columns of a tabular array from its schema.
+///
+/// Accepts either an array schema or the item schema itself. Columns follow
+/// property declaration order.
+pub fn template_fields(root: &Value) -> Result<Vec<TemplateField>, ToonCoreError> {
+    let mut item = resolve(root, root);
+    if schema_type(item) == Some("array") {
+        item = item
+            .get("items")
+            .map(|items| resolve(root, items))
+            .ok_or_else(|| ToonCoreError::InvalidJson("array schema has no 'items'".to_string()))?;
+    }
+    let merged;
+    if let Some(Value::Array(parts)) = item.get("allOf") {
+        merged = merge_all_of(root, parts);
+        item = &merged;
+    }
+    let Some(Value::Object(properties)) = item.get("properties") else {
+        return Err(ToonCoreError::InvalidJson(
+            "schema must describe an array of objects with 'properties'".to_string(),
+        ));
+    };
+    let required: Vec<&str> = item
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|r| r.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+
+    Ok(properties
+        .iter()
+        .map(|(name, property)| {
+            let property = resolve(root, property);
+            let options = match property.get("enum") {
+                Some(Value::Array(values)) => values
+                    .iter()
+                    .map(|v| match v {
+                        Value::String(s) => s.clone(),
+                        other => other.to_string(),
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            };
+            TemplateField {
+                name: name.clone(),
+                field_type: schema_type(property).unwrap_or("any").to_string(),
+                format: property
+                    .get("format")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+                required: required.contains(&name.as_str()),
+                options,
+            }
+        })
+        .collect())
+}
+
+/// Render a fill-in TOON skeleton for `fields`.
+///
+/// TOON has no comment syntax, so the leading `#` lines describing each
+/// column must be dropped before the filled template is decoded.
+pub fn render_template(name: Option<&str>, fields: &[TemplateField], delimiter: char) -> String {
+    let mut out = String::new();
+    for field in fields {
+        out.push_str(&format!("# {}: {}", field.name, field.field_type));
+        if let Some(format) = &field.format {
+            out.push_str(&format!(" ({})", format));
+        }
+        out.push_str(if field.required {
+            ", required"
+        } else {
+            ", optional"
+        });
+        if !field.options.is_empty() {
+            out.push_str(&format!(", one of {}", field.options.join("|")));
+        }
+        if matches!(field.field_type.as_str(), "object" | "array") {
+            out.push_str(", not tabular");
+        }
+        out.push('\n');
+    }
+
+    let separator = delimiter.to_string();
+    let marker = if delimiter == ',' {
+        ""
+    } else {
+        separator.as_str()
+    };
+    let columns: Vec<String> = fields.iter().map(|f| header_key(&f.name)).collect();
+    let cells: Vec<String> = fields.iter().map(|f| format!("<{}>", f.name)).collect();
+    out.push_str(&format!(
+        "{}[N{}]{{{}}}:\n  {}\n",
+        name.map(header_key).unwrap_or_default(),
+        marker,
+        columns.join(&separator),
+        cells.join(&separator)
+    ));
+    out
+}
+
+/// Quote a key unless it is a bare identifier.
+fn header_key(key: &str) -> String {
+    let bare = key
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.');
+    if bare {
+        key.to_string()
+    } else {
+        serde_json::to_string(key).unwrap_or_else(|_| key.to_string())
+    }
+}
@@ -0,0 +1,140 @@
+//! Table shape assertions for validation.
+//!
+//! Valid syntax is not enough when a model was asked for a specific table:
+//! [`check`] confirms the decoded document is a table with the requested
+//! number of rows and exactly the requested columns.
+
+use std::collections::HashSet;
+
+use serde_json::Value;
+
+use super::{suggest, ValidationError};
+
+/// Check that `value` is a table with `rows` rows and exactly the `columns`
+/// given, in any order. A table is a root array, or the array held by the
+/// only field of a root object. Rows of an empty table have no columns to
+/// check.
+pub fn check(
+    value: &Value,
+    rows: Option<usize>,
+    columns: Option<&[String]>,
+) -> Result<(), ValidationError> {
+    let table = match value {
+        Value::Array(items) => items,
+        Value::Object(map) if map.len() == 1 => match map.values().next() {
+            Some(Value::Array(items)) => items,
+            _ => return Err(error("document is not a table".to_string(), None, None)),
+        },
+        _ => return Err(error("document is not a table".to_string(), None, None)),
+    };
+
+    if let Some(expected) = rows {
+        if table.len() != expected {
+            return Err(error(
+                format!("expected {} rows, found {}", expected, table.len()),
+                None,
+                None,
+            ));
+        }
+    }
+
+    let Some(columns) = columns else {
+        return Ok(());
+    };
+    let expected: HashSet<&str> = columns.iter().map(String::as_str).collect();
+    for (index, row) in table.iter().enumerate() {
+        let Value::Object(row) = row else {
+            return Err(error(
+                "row is not an object".to_string(),
+                Some(index + 1),
+                None,
+            ));
+        };
+        let missing: Vec<&str> = columns
+            .iter()
+            .map(String::as_str)
+            .filter(|column| !row.contains_key(*column))
+            .collect();
+        let unexpected: Vec<&str> = row
+            .keys()
+            .map(String::as_str)
+            .filter(|key| !expected.contains(key))
+            .collect();
+        if missing.is_empty() && unexpected.is_empty() {
+            continue;
+        }
+
+        let mut problems = Vec::new();
+        if !unexpected.is_empty() {
+            problems.push(format!("unexpected columns {}", quoted(&unexpected)));
+        }
+        if !missing.is_empty() {
+            problems.push(format!("missing columns {}", quoted(&missing)));
+        }
+        let suggestion = unexpected
+            .iter()
+            .find_map(|key| suggest::did_you_mean(key, missing.iter().copied()));
+        return Err(error(problems.join(", "), Some(index + 1), suggestion));
+    }
+    Ok(())
+}
+
+fn quoted(names: &[&str]) -> String {
+    names
+        .iter()
+        .map(|name| format!("`{}`", name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn error(message: String, row: Option<usize>, suggestion: Option<String>) -> ValidationError {
+    ValidationError {
+        message,
+        line: None,
+        column: None,
+        suggestion,
+        row,
+        snippet: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn columns(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_check_rows_and_columns() {
+        let users = json!({"users": [
+            {"user_id": 1, "name": "Alice"},
+            {"name": "Bob", "user_id": 2}
+        ]});
+        let wanted = columns(&["user_id", "name"]);
+        assert!(check(&users, Some(2), Some(&wanted)).is_ok());
+
+        let error = check(&users, Some(3), None).unwrap_err();
+        assert_eq!(error.message, "expected 3 rows, found 2");
+
+        let error = check(&json!({"a": 1}), Some(1), None).unwrap_err();
+        assert_eq!(error.message, "document is not a table");
+    }
+
+    #[test]
+    fn test_check_reports_column_mismatch_with_suggestion() {
+        let rows = json!([
+            {"user_id": 1, "name": "Alice"},
+            {"usr_id": 2, "name": "Bob", "role": "admin"}
+        ]);
+        let error = check(&rows, None, Some(&columns(&["user_id", "name"]))).unwrap_err();
+        assert_eq!(error.row, Some(2));
+        assert_eq!(
+            error.message,
+            "unexpected columns `usr_id`, `role`, missing columns `user_id`"
+        );
+        assert_eq!(error.suggestion.as_deref(), Some("did you mean `user_id`?"));
+    }
+}
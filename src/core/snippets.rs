@@ -0,0 +1,107 @@
+//! Reusable prompt snippets about the TOON format.
+//!
+//! The built-in snippets can be overridden or extended from a directory:
+//! every `.md` or `.txt` file becomes a snippet named after its file stem.
+
+use std::path::Path;
+
+/// URI prefix under which snippets are published as MCP resources.
+pub const URI_PREFIX: &str = "toon://snippets/";
+
+/// A named block of prompt text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snippet {
+    pub name: String,
+    pub description: String,
+    pub text: String,
+}
+
+impl Snippet {
+    /// Resource URI of this snippet.
+    pub fn uri(&self) -> String {
+        format!("{}{}", URI_PREFIX, self.name)
+    }
+}
+
+const INSTRUCTIONS: &str = "\
+Data in this conversation is written in TOON (Token-Oriented Object Notation), \
+a compact encoding of JSON.
+
+- Objects use indentation: `key: value`, with nested objects indented two spaces.
+- Arrays declare their length in brackets: `tags[3]: a,b,c`.
+- Arrays of objects with the same keys are tables: `users[2]{id,name}:` followed by \
+one indented row per element, with values in header order.
+- Strings are quoted only when needed (delimiters, leading/trailing spaces, or values \
+that would otherwise read as numbers, booleans, or null).
+- `null`, `true`, and `false` are literals; numbers are plain decimals.
+
+Treat the declared lengths and headers as authoritative when reading rows.
+";
+
+const DECODE_REMINDER: &str = "\
+When you answer in TOON:
+
+- Keep the header exactly as given, e.g. `items[N]{id,name,qty}:`, and set N to the \
+number of rows you write.
+- Write one row per line, indented two spaces, with one value per header field in \
+the same order.
+- Use the same delimiter as the header (comma unless the brackets show `|` or a tab).
+- Quote values containing the delimiter, a colon, or leading/trailing spaces.
+- Do not add commentary, code fences, or trailing text inside the TOON block.
+";
+
+/// The snippets shipped with the server.
+pub fn builtin() -> Vec<Snippet> {
+    vec![
+        Snippet {
+            name: "instructions".to_string(),
+            description: "How to read TOON-encoded data".to_string(),
+            text: INSTRUCTIONS.to_string(),
+        },
+        Snippet {
+            name: "decode-reminder".to_string(),
+            description: "Rules for producing TOON output that decodes cleanly".to_string(),
+            text: DECODE_REMINDER.to_string(),
+        },
+    ]
+}
+
+/// Built-in snippets, overridden or extended by the files in `dir`.
+///
+/// Unreadable files are skipped; files are added in name order.
+pub fn load(dir: Option<&Path>) -> Vec<Snippet> {
+    let mut snippets = builtin();
+    let Some(entries) = dir.and_then(|d| std::fs::read_dir(d).ok()) else {
+        return snippets;
+    };
+
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            matches!(
+                path.extension().and_then(|e| e.to_str()),
+                Some("md") | Some("txt")
+            )
+        })
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        let (Some(name), Ok(text)) = (
+            path.file_stem().and_then(|s| s.to_str()),
+            std::fs::read_to_string(&path),
+        ) else {
+            continue;
+        };
+        let snippet = Snippet {
+            name: name.to_string(),
+            description: format!("Custom snippet from {}", path.display()),
+            text,
+        };
+        match snippets.iter_mut().find(|s| s.name == snippet.name) {
+            Some(existing) => existing.text = snippet.text,
+            None => snippets.push(snippet),
+        }
+    }
+    snippets
+}
@@ -0,0 +1,166 @@
+//! SQL generation for tabular documents.
+//!
+//! Produces PostgreSQL-flavoured DDL and batched `INSERT` statements. Column
+//! types are inferred from every row, so a column is only numeric or boolean
+//! when all of its non-null values are.
+
+use serde_json::Value;
+
+use super::SqlColumn;
+
+/// Column type inferred from the values seen so far.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Inferred {
+    Unknown,
+    Integer,
+    Float,
+    Boolean,
+    Text,
+    Json,
+}
+
+impl Inferred {
+    fn of(value: &Value) -> Self {
+        match value {
+            Value::Null => Inferred::Unknown,
+            Value::Bool(_) => Inferred::Boolean,
+            Value::Number(n) if n.is_i64() || n.is_u64() => Inferred::Integer,
+            Value::Number(_) => Inferred::Float,
+            Value::String(_) => Inferred::Text,
+            Value::Array(_) | Value::Object(_) => Inferred::Json,
+        }
+    }
+
+    fn merge(self, other: Self) -> Self {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (Inferred::Unknown, b) => b,
+            (a, Inferred::Unknown) => a,
+            (Inferred::Integer, Inferred::Float) | (Inferred::Float, Inferred::Integer) => {
+                Inferred::Float
+            }
+            _ => Inferred::Text,
+        }
+    }
+
+    fn sql_type(self) -> &'static str {
+        match self {
+            Inferred::Integer => "BIGINT",
+            Inferred::Float => "DOUBLE PRECISION",
+            Inferred::Boolean => "BOOLEAN",
+            Inferred::Json => "JSONB",
+            Inferred::Unknown | Inferred::Text => "TEXT",
+        }
+    }
+}
+
+/// Infer columns, in order of first appearance, from an array of objects.
+pub fn infer_columns(rows: &[Value]) -> Vec<SqlColumn> {
+    let mut names: Vec<&String> = Vec::new();
+    let mut types: Vec<Inferred> = Vec::new();
+    let mut nullable: Vec<bool> = Vec::new();
+
+    for (index, row) in rows.iter().enumerate() {
+        let Some(fields) = row.as_object() else {
+            continue;
+        };
+        for (name, value) in fields {
+            let position = match names.iter().position(|n| *n == name) {
+                Some(position) => position,
+                None => {
+                    names.push(name);
+                    types.push(Inferred::Unknown);
+                    // Rows seen before this column appeared lack it.
+                    nullable.push(index > 0);
+                    names.len() - 1
+                }
+            };
+            types[position] = types[position].merge(Inferred::of(value));
+            nullable[position] |= value.is_null();
+        }
+        for (position, name) in names.iter().enumerate() {
+            if !fields.contains_key(name.as_str()) {
+                nullable[position] = true;
+            }
+        }
+    }
+
+    names
+        .into_iter()
+        .zip(types)
+        .zip(nullable)
+        .map(|((name, inferred), nullable)| SqlColumn {
+            name: name.clone(),
+            sql_type: inferred.sql_type().to_string(),
+            nullable,
+        })
+        .collect()
+}
+
+/// Quote an identifier, doubling embedded quotes.
+pub fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Render a value as a SQL literal suitable for a column of `sql_type`.
+fn literal(value: &Value, sql_type: &str) -> String {
+    match value {
+        Value::Null => "NULL".to_string(),
+        Value::Bool(b) if sql_type == "BOOLEAN" => b.to_string().to_uppercase(),
+        Value::Number(n) if sql_type != "TEXT" => n.to_string(),
+        Value::String(s) => quote_string(s),
+        Value::Array(_) | Value::Object(_) if sql_type == "JSONB" => {
+            format!("{}::jsonb", quote_string(&value.to_string()))
+        }
+        other => quote_string(&other.to_string()),
+    }
+}
+
+fn quote_string(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+/// Render `CREATE TABLE` followed by `INSERT` statements of at most `batch_size` rows.
+pub fn render(table: &str, columns: &[SqlColumn], rows: &[Value], batch_size: usize) -> String {
+    let table = quote_ident(table);
+    let definitions: Vec<String> = columns
+        .iter()
+        .map(|c| {
+            let constraint = if c.nullable { "" } else { " NOT NULL" };
+            format!("  {} {}{}", quote_ident(&c.name), c.sql_type, constraint)
+        })
+        .collect();
+
+    let mut out = format!(
+        "CREATE TABLE {} (\n{}\n);\n",
+        table,
+        definitions.join(",\n")
+    );
+    if columns.is_empty() {
+        return out;
+    }
+
+    let column_list: Vec<String> = columns.iter().map(|c| quote_ident(&c.name)).collect();
+    for batch in rows.chunks(batch_size.max(1)) {
+        let tuples: Vec<String> = batch
+            .iter()
+            .map(|row| {
+                let cells: Vec<String> = columns
+                    .iter()
+                    .map(|c| match row.get(&c.name) {
+                        Some(value) => literal(value, &c.sql_type),
+                        None => "NULL".to_string(),
+                    })
+                    .collect();
+                format!("  ({})", cells.join(", "))
+            })
+            .collect();
+        out.push_str(&format!(
+            "\nINSERT INTO {} ({}) VALUES\n{};\n",
+            table,
+            column_list.join(", "),
+            tuples.join(",\n")
+        ));
+    }
+    out
+}
@@ -0,0 +1,111 @@
+//! Read-only SQLite queries (requires the `sqlite` feature).
+//!
+//! Database files must live under a sandbox root; the connection is opened
+//! read-only with `query_only` set, and statements that could write are
+//! rejected before execution.
+
+use std::path::{Path, PathBuf};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use rusqlite::types::{Value as SqlValue, ValueRef};
+use rusqlite::{Connection, OpenFlags};
+use serde_json::{Map, Value};
+
+use super::ToonCoreError;
+
+fn db_error(e: rusqlite::Error) -> ToonCoreError {
+    ToonCoreError::DataSource(e.to_string())
+}
+
+/// Resolve `requested` below `root`, refusing paths that escape it.
+pub fn resolve_path(root: &Path, requested: &str) -> Result<PathBuf, ToonCoreError> {
+    let root = root.canonicalize().map_err(|e| {
+        ToonCoreError::InvalidOption(format!("sandbox root {}: {}", root.display(), e))
+    })?;
+    let candidate = root
+        .join(requested)
+        .canonicalize()
+        .map_err(|e| ToonCoreError::InvalidOption(format!("cannot open '{}': {}", requested, e)))?;
+    if !candidate.starts_with(&root) {
+        return Err(ToonCoreError::InvalidOption(format!(
+            "'{}' is outside the sandbox root",
+            requested
+        )));
+    }
+    Ok(candidate)
+}
+
+fn bind_value(value: &Value) -> SqlValue {
+    match value {
+        Value::Null => SqlValue::Null,
+        Value::Bool(b) => SqlValue::Integer(i64::from(*b)),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => SqlValue::Integer(i),
+            None => SqlValue::Real(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(s) => SqlValue::Text(s.clone()),
+        other => SqlValue::Text(other.to_string()),
+    }
+}
+
+fn column_value(value: ValueRef<'_>) -> Value {
+    match value {
+        ValueRef::Null => Value::Null,
+        ValueRef::Integer(i) => Value::from(i),
+        ValueRef::Real(f) => serde_json::Number::from_f64(f)
+            .map(Value::Number)
+            .unwrap_or(Value::Null),
+        ValueRef::Text(t) => Value::String(String::from_utf8_lossy(t).into_owned()),
+        ValueRef::Blob(b) => Value::String(BASE64.encode(b)),
+    }
+}
+
+/// Run a read-only query and return at most `max_rows` rows as objects.
+///
+/// The second element reports whether more rows were available. Blobs are
+/// returned base64-encoded.
+pub fn query(
+    path: &Path,
+    sql: &str,
+    params: &[Value],
+    max_rows: usize,
+) -> Result<(Vec<Value>, bool), ToonCoreError> {
+    let conn = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .map_err(db_error)?;
+    conn.pragma_update(None, "query_only", true)
+        .map_err(db_error)?;
+
+    let mut statement = conn.prepare(sql).map_err(db_error)?;
+    if !statement.readonly() {
+        return Err(ToonCoreError::InvalidOption(
+            "only read-only statements are allowed".to_string(),
+        ));
+    }
+    let columns: Vec<String> = statement
+        .column_names()
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+
+    let mut rows = statement
+        .query(rusqlite::params_from_iter(params.iter().map(bind_value)))
+        .map_err(db_error)?;
+    let mut out = Vec::new();
+    while let Some(row) = rows.next().map_err(db_error)? {
+        if out.len() == max_rows {
+            return Ok((out, true));
+        }
+        let mut object = Map::with_capacity(columns.len());
+        for (i, name) in columns.iter().enumerate() {
+            object.insert(
+                name.clone(),
+                column_value(row.get_ref(i).map_err(db_error)?),
+            );
+        }
+        out.push(Value::Object(object));
+    }
+    Ok((out, false))
+}
@@ -0,0 +1,228 @@
+//! Automatic choice of encode options (`strategy: "auto"`).
+//!
+//! The best delimiter and whether to fold keys depend on the data: commas
+//! inside values force quoting with the default delimiter, and single-key
+//! wrapper objects cost a line and a level of indentation each unless
+//! folded. [`choose`] surveys the document, picks those options, and
+//! explains each decision, including which arrays the encoder will write as
+//! tables. Options the caller set are kept as given.
+
+use serde_json::{json, Value};
+
+use super::{EncodeOptionsInput, StrategyDecision};
+
+/// Delimiters in order of preference when they quote equally many values.
+const DELIMITERS: &[(&str, char, &str)] = &[
+    ("comma", ',', "commas"),
+    ("tab", '\t', "tabs"),
+    ("pipe", '|', "pipes"),
+];
+
+#[derive(Default)]
+struct Survey {
+    /// Strings containing each of [`DELIMITERS`]
+    containing: [usize; 3],
+    /// Arrays whose values sit on one line or in table rows
+    delimited_arrays: usize,
+    /// Objects with a single key holding an object
+    wrappers: usize,
+    /// Arrays of objects with the same keys and only primitive values
+    tables: usize,
+    table_rows: usize,
+    /// Arrays of objects that cannot be tables
+    nested_arrays: usize,
+}
+
+/// `options` with the delimiter and key folding chosen for `json`, and the
+/// decisions made.
+pub fn choose(
+    json: &Value,
+    options: &EncodeOptionsInput,
+) -> (EncodeOptionsInput, Vec<StrategyDecision>) {
+    let mut survey = Survey::default();
+    walk(json, &mut survey);
+    let mut chosen = options.clone();
+    let mut decisions = Vec::new();
+
+    match &options.delimiter {
+        Some(delimiter) => decisions.push(decision("delimiter", json!(delimiter), given())),
+        None => {
+            let (index, (name, _, plural)) = DELIMITERS
+                .iter()
+                .enumerate()
+                .min_by_key(|(index, _)| survey.containing[*index])
+                .filter(|_| survey.delimited_arrays > 0)
+                .unwrap_or((0, &DELIMITERS[0]));
+            let reason = if survey.delimited_arrays == 0 {
+                "no tables or inline arrays, so the delimiter is never written".to_string()
+            } else if survey.containing[0] == 0 {
+                "no string contains a comma".to_string()
+            } else if index == 0 {
+                format!(
+                    "{} strings contain commas, and no other delimiter appears in fewer",
+                    survey.containing[0]
+                )
+            } else {
+                format!(
+                    "{} strings contain commas and {} contain {}, so fewer values need quotes",
+                    survey.containing[0], survey.containing[index], plural
+                )
+            };
+            chosen.delimiter = Some(name.to_string());
+            decisions.push(decision("delimiter", json!(name), reason));
+        }
+    }
+
+    match options.fold_keys {
+        Some(fold) => decisions.push(decision("fold_keys", json!(fold), given())),
+        None => {
+            let fold = survey.wrappers > 0;
+            let reason = match survey.wrappers {
+                0 => "no single-key wrapper objects to fold".to_string(),
+                n => format!(
+                    "{} single-key wrapper objects can be written as dotted keys",
+                    n
+                ),
+            };
+            chosen.fold_keys = Some(fold);
+            decisions.push(decision("fold_keys", json!(fold), reason));
+        }
+    }
+
+    let layout = match (survey.tables, survey.nested_arrays) {
+        (0, _) => "nested",
+        (_, 0) => "tabular",
+        _ => "mixed",
+    };
+    let mut reasons = Vec::new();
+    if survey.tables > 0 {
+        reasons.push(format!(
+            "{} uniform arrays ({} rows) are written as tables",
+            survey.tables, survey.table_rows
+        ));
+    }
+    if survey.nested_arrays > 0 {
+        reasons.push(format!(
+            "{} arrays of objects with differing keys or nested values stay nested",
+            survey.nested_arrays
+        ));
+    }
+    if reasons.is_empty() {
+        reasons.push("no arrays of objects".to_string());
+    }
+    decisions.push(decision("layout", json!(layout), reasons.join("; ")));
+
+    (chosen, decisions)
+}
+
+fn walk(value: &Value, survey: &mut Survey) {
+    match value {
+        Value::String(text) => {
+            for (index, (_, delimiter, _)) in DELIMITERS.iter().enumerate() {
+                if text.contains(*delimiter) {
+                    survey.containing[index] += 1;
+                }
+            }
+        }
+        Value::Array(items) => {
+            let objects: Vec<&serde_json::Map<String, Value>> =
+                items.iter().filter_map(Value::as_object).collect();
+            if objects.is_empty() || objects.len() < items.len() {
+                if items.iter().all(is_primitive) {
+                    survey.delimited_arrays += 1;
+                } else if !objects.is_empty() {
+                    survey.nested_arrays += 1;
+                }
+            } else if is_table(&objects) {
+                survey.delimited_arrays += 1;
+                survey.tables += 1;
+                survey.table_rows += objects.len();
+            } else {
+                survey.nested_arrays += 1;
+            }
+            items.iter().for_each(|item| walk(item, survey));
+        }
+        Value::Object(map) => {
+            if map.len() == 1 && map.values().all(Value::is_object) {
+                survey.wrappers += 1;
+            }
+            map.values().for_each(|child| walk(child, survey));
+        }
+        _ => {}
+    }
+}
+
+fn is_primitive(value: &Value) -> bool {
+    !value.is_array() && !value.is_object()
+}
+
+/// Whether rows share their keys and hold only primitives, as a TOON table
+/// requires.
+fn is_table(rows: &[&serde_json::Map<String, Value>]) -> bool {
+    let Some(first) = rows.first() else {
+        return false;
+    };
+    rows.iter().all(|row| {
+        row.len() == first.len()
+            && row.keys().all(|key| first.contains_key(key))
+            && row.values().all(is_primitive)
+    })
+}
+
+fn given() -> String {
+    "set in the request".to_string()
+}
+
+fn decision(option: &str, value: Value, reason: String) -> StrategyDecision {
+    StrategyDecision {
+        option: option.to_string(),
+        value,
+        reason,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chosen(decisions: &[StrategyDecision], option: &str) -> Value {
+        decisions
+            .iter()
+            .find(|d| d.option == option)
+            .map(|d| d.value.clone())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_choose_delimiter_and_folding() {
+        let json = json!({
+            "report": {"summary": {"total": 2}},
+            "rows": [
+                {"city": "Portland, OR", "note": "a|b"},
+                {"city": "Austin, TX", "note": "c"}
+            ]
+        });
+        let (options, decisions) = choose(&json, &EncodeOptionsInput::default());
+        assert_eq!(options.delimiter.as_deref(), Some("tab"));
+        assert_eq!(options.fold_keys, Some(true));
+        assert_eq!(chosen(&decisions, "layout"), json!("tabular"));
+        assert_eq!(
+            decisions[0].reason,
+            "2 strings contain commas and 0 contain tabs, so fewer values need quotes"
+        );
+    }
+
+    #[test]
+    fn test_choose_keeps_given_options() {
+        let json = json!({"items": [{"a": 1, "b": [1]}, {"a": 2}], "tags": ["x,y"]});
+        let given = EncodeOptionsInput {
+            delimiter: Some("comma".to_string()),
+            ..Default::default()
+        };
+        let (options, decisions) = choose(&json, &given);
+        assert_eq!(options.delimiter.as_deref(), Some("comma"));
+        assert_eq!(decisions[0].reason, "set in the request");
+        assert_eq!(options.fold_keys, Some(false));
+        assert_eq!(chosen(&decisions, "layout"), json!("nested"));
+    }
+}
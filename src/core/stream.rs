@@ -0,0 +1,498 @@
+//! Row-by-row encoding of large top-level JSON arrays.
+//!
+//! Parsing a huge array into a `serde_json::Value` tree costs many times the
+//! size of the input. For a uniform array of flat objects, which TOON writes
+//! as a single table, only one row needs to be held at a time: each row is
+//! parsed, encoded to its table line, and dropped. Each row goes through the
+//! regular encoder as a one-row table, so quoting and delimiters match what
+//! a whole-document encode would produce.
+//!
+//! The row count in the table header is only known at the end, so the
+//! encoded lines are buffered; they are far smaller than the parsed rows.
+//!
+//! Decoding goes the other way with [`RowDecoder`]: a TOON table arriving in
+//! chunks is decoded one row line at a time, so rows can be handed on before
+//! the rest of the document has arrived.
+
+use std::fmt;
+use std::io::Read;
+
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde_json::Value;
+use toon_format::encode;
+
+use super::{build_encode_options, DecodeRequest, EncodeOptionsInput, ToonCoreError};
+
+/// Why a row could not be added to the table.
+const NOT_TABULAR: &str = "array is not a uniform table of flat objects";
+
+struct TableBuilder<'a> {
+    options: &'a EncodeOptionsInput,
+    header: Option<String>,
+    lines: Vec<String>,
+    tabular: bool,
+}
+
+impl TableBuilder<'_> {
+    fn push(&mut self, row: Value) -> Result<(), ToonCoreError> {
+        if !row.is_object() {
+            self.tabular = false;
+            return Ok(());
+        }
+
+        let single = encode(
+            &Value::Array(vec![row]),
+            &build_encode_options(self.options),
+        )
+        .map_err(|e| ToonCoreError::EncodeError(e.to_string()))?;
+        let mut lines = single.lines();
+        let (Some(header), Some(line), None) = (lines.next(), lines.next(), lines.next()) else {
+            self.tabular = false;
+            return Ok(());
+        };
+        if !header.contains('{') {
+            self.tabular = false;
+            return Ok(());
+        }
+
+        match &self.header {
+            Some(first) if first != header => self.tabular = false,
+            Some(_) => {}
+            None => self.header = Some(header.to_string()),
+        }
+        self.lines.push(line.to_string());
+        Ok(())
+    }
+
+    fn finish(self) -> Option<String> {
+        let header = self.header?;
+        let count = format!("[{}", self.lines.len());
+        let mut out = header.replacen("[1", &count, 1);
+        for line in &self.lines {
+            out.push('\n');
+            out.push_str(line);
+        }
+        Some(out)
+    }
+}
+
+struct RowVisitor<'a, 'b> {
+    table: &'b mut TableBuilder<'a>,
+    failure: &'b mut Option<ToonCoreError>,
+}
+
+impl<'de> Visitor<'de> for RowVisitor<'_, '_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a JSON array")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(row) = seq.next_element::<Value>()? {
+            if let Err(e) = self.table.push(row) {
+                *self.failure = Some(e);
+                return Err(de::Error::custom("encoding failed"));
+            }
+            if !self.table.tabular {
+                return Err(de::Error::custom(NOT_TABULAR));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Encode a top-level JSON array read from `reader` one row at a time.
+///
+/// Returns `Ok(None)` when the input is not a non-empty array of objects
+/// sharing the same flat fields; callers then fall back to a regular encode.
+pub fn encode_array<R: Read>(
+    reader: R,
+    options: &EncodeOptionsInput,
+) -> Result<Option<String>, ToonCoreError> {
+    let mut table = TableBuilder {
+        options,
+        header: None,
+        lines: Vec::new(),
+        tabular: true,
+    };
+    let mut failure = None;
+
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    let result = deserializer.deserialize_seq(RowVisitor {
+        table: &mut table,
+        failure: &mut failure,
+    });
+
+    if let Some(e) = failure {
+        return Err(e);
+    }
+    if !table.tabular {
+        return Ok(None);
+    }
+    result
+        .and_then(|()| deserializer.end())
+        .map_err(|e| ToonCoreError::InvalidJson(e.to_string()))?;
+    Ok(table.finish())
+}
+
+/// Incremental decoder for a TOON document arriving in chunks.
+///
+/// When the document is a single top-level table (`rows[N]{a,b}:` or
+/// `[N]{a,b}:`), every complete row line is decoded as soon as it arrives,
+/// through the regular decoder as a one-row table. Any other document is
+/// buffered, up to [`MAX_BODY_BYTES`](super::MAX_BODY_BYTES), and decoded
+/// whole by [`RowDecoder::finish`]. Either way the rows produced are those
+/// [`format_ndjson`](super::format_ndjson) would write.
+pub struct RowDecoder {
+    request: DecodeRequest,
+    /// Bytes after the last complete line
+    partial: Vec<u8>,
+    /// Lines read so far
+    line: usize,
+    /// Bytes read before `partial`
+    offset: usize,
+    /// Reject invalid UTF-8 instead of replacing it
+    strict_utf8: bool,
+    state: DecodeState,
+}
+
+enum DecodeState {
+    /// Only blank lines so far
+    Start,
+    /// Streaming rows under `header`, rewritten to declare a single row
+    Table {
+        header: String,
+        declared: usize,
+        rows: usize,
+    },
+    /// Not a single table; the document so far
+    Buffered(String),
+}
+
+impl RowDecoder {
+    /// Decode with the options in `request`; its `toon` field is ignored.
+    pub fn new(request: DecodeRequest) -> Self {
+        Self {
+            request,
+            partial: Vec::new(),
+            line: 0,
+            offset: 0,
+            strict_utf8: false,
+            state: DecodeState::Start,
+        }
+    }
+
+    /// Reject input that is not valid UTF-8, naming the byte offset, line,
+    /// and column of the first invalid byte. By default invalid sequences
+    /// are replaced with U+FFFD.
+    pub fn with_strict_utf8(mut self, strict_utf8: bool) -> Self {
+        self.strict_utf8 = strict_utf8;
+        self
+    }
+
+    /// Add the next chunk of input, appending the rows it completes to
+    /// `rows`. Rows before a failing line are appended before the error.
+    pub fn push(&mut self, chunk: &[u8], rows: &mut Vec<Value>) -> Result<(), ToonCoreError> {
+        self.partial.extend_from_slice(chunk);
+        let Some(end) = self.partial.iter().rposition(|&b| b == b'\n') else {
+            return Ok(());
+        };
+        let complete: Vec<u8> = self.partial.drain(..=end).collect();
+        self.lines(complete, rows)
+    }
+
+    /// End the input, appending the remaining rows to `rows`.
+    ///
+    /// In strict mode (the default) a table must contain as many rows as its
+    /// header declares.
+    pub fn finish(mut self, rows: &mut Vec<Value>) -> Result<(), ToonCoreError> {
+        let rest = std::mem::take(&mut self.partial);
+        self.lines(rest, rows)?;
+        match std::mem::replace(&mut self.state, DecodeState::Start) {
+            DecodeState::Table {
+                declared,
+                rows: found,
+                ..
+            } => {
+                if found != declared && self.request.strict != Some(false) {
+                    return Err(ToonCoreError::LengthMismatch {
+                        expected: declared,
+                        found,
+                    });
+                }
+            }
+            DecodeState::Start => rows.extend(self.decode_whole("")?),
+            DecodeState::Buffered(document) => rows.extend(self.decode_whole(&document)?),
+        }
+        Ok(())
+    }
+
+    fn lines(&mut self, bytes: Vec<u8>, rows: &mut Vec<Value>) -> Result<(), ToonCoreError> {
+        let text = match std::str::from_utf8(&bytes) {
+            Ok(text) => std::borrow::Cow::Borrowed(text),
+            Err(e) if self.strict_utf8 => return Err(self.invalid_utf8(&bytes, e.valid_up_to())),
+            Err(_) => String::from_utf8_lossy(&bytes),
+        };
+        let text = match self.offset {
+            0 => text.strip_prefix(super::newline::BOM).unwrap_or(&text),
+            _ => &text,
+        };
+        self.offset += bytes.len();
+        // `lines` also strips the `\r` of Windows line endings.
+        for line in text.lines() {
+            self.line += 1;
+            if let Some(row) = self.line_of(line)? {
+                rows.push(row);
+            }
+        }
+        Ok(())
+    }
+
+    fn line_of(&mut self, line: &str) -> Result<Option<Value>, ToonCoreError> {
+        if let DecodeState::Start = self.state {
+            if line.trim().is_empty() {
+                return Ok(None);
+            }
+            if let Some((header, declared)) = table_header(line).filter(|_| self.rows_stand_alone())
+            {
+                self.state = DecodeState::Table {
+                    header,
+                    declared,
+                    rows: 0,
+                };
+                return Ok(None);
+            }
+            self.state = DecodeState::Buffered(String::new());
+        }
+
+        let number = self.line;
+        match &mut self.state {
+            DecodeState::Table { header, rows, .. } => {
+                if line.trim().is_empty() {
+                    return Ok(None);
+                }
+                if !line.starts_with([' ', '\t']) {
+                    return Err(ToonCoreError::ParseError {
+                        message: "a streamed document must be a single table".to_string(),
+                        line: number,
+                        column: 1,
+                        suggestion: Some(
+                            "Decode documents with more than one top-level field as a whole"
+                                .to_string(),
+                        ),
+                        row: None,
+                        snippet: Some(super::excerpt::render(&[(number, line)], 1)),
+                    });
+                }
+                *rows += 1;
+                decode_row(header, line, &self.request, number, *rows).map(Some)
+            }
+            DecodeState::Buffered(document) => {
+                if document.len() + line.len() >= super::MAX_BODY_BYTES {
+                    return Err(ToonCoreError::InvalidOption(format!(
+                        "documents other than a single table are limited to {} bytes",
+                        super::MAX_BODY_BYTES
+                    )));
+                }
+                document.push_str(line);
+                document.push('\n');
+                Ok(None)
+            }
+            DecodeState::Start => Ok(None),
+        }
+    }
+
+    /// Error for the invalid byte at `index` in `bytes`, the next complete
+    /// lines of input.
+    fn invalid_utf8(&self, bytes: &[u8], index: usize) -> ToonCoreError {
+        let before = &bytes[..index];
+        let line_start = before
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |i| i + 1);
+        let line = self.line + 1 + before.iter().filter(|&&b| b == b'\n').count();
+        let column = index - line_start + 1;
+        ToonCoreError::ParseError {
+            message: format!("invalid UTF-8 at byte {}", self.offset + index),
+            line,
+            column,
+            suggestion: Some("Save the document as UTF-8".to_string()),
+            row: None,
+            snippet: None,
+        }
+    }
+
+    /// Whether rows decode the same on their own as inside the document;
+    /// options that rewrite the whole value rule that out.
+    fn rows_stand_alone(&self) -> bool {
+        self.request.expand_paths != Some(true)
+            && self.request.type_hints != Some(true)
+            && self.request.metadata.is_none()
+    }
+
+    fn decode_whole(&self, document: &str) -> Result<Vec<Value>, ToonCoreError> {
+        let value = super::decode_toon(document, &self.request)?;
+        Ok(super::ndjson_rows(&value).to_vec())
+    }
+}
+
+/// Split a top-level table header such as `users[2|]{id|name}:` into the
+/// same header declaring one row (`[1|]{id|name}:`) and the declared count.
+fn table_header(line: &str) -> Option<(String, usize)> {
+    if line.starts_with([' ', '\t', '"']) {
+        return None;
+    }
+    let (key, rest) = line.split_once('[')?;
+    if key.contains([':', '{']) {
+        return None;
+    }
+    let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+    let declared = rest[..digits].parse().ok()?;
+    let (delimiter, fields) = rest[digits..].split_once(']')?;
+    if delimiter.chars().count() > 1 || !fields.starts_with('{') || !fields.ends_with("}:") {
+        return None;
+    }
+    Some((format!("[1{}]{}", delimiter, fields), declared))
+}
+
+/// Decode `line`, row `row` of the table under `header`, reporting errors
+/// at its place in the document.
+fn decode_row(
+    header: &str,
+    line: &str,
+    request: &DecodeRequest,
+    number: usize,
+    row: usize,
+) -> Result<Value, ToonCoreError> {
+    let text = line.trim_start();
+    let toon = format!("{}\n  {}", header, text);
+    let value = super::decode_toon(&toon, request).map_err(|e| match e {
+        ToonCoreError::ParseError {
+            message,
+            column,
+            suggestion,
+            ..
+        } => {
+            // Columns in the one-row table are past a two-space indent.
+            let column = column.saturating_sub(2).max(1) + (line.len() - text.len());
+            ToonCoreError::ParseError {
+                message,
+                line: number,
+                column,
+                suggestion,
+                row: Some(row),
+                snippet: Some(super::excerpt::render(&[(number, line)], column)),
+            }
+        }
+        other => other,
+    })?;
+    match value {
+        Value::Array(mut rows) if rows.len() == 1 => Ok(rows.remove(0)),
+        _ => Err(ToonCoreError::DecodeError(format!(
+            "line {} is not a row of its table",
+            number
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn decode_in_chunks(toon: &str, size: usize) -> Result<Vec<Value>, ToonCoreError> {
+        let mut decoder = RowDecoder::new(DecodeRequest::default());
+        let mut rows = Vec::new();
+        for chunk in toon.as_bytes().chunks(size) {
+            decoder.push(chunk, &mut rows)?;
+        }
+        decoder.finish(&mut rows)?;
+        Ok(rows)
+    }
+
+    #[test]
+    fn test_row_decoder_emits_rows_as_lines_complete() {
+        let mut decoder = RowDecoder::new(DecodeRequest::default());
+        let mut rows = Vec::new();
+        decoder
+            .push(b"users[2]{id,name}:\n  1,Al", &mut rows)
+            .unwrap();
+        assert!(rows.is_empty());
+        decoder.push(b"ice\n  2,\"Bob, Jr\"", &mut rows).unwrap();
+        assert_eq!(rows, vec![json!({"id": 1, "name": "Alice"})]);
+        decoder.finish(&mut rows).unwrap();
+        assert_eq!(rows[1], json!({"id": 2, "name": "Bob, Jr"}));
+
+        // Rows before a bad line are kept.
+        let mut decoder = RowDecoder::new(DecodeRequest::default());
+        let mut rows = Vec::new();
+        assert!(decoder.push(b"t[2]{a}:\n  1\nb: 2\n", &mut rows).is_err());
+        assert_eq!(rows, vec![json!({"a": 1})]);
+    }
+
+    #[test]
+    fn test_row_decoder_matches_whole_document_decode() {
+        let toon = "[3|]{id|tag}:\r\n  1|a\r\n  2|\"b|c\"\r\n  3|d\r\n";
+        let whole = super::super::decode_toon(toon, &DecodeRequest::default()).unwrap();
+        for size in [1, 3, 64] {
+            assert_eq!(
+                decode_in_chunks(toon, size).unwrap(),
+                whole.as_array().unwrap().clone()
+            );
+        }
+
+        // Not a table: decoded whole at the end.
+        assert_eq!(
+            decode_in_chunks("name: Alice\nage: 30\n", 4).unwrap(),
+            vec![json!({"name": "Alice", "age": 30})]
+        );
+    }
+
+    #[test]
+    fn test_row_decoder_errors() {
+        assert!(matches!(
+            decode_in_chunks("t[3]{a}:\n  1\n  2\n", 5),
+            Err(ToonCoreError::LengthMismatch {
+                expected: 3,
+                found: 2
+            })
+        ));
+        assert!(matches!(
+            decode_in_chunks("t[1]{a}:\n  1\nmore: 2\n", 5),
+            Err(ToonCoreError::ParseError { line: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn test_row_decoder_encoding_tolerance() {
+        assert_eq!(
+            decode_in_chunks("\u{feff}t[1]{a}:\r\n  x\r\n", 2).unwrap(),
+            vec![json!({"a": "x"})]
+        );
+
+        let input: &[u8] = b"t[2]{a}:\n  caf\xe9\n  ok\n";
+        let decode = |strict_utf8| {
+            let mut decoder =
+                RowDecoder::new(DecodeRequest::default()).with_strict_utf8(strict_utf8);
+            let mut rows = Vec::new();
+            decoder.push(&input[..12], &mut rows)?;
+            decoder.push(&input[12..], &mut rows)?;
+            decoder.finish(&mut rows)?;
+            Ok::<_, ToonCoreError>(rows)
+        };
+        assert_eq!(decode(false).unwrap()[0], json!({"a": "caf\u{fffd}"}));
+        match decode(true) {
+            Err(ToonCoreError::ParseError {
+                message,
+                line,
+                column,
+                ..
+            }) => {
+                assert_eq!(message, "invalid UTF-8 at byte 14");
+                assert_eq!((line, column), (2, 6));
+            }
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+}
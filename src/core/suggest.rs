@@ -0,0 +1,93 @@
+//! "Did you mean" suggestions for misspelled names.
+//!
+//! Models often misspell column names, writing `usr_id` for `user_id`. Given
+//! the names that were expected, [`did_you_mean`] picks the closest ones by
+//! edit distance, so an error can point at the name that was intended.
+
+/// Expected names closest to `name`, all at the smallest edit distance, in
+/// the order given. Case is ignored, and names further away than a third of
+/// `name`'s length (at least one edit) are not considered.
+pub fn closest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Vec<&'a str> {
+    let name_lower = name.to_lowercase();
+    let limit = (name.chars().count() / 3).max(1);
+    let mut best: Vec<&str> = Vec::new();
+    let mut best_distance = limit + 1;
+    for candidate in candidates {
+        if candidate == name {
+            continue;
+        }
+        let distance = levenshtein(&name_lower, &candidate.to_lowercase());
+        if distance < best_distance {
+            best_distance = distance;
+            best.clear();
+        }
+        if distance == best_distance && !best.contains(&candidate) {
+            best.push(candidate);
+        }
+    }
+    best
+}
+
+/// "did you mean `user_id`?" for the names [`closest`] finds, if any.
+pub fn did_you_mean<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<String> {
+    let names: Vec<String> = closest(name, candidates)
+        .into_iter()
+        .map(|candidate| format!("`{}`", candidate))
+        .collect();
+    match names.as_slice() {
+        [] => None,
+        [only] => Some(format!("did you mean {}?", only)),
+        [rest @ .., last] => Some(format!("did you mean {} or {}?", rest.join(", "), last)),
+    }
+}
+
+/// Number of single-character insertions, deletions, and substitutions
+/// turning `a` into `b`.
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("usr_id", "user_id"), 1);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("same", "same"), 0);
+    }
+
+    #[test]
+    fn test_did_you_mean() {
+        let columns = ["user_id", "name", "email"];
+        assert_eq!(
+            did_you_mean("usr_id", columns).as_deref(),
+            Some("did you mean `user_id`?")
+        );
+        assert_eq!(
+            did_you_mean("Email", columns).as_deref(),
+            Some("did you mean `email`?")
+        );
+        assert_eq!(did_you_mean("role", columns), None);
+        assert_eq!(
+            did_you_mean("nam", ["name", "nan", "id"]).as_deref(),
+            Some("did you mean `name` or `nan`?")
+        );
+    }
+}
@@ -0,0 +1,375 @@
+//! Tabular operations on arrays of objects.
+//!
+//! These reshape data before it is encoded, so agents can ask for summaries
+//! instead of pulling raw rows into context.
+
+use std::collections::HashMap;
+
+use serde_json::{Map, Value};
+
+use super::{Aggregation, ToonCoreError};
+
+/// Borrow `value` as an array of objects, or fail with a descriptive error.
+pub fn as_rows<'a>(value: &'a Value, name: &str) -> Result<&'a Vec<Value>, ToonCoreError> {
+    match value {
+        Value::Array(items) if items.iter().all(Value::is_object) => Ok(items),
+        _ => Err(ToonCoreError::InvalidJson(format!(
+            "{} must be an array of objects",
+            name
+        ))),
+    }
+}
+
+/// Convert an f64 back into a JSON number, preferring integers when exact.
+pub fn number_value(n: f64) -> Value {
+    if n.fract() == 0.0 && n.abs() < i64::MAX as f64 {
+        Value::from(n as i64)
+    } else {
+        serde_json::Number::from_f64(n)
+            .map(Value::Number)
+            .unwrap_or(Value::Null)
+    }
+}
+
+/// Running state for one aggregation within one group.
+#[derive(Debug, Default, Clone)]
+struct Accumulator {
+    count: usize,
+    numeric: usize,
+    sum: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+impl Accumulator {
+    fn push(&mut self, n: f64) {
+        self.numeric += 1;
+        self.sum += n;
+        self.min = Some(self.min.map_or(n, |m| m.min(n)));
+        self.max = Some(self.max.map_or(n, |m| m.max(n)));
+    }
+}
+
+/// Aggregation with its operator validated and output column resolved.
+struct ResolvedAggregation<'a> {
+    op: &'a str,
+    column: Option<&'a str>,
+    alias: String,
+}
+
+fn resolve(aggregation: &Aggregation) -> Result<ResolvedAggregation<'_>, ToonCoreError> {
+    let op = aggregation.op.as_str();
+    let column = aggregation.column.as_deref();
+    match (op, column) {
+        ("count", _) => {}
+        ("sum" | "avg" | "min" | "max", Some(_)) => {}
+        ("sum" | "avg" | "min" | "max", None) => {
+            return Err(ToonCoreError::InvalidOption(format!(
+                "aggregation '{}' requires a column",
+                op
+            )))
+        }
+        _ => {
+            return Err(ToonCoreError::InvalidOption(format!(
+                "unknown aggregation '{}' (expected count, sum, avg, min, or max)",
+                op
+            )))
+        }
+    }
+
+    let alias = aggregation.alias.clone().unwrap_or_else(|| match column {
+        Some(c) => format!("{}_{}", op, c),
+        None => op.to_string(),
+    });
+
+    Ok(ResolvedAggregation { op, column, alias })
+}
+
+/// Group `rows` by the `group_by` columns and compute `aggregations` per group.
+///
+/// Groups are returned in order of first appearance. Without aggregations a
+/// plain row count is produced. Non-numeric values are ignored by
+/// sum/avg/min/max; `count` with a column counts its non-null values.
+pub fn aggregate_rows(
+    rows: &[Value],
+    group_by: &[String],
+    aggregations: &[Aggregation],
+) -> Result<Vec<Value>, ToonCoreError> {
+    let default_count = [Aggregation {
+        op: "count".to_string(),
+        column: None,
+        alias: None,
+    }];
+    let aggregations = if aggregations.is_empty() {
+        &default_count[..]
+    } else {
+        aggregations
+    };
+    let resolved = aggregations
+        .iter()
+        .map(resolve)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let mut groups: Vec<(Vec<Value>, Vec<Accumulator>)> = Vec::new();
+
+    for row in rows {
+        let key: Vec<Value> = group_by
+            .iter()
+            .map(|c| row.get(c).cloned().unwrap_or(Value::Null))
+            .collect();
+        let slot = *index
+            .entry(Value::Array(key.clone()).to_string())
+            .or_insert_with(|| {
+                groups.push((key, vec![Accumulator::default(); resolved.len()]));
+                groups.len() - 1
+            });
+
+        for (agg, acc) in resolved.iter().zip(groups[slot].1.iter_mut()) {
+            match agg.column {
+                None => acc.count += 1,
+                Some(column) => match row.get(column) {
+                    None | Some(Value::Null) => {}
+                    Some(value) => {
+                        acc.count += 1;
+                        if let Some(n) = value.as_f64() {
+                            acc.push(n);
+                        }
+                    }
+                },
+            }
+        }
+    }
+
+    Ok(groups
+        .into_iter()
+        .map(|(key, accs)| {
+            let mut out = Map::new();
+            for (column, value) in group_by.iter().zip(key) {
+                out.insert(column.clone(), value);
+            }
+            for (agg, acc) in resolved.iter().zip(accs) {
+                let value = match agg.op {
+                    "count" => Value::from(acc.count),
+                    "sum" => number_value(acc.sum),
+                    "avg" if acc.numeric > 0 => number_value(acc.sum / acc.numeric as f64),
+                    "min" => acc.min.map(number_value).unwrap_or(Value::Null),
+                    "max" => acc.max.map(number_value).unwrap_or(Value::Null),
+                    _ => Value::Null,
+                };
+                out.insert(agg.alias.clone(), value);
+            }
+            Value::Object(out)
+        })
+        .collect())
+}
+
+/// Join kind for [`join_rows`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinKind {
+    Inner,
+    Left,
+}
+
+impl JoinKind {
+    /// Parse a join kind name ("inner" or "left").
+    pub fn parse(name: Option<&str>) -> Result<Self, ToonCoreError> {
+        match name {
+            None | Some("inner") => Ok(JoinKind::Inner),
+            Some("left") => Ok(JoinKind::Left),
+            Some(other) => Err(ToonCoreError::InvalidOption(format!(
+                "unknown join type '{}' (expected inner or left)",
+                other
+            ))),
+        }
+    }
+}
+
+/// Key used to match rows; `None` when any key column is missing or null.
+fn join_key(row: &Value, on: &[String]) -> Option<String> {
+    let mut key = Vec::with_capacity(on.len());
+    for column in on {
+        match row.get(column) {
+            None | Some(Value::Null) => return None,
+            Some(value) => key.push(value.clone()),
+        }
+    }
+    Some(Value::Array(key).to_string())
+}
+
+/// Join `left` and `right` on the `on` columns.
+///
+/// Non-key columns from `right` that collide with a `left` column are
+/// prefixed with `right_`; if that name is taken as well, the join fails
+/// rather than overwrite it. Left joins fill unmatched right columns with null.
+/// Fails once the result would exceed `max_rows`.
+pub fn join_rows(
+    left: &[Value],
+    right: &[Value],
+    on: &[String],
+    kind: JoinKind,
+    max_rows: usize,
+) -> Result<Vec<Value>, ToonCoreError> {
+    if on.is_empty() {
+        return Err(ToonCoreError::InvalidOption(
+            "join requires at least one key column".to_string(),
+        ));
+    }
+
+    let mut index: HashMap<String, Vec<&Map<String, Value>>> = HashMap::new();
+    let mut right_columns: Vec<&String> = Vec::new();
+    for row in right {
+        let Some(fields) = row.as_object() else {
+            continue;
+        };
+        for column in fields.keys() {
+            if !on.contains(column) && !right_columns.contains(&column) {
+                right_columns.push(column);
+            }
+        }
+        if let Some(key) = join_key(row, on) {
+            index.entry(key).or_default().push(fields);
+        }
+    }
+
+    let mut joined = Vec::new();
+    for row in left {
+        let Some(fields) = row.as_object() else {
+            continue;
+        };
+        let matches = join_key(row, on).and_then(|key| index.get(&key));
+
+        let mut emit = |right_fields: Option<&Map<String, Value>>| {
+            if joined.len() >= max_rows {
+                return Err(ToonCoreError::InvalidOption(format!(
+                    "join result exceeds max_rows ({}); narrow the keys or raise the limit",
+                    max_rows
+                )));
+            }
+            let mut out = fields.clone();
+            for column in &right_columns {
+                let name = if fields.contains_key(column.as_str()) {
+                    let renamed = format!("right_{}", column);
+                    if fields.contains_key(&renamed) || right_columns.contains(&&renamed) {
+                        return Err(ToonCoreError::InvalidOption(format!(
+                            "right column '{}' clashes with a left column, and '{}' is taken too; rename one of them",
+                            column, renamed
+                        )));
+                    }
+                    renamed
+                } else {
+                    (*column).clone()
+                };
+                let value = right_fields
+                    .and_then(|r| r.get(column.as_str()))
+                    .cloned()
+                    .unwrap_or(Value::Null);
+                out.insert(name, value);
+            }
+            joined.push(Value::Object(out));
+            Ok(())
+        };
+
+        match matches {
+            Some(rows) => {
+                for right_fields in rows {
+                    emit(Some(right_fields))?;
+                }
+            }
+            None if kind == JoinKind::Left => emit(None)?,
+            None => {}
+        }
+    }
+
+    Ok(joined)
+}
+
+/// Reshape long rows into a wide table.
+///
+/// Rows sharing the same `index` values collapse into one row whose extra
+/// columns are named after `columns` values and filled from `values`. Missing
+/// cells are null so the result stays tabular; duplicates keep the last value.
+/// Rows without a `columns` value have no column to go in; they are left out
+/// and counted, and the count is returned with the table.
+pub fn pivot_wider(
+    rows: &[Value],
+    index: &[String],
+    columns: &str,
+    values: &str,
+) -> Result<(Vec<Value>, usize), ToonCoreError> {
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    let mut wide: Vec<Map<String, Value>> = Vec::new();
+    let mut wide_columns: Vec<String> = Vec::new();
+    let mut skipped = 0;
+
+    for row in rows {
+        let name = match row.get(columns) {
+            Some(Value::String(s)) => s.clone(),
+            Some(Value::Null) | None => {
+                skipped += 1;
+                continue;
+            }
+            Some(other) => other.to_string(),
+        };
+        if index.contains(&name) {
+            return Err(ToonCoreError::InvalidOption(format!(
+                "pivoted column '{}' collides with an index column",
+                name
+            )));
+        }
+        if !wide_columns.contains(&name) {
+            wide_columns.push(name.clone());
+        }
+
+        let key: Vec<Value> = index
+            .iter()
+            .map(|c| row.get(c).cloned().unwrap_or(Value::Null))
+            .collect();
+        let slot = *positions
+            .entry(Value::Array(key.clone()).to_string())
+            .or_insert_with(|| {
+                wide.push(index.iter().cloned().zip(key).collect());
+                wide.len() - 1
+            });
+
+        let cell = row.get(values).cloned().unwrap_or(Value::Null);
+        wide[slot].insert(name, cell);
+    }
+
+    let wide = wide
+        .into_iter()
+        .map(|mut row| {
+            for column in &wide_columns {
+                row.entry(column.clone()).or_insert(Value::Null);
+            }
+            Value::Object(row)
+        })
+        .collect();
+    Ok((wide, skipped))
+}
+
+/// Reshape a wide table into long rows.
+///
+/// Every column not listed in `index` becomes its own row with the column
+/// name under `columns` and the cell under `values`.
+pub fn pivot_longer(rows: &[Value], index: &[String], columns: &str, values: &str) -> Vec<Value> {
+    let mut long = Vec::new();
+    for row in rows {
+        let Some(fields) = row.as_object() else {
+            continue;
+        };
+        for (name, cell) in fields {
+            if index.contains(name) {
+                continue;
+            }
+            let mut out: Map<String, Value> = index
+                .iter()
+                .map(|c| (c.clone(), fields.get(c).cloned().unwrap_or(Value::Null)))
+                .collect();
+            out.insert(columns.to_string(), Value::String(name.clone()));
+            out.insert(values.to_string(), cell.clone());
+            long.push(Value::Object(out));
+        }
+    }
+    long
+}
@@ -0,0 +1,545 @@
+//! JSON-level transforms applied before encoding and reversed after decoding.
+//!
+//! Transforms run on the `serde_json::Value` tree so they stay independent of
+//! the TOON encoder. Anything lossy-looking but reversible records what it did
+//! in [`EncodeMetadata`], which callers hand back on decode.
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet};
+
+use serde_json::{Map, Value};
+
+use super::{
+    compact, nested, path, DecodeRequest, EncodeMetadata, EncodeOptionsInput, ToonCoreError,
+};
+
+/// Apply the pre-encode transforms requested in `options`.
+///
+/// Returns the borrowed input untouched when no transform is enabled.
+pub fn apply<'a>(
+    json: &'a Value,
+    options: &EncodeOptionsInput,
+) -> Result<(Cow<'a, Value>, EncodeMetadata), ToonCoreError> {
+    let mut metadata = EncodeMetadata::default();
+    let mut value = Cow::Borrowed(json);
+
+    if let Some(ref rename) = options.rename {
+        if !rename.is_empty() {
+            check_rename_targets(&value, rename)?;
+            value = Cow::Owned(rename_keys(&value, rename)?);
+            metadata.rename = Some(invert_rename(rename)?);
+        }
+    }
+
+    if options.dedupe_rows.unwrap_or(false) {
+        let mut deduped = value.into_owned();
+        metadata.rows_dropped = Some(dedupe_rows(&mut deduped));
+        value = Cow::Owned(deduped);
+    }
+
+    let layout = nested::NestedTables::parse(options.nested_tables.as_deref())?;
+    if layout != nested::NestedTables::Keep {
+        let mut flattened = value.into_owned();
+        metadata.tables_flattened = Some(nested::flatten(&mut flattened, layout));
+        value = Cow::Owned(flattened);
+    }
+
+    if options.fill_missing.unwrap_or(false) {
+        let mut filled = value.into_owned();
+        metadata.cells_filled = Some(fill_missing(&mut filled));
+        value = Cow::Owned(filled);
+    }
+
+    let policy = BlobPolicy::parse(options.blob_policy.as_deref())?;
+    if policy != BlobPolicy::Keep {
+        let min_length = options.blob_min_length.unwrap_or(DEFAULT_BLOB_MIN_LENGTH);
+        let mut replaced = value.into_owned();
+        metadata.blobs_replaced = Some(replace_blobs(&mut replaced, policy, min_length));
+        value = Cow::Owned(replaced);
+    }
+
+    if options.deterministic.unwrap_or(false) {
+        value = Cow::Owned(canonicalize(&value));
+    } else if let Some(ref previous) = options.previous {
+        let previous = super::decode_toon(
+            previous,
+            &DecodeRequest {
+                expand_paths: options.fold_keys,
+                ..DecodeRequest::default()
+            },
+        )
+        .map_err(|e| ToonCoreError::InvalidOption(format!("previous: {}", e)))?;
+        value = Cow::Owned(align_to(&value, &previous));
+    }
+
+    if let Some(ref columns) = options.column_order {
+        if !columns.is_empty() {
+            value = Cow::Owned(order_columns(&value, columns));
+        }
+    }
+
+    if options.url_prefixes.unwrap_or(false) {
+        let mut relative = value.into_owned();
+        let prefixes = compact::encode_prefixes(&mut relative);
+        metadata.prefixes = (!prefixes.is_empty()).then_some(prefixes);
+        value = Cow::Owned(relative);
+    }
+
+    if options.enum_codes.unwrap_or(false) {
+        let mut coded = value.into_owned();
+        let legends = compact::encode_enums(&mut coded);
+        metadata.enums = (!legends.is_empty()).then_some(legends);
+        value = Cow::Owned(coded);
+    }
+
+    if options.dictionary.unwrap_or(false) {
+        let mut tokenized = value.into_owned();
+        let legend = compact::encode_dictionary(&mut tokenized);
+        metadata.dictionary = (!legend.is_empty()).then_some(legend);
+        value = Cow::Owned(tokenized);
+    }
+
+    if let Some(ref literals) = options.literals {
+        let mut compacted = value.into_owned();
+        compact::encode_literals(&mut compacted, literals)?;
+        metadata.literals = Some(literals.clone());
+        value = Cow::Owned(compacted);
+    }
+
+    Ok((value, metadata))
+}
+
+/// Reverse the transforms recorded in `metadata` on a decoded value.
+pub fn restore(value: &mut Value, metadata: &EncodeMetadata) -> Result<(), ToonCoreError> {
+    if let Some(ref literals) = metadata.literals {
+        compact::decode_literals(value, literals);
+    }
+    if let Some(ref legend) = metadata.dictionary {
+        compact::decode_dictionary(value, legend);
+    }
+    if let Some(ref legends) = metadata.enums {
+        compact::decode_enums(value, legends);
+    }
+    if let Some(ref prefixes) = metadata.prefixes {
+        compact::decode_prefixes(value, prefixes);
+    }
+    if let Some(ref rename) = metadata.rename {
+        *value = rename_keys(value, rename)?;
+    }
+    Ok(())
+}
+
+/// Rename object keys at every depth according to `mapping`.
+pub fn rename_keys(
+    value: &Value,
+    mapping: &BTreeMap<String, String>,
+) -> Result<Value, ToonCoreError> {
+    match value {
+        Value::Object(map) => {
+            let mut renamed = Map::with_capacity(map.len());
+            for (key, child) in map {
+                let new_key = mapping.get(key).unwrap_or(key);
+                if renamed.contains_key(new_key) {
+                    return Err(ToonCoreError::InvalidOption(format!(
+                        "rename of '{}' to '{}' collides with an existing key",
+                        key, new_key
+                    )));
+                }
+                renamed.insert(new_key.clone(), rename_keys(child, mapping)?);
+            }
+            Ok(Value::Object(renamed))
+        }
+        Value::Array(items) => items
+            .iter()
+            .map(|item| rename_keys(item, mapping))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::Array),
+        other => Ok(other.clone()),
+    }
+}
+
+/// Replace the values at the `placeholders` paths (path to variable name) with
+/// marker strings.
+///
+/// Returns each marker paired with its `{{variable}}` text. Markers need no
+/// quoting, so swapping them in the encoded output leaves bare template
+/// variables.
+pub fn insert_placeholders(
+    value: &Value,
+    placeholders: &BTreeMap<String, String>,
+) -> Result<(Value, Vec<(String, String)>), ToonCoreError> {
+    let mut markers = BTreeMap::new();
+    for (i, (path, name)) in placeholders.iter().enumerate() {
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+        if !valid {
+            return Err(ToonCoreError::InvalidOption(format!(
+                "placeholder name '{}' must be letters, digits, '_', '-' or '.'",
+                name
+            )));
+        }
+        markers.insert(path.as_str(), format!("__toon_placeholder_{}__", i));
+    }
+
+    let mut found = HashSet::new();
+    let marked = mark(value, "", &markers, &mut found);
+    if let Some(missing) = placeholders.keys().find(|p| !found.contains(p.as_str())) {
+        return Err(ToonCoreError::InvalidOption(format!(
+            "placeholder path '{}' not found in document",
+            missing
+        )));
+    }
+
+    let pairs = placeholders
+        .iter()
+        .map(|(path, name)| (markers[path.as_str()].clone(), format!("{{{{{}}}}}", name)))
+        .collect();
+    Ok((marked, pairs))
+}
+
+fn mark<'a>(
+    value: &Value,
+    at: &str,
+    markers: &BTreeMap<&'a str, String>,
+    found: &mut HashSet<&'a str>,
+) -> Value {
+    if let Some((path, marker)) = markers.get_key_value(at) {
+        found.insert(path);
+        return Value::String(marker.clone());
+    }
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), mark(v, &path::key(at, k), markers, found)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .enumerate()
+                .map(|(i, v)| mark(v, &path::index(at, i), markers, found))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Remove exact-duplicate rows from every array of objects, keeping the first
+/// occurrence. Rows are compared in canonical form, so key order does not
+/// matter. Returns the number of rows dropped.
+pub fn dedupe_rows(value: &mut Value) -> usize {
+    match value {
+        Value::Object(map) => map.values_mut().map(dedupe_rows).sum(),
+        Value::Array(items) => {
+            let mut dropped = 0;
+            if !items.is_empty() && items.iter().all(Value::is_object) {
+                let mut seen = HashSet::new();
+                let before = items.len();
+                items.retain(|row| seen.insert(canonicalize(row).to_string()));
+                dropped += before - items.len();
+            }
+            dropped + items.iter_mut().map(dedupe_rows).sum::<usize>()
+        }
+        _ => 0,
+    }
+}
+
+/// Give every row of a near-uniform array of objects the same keys, filling
+/// missing cells with null so the encoder can write a table. An array
+/// qualifies when its rows hold only primitives and at most half of the
+/// table's cells are missing. Keys keep the order in which they first
+/// appear. Returns the number of cells filled.
+pub fn fill_missing(value: &mut Value) -> usize {
+    match value {
+        Value::Object(map) => map.values_mut().map(fill_missing).sum(),
+        Value::Array(items) => {
+            let filled = fill_table(items);
+            filled + items.iter_mut().map(fill_missing).sum::<usize>()
+        }
+        _ => 0,
+    }
+}
+
+fn fill_table(items: &mut [Value]) -> usize {
+    let rows: Vec<&Map<String, Value>> = items.iter().filter_map(Value::as_object).collect();
+    let flat = |row: &&Map<String, Value>| row.values().all(|v| !v.is_array() && !v.is_object());
+    if rows.len() < 2 || rows.len() < items.len() || !rows.iter().all(flat) {
+        return 0;
+    }
+    let mut columns: Vec<&str> = Vec::new();
+    for row in &rows {
+        for key in row.keys() {
+            if !columns.contains(&key.as_str()) {
+                columns.push(key);
+            }
+        }
+    }
+    let cells = rows.len() * columns.len();
+    let missing = cells - rows.iter().map(|row| row.len()).sum::<usize>();
+    if missing == 0 || missing * 2 > cells {
+        return 0;
+    }
+
+    let columns: Vec<String> = columns.into_iter().map(str::to_string).collect();
+    for item in items.iter_mut() {
+        if let Value::Object(row) = item {
+            let mut filled = Map::new();
+            for column in &columns {
+                filled.insert(column.clone(), row.remove(column).unwrap_or(Value::Null));
+            }
+            *row = filled;
+        }
+    }
+    missing
+}
+
+/// Default length from which an opaque string counts as a blob.
+pub const DEFAULT_BLOB_MIN_LENGTH: usize = 256;
+
+/// Characters of a blob kept by [`BlobPolicy::Truncate`].
+const BLOB_PREVIEW_CHARS: usize = 32;
+
+/// What to do with base64 blobs and other long opaque strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlobPolicy {
+    /// Leave them as they are
+    Keep,
+    /// Remove them (object members are deleted, array elements skipped)
+    Drop,
+    /// Keep the first characters and note the full length
+    Truncate,
+    /// Replace with a truncated SHA-256 digest such as `sha256:3fa9c1d2e4b7a8c0`
+    Hash,
+}
+
+impl BlobPolicy {
+    /// Parse a policy name ("keep", "drop", "truncate", or "hash").
+    pub fn parse(name: Option<&str>) -> Result<Self, ToonCoreError> {
+        match name {
+            None | Some("keep") => Ok(BlobPolicy::Keep),
+            Some("drop") => Ok(BlobPolicy::Drop),
+            Some("truncate") => Ok(BlobPolicy::Truncate),
+            Some("hash") => Ok(BlobPolicy::Hash),
+            Some(other) => Err(ToonCoreError::InvalidOption(format!(
+                "unknown blob policy '{}' (expected keep, drop, truncate, or hash)",
+                other
+            ))),
+        }
+    }
+}
+
+/// Whether `s` is a blob: at least `min_length` characters with no whitespace,
+/// as base64 data, data URIs, and tokens are.
+pub fn is_blob(s: &str, min_length: usize) -> bool {
+    s.len() >= min_length && s.chars().count() >= min_length && !s.chars().any(char::is_whitespace)
+}
+
+/// Apply `policy` to every blob in `value`. Returns the number of blobs replaced.
+pub fn replace_blobs(value: &mut Value, policy: BlobPolicy, min_length: usize) -> usize {
+    match value {
+        Value::Array(items) => {
+            let mut count = 0;
+            if policy == BlobPolicy::Drop {
+                let before = items.len();
+                items.retain(|item| !item.as_str().is_some_and(|s| is_blob(s, min_length)));
+                count += before - items.len();
+            }
+            count
+                + items
+                    .iter_mut()
+                    .map(|item| replace_blobs(item, policy, min_length))
+                    .sum::<usize>()
+        }
+        Value::Object(map) => {
+            let mut count = 0;
+            if policy == BlobPolicy::Drop {
+                let before = map.len();
+                map.retain(|_, v| !v.as_str().is_some_and(|s| is_blob(s, min_length)));
+                count += before - map.len();
+            }
+            count
+                + map
+                    .values_mut()
+                    .map(|v| replace_blobs(v, policy, min_length))
+                    .sum::<usize>()
+        }
+        Value::String(s) if is_blob(s, min_length) => {
+            let replacement = match policy {
+                BlobPolicy::Keep | BlobPolicy::Drop => return 0,
+                BlobPolicy::Truncate => {
+                    let preview: String = s.chars().take(BLOB_PREVIEW_CHARS).collect();
+                    format!("{}... [{} chars]", preview, s.chars().count())
+                }
+                BlobPolicy::Hash => {
+                    format!(
+                        "sha256:{}",
+                        &super::anonymize::hex_digest(s.as_bytes())[..16]
+                    )
+                }
+            };
+            *s = replacement;
+            1
+        }
+        _ => 0,
+    }
+}
+
+/// Recursively sort object keys, producing the canonical form of a document.
+pub fn sort_keys(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (k.clone(), sort_keys(v)))
+                    .collect(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.iter().map(sort_keys).collect()),
+        other => other.clone(),
+    }
+}
+
+/// Canonical form with fixed number formatting: keys sorted, and floats
+/// with no fractional part (including `-0.0`) turned into integers so their
+/// rendering does not depend on the encoder's float formatting.
+pub fn canonicalize(value: &Value) -> Value {
+    normalize_numbers(sort_keys(value))
+}
+
+/// Order object keys the way `previous` does, so re-encoding a changed
+/// document keeps its unchanged lines identical to the earlier output.
+///
+/// Keys missing from `previous` follow the known ones in their own order.
+/// Array elements follow the element at the same index, or the first
+/// element for rows added since.
+pub fn align_to(value: &Value, previous: &Value) -> Value {
+    match (value, previous) {
+        (Value::Object(map), Value::Object(before)) => {
+            let mut aligned = Map::with_capacity(map.len());
+            for (key, old) in before {
+                if let Some(v) = map.get(key) {
+                    aligned.insert(key.clone(), align_to(v, old));
+                }
+            }
+            for (key, v) in map {
+                if !before.contains_key(key) {
+                    aligned.insert(key.clone(), v.clone());
+                }
+            }
+            Value::Object(aligned)
+        }
+        (Value::Array(items), Value::Array(before)) => Value::Array(
+            items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| match before.get(i).or(before.first()) {
+                    Some(old) => align_to(item, old),
+                    None => item.clone(),
+                })
+                .collect(),
+        ),
+        _ => value.clone(),
+    }
+}
+
+/// Put the listed columns first, in the given order, in every row of every
+/// array of objects. Other columns keep their order after them.
+pub fn order_columns(value: &Value, columns: &[String]) -> Value {
+    match value {
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| match item {
+                    Value::Object(row) => {
+                        let mut ordered = Map::with_capacity(row.len());
+                        for column in columns {
+                            if let Some(v) = row.get(column) {
+                                ordered.insert(column.clone(), order_columns(v, columns));
+                            }
+                        }
+                        for (k, v) in row {
+                            if !ordered.contains_key(k) {
+                                ordered.insert(k.clone(), order_columns(v, columns));
+                            }
+                        }
+                        Value::Object(ordered)
+                    }
+                    other => order_columns(other, columns),
+                })
+                .collect(),
+        ),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), order_columns(v, columns)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+fn normalize_numbers(value: Value) -> Value {
+    match value {
+        Value::Number(n) if n.is_f64() => match n.as_f64() {
+            Some(f) if f.fract() == 0.0 && f.abs() < 9.007_199_254_740_992e15 => {
+                Value::from(f as i64)
+            }
+            _ => Value::Number(n),
+        },
+        Value::Array(items) => Value::Array(items.into_iter().map(normalize_numbers).collect()),
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(k, v)| (k, normalize_numbers(v)))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+/// Reject a rename to a key the input already uses anywhere (and keeps): the
+/// inverse rename on decode applies at every depth, so it could not tell the
+/// two apart.
+fn check_rename_targets(
+    value: &Value,
+    mapping: &BTreeMap<String, String>,
+) -> Result<(), ToonCoreError> {
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map {
+                if !mapping.contains_key(key) {
+                    if let Some((from, _)) = mapping.iter().find(|(_, to)| *to == key) {
+                        return Err(ToonCoreError::InvalidOption(format!(
+                            "rename of '{}' to '{}' is ambiguous: the input already has '{}' keys",
+                            from, key, key
+                        )));
+                    }
+                }
+                check_rename_targets(child, mapping)?;
+            }
+            Ok(())
+        }
+        Value::Array(items) => items
+            .iter()
+            .try_for_each(|item| check_rename_targets(item, mapping)),
+        _ => Ok(()),
+    }
+}
+
+fn invert_rename(
+    mapping: &BTreeMap<String, String>,
+) -> Result<BTreeMap<String, String>, ToonCoreError> {
+    let mut inverse = BTreeMap::new();
+    for (from, to) in mapping {
+        if let Some(previous) = inverse.insert(to.clone(), from.clone()) {
+            return Err(ToonCoreError::InvalidOption(format!(
+                "rename maps both '{}' and '{}' to '{}'",
+                previous, from, to
+            )));
+        }
+    }
+    Ok(inverse)
+}
@@ -1,5 +1,7 @@
 //! Shared types for TOON operations across HTTP and MCP transports.
 
+use std::collections::BTreeMap;
+
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
@@ -14,6 +16,10 @@ pub enum ToonCoreError {
         line: usize,
         column: usize,
         suggestion: Option<String>,
+        /// 1-based row of the enclosing table, when the line is a table row
+        row: Option<usize>,
+        /// The offending line and the one before it, with a caret under the column
+        snippet: Option<String>,
     },
 
     #[error("Array length mismatch: expected {expected}, found {found}")]
@@ -30,6 +36,12 @@ pub enum ToonCoreError {
 
     #[error("Serialization failed: {0}")]
     SerializationError(String),
+
+    #[error("Invalid option: {0}")]
+    InvalidOption(String),
+
+    #[error("Data source error: {0}")]
+    DataSource(String),
 }
 
 impl From<ToonError> for ToonCoreError {
@@ -45,6 +57,8 @@ impl From<ToonError> for ToonCoreError {
                 line,
                 column,
                 suggestion: context.and_then(|c| c.suggestion),
+                row: None,
+                snippet: None,
             },
             ToonError::LengthMismatch {
                 expected, found, ..
@@ -67,6 +81,15 @@ pub struct EncodeRequest {
     /// JSON to encode (object, array, or JSON string)
     pub json: serde_json::Value,
 
+    /// Encoding options
+    #[serde(flatten)]
+    pub options: EncodeOptionsInput,
+}
+
+/// Encoding options input for stats and other operations.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct EncodeOptionsInput {
     /// Delimiter: "comma" (default), "tab", or "pipe"
     #[serde(default)]
     pub delimiter: Option<String>,
@@ -82,31 +105,161 @@ pub struct EncodeRequest {
     /// Max depth for key folding
     #[serde(default)]
     pub flatten_depth: Option<usize>,
-}
 
-/// Encoding options input for stats and other operations.
-#[derive(Debug, Default, Clone, Serialize, Deserialize, JsonSchema)]
-#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
-pub struct EncodeOptionsInput {
-    /// Delimiter: "comma" (default), "tab", or "pipe"
+    /// Rename object keys before encoding, e.g. {"customer_identifier": "id"}
     #[serde(default)]
-    pub delimiter: Option<String>,
+    pub rename: Option<BTreeMap<String, String>>,
 
-    /// Spaces for indentation (0-8, default: 2)
+    /// Drop exact-duplicate rows from arrays of objects
     #[serde(default)]
-    pub indent: Option<u8>,
+    pub dedupe_rows: Option<bool>,
 
-    /// Enable v1.5 key folding
+    /// Write arrays of objects whose rows mostly share keys as tables,
+    /// filling missing cells with null
     #[serde(default)]
-    pub fold_keys: Option<bool>,
+    pub fill_missing: Option<bool>,
 
-    /// Max depth for key folding
+    /// Tables for rows holding one nested array, e.g. orders with line
+    /// items: "keep" (default), "prefix" (one row per child with prefixed
+    /// child columns), or "link" (a separate child table with a foreign key)
     #[serde(default)]
-    pub flatten_depth: Option<usize>,
+    pub nested_tables: Option<String>,
+
+    /// Canonical output for reproducible snapshots: sort object keys and
+    /// write whole-number floats as integers
+    #[serde(default)]
+    pub deterministic: Option<bool>,
+
+    /// TOON previously emitted for an earlier version of this document; key
+    /// order follows it so unchanged lines stay byte-identical
+    #[serde(default)]
+    pub previous: Option<String>,
+
+    /// Replace the values at these paths with bare `{{variable}}` markers,
+    /// e.g. {"users[0].name": "name"}, to produce a prompt template
+    #[serde(default)]
+    pub placeholders: Option<BTreeMap<String, String>>,
+
+    /// Columns to put first in tables, in this order; the rest follow
+    #[serde(default)]
+    pub column_order: Option<Vec<String>>,
+
+    /// Add a row of column types (int, float, str, bool, ts) under each table
+    /// header; decode the output with `type_hints` enabled
+    #[serde(default)]
+    pub type_hints: Option<bool>,
+
+    /// Move the prefix shared by a URL or path column (e.g. "https://api.example.com/v1/")
+    /// into `metadata.prefixes`, leaving relative values in the cells
+    #[serde(default)]
+    pub url_prefixes: Option<bool>,
+
+    /// Replace values in low-cardinality string columns (e.g. `status`) with
+    /// short codes; the legend is returned in `metadata.enums`
+    #[serde(default)]
+    pub enum_codes: Option<bool>,
+
+    /// Replace string values repeated across the document with short tokens
+    /// such as `@0`; the legend is returned in `metadata.dictionary`
+    #[serde(default)]
+    pub dictionary: Option<bool>,
+
+    /// Shorter spellings for booleans and null, e.g. {"true": "T", "false": "F", "null": "-"}
+    #[serde(default)]
+    pub literals: Option<Literals>,
+
+    /// What to do with base64 blobs and other long opaque strings: "keep"
+    /// (default), "drop", "truncate", or "hash"
+    #[serde(default)]
+    pub blob_policy: Option<String>,
+
+    /// Length from which a string without whitespace counts as a blob (default: 256)
+    #[serde(default)]
+    pub blob_min_length: Option<usize>,
+
+    /// Line ending: "lf" (default) or "crlf"
+    #[serde(default)]
+    pub line_ending: Option<String>,
+
+    /// End the output with a line ending (default: false)
+    #[serde(default)]
+    pub final_newline: Option<bool>,
+
+    /// "manual" (default) or "auto" to choose the delimiter and key folding
+    /// from the data; options given explicitly are kept
+    #[serde(default)]
+    pub strategy: Option<String>,
+}
+
+/// Replacement spellings for `true`, `false`, and `null`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct Literals {
+    /// Written instead of `true`
+    #[serde(default, rename = "true", skip_serializing_if = "Option::is_none")]
+    pub true_as: Option<String>,
+
+    /// Written instead of `false`
+    #[serde(default, rename = "false", skip_serializing_if = "Option::is_none")]
+    pub false_as: Option<String>,
+
+    /// Written instead of `null`
+    #[serde(default, rename = "null", skip_serializing_if = "Option::is_none")]
+    pub null_as: Option<String>,
+}
+
+/// Metadata describing reversible transforms applied during encoding.
+///
+/// Pass it back as `metadata` when decoding to restore the original document.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct EncodeMetadata {
+    /// Inverse key mapping for `rename` (encoded name -> original name)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rename: Option<BTreeMap<String, String>>,
+
+    /// Number of duplicate rows removed by `dedupe_rows` (informational, not reversed)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rows_dropped: Option<usize>,
+
+    /// Number of null cells added by `fill_missing` (informational, not reversed)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cells_filled: Option<usize>,
+
+    /// Number of arrays flattened by `nested_tables` (informational, not reversed)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tables_flattened: Option<usize>,
+
+    /// Prefixes removed by `url_prefixes`: column path (e.g. "logs[].url") -> prefix
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefixes: Option<BTreeMap<String, String>>,
+
+    /// Legends for `enum_codes`: column path (e.g. "orders[].status") -> code -> value
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enums: Option<BTreeMap<String, BTreeMap<String, String>>>,
+
+    /// Legend for `dictionary`: token -> string value
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dictionary: Option<BTreeMap<String, String>>,
+
+    /// Spellings used for `literals`, mapped back to booleans and null on decode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub literals: Option<Literals>,
+
+    /// Number of blobs dropped, truncated, or hashed by `blob_policy` (informational, not reversed)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blobs_replaced: Option<usize>,
+}
+
+impl EncodeMetadata {
+    /// Whether no reversible transform was recorded.
+    pub fn is_empty(&self) -> bool {
+        self == &EncodeMetadata::default()
+    }
 }
 
 /// Request to decode TOON to JSON format.
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
 #[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
 pub struct DecodeRequest {
     /// TOON string to decode
@@ -124,9 +277,78 @@ pub struct DecodeRequest {
     #[serde(default)]
     pub expand_paths: Option<bool>,
 
-    /// Output: "json" or "json_pretty" (default: "json")
+    /// Output: "json", "json_pretty", or "ndjson" (default: "json")
     #[serde(default)]
     pub output_format: Option<String>,
+
+    /// Metadata returned by encode, used to reverse transforms such as `rename`
+    #[serde(default)]
+    pub metadata: Option<EncodeMetadata>,
+
+    /// Canonical output: sort object keys and write whole-number floats as integers
+    #[serde(default)]
+    pub deterministic: Option<bool>,
+
+    /// Return only this 1-based page of rows
+    #[serde(default)]
+    pub page: Option<usize>,
+
+    /// Rows per page (default: 100 when paging)
+    #[serde(default)]
+    pub page_size: Option<usize>,
+
+    /// Continue from a previous page's `next_cursor` (overrides `page`)
+    #[serde(default)]
+    pub cursor: Option<String>,
+
+    /// Read the type rows written by encode's `type_hints` and coerce each
+    /// table cell to its column's type
+    #[serde(default)]
+    pub type_hints: Option<bool>,
+
+    /// List every value type coercion changed, as `coercions`
+    #[serde(default)]
+    pub report_coercions: Option<bool>,
+
+    /// Coerce locale-formatted numbers such as `1,234.56` or `1 234,56`
+    /// (default: false)
+    #[serde(default)]
+    pub locale_numbers: Option<bool>,
+}
+
+/// Decode options for `POST /api/v1/decode/stream`, given as query
+/// parameters because the body is the TOON document itself.
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "http", into_params(parameter_in = Query))]
+pub struct DecodeStreamQuery {
+    /// Strict validation, including the declared row count (default: true)
+    #[serde(default)]
+    pub strict: Option<bool>,
+
+    /// Type coercion (default: true)
+    #[serde(default)]
+    pub coerce_types: Option<bool>,
+
+    /// Canonical output: sort object keys and write whole-number floats as integers
+    #[serde(default)]
+    pub deterministic: Option<bool>,
+
+    /// Reject input that is not valid UTF-8 with the offset of the first
+    /// invalid byte, instead of replacing invalid bytes (default: false)
+    #[serde(default)]
+    pub strict_utf8: Option<bool>,
+}
+
+impl From<DecodeStreamQuery> for DecodeRequest {
+    fn from(query: DecodeStreamQuery) -> Self {
+        Self {
+            strict: query.strict,
+            coerce_types: query.coerce_types,
+            deterministic: query.deterministic,
+            ..Self::default()
+        }
+    }
 }
 
 /// Request to validate TOON syntax.
@@ -139,6 +361,57 @@ pub struct ValidateRequest {
     /// Strict validation (default: true)
     #[serde(default)]
     pub strict: Option<bool>,
+
+    /// Require a table (root array, or the only field of a root object)
+    /// with exactly this many rows
+    #[serde(default)]
+    pub expected_rows: Option<usize>,
+
+    /// Require a table whose rows have exactly these columns, in any order
+    #[serde(default)]
+    pub expected_columns: Option<Vec<String>>,
+
+    /// Rules every row must follow
+    #[serde(default)]
+    pub contract: Option<Contract>,
+}
+
+/// Output contract checked against each row of a document.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct Contract {
+    /// Keys every row must have
+    #[serde(default)]
+    pub required: Vec<String>,
+
+    /// Rules for the values of each column
+    #[serde(default)]
+    pub columns: std::collections::BTreeMap<String, ColumnContract>,
+}
+
+/// Rules for the values of one column; absent rules are not checked.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct ColumnContract {
+    /// "string", "number", "integer", "boolean", "null", "array", or "object"
+    #[serde(default, rename = "type")]
+    pub kind: Option<String>,
+
+    /// Smallest allowed number
+    #[serde(default)]
+    pub min: Option<f64>,
+
+    /// Largest allowed number
+    #[serde(default)]
+    pub max: Option<f64>,
+
+    /// Regular expression the value must match (non-strings as JSON)
+    #[serde(default)]
+    pub pattern: Option<String>,
+
+    /// Allow null values (default: false)
+    #[serde(default)]
+    pub nullable: Option<bool>,
 }
 
 /// Response from validation.
@@ -151,6 +424,10 @@ pub struct ValidateResponse {
     /// Error details if invalid
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<ValidationError>,
+
+    /// Every contract violation, when a contract was given
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub violations: Vec<SchemaViolation>,
 }
 
 /// Validation error details.
@@ -171,6 +448,14 @@ pub struct ValidationError {
     /// Suggestion to fix the error
     #[serde(skip_serializing_if = "Option::is_none")]
     pub suggestion: Option<String>,
+
+    /// Row of the enclosing table, when the line is a table row
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub row: Option<usize>,
+
+    /// Source lines around the error with a caret under the column
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
 }
 
 impl From<ToonCoreError> for ValidationError {
@@ -181,28 +466,80 @@ impl From<ToonCoreError> for ValidationError {
                 line,
                 column,
                 suggestion,
+                row,
+                snippet,
             } => ValidationError {
                 message,
                 line: Some(line),
                 column: Some(column),
                 suggestion,
+                row,
+                snippet,
             },
             ToonCoreError::LengthMismatch { expected, found } => ValidationError {
                 message: format!("Array length mismatch: expected {}, found {}", expected, found),
                 line: None,
                 column: None,
                 suggestion: Some(format!("Expected {} items but found {}", expected, found)),
+                row: None,
+                snippet: None,
             },
             other => ValidationError {
                 message: other.to_string(),
                 line: None,
                 column: None,
                 suggestion: None,
+                row: None,
+                snippet: None,
             },
         }
     }
 }
 
+/// Request to format part of a TOON document.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct FormatRangeRequest {
+    /// Complete TOON document
+    pub toon: String,
+
+    /// First line to format (1-based)
+    pub start_line: usize,
+
+    /// Last line to format (1-based, inclusive)
+    pub end_line: usize,
+
+    /// Strict parsing (default: true)
+    #[serde(default)]
+    pub strict: Option<bool>,
+
+    /// Layout options for the formatted lines
+    #[serde(flatten)]
+    pub options: EncodeOptionsInput,
+}
+
+/// Replacement of whole lines in a document.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct TextEdit {
+    /// First line replaced (1-based)
+    pub start_line: usize,
+
+    /// Line after the last one replaced; equal to `start_line` for insertions
+    pub end_line: usize,
+
+    /// Replacement lines, each ending in a newline
+    pub new_text: String,
+}
+
+/// Edits that format the requested range.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct FormatRangeResponse {
+    /// Edits in document order; empty when the range is already formatted
+    pub edits: Vec<TextEdit>,
+}
+
 /// Request to compute statistics.
 #[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
@@ -213,6 +550,30 @@ pub struct StatsRequest {
     /// Encoding options to apply
     #[serde(default)]
     pub encode_options: EncodeOptionsInput,
+
+    /// Presentation options for the reported numbers
+    #[serde(flatten)]
+    pub stats_options: StatsOptions,
+}
+
+/// Presentation and measurement options for statistics responses.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct StatsOptions {
+    /// Decimal places for percentages and human-readable sizes (default: 2, max: 6)
+    #[serde(default)]
+    pub precision: Option<u32>,
+
+    /// Add human-readable sizes such as "1.5 KB" and "2.3k tokens" (default: false)
+    #[serde(default)]
+    pub human_units: Option<bool>,
+
+    /// Report what limits the savings, with estimated costs and suggested options (default: false)
+    #[serde(default)]
+    pub diagnose: Option<bool>,
+    /// Estimate from this many rows of the largest array instead of encoding all of it, for very large inputs (at least 2)
+    #[serde(default)]
+    pub sample_rows: Option<usize>,
 }
 
 /// Response with format statistics.
@@ -227,6 +588,51 @@ pub struct StatsResponse {
 
     /// Savings comparison
     pub savings: SavingsStats,
+
+    /// Causes of poor savings, costliest first; present when `diagnose` is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diagnostics: Option<Vec<Diagnosis>>,
+    /// How the counts were estimated; present when they come from a sample
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample: Option<SampleInfo>,
+}
+
+/// The sample a statistics estimate was extrapolated from.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct SampleInfo {
+    /// Path of the sampled array ("" for the root)
+    pub path: String,
+
+    /// Rows in the array
+    pub rows_total: usize,
+
+    /// Rows measured, one from each of as many equal stretches of the array
+    pub rows_sampled: usize,
+
+    /// Confidence level of the `bytes_margin` and `tokens_margin` bounds
+    pub confidence: f64,
+}
+
+/// One reason a document saves less than it could.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct Diagnosis {
+    /// "non_uniform_rows", "deep_nesting", "long_text", or "many_unique_keys"
+    pub cause: String,
+
+    /// What was found
+    pub detail: String,
+
+    /// Estimated TOON tokens this cause costs
+    pub estimated_tokens: usize,
+
+    /// How to restructure the data
+    pub suggestion: String,
+
+    /// Encode options that can help
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<String>,
 }
 
 /// Statistics for a single format.
@@ -238,6 +644,21 @@ pub struct FormatStats {
 
     /// Approximate token count
     pub tokens_approx: usize,
+
+    /// Human-readable size, present when `human_units` is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bytes_human: Option<String>,
+
+    /// Human-readable token count, present when `human_units` is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens_human: Option<String>,
+    /// Bound on the error of `bytes`, present when estimated from a sample
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bytes_margin: Option<usize>,
+
+    /// Bound on the error of `tokens_approx`, present when estimated from a sample
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens_margin: Option<usize>,
 }
 
 /// Savings comparison between formats.
@@ -249,6 +670,51 @@ pub struct SavingsStats {
 
     /// Token savings percentage
     pub tokens_percent: f64,
+
+    /// Bytes saved by TOON (negative when TOON is larger)
+    pub bytes_saved: i64,
+
+    /// Approximate tokens saved by TOON (negative when TOON is larger)
+    pub tokens_saved: i64,
+}
+
+/// Request to check that encoding a document saves at least a given share.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct AssertSavingsRequest {
+    /// JSON to encode (object, array, or JSON string)
+    pub json: serde_json::Value,
+
+    /// Encoding options to apply
+    #[serde(default)]
+    pub encode_options: EncodeOptionsInput,
+
+    /// Smallest acceptable savings percentage, e.g. 30.0
+    pub min_savings_percent: f64,
+
+    /// Savings measured in "tokens" (default) or "bytes"
+    #[serde(default)]
+    pub metric: Option<String>,
+}
+
+/// Outcome of a savings check.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct AssertSavingsResponse {
+    /// Whether the actual savings reached `min_savings_percent`
+    pub passed: bool,
+
+    /// Metric compared ("tokens" or "bytes")
+    pub metric: String,
+
+    /// Savings percentage achieved for `metric`
+    pub actual_percent: f64,
+
+    /// Threshold from the request
+    pub min_savings_percent: f64,
+
+    /// Full statistics behind the check
+    pub stats: StatsResponse,
 }
 
 /// Simple encode response for HTTP API.
@@ -257,6 +723,28 @@ pub struct SavingsStats {
 pub struct EncodeResponse {
     /// The encoded TOON string
     pub toon: String,
+
+    /// Reversible transform metadata, present when options such as `rename` were applied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<EncodeMetadata>,
+
+    /// Options chosen by `strategy: "auto"`, and why
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decisions: Option<Vec<StrategyDecision>>,
+}
+
+/// One option chosen by the `auto` encode strategy.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct StrategyDecision {
+    /// Option name, e.g. `delimiter`, or `layout` for how arrays are written
+    pub option: String,
+
+    /// Value chosen
+    pub value: serde_json::Value,
+
+    /// Why it was chosen
+    pub reason: String,
 }
 
 /// Simple decode response for HTTP API.
@@ -265,6 +753,46 @@ pub struct EncodeResponse {
 pub struct DecodeResponse {
     /// The decoded JSON value
     pub json: serde_json::Value,
+
+    /// Position of the returned rows, when paging was requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<PageInfo>,
+
+    /// Values changed by type coercion, when `report_coercions` was set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coercions: Option<Vec<Coercion>>,
+}
+
+/// One value type coercion changed while decoding.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct Coercion {
+    /// Path of the value, e.g. `users[0].age`
+    pub path: String,
+
+    /// The text as written in the TOON document
+    pub from: String,
+
+    /// The value it became
+    pub to: serde_json::Value,
+}
+
+/// Where a page of decoded rows sits in the full table.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct PageInfo {
+    /// Rows in the full table
+    pub total_rows: usize,
+
+    /// Index of the first returned row
+    pub offset: usize,
+
+    /// Rows in this page
+    pub returned: usize,
+
+    /// Cursor for the next page; absent on the last page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
 }
 
 /// Health check response.
@@ -276,4 +804,911 @@ pub struct HealthResponse {
 
     /// Service version
     pub version: String,
+
+    /// Address the server is listening on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+}
+
+/// Build and capability information for the running binary.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct VersionResponse {
+    /// Crate version
+    pub version: String,
+
+    /// Git commit the binary was built from ("unknown" outside a checkout)
+    pub git_sha: String,
+
+    /// Unix timestamp (seconds) of the build
+    pub build_timestamp: u64,
+
+    /// Cargo features compiled in
+    pub features: Vec<String>,
+
+    /// Version of the toon-format crate doing the encoding
+    pub toon_format_version: String,
+
+    /// TOON specification versions the encoder and decoder follow
+    pub spec_versions: Vec<String>,
+}
+
+/// What this build supports, for clients deciding which options to offer.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct CapabilitiesResponse {
+    /// Version of the toon-format crate doing the encoding
+    pub toon_format_version: String,
+
+    /// TOON specification versions the encoder and decoder follow
+    pub spec_versions: Vec<String>,
+
+    /// Cargo features compiled in
+    pub features: Vec<String>,
+
+    /// Accepted input formats
+    pub input_formats: Vec<String>,
+
+    /// Values accepted by `output_format`
+    pub output_formats: Vec<String>,
+
+    /// Values accepted by `delimiter`
+    pub delimiters: Vec<String>,
+
+    /// Token counting methods used by stats
+    pub tokenizers: Vec<String>,
+
+    /// Size and row limits
+    pub limits: CapabilityLimits,
+
+    /// Defaults applied when an option is omitted
+    pub defaults: OptionDefaults,
+}
+
+/// Size and row limits enforced by the server.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct CapabilityLimits {
+    /// Largest accepted HTTP request body in bytes
+    pub max_body_bytes: usize,
+
+    /// Largest `indent`
+    pub max_indent: u8,
+
+    /// Default `max_rows` for joins
+    pub join_max_rows: usize,
+
+    /// Default `max_rows` for database queries
+    pub query_max_rows: usize,
+
+    /// Default `batch_size` for SQL export
+    pub sql_batch_size: usize,
+}
+
+/// Defaults for encode and decode options.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct OptionDefaults {
+    pub delimiter: String,
+    pub indent: u8,
+    pub fold_keys: bool,
+    pub strict: bool,
+    pub coerce_types: bool,
+    pub expand_paths: bool,
+    pub output_format: String,
+}
+
+/// Usage counters for one API principal over the current day.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct KeyUsage {
+    /// Principal the usage is attributed to (JWT subject, or "anonymous")
+    pub key: String,
+
+    /// Requests made today
+    pub requests: u64,
+
+    /// Request body bytes received today
+    pub bytes: u64,
+
+    /// Estimated tokens in request bodies received today
+    pub tokens: u64,
+}
+
+/// Daily usage limits per principal; absent limits are unlimited.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct UsageQuota {
+    /// Maximum requests per day
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requests: Option<u64>,
+
+    /// Maximum request body bytes per day
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bytes: Option<u64>,
+
+    /// Maximum estimated tokens per day
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens: Option<u64>,
+}
+
+/// Usage report for all principals.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct UsageResponse {
+    /// Unix timestamp (seconds) when the current UTC day's counters reset
+    pub resets_at: u64,
+
+    /// Configured daily quota
+    pub quota: UsageQuota,
+
+    /// Per-principal usage, sorted by key
+    pub keys: Vec<KeyUsage>,
+}
+
+/// Latency objective for every API route.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct LatencySlo {
+    /// Highest acceptable p99 latency in milliseconds
+    pub p99_ms: f64,
+
+    /// Consecutive minutes over `p99_ms` that count as a breach
+    pub minutes: u32,
+}
+
+/// Rolling latency of one route.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct RouteLatency {
+    /// Method and route, e.g. "POST /api/v1/encode"
+    pub route: String,
+
+    /// Requests in the window
+    pub requests: u64,
+
+    /// 50th percentile latency in milliseconds
+    pub p50_ms: f64,
+
+    /// 99th percentile latency in milliseconds
+    pub p99_ms: f64,
+
+    /// Whether the route is in breach of the SLO
+    pub breached: bool,
+}
+
+/// Rolling latency report for all routes.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct LatencyResponse {
+    /// Minutes of history the percentiles cover
+    pub window_minutes: u32,
+
+    /// Configured objective, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slo: Option<LatencySlo>,
+
+    /// Per-route latency, sorted by route
+    pub routes: Vec<RouteLatency>,
+}
+
+/// A runnable request against an API route, with the response it gets.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct ApiExample {
+    /// HTTP method
+    pub method: String,
+
+    /// Route path, e.g. "/api/v1/encode"
+    pub path: String,
+
+    /// What the example shows
+    pub summary: String,
+
+    /// JSON request body (absent for GET routes)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request: Option<serde_json::Value>,
+
+    /// Status this build answers with
+    #[serde(default)]
+    pub status: u16,
+
+    /// Response body from this build
+    #[serde(default)]
+    pub response: serde_json::Value,
+}
+
+/// Examples for every API route.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct ExamplesResponse {
+    pub examples: Vec<ApiExample>,
+}
+
+/// Request for HTTP API snippets performing an operation.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct HowtoRequest {
+    /// Tool name (e.g. "toon_encode") or API route (e.g. "encode", "schema/example")
+    pub operation: String,
+
+    /// Request body to send, i.e. the tool's arguments (default: a working example)
+    #[serde(default)]
+    pub arguments: Option<serde_json::Value>,
+
+    /// Server base URL (default: http://localhost:8080)
+    #[serde(default)]
+    pub base_url: Option<String>,
+}
+
+/// The HTTP request for an operation, as ready-to-run snippets.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct HowtoResponse {
+    /// HTTP method
+    pub method: String,
+
+    /// Full endpoint URL
+    pub url: String,
+
+    /// JSON request body (absent for GET routes)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<serde_json::Value>,
+
+    /// curl command
+    pub curl: String,
+
+    /// Python snippet using `requests`
+    pub python: String,
+
+    /// TypeScript snippet using `fetch`
+    pub typescript: String,
+}
+
+/// Request for a built-in demo dataset.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct DemoDataRequest {
+    /// Dataset: "iris" (CSV table), "countries" (table with list columns), or "orders" (nested records)
+    pub dataset: String,
+
+    /// Encoding options for the TOON rendering
+    #[serde(default)]
+    pub encode_options: EncodeOptionsInput,
+}
+
+/// A built-in demo dataset.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct DemoDataset {
+    /// Name to request it by
+    pub name: String,
+
+    /// What the data is and which TOON features it shows off
+    pub description: String,
+
+    /// Format of the embedded file: "csv" or "json"
+    pub format: String,
+
+    /// Where the data comes from
+    pub source: String,
+}
+
+/// A demo dataset with its contents.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct DemoDataResponse {
+    pub dataset: DemoDataset,
+
+    /// The embedded file as shipped
+    pub raw: String,
+
+    /// The data as JSON; CSV rows become an array of objects
+    pub json: serde_json::Value,
+
+    /// The data encoded as TOON
+    pub toon: String,
+}
+
+/// The built-in demo datasets.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct DemoListResponse {
+    pub datasets: Vec<DemoDataset>,
+}
+
+/// A single aggregation applied to each group.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct Aggregation {
+    /// Function: "count", "sum", "avg", "min", or "max"
+    pub op: String,
+
+    /// Column to aggregate (optional for "count")
+    #[serde(default)]
+    pub column: Option<String>,
+
+    /// Output column name (default: "<op>_<column>", or "count")
+    #[serde(default)]
+    pub alias: Option<String>,
+}
+
+/// Request to group an array of objects and aggregate each group.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct AggregateRequest {
+    /// Array of objects to aggregate (or JSON string)
+    pub json: serde_json::Value,
+
+    /// Columns to group by
+    pub group_by: Vec<String>,
+
+    /// Aggregations to compute per group (default: row count)
+    #[serde(default)]
+    pub aggregations: Vec<Aggregation>,
+
+    /// Encoding options for the TOON result
+    #[serde(default)]
+    pub encode_options: EncodeOptionsInput,
+}
+
+/// Response for operations that produce a table encoded as TOON.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct TableResponse {
+    /// The resulting table encoded as TOON
+    pub toon: String,
+
+    /// Number of rows in the result
+    pub rows: usize,
+
+    /// Input rows left out of a wide pivot because their `columns` value
+    /// was null or missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rows_skipped: Option<usize>,
+}
+
+/// Request to join two arrays of objects on key columns.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct JoinRequest {
+    /// Left-hand array of objects (or JSON string)
+    pub left: serde_json::Value,
+
+    /// Right-hand array of objects (or JSON string)
+    pub right: serde_json::Value,
+
+    /// Key columns present in both datasets
+    pub on: Vec<String>,
+
+    /// Join type: "inner" (default) or "left"
+    #[serde(default)]
+    pub how: Option<String>,
+
+    /// Maximum rows in the result (default: 10000)
+    #[serde(default)]
+    pub max_rows: Option<usize>,
+
+    /// Encoding options for the TOON result
+    #[serde(default)]
+    pub encode_options: EncodeOptionsInput,
+}
+
+/// Request to reshape an array of objects between long and wide layouts.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct PivotRequest {
+    /// Array of objects to reshape (or JSON string)
+    pub json: serde_json::Value,
+
+    /// Direction: "wide" (long to wide, default) or "long" (wide to long)
+    #[serde(default)]
+    pub direction: Option<String>,
+
+    /// Identifier columns kept on every output row
+    #[serde(default)]
+    pub index: Vec<String>,
+
+    /// Column holding the wide column names (default: "key")
+    #[serde(default)]
+    pub columns: Option<String>,
+
+    /// Column holding the cell values (default: "value")
+    #[serde(default)]
+    pub values: Option<String>,
+
+    /// Encoding options for the TOON result
+    #[serde(default)]
+    pub encode_options: EncodeOptionsInput,
+}
+
+/// A custom PII detector.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct PiiPattern {
+    /// Name used in replacements and the report, e.g. "employee_id"
+    pub name: String,
+
+    /// Regular expression matching the sensitive value
+    pub regex: String,
+}
+
+/// Request to anonymize PII before encoding.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct AnonymizeRequest {
+    /// JSON to anonymize (object, array, or JSON string)
+    pub json: serde_json::Value,
+
+    /// Replacement mode: "mask" (default), "hash", "pseudonymize", or "hmac"
+    #[serde(default)]
+    pub mode: Option<String>,
+
+    /// Built-in detectors to run: "email", "credit_card", "ip", "phone" (default: all)
+    #[serde(default)]
+    pub detectors: Option<Vec<String>>,
+
+    /// Additional regex detectors
+    #[serde(default)]
+    pub patterns: Vec<PiiPattern>,
+
+    /// Secret for "hmac" mode and mapping encryption (default: TOON_PSEUDONYM_KEY env var)
+    #[serde(default)]
+    pub key: Option<String>,
+
+    /// Return an encrypted token mapping for later de-anonymization
+    #[serde(default)]
+    pub return_mapping: Option<bool>,
+
+    /// Encoding options for the TOON result
+    #[serde(default)]
+    pub encode_options: EncodeOptionsInput,
+}
+
+/// Summary of values replaced during anonymization.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct AnonymizeReport {
+    /// Total number of replaced values
+    pub total: usize,
+
+    /// Replacements per detector
+    pub by_type: BTreeMap<String, usize>,
+
+    /// Paths of string values that were modified
+    pub paths: Vec<String>,
+}
+
+/// Response from anonymization.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct AnonymizeResponse {
+    /// The anonymized document encoded as TOON
+    pub toon: String,
+
+    /// What was transformed
+    pub report: AnonymizeReport,
+
+    /// Encrypted token mapping (base64), present when `return_mapping` is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mapping: Option<String>,
+}
+
+/// Request to restore original values in text containing anonymization tokens.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct DeanonymizeRequest {
+    /// Text (e.g. LLM output) containing tokens produced by anonymize
+    pub text: String,
+
+    /// Encrypted mapping returned by anonymize
+    pub mapping: String,
+
+    /// Secret used when anonymizing (default: TOON_PSEUDONYM_KEY env var)
+    #[serde(default)]
+    pub key: Option<String>,
+}
+
+/// Response from de-anonymization.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct DeanonymizeResponse {
+    /// Text with tokens replaced by their original values
+    pub text: String,
+
+    /// Number of tokens replaced
+    pub replaced: usize,
+}
+
+/// Request to compute integrity digests of a document.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct HashRequest {
+    /// JSON document to hash (object, array, or JSON string)
+    #[serde(default)]
+    pub json: Option<serde_json::Value>,
+
+    /// TOON document to hash, as an alternative to `json`
+    #[serde(default)]
+    pub toon: Option<String>,
+
+    /// Encoding options used for the canonical TOON form
+    #[serde(default)]
+    pub encode_options: EncodeOptionsInput,
+}
+
+/// SHA-256 digests of the canonical JSON and TOON forms of a document.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct HashResponse {
+    /// Hex SHA-256 of compact JSON with recursively sorted keys
+    pub json_sha256: String,
+
+    /// Hex SHA-256 of the TOON encoding of the canonical JSON
+    pub toon_sha256: String,
+}
+
+/// Request to compare prompt-cache reuse between two payload versions.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct CacheAnalysisRequest {
+    /// Earlier payload (object, array, or JSON string)
+    pub before: serde_json::Value,
+
+    /// Later payload (object, array, or JSON string)
+    pub after: serde_json::Value,
+
+    /// Encoding options applied to both versions
+    #[serde(default)]
+    pub encode_options: EncodeOptionsInput,
+
+    /// Smallest prefix in tokens the provider caches (default: 1024)
+    #[serde(default)]
+    pub min_cacheable_tokens: Option<usize>,
+}
+
+/// How much of the later payload's encoding a prefix cache could reuse.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct CacheAnalysisResponse {
+    /// Characters shared at the start of both encodings
+    pub prefix_chars: usize,
+
+    /// Complete lines shared at the start of both encodings
+    pub prefix_lines: usize,
+
+    /// Estimated tokens in the shared prefix
+    pub prefix_tokens: usize,
+
+    /// Estimated tokens in the later encoding
+    pub after_tokens: usize,
+
+    /// Tokens a provider could serve from cache (0 below the cacheable minimum)
+    pub reusable_tokens: usize,
+
+    /// `reusable_tokens / after_tokens`
+    pub reuse_ratio: f64,
+
+    /// First line (1-based) where the encodings differ; absent when identical
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_difference_line: Option<usize>,
+
+    /// Ways to restructure the payload for more cache hits
+    pub suggestions: Vec<String>,
+}
+
+/// A single structural difference between two documents.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct Difference {
+    /// Location of the difference, e.g. "users[0].email" (empty for the root)
+    pub path: String,
+
+    /// Kind of difference: "changed", "added", or "removed"
+    pub kind: String,
+
+    /// Value before the change (absent when added)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<serde_json::Value>,
+
+    /// Value after the change (absent when removed)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<serde_json::Value>,
+}
+
+impl Difference {
+    pub fn changed(path: String, before: &serde_json::Value, after: &serde_json::Value) -> Self {
+        Self {
+            path,
+            kind: "changed".to_string(),
+            before: Some(before.clone()),
+            after: Some(after.clone()),
+        }
+    }
+
+    pub fn added(path: String, after: &serde_json::Value) -> Self {
+        Self {
+            path,
+            kind: "added".to_string(),
+            before: None,
+            after: Some(after.clone()),
+        }
+    }
+
+    pub fn removed(path: String, before: &serde_json::Value) -> Self {
+        Self {
+            path,
+            kind: "removed".to_string(),
+            before: Some(before.clone()),
+            after: None,
+        }
+    }
+}
+
+/// Request to verify that encoding and decoding a document is lossless.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct RoundTripRequest {
+    /// JSON to round-trip (object, array, or JSON string)
+    pub json: serde_json::Value,
+
+    /// Encoding options to verify
+    #[serde(default)]
+    pub encode_options: EncodeOptionsInput,
+}
+
+/// Result of a round-trip verification.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct RoundTripResponse {
+    /// Whether the decoded document equals the original
+    pub lossless: bool,
+
+    /// The intermediate TOON encoding
+    pub toon: String,
+
+    /// Paths that differ between the original and the decoded document
+    pub differences: Vec<Difference>,
+}
+
+/// A structural rule broken by a modified document.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct SchemaViolation {
+    /// Location of the violation
+    pub path: String,
+
+    /// What is wrong, e.g. "type changed from number to string"
+    pub message: String,
+
+    /// Likely fix, e.g. "did you mean `user_id`?" for a misspelled key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+}
+
+/// Request to compare LLM-returned TOON against the original JSON.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct DiffValidateRequest {
+    /// The original JSON sent to the model (object, array, or JSON string)
+    pub original: serde_json::Value,
+
+    /// The TOON returned by the model
+    pub toon: String,
+
+    /// Strict validation (default: true)
+    #[serde(default)]
+    pub strict: Option<bool>,
+
+    /// Metadata returned by encode, used to reverse transforms before comparing
+    #[serde(default)]
+    pub metadata: Option<EncodeMetadata>,
+}
+
+/// Report of how LLM-returned TOON differs from the original JSON.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct DiffValidateResponse {
+    /// Whether the TOON decoded and no shape violations were found
+    pub valid: bool,
+
+    /// Decode error if the TOON is not valid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ValidationError>,
+
+    /// Values that were modified
+    pub changed: Vec<Difference>,
+
+    /// Values that were added
+    pub added: Vec<Difference>,
+
+    /// Values that were removed
+    pub removed: Vec<Difference>,
+
+    /// Type changes and invented or dropped keys
+    pub violations: Vec<SchemaViolation>,
+}
+
+/// Request to generate an example document from a JSON Schema.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct SchemaExampleRequest {
+    /// JSON Schema describing the document
+    pub schema: serde_json::Value,
+
+    /// Elements generated per array when `minItems` is not set (default: 2)
+    #[serde(default)]
+    pub rows: Option<usize>,
+
+    /// Include optional properties as well as required ones (default: true)
+    #[serde(default)]
+    pub include_optional: Option<bool>,
+
+    /// Encoding options for the TOON result
+    #[serde(default)]
+    pub encode_options: EncodeOptionsInput,
+}
+
+/// A synthetic example document in both formats.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct SchemaExampleResponse {
+    /// The example encoded as TOON
+    pub toon: String,
+
+    /// The example as JSON
+    pub json: serde_json::Value,
+}
+
+/// Request for a TOON tabular header template derived from a JSON Schema.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct SchemaTemplateRequest {
+    /// JSON Schema of an array of objects (or of the item object)
+    pub schema: serde_json::Value,
+
+    /// Key the array is stored under; omitted for a root array
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// Delimiter: "comma" (default), "tab", or "pipe"
+    #[serde(default)]
+    pub delimiter: Option<String>,
+}
+
+/// One column of a schema-derived template.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct TemplateField {
+    /// Column name
+    pub name: String,
+
+    /// JSON Schema type ("any" when unspecified)
+    #[serde(rename = "type")]
+    pub field_type: String,
+
+    /// JSON Schema format, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+
+    /// Whether the property is required
+    pub required: bool,
+
+    /// Allowed values when the property is an enum
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<String>,
+}
+
+/// A fill-in TOON skeleton and the columns it was built from.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct SchemaTemplateResponse {
+    /// Template with `#` column notes, header, and one placeholder row
+    pub template: String,
+
+    /// Column descriptions in header order
+    pub fields: Vec<TemplateField>,
+}
+
+/// Request to convert a tabular document into SQL statements.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct SqlRequest {
+    /// Array of objects (or an object with a single array field) as JSON
+    #[serde(default)]
+    pub json: Option<serde_json::Value>,
+
+    /// The same table as TOON, as an alternative to `json`
+    #[serde(default)]
+    pub toon: Option<String>,
+
+    /// Table name (default: the wrapping field name, or "data")
+    #[serde(default)]
+    pub table: Option<String>,
+
+    /// Rows per INSERT statement (default: 500)
+    #[serde(default)]
+    pub batch_size: Option<usize>,
+}
+
+/// A column of a generated SQL table.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct SqlColumn {
+    /// Column name
+    pub name: String,
+
+    /// Inferred PostgreSQL type
+    pub sql_type: String,
+
+    /// Whether any row has a null or missing value
+    pub nullable: bool,
+}
+
+/// Generated SQL statements.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct SqlResponse {
+    /// CREATE TABLE and INSERT statements
+    pub sql: String,
+
+    /// Inferred columns
+    pub columns: Vec<SqlColumn>,
+
+    /// Number of rows inserted
+    pub rows: usize,
+}
+
+/// Request to run a read-only query against a SQLite database.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct SqliteQueryRequest {
+    /// Database file, relative to the sandbox root (`TOON_SQLITE_ROOT`)
+    pub path: String,
+
+    /// SQL query; must be read-only
+    pub query: String,
+
+    /// Positional parameters bound to `?` placeholders
+    #[serde(default)]
+    pub params: Vec<serde_json::Value>,
+
+    /// Maximum rows returned (default: 1000)
+    #[serde(default)]
+    pub max_rows: Option<usize>,
+
+    /// Encoding options for the TOON result
+    #[serde(default)]
+    pub encode_options: EncodeOptionsInput,
+}
+
+/// Request to run a read-only query against the configured PostgreSQL database.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct PostgresQueryRequest {
+    /// SQL query using `$1`, `$2`, ... placeholders; runs in a read-only transaction
+    pub query: String,
+
+    /// Parameters bound to the placeholders, converted to the inferred column types
+    #[serde(default)]
+    pub params: Vec<serde_json::Value>,
+
+    /// Maximum rows returned (default: 1000)
+    #[serde(default)]
+    pub max_rows: Option<usize>,
+
+    /// Encoding options for the TOON result
+    #[serde(default)]
+    pub encode_options: EncodeOptionsInput,
+}
+
+/// A query result set encoded as TOON.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(feature = "http", derive(utoipa::ToSchema))]
+pub struct QueryResponse {
+    /// The rows encoded as TOON
+    pub toon: String,
+
+    /// Number of rows returned
+    pub rows: usize,
+
+    /// Whether the result was cut off at `max_rows`
+    pub truncated: bool,
 }
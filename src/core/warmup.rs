@@ -0,0 +1,104 @@
+//! Startup warm-up (`--warmup`).
+//!
+//! Regexes, detector tables, and the allocator's pools are set up on first
+//! use, so the first request after a deploy pays for them. [`run`] sends a
+//! few representative documents through every conversion path once, before
+//! the server starts accepting requests.
+
+use serde_json::{json, Value};
+
+use super::{
+    anonymize, compute_stats, decode_toon, encode_request, validate_toon, AnonymizeRequest,
+    DecodeRequest, EncodeOptionsInput, EncodeRequest, StatsOptions, ToonCoreError,
+};
+
+/// A table, nested objects, and a mix of both, with values the type hints
+/// and anonymizer detectors recognize.
+fn fixtures() -> Vec<Value> {
+    let rows: Vec<Value> = (0..200)
+        .map(|i| {
+            json!({
+                "id": i,
+                "email": format!("user{}@example.com", i),
+                "ip": format!("10.0.{}.{}", i / 256, i % 256),
+                "created": "2024-01-15T09:30:00Z",
+                "score": f64::from(i) / 8.0,
+                "active": i % 3 != 0,
+                "note": if i % 5 == 0 { Value::Null } else { json!("ok, checked") }
+            })
+        })
+        .collect();
+    vec![
+        json!({"users": rows}),
+        json!({"config": {"server": {"host": "localhost", "port": 8080}, "tags": ["a", "b"]}}),
+        json!({"orders": [
+            {"id": 1, "items": [{"sku": "A1", "qty": 2}], "meta": {"source": "web"}},
+            {"id": 2, "items": [], "meta": {"source": "api"}}
+        ]}),
+    ]
+}
+
+/// Option sets covering the default encoder and the common transforms.
+fn option_sets() -> Vec<EncodeOptionsInput> {
+    vec![
+        EncodeOptionsInput::default(),
+        EncodeOptionsInput {
+            strategy: Some("auto".to_string()),
+            type_hints: Some(true),
+            ..Default::default()
+        },
+        EncodeOptionsInput {
+            delimiter: Some("tab".to_string()),
+            fold_keys: Some(true),
+            nested_tables: Some("prefix".to_string()),
+            ..Default::default()
+        },
+    ]
+}
+
+/// Encode, decode, validate, measure, and anonymize every fixture. Returns
+/// the number of operations run.
+pub fn run() -> Result<usize, ToonCoreError> {
+    let mut operations = 0;
+    for json in fixtures() {
+        for options in option_sets() {
+            let type_hints = options.type_hints;
+            let encoded = encode_request(&EncodeRequest {
+                json: json.clone(),
+                options: options.clone(),
+            })?;
+            let decoded = decode_toon(
+                &encoded.toon,
+                &DecodeRequest {
+                    type_hints,
+                    ..Default::default()
+                },
+            )?;
+            validate_toon(&encoded.toon, Some(true));
+            compute_stats(&decoded, &options, &StatsOptions::default())?;
+            operations += 4;
+        }
+        anonymize(&AnonymizeRequest {
+            json,
+            mode: None,
+            detectors: None,
+            patterns: Vec::new(),
+            key: None,
+            return_mapping: None,
+            encode_options: EncodeOptionsInput::default(),
+        })?;
+        operations += 1;
+    }
+    Ok(operations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_exercises_every_path() {
+        let per_fixture = 4 * option_sets().len() + 1;
+        assert_eq!(run().unwrap(), fixtures().len() * per_fixture);
+    }
+}
@@ -0,0 +1,176 @@
+//! Differential testing against the reference TypeScript implementation.
+//!
+//! Each JSON payload is encoded by this server and, when a reference command
+//! is configured, by the reference CLI (`npx @toon-format/cli` by default).
+//! The outputs are compared line by line, and each side's TOON is decoded by
+//! both implementations to check that it round-trips to the input. Running
+//! this after upgrading either side catches divergence before users do.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use serde_json::Value;
+
+use crate::conformance::same_json;
+use crate::core::{self, lines, DecodeRequest, EncodeOptionsInput, TextEdit};
+
+/// Reference command used when none is configured.
+pub const DEFAULT_REFERENCE: &str = "npx @toon-format/cli";
+
+/// An external TOON implementation driven through its CLI over stdin/stdout.
+#[derive(Debug, Clone)]
+pub struct Reference {
+    program: String,
+    args: Vec<String>,
+}
+
+impl Reference {
+    /// Parse a command line such as `npx @toon-format/cli`.
+    pub fn parse(command: &str) -> anyhow::Result<Self> {
+        let mut words = command.split_whitespace().map(str::to_string);
+        let program = words
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("empty reference command"))?;
+        Ok(Self {
+            program,
+            args: words.collect(),
+        })
+    }
+
+    /// Encode JSON text to TOON.
+    pub fn encode(&self, json: &str) -> anyhow::Result<String> {
+        self.run("--encode", json)
+    }
+
+    /// Decode TOON to JSON.
+    pub fn decode(&self, toon: &str) -> anyhow::Result<Value> {
+        Ok(serde_json::from_str(&self.run("--decode", toon)?)?)
+    }
+
+    fn run(&self, mode: &str, input: &str) -> anyhow::Result<String> {
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .arg(mode)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| anyhow::anyhow!("cannot run {}: {}", self.program, e))?;
+
+        // Write from another thread so a large output cannot block the child.
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let input = input.to_string();
+        let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+        let output = child.wait_with_output()?;
+        writer
+            .join()
+            .map_err(|_| anyhow::anyhow!("stdin writer panicked"))??;
+
+        anyhow::ensure!(
+            output.status.success(),
+            "{} {} failed: {}",
+            self.program,
+            mode,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        Ok(String::from_utf8(output.stdout)?.trim_end().to_string())
+    }
+}
+
+/// Comparison of one payload across implementations.
+#[derive(Debug)]
+pub struct CaseReport {
+    pub file: PathBuf,
+    /// Whether this server decodes its own output back to the input
+    pub round_trip: bool,
+    /// Edits turning the reference encoding into ours; empty when identical
+    pub encode_diff: Option<Vec<TextEdit>>,
+    /// Whether the reference decodes our output back to the input
+    pub reference_decodes_ours: Option<bool>,
+    /// Whether this server decodes the reference output back to the input
+    pub ours_decodes_reference: Option<bool>,
+}
+
+impl CaseReport {
+    /// Whether every check that ran agreed.
+    pub fn agrees(&self) -> bool {
+        self.round_trip
+            && self.encode_diff.as_ref().is_none_or(Vec::is_empty)
+            && self.reference_decodes_ours != Some(false)
+            && self.ours_decodes_reference != Some(false)
+    }
+}
+
+/// JSON payloads to compare: files as given, directories expanded to the
+/// `.json` files they contain.
+pub fn inputs(paths: &[PathBuf]) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            files.extend(crate::golden::cases(path)?);
+        } else {
+            files.push(path.clone());
+        }
+    }
+    Ok(files)
+}
+
+/// Compare the encodings of the payload at `path`.
+pub fn compare(path: &Path, reference: Option<&Reference>) -> anyhow::Result<CaseReport> {
+    let text = std::fs::read_to_string(path)?;
+    let json: Value = serde_json::from_str(&text)?;
+    let ours = core::encode_json(&json, &EncodeOptionsInput::default())?;
+    let decodes_to_input = |toon: &str| {
+        core::decode_toon(toon, &DecodeRequest::default())
+            .is_ok_and(|decoded| same_json(&decoded, &json))
+    };
+
+    let mut report = CaseReport {
+        file: path.to_path_buf(),
+        round_trip: decodes_to_input(&ours),
+        encode_diff: None,
+        reference_decodes_ours: None,
+        ours_decodes_reference: None,
+    };
+    if let Some(reference) = reference {
+        let theirs = reference.encode(&text)?;
+        report.encode_diff = Some(lines::edits(&theirs, &ours));
+        report.reference_decodes_ours = Some(
+            reference
+                .decode(&ours)
+                .is_ok_and(|decoded| same_json(&decoded, &json)),
+        );
+        report.ours_decodes_reference = Some(decodes_to_input(&theirs));
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn golden(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join(crate::golden::DEFAULT_DIR)
+            .join(name)
+    }
+
+    #[test]
+    fn test_compare_without_reference_checks_round_trip() {
+        let report = compare(&golden("analytics.json"), None).unwrap();
+        assert!(report.round_trip);
+        assert!(report.encode_diff.is_none());
+        assert!(report.agrees());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_divergent_reference_is_reported() {
+        // Echoes its input, so its "TOON" is the JSON text itself.
+        let reference = Reference::parse("sh -c cat").unwrap();
+        let report = compare(&golden("analytics.json"), Some(&reference)).unwrap();
+        assert!(!report.encode_diff.as_ref().unwrap().is_empty());
+        assert!(!report.agrees());
+    }
+}
@@ -0,0 +1,121 @@
+//! Data files in either format, for the commands that compare or merge
+//! them (`toon-mcp diff`, `textconv`, `merge`).
+//!
+//! A file is read as TOON or JSON by its extension, or by its content when
+//! the extension is neither. For comparison it is brought to canonical form
+//! (sorted keys, integral floats as integers), so the same data compares
+//! equal whichever format or key order it was written in.
+
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::convert::{self, Format};
+use crate::core::{self, diff, transform, DecodeRequest, Difference};
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const RESET: &str = "\x1b[0m";
+
+/// Read the document at `path` (`-` for stdin) as written, with its format.
+pub fn read(path: &Path) -> anyhow::Result<(Value, Format)> {
+    let text = if path == Path::new("-") {
+        let mut text = String::new();
+        std::io::stdin().read_to_string(&mut text)?;
+        text
+    } else {
+        fs::read_to_string(path).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?
+    };
+    parse(&text, path).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))
+}
+
+/// Read the document at `path` (`-` for stdin) in canonical form.
+pub fn load(path: &Path) -> anyhow::Result<Value> {
+    Ok(transform::canonicalize(&read(path)?.0))
+}
+
+/// The format a file name declares: `.toon` or `.json`.
+pub fn named_format(path: &Path) -> Option<Format> {
+    match path.extension() {
+        Some(ext) if ext.eq_ignore_ascii_case("toon") => Some(Format::Toon),
+        Some(ext) if ext.eq_ignore_ascii_case("json") => Some(Format::Json),
+        _ => None,
+    }
+}
+
+/// Parse `text`, read from `path`, in the format its name declares or else
+/// the one detected from the content.
+pub fn parse(text: &str, path: &Path) -> anyhow::Result<(Value, Format)> {
+    let format = named_format(path).unwrap_or_else(|| convert::detect(text).0);
+    let value = match format {
+        Format::Json => serde_json::from_str(text)?,
+        Format::Toon => core::decode_toon(text, &DecodeRequest::default())?,
+    };
+    Ok((value, format))
+}
+
+/// Every difference between the documents at `before` and `after`.
+pub fn compare(before: &Path, after: &Path) -> anyhow::Result<Vec<Difference>> {
+    Ok(diff::diff_values(&load(before)?, &load(after)?))
+}
+
+/// One line describing `difference`: `~` changed, `+` added, `-` removed,
+/// in yellow, green, or red with `color`.
+pub fn render(difference: &Difference, color: bool) -> String {
+    let at = match difference.path.as_str() {
+        "" => "(root)",
+        path => path,
+    };
+    let value = |v: &Option<Value>| v.as_ref().map(Value::to_string).unwrap_or_default();
+    let (sign, tint, text) = match difference.kind.as_str() {
+        "added" => ("+", GREEN, format!("{}: {}", at, value(&difference.after))),
+        "removed" => ("-", RED, format!("{}: {}", at, value(&difference.before))),
+        _ => (
+            "~",
+            YELLOW,
+            format!(
+                "{}: {} -> {}",
+                at,
+                value(&difference.before),
+                value(&difference.after)
+            ),
+        ),
+    };
+    if color {
+        format!("{}{} {}{}", tint, sign, text, RESET)
+    } else {
+        format!("{} {}", sign, text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_formats_compare_equal() {
+        let canonical = |text: &str, name: &str| {
+            let (value, _) = parse(text, Path::new(name)).unwrap();
+            transform::canonicalize(&value)
+        };
+        let json = canonical(r#"{"b": 1.0, "a": [1, 2]}"#, "a.json");
+        let toon = canonical("a[2]: 1,2\nb: 1", "b.toon");
+        assert!(diff::diff_values(&json, &toon).is_empty());
+        let (_, format) = parse("a[2]: 1,2\nb: 1", Path::new("data.txt")).unwrap();
+        assert_eq!(format, Format::Toon);
+    }
+
+    #[test]
+    fn test_render() {
+        let changed = Difference::changed("users[0].name".to_string(), &json!("Ada"), &json!(1));
+        assert_eq!(render(&changed, false), r#"~ users[0].name: "Ada" -> 1"#);
+        let added = Difference::added(String::new(), &json!(true));
+        assert_eq!(render(&added, false), "+ (root): true");
+        let removed = Difference::removed("a".to_string(), &json!(null));
+        assert_eq!(render(&removed, true), "\x1b[31m- a: null\x1b[0m");
+    }
+}
@@ -0,0 +1,55 @@
+//! Golden-file corpus guarding encoder output.
+//!
+//! `tests/golden/` holds representative payloads (`<name>.json`) next to
+//! their expected TOON (`<name>.snap`, in insta's snapshot format). The
+//! snapshot suite in `tests/golden_tests.rs` fails whenever the output
+//! changes, and `toon-mcp regen-golden` rewrites the snapshots from the
+//! current build, so an intended change such as a toon-format upgrade shows
+//! up as a reviewable diff.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::core::{self, EncodeOptionsInput};
+
+/// Corpus location relative to the crate root.
+pub const DEFAULT_DIR: &str = "tests/golden";
+
+/// Header insta writes above a snapshot from the golden suite.
+const SNAPSHOT_HEADER: &str = "---\nsource: tests/golden_tests.rs\n---\n";
+
+/// Payload files in `dir`, sorted by name.
+pub fn cases(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut cases = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            cases.push(path);
+        }
+    }
+    cases.sort();
+    Ok(cases)
+}
+
+/// Encode the payload at `path` with default options.
+pub fn encode_case(path: &Path) -> anyhow::Result<String> {
+    let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(path)?)?;
+    Ok(core::encode_json(&json, &EncodeOptionsInput::default())?)
+}
+
+/// Rewrite the snapshot of every payload in `dir` from the current encoder.
+///
+/// Returns the names of the cases whose snapshot changed.
+pub fn regen(dir: &Path) -> anyhow::Result<Vec<String>> {
+    let mut changed = Vec::new();
+    for case in cases(dir)? {
+        let snapshot = format!("{}{}\n", SNAPSHOT_HEADER, encode_case(&case)?);
+        let target = case.with_extension("snap");
+        if fs::read_to_string(&target).ok().as_deref() != Some(snapshot.as_str()) {
+            fs::write(&target, snapshot)?;
+            let name = case.file_stem().unwrap_or_default().to_string_lossy();
+            changed.push(name.into_owned());
+        }
+    }
+    Ok(changed)
+}
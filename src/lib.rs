@@ -1,7 +1,16 @@
+pub mod check;
 pub mod cli;
+pub mod conformance;
+pub mod convert;
 pub mod core;
+pub mod differential;
+pub mod document;
 pub mod error;
+pub mod golden;
+pub mod replay;
+pub mod report;
 pub mod server;
+pub mod worker;
 
 #[cfg(feature = "mcp")]
 pub mod tools;
@@ -1,40 +1,435 @@
 //! TOON MCP Server - Token-efficient JSON encoding for LLM prompts.
 
-mod cli;
-mod core;
-mod error;
-mod server;
+use std::io::{IsTerminal, Read};
+use std::process::ExitCode;
 
-#[cfg(feature = "mcp")]
-mod tools;
-
-use cli::{Args, ServerMode};
+use clap::CommandFactory;
+use serde_json::json;
+use toon_mcp::cli::{Args, Command, ServerMode};
+use toon_mcp::report::{self, CommandReport};
+#[cfg(any(feature = "mcp", feature = "http"))]
+use toon_mcp::server;
+use toon_mcp::{check, conformance, convert, core, differential, document, golden, replay, worker};
 
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
+async fn main() -> anyhow::Result<ExitCode> {
     let args = Args::parse_args();
 
-    match args.mode {
+    if let Some(command) = &args.command {
+        let outcome = run_command(command, args.quiet).await;
+        return Ok(report::finish(
+            command.name(),
+            outcome,
+            args.output,
+            args.quiet,
+        ));
+    }
+
+    if args.warmup {
+        warm_up(args.mode).await?;
+    }
+
+    let served: anyhow::Result<()> = match args.mode {
         ServerMode::Mcp => {
             #[cfg(feature = "mcp")]
             {
-                server::run_mcp_server().await
+                match args.pipe.as_deref() {
+                    Some(pipe) => server::run_mcp_pipe_server(pipe).await,
+                    None => server::run_mcp_server(args.keepalive_interval()).await,
+                }
             }
             #[cfg(not(feature = "mcp"))]
             {
-                anyhow::bail!("MCP mode not available. Build with --features mcp")
+                Err(anyhow::anyhow!(
+                    "MCP mode not available. Build with --features mcp"
+                ))
             }
         }
         ServerMode::Http => {
             #[cfg(feature = "http")]
             {
-                let addr = args.socket_addr();
-                server::run_http_server(&addr).await
+                let listen = args.listen_addrs()?;
+                server::run_http_server(&listen, args.chaos_config()).await
+            }
+            #[cfg(not(feature = "http"))]
+            {
+                Err(anyhow::anyhow!(
+                    "HTTP mode not available. Build with --features http"
+                ))
+            }
+        }
+        ServerMode::Mock => {
+            #[cfg(feature = "http")]
+            {
+                let listen = args.listen_addrs()?;
+                server::run_mock_server(&listen, &args.fixtures).await
             }
             #[cfg(not(feature = "http"))]
             {
-                anyhow::bail!("HTTP mode not available. Build with --features http")
+                Err(anyhow::anyhow!(
+                    "Mock mode not available. Build with --features http"
+                ))
             }
         }
+    };
+    served.map(|()| ExitCode::SUCCESS)
+}
+
+/// Run every conversion path once, so the first request after startup
+/// doesn't pay for lazy initialization.
+async fn warm_up(
+    #[cfg_attr(not(feature = "http"), allow(unused_variables))] mode: ServerMode,
+) -> anyhow::Result<()> {
+    let started = std::time::Instant::now();
+    let operations = core::warmup::run()?;
+    // Sends each published example through the router and caches the
+    // responses `GET /api/v1/examples` serves.
+    #[cfg(feature = "http")]
+    if let ServerMode::Http = mode {
+        server::examples::examples().await;
+    }
+    eprintln!(
+        "toon-mcp: warmed up with {} operations in {} ms",
+        operations,
+        started.elapsed().as_millis()
+    );
+    Ok(())
+}
+
+/// Run a subcommand instead of a server. Returns `None` for commands that
+/// write their own output.
+async fn run_command(command: &Command, quiet: bool) -> anyhow::Result<Option<CommandReport>> {
+    let report = match command {
+        Command::RegenGolden { dir } => {
+            let changed = golden::regen(dir)?;
+            let mut report = CommandReport::new(json!(changed));
+            for name in &changed {
+                report.line(format!("updated {}", name));
+            }
+            report.line(format!("{} golden snapshots changed", changed.len()));
+            report.stat("changed", changed.len());
+            report
+        }
+        Command::Conformance { dir } => {
+            let results = conformance::run(dir)?;
+            let mut report = CommandReport::new(serde_json::to_value(&results.cases)?);
+            for case in &results.cases {
+                let outcome = match case.outcome {
+                    conformance::Outcome::Pass => "PASS",
+                    conformance::Outcome::Fail => "FAIL",
+                    conformance::Outcome::Skip => "SKIP",
+                };
+                report.line(match &case.message {
+                    Some(message) => {
+                        format!("{} {} / {}: {}", outcome, case.file, case.name, message)
+                    }
+                    None => format!("{} {} / {}", outcome, case.file, case.name),
+                });
+            }
+            report.line(format!(
+                "{} passed, {} failed, {} skipped",
+                results.passed, results.failed, results.skipped
+            ));
+            report.stat("passed", results.passed);
+            report.stat("failed", results.failed);
+            report.stat("skipped", results.skipped);
+            if results.failed > 0 {
+                report.failure = Some(format!("{} spec cases failed", results.failed));
+            }
+            report
+        }
+        Command::Differential { paths, reference } => {
+            let reference = reference
+                .as_deref()
+                .map(differential::Reference::parse)
+                .transpose()?;
+            let mut report = CommandReport::default();
+            if reference.is_none() {
+                report
+                    .warnings
+                    .push("no --reference given; only this build's round trip was checked".into());
+            }
+            let mut cases = Vec::new();
+            let mut diverged = 0;
+            for file in differential::inputs(paths)? {
+                let case = differential::compare(&file, reference.as_ref())?;
+                cases.push(json!({
+                    "file": file,
+                    "agrees": case.agrees(),
+                    "round_trip": case.round_trip,
+                    "encode_diff": case.encode_diff,
+                    "reference_decodes_ours": case.reference_decodes_ours,
+                    "ours_decodes_reference": case.ours_decodes_reference,
+                }));
+                if case.agrees() {
+                    report.line(format!("MATCH {}", file.display()));
+                    continue;
+                }
+                diverged += 1;
+                report.line(format!("DIFF  {}", file.display()));
+                if !case.round_trip {
+                    report.line("  our output does not decode back to the input");
+                }
+                if case.reference_decodes_ours == Some(false) {
+                    report.line("  the reference does not decode our output back to the input");
+                }
+                if case.ours_decodes_reference == Some(false) {
+                    report.line("  we do not decode the reference output back to the input");
+                }
+                for edit in case.encode_diff.iter().flatten() {
+                    report.line(format!(
+                        "  reference lines {}..{} encode here as:\n{}",
+                        edit.start_line, edit.end_line, edit.new_text
+                    ));
+                }
+            }
+            report.stat("payloads", cases.len());
+            report.stat("diverged", diverged);
+            report.result = json!(cases);
+            if diverged > 0 {
+                report.failure = Some(format!("{} payloads diverged", diverged));
+            }
+            report
+        }
+        Command::Replay { file } => {
+            let results = replay::run(file).await?;
+            let mut report = CommandReport::default();
+            let mut exchanges = Vec::new();
+            let (mut changed, mut skipped) = (0, 0);
+            for result in &results {
+                match &result.verdict {
+                    replay::Verdict::Match => {
+                        report.line(format!("MATCH {}", result.label));
+                        exchanges.push(json!({"label": result.label, "verdict": "match"}));
+                    }
+                    replay::Verdict::Diff { recorded, replayed } => {
+                        changed += 1;
+                        report.line(format!("DIFF  {}", result.label));
+                        report.line(format!("  recorded: {}", recorded));
+                        report.line(format!("  replayed: {}", replayed));
+                        exchanges.push(json!({
+                            "label": result.label,
+                            "verdict": "diff",
+                            "recorded": recorded,
+                            "replayed": replayed,
+                        }));
+                    }
+                    replay::Verdict::Skip(reason) => {
+                        skipped += 1;
+                        report.line(format!("SKIP  {}: {}", result.label, reason));
+                        exchanges.push(json!({
+                            "label": result.label,
+                            "verdict": "skip",
+                            "reason": reason,
+                        }));
+                    }
+                }
+            }
+            report.line(format!(
+                "{} exchanges replayed, {} changed",
+                results.len(),
+                changed
+            ));
+            report.stat("exchanges", results.len());
+            report.stat("changed", changed);
+            report.stat("skipped", skipped);
+            report.result = json!(exchanges);
+            if changed > 0 {
+                report.failure = Some(format!("{} responses changed", changed));
+            }
+            report
+        }
+        Command::Convert {
+            patterns,
+            to,
+            out_dir,
+            jobs,
+            skip_unchanged,
+        } => {
+            if patterns.is_empty() || *patterns == ["-"] {
+                return convert_stdin(*to, quiet);
+            }
+            let inputs = convert::inputs(patterns)?;
+            let options = convert::Options {
+                to: *to,
+                out_dir: out_dir.clone(),
+                jobs: jobs.unwrap_or_else(worker::default_workers),
+                skip_unchanged: *skip_unchanged,
+            };
+            let results = convert::run(&inputs, &options)?;
+            let mut report = CommandReport::new(serde_json::to_value(&results)?);
+            let count = |status| results.iter().filter(|r| r.status == status).count();
+            let (converted, skipped, failed) = (
+                count(convert::Status::Converted),
+                count(convert::Status::Skipped),
+                count(convert::Status::Failed),
+            );
+            for result in &results {
+                let input = result.input.display();
+                match (result.status, &result.error) {
+                    (convert::Status::Failed, Some(error)) => {
+                        report.line(format!("FAIL  {}: {}", input, error))
+                    }
+                    (convert::Status::Skipped, _) => report.line(format!("SKIP  {}", input)),
+                    _ => report.line(format!("OK    {} -> {}", input, result.output.display())),
+                }
+            }
+            report.line(format!(
+                "{} converted, {} skipped, {} failed",
+                converted, skipped, failed
+            ));
+            report.stat("files", results.len());
+            report.stat("converted", converted);
+            report.stat("skipped", skipped);
+            report.stat("failed", failed);
+            if failed > 0 {
+                report.failure = Some(format!("{} files failed to convert", failed));
+            }
+            report
+        }
+        Command::Diff {
+            before,
+            after,
+            color,
+        } => {
+            let differences = document::compare(before, after)?;
+            let color = match color {
+                clap::ColorChoice::Always => true,
+                clap::ColorChoice::Never => false,
+                clap::ColorChoice::Auto => {
+                    std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+                }
+            };
+            let mut report = CommandReport::new(serde_json::to_value(&differences)?);
+            for difference in &differences {
+                report.line(document::render(difference, color));
+            }
+            for kind in ["changed", "added", "removed"] {
+                let count = differences.iter().filter(|d| d.kind == kind).count();
+                report.stat(kind, count);
+            }
+            report.stat("differences", differences.len());
+            if !differences.is_empty() {
+                report.failure = Some(format!("documents differ in {} places", differences.len()));
+            }
+            report
+        }
+        Command::Textconv { file } => {
+            println!("{}", serde_json::to_string_pretty(&document::load(file)?)?);
+            return Ok(None);
+        }
+        Command::Merge {
+            base,
+            ours,
+            theirs,
+            path,
+        } => {
+            let (base_value, _) = document::read(base)?;
+            let (ours_value, detected) = document::read(ours)?;
+            let (theirs_value, _) = document::read(theirs)?;
+            let format = path
+                .as_deref()
+                .and_then(document::named_format)
+                .unwrap_or(detected);
+            let merged = core::merge::merge3(&base_value, &ours_value, &theirs_value);
+            std::fs::write(ours, convert::render(&merged.value, format)?)?;
+
+            let mut report = CommandReport::new(serde_json::to_value(&merged.conflicts)?);
+            let show = |value: &Option<serde_json::Value>| match value {
+                Some(value) => value.to_string(),
+                None => "(absent)".to_string(),
+            };
+            for conflict in &merged.conflicts {
+                report.line(format!(
+                    "CONFLICT {}: base {}, ours {}, theirs {}",
+                    conflict.path,
+                    show(&conflict.base),
+                    show(&conflict.ours),
+                    show(&conflict.theirs)
+                ));
+            }
+            report.stat("conflicts", merged.conflicts.len());
+            if !merged.conflicts.is_empty() {
+                report.failure = Some(format!(
+                    "{} conflicts; our side was kept",
+                    merged.conflicts.len()
+                ));
+            }
+            report
+        }
+        Command::Check { paths, format } => {
+            let mut checks = Vec::new();
+            for file in check::files(paths)? {
+                checks.push(check::check(&file, *format)?);
+            }
+            let count = |outcome| checks.iter().filter(|c| c.outcome == outcome).count();
+            let (invalid, formatted) = (
+                count(check::Outcome::Invalid),
+                count(check::Outcome::Formatted),
+            );
+            let mut report = CommandReport::new(serde_json::to_value(&checks)?);
+            for file in checks.iter().filter(|c| c.outcome != check::Outcome::Valid) {
+                report.line(file.describe());
+            }
+            report.line(format!(
+                "{} files checked, {} invalid, {} reformatted",
+                checks.len(),
+                invalid,
+                formatted
+            ));
+            report.stat("files", checks.len());
+            report.stat("invalid", invalid);
+            report.stat("formatted", formatted);
+            if invalid + formatted > 0 {
+                report.failure = Some(format!("{} invalid, {} reformatted", invalid, formatted));
+            }
+            report
+        }
+        Command::Completions { shell } => {
+            let mut command = Args::command();
+            let name = command.get_name().to_string();
+            clap_complete::generate(*shell, &mut command, name, &mut std::io::stdout());
+            return Ok(None);
+        }
+        Command::Man => {
+            clap_mangen::Man::new(Args::command()).render(&mut std::io::stdout())?;
+            return Ok(None);
+        }
+    };
+    Ok(Some(report))
+}
+
+/// `convert` without paths: convert stdin to stdout, detecting its format
+/// unless `--to` is given. The decision is printed to stderr.
+fn convert_stdin(
+    to: Option<convert::Format>,
+    quiet: bool,
+) -> anyhow::Result<Option<CommandReport>> {
+    let mut text = String::new();
+    std::io::stdin().read_to_string(&mut text)?;
+    let from = match to {
+        Some(to) => to.other(),
+        None => {
+            let (from, reason) = convert::detect(&text);
+            if !quiet {
+                eprintln!(
+                    "toon-mcp: stdin {}, converting {} to {}",
+                    reason,
+                    from,
+                    from.other()
+                );
+            }
+            from
+        }
+    };
+    match convert::convert_text(&text, from, from.other()) {
+        Ok(converted) => {
+            print!("{}", converted);
+            Ok(None)
+        }
+        Err(e) => Ok(Some(CommandReport {
+            failure: Some(e.to_string()),
+            ..Default::default()
+        })),
     }
 }
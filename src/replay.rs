@@ -0,0 +1,285 @@
+//! Replay of recorded exchanges against the current build.
+//!
+//! `toon-mcp replay <file>` reads a recording made with `TOON_RECORD` (see
+//! [`crate::server::record`]) and sends each request again: HTTP requests
+//! through a fresh router, MCP tool calls through a fresh MCP session under
+//! the recorded client name. Responses are compared with the recorded ones,
+//! so a behavior change between builds shows up as a `DIFF`.
+
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::conformance::same_json;
+use crate::server::record::{self, Exchange};
+
+/// Outcome of replaying one exchange.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    Match,
+    /// The response changed; both sides are the response as recorded
+    Diff {
+        recorded: Value,
+        replayed: Value,
+    },
+    /// The transport is not compiled into this build
+    Skip(String),
+}
+
+/// One replayed exchange.
+#[derive(Debug)]
+pub struct Replayed {
+    pub label: String,
+    pub verdict: Verdict,
+}
+
+/// Replay every exchange in the recording at `path`, in order.
+pub async fn run(path: &Path) -> anyhow::Result<Vec<Replayed>> {
+    let mut results = Vec::new();
+    for exchange in record::load(path)? {
+        let label = exchange.label();
+        let verdict = match replay(&exchange).await? {
+            Some((recorded, replayed)) if same_json(&recorded, &replayed) => Verdict::Match,
+            Some((recorded, replayed)) => Verdict::Diff { recorded, replayed },
+            None => Verdict::Skip(format!(
+                "{} is not compiled into this build",
+                transport(&exchange)
+            )),
+        };
+        results.push(Replayed { label, verdict });
+    }
+    Ok(results)
+}
+
+fn transport(exchange: &Exchange) -> &'static str {
+    match exchange {
+        Exchange::Http { .. } => "HTTP",
+        Exchange::Mcp { .. } => "MCP",
+    }
+}
+
+/// Recorded and replayed responses, or `None` when the transport is missing.
+async fn replay(exchange: &Exchange) -> anyhow::Result<Option<(Value, Value)>> {
+    match exchange {
+        #[cfg(feature = "http")]
+        Exchange::Http {
+            method,
+            path,
+            content_type,
+            request,
+            status,
+            response,
+        } => {
+            let (replayed_status, replayed_body) =
+                http::send(method, path, content_type.as_deref(), request).await?;
+            let recorded = http_response(*status, response);
+            Ok(Some((
+                recorded,
+                http_response(replayed_status, &replayed_body),
+            )))
+        }
+        #[cfg(feature = "mcp")]
+        Exchange::Mcp {
+            client,
+            tool,
+            arguments,
+            response,
+        } => {
+            let replayed = mcp::call(client.as_deref(), tool, arguments).await?;
+            Ok(Some((response.clone(), replayed)))
+        }
+        #[allow(unreachable_patterns)]
+        _ => Ok(None),
+    }
+}
+
+/// Status and body for comparison, with JSON bodies compared as JSON.
+#[cfg(feature = "http")]
+fn http_response(status: u16, body: &str) -> Value {
+    let body = serde_json::from_str(body).unwrap_or_else(|_| Value::String(body.to_string()));
+    serde_json::json!({ "status": status, "body": body })
+}
+
+#[cfg(feature = "http")]
+mod http {
+    use axum::body::{to_bytes, Body};
+    use axum::http::{header, Request};
+    use tower::ServiceExt;
+
+    use crate::server::http::build_router;
+
+    /// Send one request through a router with default settings.
+    pub async fn send(
+        method: &str,
+        path: &str,
+        content_type: Option<&str>,
+        body: &str,
+    ) -> anyhow::Result<(u16, String)> {
+        let mut request = Request::builder().method(method).uri(path);
+        if let Some(content_type) = content_type {
+            request = request.header(header::CONTENT_TYPE, content_type);
+        }
+        let request = request.body(Body::from(body.to_string()))?;
+        let response = build_router().oneshot(request).await?;
+        let status = response.status().as_u16();
+        let body = to_bytes(response.into_body(), usize::MAX).await?;
+        Ok((status, String::from_utf8_lossy(&body).into_owned()))
+    }
+}
+
+#[cfg(feature = "mcp")]
+mod mcp {
+    use std::time::Duration;
+
+    use rmcp::ServiceExt;
+    use serde_json::{json, Value};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    use crate::tools::ToonTools;
+
+    /// Longest a replayed session may take.
+    const TIMEOUT: Duration = Duration::from_secs(60);
+
+    /// Call one tool in a fresh session, returning `{"result"}` or `{"error"}`.
+    pub async fn call(
+        client: Option<&str>,
+        tool: &str,
+        arguments: &Value,
+    ) -> anyhow::Result<Value> {
+        tokio::time::timeout(TIMEOUT, session(client, tool, arguments))
+            .await
+            .map_err(|_| anyhow::anyhow!("replaying {} timed out", tool))?
+    }
+
+    async fn session(client: Option<&str>, tool: &str, arguments: &Value) -> anyhow::Result<Value> {
+        let (server_io, client_io) = tokio::io::duplex(64 * 1024);
+        let service = ToonTools::new()
+            .without_recording()
+            .serve(tokio::io::split(server_io));
+        let server = tokio::spawn(async move {
+            if let Ok(service) = service.await {
+                let _ = service.waiting().await;
+            }
+        });
+
+        let (reader, mut writer) = tokio::io::split(client_io);
+        let mut lines = BufReader::new(reader).lines();
+        let messages = [
+            json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "initialize",
+                "params": {
+                    "protocolVersion": "2025-06-18",
+                    "capabilities": {},
+                    "clientInfo": {"name": client.unwrap_or("toon-mcp-replay"), "version": "replay"}
+                }
+            }),
+            json!({"jsonrpc": "2.0", "method": "notifications/initialized"}),
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "tools/call",
+                "params": {"name": tool, "arguments": arguments}
+            }),
+        ];
+        for message in messages {
+            writer
+                .write_all(format!("{}\n", message).as_bytes())
+                .await?;
+        }
+
+        // Skip the handshake reply and any notifications.
+        let response = loop {
+            let line = lines
+                .next_line()
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("MCP session closed before {} answered", tool))?;
+            let message: Value = serde_json::from_str(&line)?;
+            if message.get("id") == Some(&json!(1)) {
+                break message;
+            }
+        };
+        drop(writer);
+        server.abort();
+
+        Ok(match response.get("error") {
+            Some(error) => json!({ "error": error }),
+            None => json!({ "result": response.get("result").cloned().unwrap_or_default() }),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::record::Recorder;
+
+    fn recording(name: &str, exchanges: &[Exchange]) -> std::path::PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("toon-replay-{}-{}.jsonl", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let recorder = Recorder::create(&path).unwrap();
+        for exchange in exchanges {
+            recorder.record(exchange);
+        }
+        path
+    }
+
+    #[cfg(feature = "http")]
+    #[tokio::test]
+    async fn test_replay_http_reports_changed_responses() {
+        let encode = |response: &str| Exchange::Http {
+            method: "POST".to_string(),
+            path: "/api/v1/encode".to_string(),
+            content_type: Some("application/json".to_string()),
+            request: r#"{"json":{"a":1}}"#.to_string(),
+            status: 200,
+            response: response.to_string(),
+        };
+        let current = crate::core::encode_json(
+            &serde_json::json!({"a": 1}),
+            &crate::core::EncodeOptionsInput::default(),
+        )
+        .unwrap();
+        let (status, body) = http::send(
+            "POST",
+            "/api/v1/encode",
+            Some("application/json"),
+            r#"{"json":{"a":1}}"#,
+        )
+        .await
+        .unwrap();
+        assert_eq!(status, 200);
+        assert!(body.contains(&current));
+
+        let path = recording("http", &[encode(&body), encode(r#"{"toon":"a: 2"}"#)]);
+        let results = run(&path).await.unwrap();
+        assert_eq!(results[0].verdict, Verdict::Match);
+        assert!(matches!(results[1].verdict, Verdict::Diff { .. }));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[cfg(feature = "mcp")]
+    #[tokio::test]
+    async fn test_replay_mcp_tool_call() {
+        let response = mcp::call(None, "toon_ping", &serde_json::json!({}))
+            .await
+            .unwrap();
+        assert!(response.to_string().contains("pong"));
+
+        let path = recording(
+            "mcp",
+            &[Exchange::Mcp {
+                client: None,
+                tool: "toon_ping".to_string(),
+                arguments: serde_json::json!({}),
+                response,
+            }],
+        );
+        let results = run(&path).await.unwrap();
+        assert_eq!(results[0].label, "mcp toon_ping");
+        assert_eq!(results[0].verdict, Verdict::Match);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,159 @@
+//! Results of subcommands, printed as text or JSON, and their exit codes.
+//!
+//! Every subcommand returns a [`CommandReport`]. With `--output json` it is
+//! printed as one JSON object on stdout (`command`, `ok`, `result`, `stats`,
+//! `warnings`, and `error` when the command could not run); otherwise its
+//! text lines are printed, with warnings and errors on stderr. `--quiet`
+//! prints nothing but errors.
+//!
+//! Exit codes: 0 when the command found nothing wrong, 1 when it ran and
+//! found problems, 2 for invalid arguments (reported by clap before anything
+//! runs), and 3 when it could not run.
+
+use std::process::ExitCode;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::cli::OutputFormat;
+
+/// The command ran and found nothing wrong.
+pub const EXIT_OK: u8 = 0;
+/// The command ran and found problems: failed cases, diverged payloads,
+/// changed responses.
+pub const EXIT_FAILED: u8 = 1;
+/// The command could not run, for example because an input was unreadable.
+pub const EXIT_ERROR: u8 = 3;
+
+/// Outcome of a subcommand.
+#[derive(Debug, Default, Serialize)]
+pub struct CommandReport {
+    /// Subcommand name, filled in by [`finish`]
+    pub command: String,
+    /// Whether the command ran and found nothing wrong
+    pub ok: bool,
+    /// Command-specific result, such as the outcome of every case
+    pub result: Value,
+    /// Counts summarizing `result`
+    pub stats: Map<String, Value>,
+    pub warnings: Vec<String>,
+    /// Why the command could not run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Why the command failed, printed to stderr in text mode
+    #[serde(skip)]
+    pub failure: Option<String>,
+    /// Human-readable output
+    #[serde(skip)]
+    pub lines: Vec<String>,
+}
+
+impl CommandReport {
+    /// A report holding `result`.
+    pub fn new(result: Value) -> Self {
+        Self {
+            result,
+            ..Default::default()
+        }
+    }
+
+    /// Add a line of text output.
+    pub fn line(&mut self, line: impl Into<String>) {
+        self.lines.push(line.into());
+    }
+
+    /// Record a count.
+    pub fn stat(&mut self, name: &str, value: impl Into<Value>) {
+        self.stats.insert(name.to_string(), value.into());
+    }
+
+    /// Exit code for this report.
+    pub fn exit_code(&self) -> u8 {
+        if self.error.is_some() {
+            EXIT_ERROR
+        } else if self.failure.is_some() {
+            EXIT_FAILED
+        } else {
+            EXIT_OK
+        }
+    }
+}
+
+/// Print the outcome of `command` and return its exit code. `Ok(None)` is a
+/// command that wrote its own output, such as `completions`.
+pub fn finish(
+    command: &str,
+    outcome: anyhow::Result<Option<CommandReport>>,
+    output: OutputFormat,
+    quiet: bool,
+) -> ExitCode {
+    let mut report = match outcome {
+        Ok(None) => return ExitCode::SUCCESS,
+        Ok(Some(report)) => report,
+        Err(e) => CommandReport {
+            error: Some(format!("{:#}", e)),
+            ..Default::default()
+        },
+    };
+    report.command = command.to_string();
+    report.ok = report.exit_code() == EXIT_OK;
+
+    if let Some(error) = &report.error {
+        eprintln!("Error: {}", error);
+    }
+    if !quiet {
+        match output {
+            OutputFormat::Json => match serde_json::to_string(&report) {
+                Ok(json) => println!("{}", json),
+                Err(e) => eprintln!("Error: {}", e),
+            },
+            OutputFormat::Text => {
+                for line in &report.lines {
+                    println!("{}", line);
+                }
+                for warning in &report.warnings {
+                    eprintln!("warning: {}", warning);
+                }
+                if let Some(failure) = &report.failure {
+                    eprintln!("Error: {}", failure);
+                }
+            }
+        }
+    }
+    ExitCode::from(report.exit_code())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_exit_codes() {
+        let mut report = CommandReport::new(json!([]));
+        assert_eq!(report.exit_code(), EXIT_OK);
+        report.failure = Some("2 spec cases failed".to_string());
+        assert_eq!(report.exit_code(), EXIT_FAILED);
+        report.error = Some("no fixture files in x".to_string());
+        assert_eq!(report.exit_code(), EXIT_ERROR);
+    }
+
+    #[test]
+    fn test_json_shape() {
+        let mut report = CommandReport::new(json!(["a"]));
+        report.command = "regen-golden".to_string();
+        report.ok = true;
+        report.stat("changed", 1);
+        report.line("updated a");
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            json!({
+                "command": "regen-golden",
+                "ok": true,
+                "result": ["a"],
+                "stats": {"changed": 1},
+                "warnings": []
+            })
+        );
+    }
+}
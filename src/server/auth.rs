@@ -0,0 +1,376 @@
+//! JWT bearer authentication for the HTTP API (requires the `jwt` feature).
+//!
+//! Tokens are verified against keys fetched from a JWKS endpoint. Keys are
+//! cached for a configurable TTL and refetched early when a token names an
+//! unknown key ID, so identity-provider key rotation needs no restart.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, Algorithm, DecodingKey, Validation};
+use serde::Deserialize;
+use thiserror::Error;
+use tokio::sync::RwLock;
+
+use super::http::ApiError;
+
+/// Minimum time between JWKS fetches triggered by unknown key IDs.
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Authentication failures; all map to 401 responses.
+#[derive(Error, Debug)]
+pub enum AuthError {
+    #[error("missing bearer token")]
+    MissingToken,
+
+    #[error("invalid token: {0}")]
+    InvalidToken(String),
+
+    #[error("unknown signing key '{0}'")]
+    UnknownKey(String),
+
+    #[error("could not fetch signing keys: {0}")]
+    KeyFetch(String),
+}
+
+/// Access level granted to a token. Each role includes the ones below it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    /// Inspection endpoints: validate, stats, hashing, diffing, schema helpers
+    Read,
+    /// Data-transforming endpoints: encode, decode, reshaping, anonymization
+    Convert,
+    /// Expensive or privileged endpoints: database queries
+    Admin,
+}
+
+impl Role {
+    /// Parse a role name ("read", "convert", or "admin").
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "read" => Some(Role::Read),
+            "convert" => Some(Role::Convert),
+            "admin" => Some(Role::Admin),
+            _ => None,
+        }
+    }
+
+    /// Highest role named in a claim value (an array or a space-separated string).
+    fn from_claim(value: &serde_json::Value) -> Option<Self> {
+        match value {
+            serde_json::Value::String(s) => s.split_whitespace().filter_map(Role::parse).max(),
+            serde_json::Value::Array(items) => items
+                .iter()
+                .filter_map(|v| v.as_str())
+                .filter_map(Role::parse)
+                .max(),
+            _ => None,
+        }
+    }
+}
+
+/// JWT validation settings.
+#[derive(Debug, Clone)]
+pub struct JwtConfig {
+    /// URL of the identity provider's JWKS document
+    pub jwks_url: String,
+    /// Required `iss` claim, if any
+    pub issuer: Option<String>,
+    /// Required `aud` claim, if any
+    pub audience: Option<String>,
+    /// How long fetched keys are trusted before refetching
+    pub jwks_ttl: Duration,
+    /// Claim holding the token's roles
+    pub roles_claim: String,
+    /// Role assumed when the token has no recognised role
+    pub default_role: Role,
+    /// Claim naming the token's tenant, if tenants are enabled
+    pub tenant_claim: Option<String>,
+}
+
+impl JwtConfig {
+    /// Read settings from `TOON_JWT_JWKS_URL`, `TOON_JWT_ISSUER`,
+    /// `TOON_JWT_AUDIENCE`, `TOON_JWT_JWKS_TTL` (seconds, default 300),
+    /// `TOON_JWT_ROLES_CLAIM` (default "roles"), `TOON_JWT_DEFAULT_ROLE`
+    /// (default "convert"), and `TOON_JWT_TENANT_CLAIM` (unset: no tenants).
+    ///
+    /// Returns `None` when no JWKS URL is configured, leaving the API open.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        let Some(jwks_url) = var("TOON_JWT_JWKS_URL") else {
+            return Ok(None);
+        };
+        let jwks_ttl =
+            match var("TOON_JWT_JWKS_TTL") {
+                Some(secs) => Duration::from_secs(secs.parse().map_err(|_| {
+                    anyhow::anyhow!("TOON_JWT_JWKS_TTL must be a number of seconds")
+                })?),
+                None => Duration::from_secs(300),
+            };
+        let default_role = match var("TOON_JWT_DEFAULT_ROLE") {
+            Some(name) => Role::parse(&name).ok_or_else(|| {
+                anyhow::anyhow!("TOON_JWT_DEFAULT_ROLE must be read, convert, or admin")
+            })?,
+            None => Role::Convert,
+        };
+        Ok(Some(Self {
+            jwks_url,
+            issuer: var("TOON_JWT_ISSUER"),
+            audience: var("TOON_JWT_AUDIENCE"),
+            jwks_ttl,
+            roles_claim: var("TOON_JWT_ROLES_CLAIM").unwrap_or_else(|| "roles".to_string()),
+            default_role,
+            tenant_claim: var("TOON_JWT_TENANT_CLAIM"),
+        }))
+    }
+}
+
+/// Verified token claims, attached to the request extensions.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Claims {
+    /// Subject (the authenticated principal)
+    pub sub: String,
+
+    /// Role resolved from the configured roles claim
+    #[serde(skip, default = "default_claims_role")]
+    pub role: Role,
+
+    /// Tenant named by the configured tenant claim
+    #[serde(skip)]
+    pub tenant: Option<String>,
+
+    /// All other claims
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+fn default_claims_role() -> Role {
+    Role::Read
+}
+
+struct CachedKeys {
+    keys: JwkSet,
+    fetched: Instant,
+}
+
+/// Verifies bearer tokens against a cached JWKS.
+pub struct JwtAuth {
+    config: JwtConfig,
+    client: reqwest::Client,
+    cache: RwLock<Option<CachedKeys>>,
+}
+
+impl JwtAuth {
+    pub fn new(config: JwtConfig) -> Self {
+        Self {
+            config,
+            client: reqwest::Client::new(),
+            cache: RwLock::new(None),
+        }
+    }
+
+    /// Verify a token's signature, expiry, issuer, and audience.
+    pub async fn verify(&self, token: &str) -> Result<Claims, AuthError> {
+        let header = decode_header(token).map_err(|e| AuthError::InvalidToken(e.to_string()))?;
+        // Only asymmetric algorithms make sense with published keys; refusing
+        // HMAC prevents signing tokens with a public key as the secret.
+        if matches!(
+            header.alg,
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+        ) {
+            return Err(AuthError::InvalidToken(
+                "HMAC-signed tokens are not accepted".to_string(),
+            ));
+        }
+        let kid = header
+            .kid
+            .ok_or_else(|| AuthError::InvalidToken("token has no 'kid' header".to_string()))?;
+        let key = self.decoding_key(&kid).await?;
+
+        let mut validation = Validation::new(header.alg);
+        match &self.config.issuer {
+            Some(issuer) => validation.set_issuer(&[issuer]),
+            None => validation.iss = None,
+        }
+        match &self.config.audience {
+            Some(audience) => validation.set_audience(&[audience]),
+            None => validation.validate_aud = false,
+        }
+
+        let mut claims = decode::<Claims>(token, &key, &validation)
+            .map(|data| data.claims)
+            .map_err(|e| AuthError::InvalidToken(e.to_string()))?;
+        claims.role = claims
+            .extra
+            .get(&self.config.roles_claim)
+            .and_then(Role::from_claim)
+            .unwrap_or(self.config.default_role);
+        if let Some(name) = &self.config.tenant_claim {
+            claims.tenant = match claims.extra.get(name) {
+                Some(serde_json::Value::String(tenant)) if !tenant.is_empty() => {
+                    Some(tenant.clone())
+                }
+                _ => {
+                    return Err(AuthError::InvalidToken(format!(
+                        "token has no '{}' tenant claim",
+                        name
+                    )))
+                }
+            };
+        }
+        Ok(claims)
+    }
+
+    /// Find the key for `kid`, refetching the JWKS when stale or when the key is unknown.
+    async fn decoding_key(&self, kid: &str) -> Result<DecodingKey, AuthError> {
+        {
+            let cache = self.cache.read().await;
+            if let Some(cached) = cache.as_ref() {
+                let fresh = cached.fetched.elapsed() < self.config.jwks_ttl;
+                match cached.keys.find(kid) {
+                    Some(jwk) if fresh => {
+                        return DecodingKey::from_jwk(jwk)
+                            .map_err(|e| AuthError::InvalidToken(e.to_string()))
+                    }
+                    None if cached.fetched.elapsed() < MIN_REFRESH_INTERVAL => {
+                        return Err(AuthError::UnknownKey(kid.to_string()))
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let keys: JwkSet = self
+            .client
+            .get(&self.config.jwks_url)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| AuthError::KeyFetch(e.to_string()))?
+            .json()
+            .await
+            .map_err(|e| AuthError::KeyFetch(e.to_string()))?;
+
+        let key = keys
+            .find(kid)
+            .ok_or_else(|| AuthError::UnknownKey(kid.to_string()))
+            .and_then(|jwk| {
+                DecodingKey::from_jwk(jwk).map_err(|e| AuthError::InvalidToken(e.to_string()))
+            });
+        *self.cache.write().await = Some(CachedKeys {
+            keys,
+            fetched: Instant::now(),
+        });
+        key
+    }
+}
+
+/// Extract the token from an `Authorization: Bearer <token>` header.
+pub fn bearer_token(headers: &axum::http::HeaderMap) -> Option<&str> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    scheme
+        .eq_ignore_ascii_case("bearer")
+        .then(|| token.trim())
+        .filter(|t| !t.is_empty())
+}
+
+/// Middleware rejecting requests without a valid bearer token.
+///
+/// On success the verified [`Claims`] are inserted into the request extensions.
+pub async fn require_jwt(
+    State(auth): State<Arc<JwtAuth>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let result = match bearer_token(request.headers()) {
+        Some(token) => auth.verify(token).await,
+        None => Err(AuthError::MissingToken),
+    };
+    match result {
+        Ok(claims) => {
+            request.extensions_mut().insert(claims);
+            next.run(request).await
+        }
+        Err(e) => (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Bearer")],
+            Json(ApiError {
+                error: e.to_string(),
+                details: None,
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// Middleware rejecting requests whose token lacks `role`; must run after [`require_jwt`].
+pub async fn require_role(State(role): State<Role>, request: Request, next: Next) -> Response {
+    match request.extensions().get::<Claims>() {
+        Some(claims) if claims.role >= role => next.run(request).await,
+        _ => (
+            StatusCode::FORBIDDEN,
+            Json(ApiError {
+                error: format!("this endpoint requires the {:?} role", role).to_lowercase(),
+                details: None,
+            }),
+        )
+            .into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderMap;
+
+    #[test]
+    fn test_bearer_token_parsing() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(bearer_token(&headers), None);
+
+        headers.insert(header::AUTHORIZATION, "Bearer abc.def.ghi".parse().unwrap());
+        assert_eq!(bearer_token(&headers), Some("abc.def.ghi"));
+
+        headers.insert(header::AUTHORIZATION, "Basic dXNlcjpwYXNz".parse().unwrap());
+        assert_eq!(bearer_token(&headers), None);
+    }
+
+    #[test]
+    fn test_role_from_claim() {
+        assert_eq!(
+            Role::from_claim(&serde_json::json!(["read", "admin"])),
+            Some(Role::Admin)
+        );
+        assert_eq!(
+            Role::from_claim(&serde_json::json!("read convert")),
+            Some(Role::Convert)
+        );
+        assert_eq!(Role::from_claim(&serde_json::json!(["billing"])), None);
+        assert!(Role::Admin > Role::Convert && Role::Convert > Role::Read);
+    }
+
+    #[tokio::test]
+    async fn test_verify_rejects_malformed_token() {
+        let auth = JwtAuth::new(JwtConfig {
+            jwks_url: "http://127.0.0.1:9/jwks.json".to_string(),
+            issuer: None,
+            audience: None,
+            jwks_ttl: Duration::from_secs(300),
+            roles_claim: "roles".to_string(),
+            default_role: Role::Convert,
+            tenant_claim: None,
+        });
+        assert!(matches!(
+            auth.verify("not-a-jwt").await,
+            Err(AuthError::InvalidToken(_))
+        ));
+    }
+}
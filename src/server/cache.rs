@@ -0,0 +1,118 @@
+//! Cache hints and conditional GETs for responses that only change between
+//! builds.
+//!
+//! Capabilities, version information, and the API docs are the same for every
+//! request to a given build, so CDNs and clients may keep them for a while.
+//! Each response carries a strong `ETag` over its body; a request whose
+//! `If-None-Match` lists that tag gets `304 Not Modified` without a body.
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+
+/// Seconds clients and shared caches may reuse a response without revalidating.
+pub const MAX_AGE_SECS: u64 = 300;
+
+/// Strong entity tag for `body`.
+pub fn etag(body: &[u8]) -> String {
+    let digest = Sha256::digest(body);
+    let hex: String = digest[..16].iter().map(|b| format!("{:02x}", b)).collect();
+    format!("\"{}\"", hex)
+}
+
+/// Whether an `If-None-Match` header value matches `etag`.
+///
+/// Uses the weak comparison RFC 9110 requires for `If-None-Match`.
+pub fn none_match(if_none_match: &str, etag: &str) -> bool {
+    if_none_match.split(',').map(str::trim).any(|candidate| {
+        candidate == "*" || candidate.strip_prefix("W/").unwrap_or(candidate) == etag
+    })
+}
+
+/// Add `Cache-Control`, `Vary`, and `ETag` to successful GETs and answer
+/// matching conditional requests with `304 Not Modified`.
+///
+/// Authenticated requests are marked `private` so shared caches do not serve
+/// them to callers that have not passed authentication.
+pub async fn cache_hints(request: Request, next: Next) -> Response {
+    if request.method() != Method::GET && request.method() != Method::HEAD {
+        return next.run(request).await;
+    }
+    let scope = if request.headers().contains_key(header::AUTHORIZATION) {
+        "private"
+    } else {
+        "public"
+    };
+    let if_none_match = request
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+
+    let response = next.run(request).await;
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+    let tag = etag(&bytes);
+    set_hints(&mut parts.headers, scope, &tag);
+
+    if if_none_match.is_some_and(|value| none_match(&value, &tag)) {
+        let mut not_modified = StatusCode::NOT_MODIFIED.into_response();
+        for name in [header::CACHE_CONTROL, header::VARY, header::ETAG] {
+            if let Some(value) = parts.headers.remove(&name) {
+                not_modified.headers_mut().insert(name, value);
+            }
+        }
+        return not_modified;
+    }
+
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+fn set_hints(headers: &mut HeaderMap, scope: &str, tag: &str) {
+    let cache_control = format!("{}, max-age={}", scope, MAX_AGE_SECS);
+    if let Ok(value) = HeaderValue::from_str(&cache_control) {
+        headers.insert(header::CACHE_CONTROL, value);
+    }
+    headers.insert(
+        header::VARY,
+        HeaderValue::from_static("Accept-Encoding, Authorization"),
+    );
+    if let Ok(value) = HeaderValue::from_str(tag) {
+        headers.insert(header::ETAG, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_etag_is_stable_and_quoted() {
+        let tag = etag(b"{\"a\":1}");
+        assert_eq!(tag, etag(b"{\"a\":1}"));
+        assert_ne!(tag, etag(b"{\"a\":2}"));
+        assert!(tag.starts_with('"') && tag.ends_with('"'));
+        assert_eq!(tag.len(), 34);
+    }
+
+    #[test]
+    fn test_none_match_lists_and_weak_tags() {
+        let tag = etag(b"body");
+        assert!(none_match(&tag, &tag));
+        assert!(none_match(&format!("\"other\", W/{}", tag), &tag));
+        assert!(none_match("*", &tag));
+        assert!(!none_match("\"other\"", &tag));
+    }
+}
@@ -0,0 +1,156 @@
+//! Fault injection for testing client resilience (`--chaos`).
+//!
+//! Chaos mode delays, fails, or truncates a random share of API responses,
+//! so clients can exercise their timeouts, retries, and parse-error handling
+//! against a local server. Every injected fault is marked with an
+//! `x-toon-chaos` response header. Never enable it in production.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use crate::core::rand;
+
+/// Header naming the fault injected into a response.
+pub const CHAOS_HEADER: &str = "x-toon-chaos";
+
+/// Fault rates, each a percentage of API requests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChaosConfig {
+    /// Requests delayed by `latency`
+    pub latency_percent: u8,
+    /// Added delay
+    pub latency: Duration,
+    /// Requests answered with 500 Internal Server Error
+    pub error_percent: u8,
+    /// Responses whose body is cut in half
+    pub truncate_percent: u8,
+}
+
+/// Fault injector shared by all requests.
+#[derive(Debug)]
+pub struct Chaos {
+    config: ChaosConfig,
+    state: AtomicU64,
+}
+
+impl Chaos {
+    pub fn new(config: ChaosConfig) -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default();
+        Self::with_seed(config, seed)
+    }
+
+    fn with_seed(config: ChaosConfig, seed: u64) -> Self {
+        Self {
+            config,
+            state: AtomicU64::new(seed),
+        }
+    }
+
+    /// Whether an event with probability `percent` happens this time.
+    fn roll(&self, percent: u8) -> bool {
+        let state = self.state.fetch_add(rand::GAMMA, Ordering::Relaxed);
+        rand::mix(state.wrapping_add(rand::GAMMA)) % 100 < u64::from(percent)
+    }
+
+    /// One-line summary for the startup banner.
+    pub fn describe(&self) -> String {
+        let c = &self.config;
+        format!(
+            "{}% delayed {} ms, {}% failed with 500, {}% truncated",
+            c.latency_percent,
+            c.latency.as_millis(),
+            c.error_percent,
+            c.truncate_percent
+        )
+    }
+}
+
+fn mark(mut response: Response, fault: &'static str) -> Response {
+    response
+        .headers_mut()
+        .insert(CHAOS_HEADER, HeaderValue::from_static(fault));
+    response
+}
+
+/// Middleware injecting the configured faults.
+pub async fn inject_chaos(
+    State(chaos): State<Arc<Chaos>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let delayed = chaos.roll(chaos.config.latency_percent);
+    if delayed {
+        tokio::time::sleep(chaos.config.latency).await;
+    }
+
+    if chaos.roll(chaos.config.error_percent) {
+        let body = Json(serde_json::json!({"error": "Injected failure (chaos mode)"}));
+        return mark(
+            (StatusCode::INTERNAL_SERVER_ERROR, body).into_response(),
+            "error",
+        );
+    }
+
+    let response = next.run(request).await;
+    if !chaos.roll(chaos.config.truncate_percent) {
+        return if delayed {
+            mark(response, "latency")
+        } else {
+            response
+        };
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let body = to_bytes(body, usize::MAX).await.unwrap_or_default();
+    parts.headers.remove(header::CONTENT_LENGTH);
+    let truncated = body.slice(..body.len() / 2);
+    mark(
+        Response::from_parts(parts, Body::from(truncated)),
+        "truncate",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chaos(percent: u8) -> Chaos {
+        Chaos::with_seed(
+            ChaosConfig {
+                latency_percent: percent,
+                latency: Duration::ZERO,
+                error_percent: percent,
+                truncate_percent: percent,
+            },
+            42,
+        )
+    }
+
+    #[test]
+    fn test_roll_extremes() {
+        let never = chaos(0);
+        let always = chaos(100);
+        assert!((0..1000).all(|_| !never.roll(0)));
+        assert!((0..1000).all(|_| always.roll(100)));
+    }
+
+    #[test]
+    fn test_roll_rate() {
+        let chaos = chaos(0);
+        let hits = (0..10_000).filter(|_| chaos.roll(20)).count();
+        assert!((1_700..2_300).contains(&hits), "{} hits", hits);
+    }
+}
@@ -0,0 +1,90 @@
+//! Runnable request/response examples for the API routes.
+//!
+//! The requests live in `src/core/api_examples.json`, which the HTTP test
+//! suite also runs, so a published example cannot stop working
+//! unnoticed. Responses are not stored: `GET /api/v1/examples` sends each
+//! request through a fresh router once and serves what this build answers.
+//! The requests are also attached to the OpenAPI document, so SDK generators
+//! and the docs UIs show working payloads.
+
+use axum::{
+    body::{to_bytes, Body},
+    http::{header, Request},
+};
+use tokio::sync::OnceCell;
+use tower::ServiceExt;
+use utoipa::openapi::OpenApi;
+
+use crate::core::{howto, ApiExample, ExamplesResponse};
+
+/// Prefix the examples are served and documented under.
+const PREFIX: &str = "/api/v1";
+
+/// The example requests, without responses.
+pub fn requests() -> Vec<ApiExample> {
+    let mut examples = howto::examples();
+    for example in &mut examples {
+        example.path = howto::route_path(PREFIX, &example.path);
+    }
+    examples
+}
+
+/// Every example with the response this build gives, computed on first use.
+pub async fn examples() -> &'static ExamplesResponse {
+    static CACHE: OnceCell<ExamplesResponse> = OnceCell::const_new();
+    CACHE
+        .get_or_init(|| async {
+            let mut examples = requests();
+            for example in &mut examples {
+                run(example).await;
+            }
+            ExamplesResponse { examples }
+        })
+        .await
+}
+
+/// Send an example's request and store the response in it.
+async fn run(example: &mut ApiExample) {
+    let mut request = Request::builder()
+        .method(example.method.as_str())
+        .uri(example.path.as_str());
+    let body = match &example.request {
+        Some(json) => {
+            request = request.header(header::CONTENT_TYPE, "application/json");
+            Body::from(json.to_string())
+        }
+        None => Body::empty(),
+    };
+    let Ok(request) = request.body(body) else {
+        return;
+    };
+    let Ok(response) = super::http::build_router().oneshot(request).await;
+    example.status = response.status().as_u16();
+    let body = to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap_or_default();
+    example.response = serde_json::from_slice(&body)
+        .unwrap_or_else(|_| String::from_utf8_lossy(&body).into_owned().into());
+}
+
+/// Attach each example request to its operation in the OpenAPI document.
+pub fn annotate(openapi: &mut OpenApi) {
+    for example in requests() {
+        let (Some(request), Some(item)) =
+            (example.request, openapi.paths.paths.get_mut(&example.path))
+        else {
+            continue;
+        };
+        let operation = match example.method.as_str() {
+            "POST" => item.post.as_mut(),
+            "PUT" => item.put.as_mut(),
+            _ => None,
+        };
+        let Some(body) = operation.and_then(|op| op.request_body.as_mut()) else {
+            continue;
+        };
+        for content in body.content.values_mut() {
+            content.example = Some(request.clone());
+        }
+    }
+}
@@ -1,32 +1,78 @@
 //! HTTP REST API server implementation using axum.
 
 use axum::{
-    extract::State,
-    http::StatusCode,
-    response::IntoResponse,
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    middleware::from_fn,
+    response::{IntoResponse, Response},
     routing::{get, post},
     Json, Router,
 };
 use std::sync::Arc;
+use tower_http::catch_panic::CatchPanicLayer;
 use tower_http::cors::{Any, CorsLayer};
 use utoipa::OpenApi;
+use utoipa_redoc::{Redoc, Servable};
 use utoipa_swagger_ui::SwaggerUi;
 
+use super::cache::cache_hints;
+use super::chaos::{inject_chaos, Chaos, ChaosConfig};
+use super::latency::{track_latency, LatencyTracker, SloConfig};
+use super::payload_log::{log_payloads, PayloadLogConfig, PayloadLogger};
+use super::record::{record_http, Recorder};
+use super::usage::{track_usage, UsageTracker};
+use crate::cli::ListenAddr;
 use crate::core::{
-    self, DecodeRequest, DecodeResponse, EncodeOptionsInput, EncodeRequest, EncodeResponse,
-    HealthResponse, StatsRequest, StatsResponse, ToonCoreError, ValidateRequest, ValidateResponse,
+    self, AggregateRequest, AnonymizeRequest, AnonymizeResponse, AssertSavingsRequest,
+    AssertSavingsResponse, CacheAnalysisRequest, CacheAnalysisResponse, CapabilitiesResponse,
+    DeanonymizeRequest, DeanonymizeResponse, DecodeRequest, DecodeResponse, DecodeStreamQuery,
+    DemoDataRequest, DemoDataResponse, DemoListResponse, DiffValidateRequest, DiffValidateResponse,
+    EncodeRequest, EncodeResponse, ExamplesResponse, FormatRangeRequest, FormatRangeResponse,
+    HashRequest, HashResponse, HealthResponse, JoinRequest, LatencyResponse, PivotRequest,
+    PostgresQueryRequest, QueryResponse, RoundTripRequest, RoundTripResponse, SchemaExampleRequest,
+    SchemaExampleResponse, SchemaTemplateRequest, SchemaTemplateResponse, SqlRequest, SqlResponse,
+    SqliteQueryRequest, StatsRequest, StatsResponse, TableResponse, ToonCoreError, UsageResponse,
+    ValidateRequest, ValidateResponse, VersionResponse,
 };
+use crate::worker::WorkerPool;
 
 /// Application state shared across handlers.
 #[derive(Clone)]
 pub struct AppState {
     pub version: String,
+    /// Address the server is bound to, once known
+    pub listen_addr: Option<String>,
+    /// JWT verifier guarding `/api/v1`, when configured
+    #[cfg(feature = "jwt")]
+    pub auth: Option<Arc<super::auth::JwtAuth>>,
+    /// Per-principal usage counters and quotas
+    pub usage: Arc<UsageTracker>,
+    /// Rolling per-route latency and the SLO it is held to
+    pub latency: Arc<LatencyTracker>,
+    /// Sampled payload logger, when debug logging is enabled
+    pub payload_log: Option<Arc<PayloadLogger>>,
+    /// Pool for large encode/decode jobs
+    pub workers: WorkerPool,
+    /// Fault injector for `--chaos`, when enabled
+    pub chaos: Option<Arc<Chaos>>,
+    /// Exchange recorder for `TOON_RECORD`, when enabled
+    pub recorder: Option<Arc<Recorder>>,
 }
 
 impl Default for AppState {
     fn default() -> Self {
         Self {
             version: env!("CARGO_PKG_VERSION").to_string(),
+            listen_addr: None,
+            #[cfg(feature = "jwt")]
+            auth: None,
+            usage: Arc::new(UsageTracker::default()),
+            latency: Arc::new(LatencyTracker::default()),
+            payload_log: None,
+            workers: WorkerPool::default(),
+            chaos: None,
+            recorder: None,
         }
     }
 }
@@ -48,6 +94,10 @@ pub struct ErrorDetails {
     pub column: Option<usize>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub suggestion: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub row: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
 }
 
 impl From<ToonCoreError> for ApiError {
@@ -58,12 +108,16 @@ impl From<ToonCoreError> for ApiError {
                 line,
                 column,
                 suggestion,
+                row,
+                snippet,
             } => ApiError {
                 error: message,
                 details: Some(ErrorDetails {
                     line: Some(line),
                     column: Some(column),
                     suggestion,
+                    row,
+                    snippet,
                 }),
             },
             ToonCoreError::LengthMismatch { expected, found } => ApiError {
@@ -89,26 +143,113 @@ impl IntoResponse for ApiError {
 #[openapi(
     paths(
         health,
+        version,
+        capabilities,
+        examples,
+        demo_list,
+        demo_data,
         encode,
         decode,
+        decode_stream,
         validate,
+        format_range,
         stats,
+        stats_decode,
+        assert_savings,
+        aggregate,
+        join,
+        pivot,
+        anonymize,
+        deanonymize,
+        hash,
+        cache_analysis,
+        verify_roundtrip,
+        diff_validate,
+        example_from_schema,
+        schema_template,
+        to_sql,
+        query_sqlite,
+        query_postgres,
+        usage,
+        latency,
     ),
     components(
         schemas(
             HealthResponse,
+            VersionResponse,
+            CapabilitiesResponse,
+            crate::core::CapabilityLimits,
+            crate::core::OptionDefaults,
+            ExamplesResponse,
+            crate::core::ApiExample,
             EncodeRequest,
             EncodeResponse,
+            crate::core::StrategyDecision,
             DecodeRequest,
             DecodeResponse,
+            crate::core::PageInfo,
+            crate::core::Coercion,
             ValidateRequest,
             ValidateResponse,
+            crate::core::Contract,
+            crate::core::ColumnContract,
+            FormatRangeRequest,
+            FormatRangeResponse,
+            crate::core::TextEdit,
             StatsRequest,
             StatsResponse,
+            crate::core::StatsOptions,
             crate::core::FormatStats,
             crate::core::SavingsStats,
+            crate::core::SampleInfo,
+            crate::core::Diagnosis,
+            AssertSavingsRequest,
+            AssertSavingsResponse,
+            UsageResponse,
+            crate::core::UsageQuota,
+            crate::core::KeyUsage,
+            DemoListResponse,
+            DemoDataResponse,
+            crate::core::DemoDataset,
+            LatencyResponse,
+            crate::core::LatencySlo,
+            crate::core::RouteLatency,
             crate::core::ValidationError,
             crate::core::EncodeOptionsInput,
+            crate::core::EncodeMetadata,
+            crate::core::Literals,
+            AggregateRequest,
+            crate::core::Aggregation,
+            JoinRequest,
+            PivotRequest,
+            TableResponse,
+            AnonymizeRequest,
+            AnonymizeResponse,
+            crate::core::AnonymizeReport,
+            crate::core::PiiPattern,
+            DeanonymizeRequest,
+            DeanonymizeResponse,
+            HashRequest,
+            HashResponse,
+            CacheAnalysisRequest,
+            CacheAnalysisResponse,
+            RoundTripRequest,
+            RoundTripResponse,
+            crate::core::Difference,
+            DiffValidateRequest,
+            DiffValidateResponse,
+            crate::core::SchemaViolation,
+            SchemaExampleRequest,
+            SchemaExampleResponse,
+            SchemaTemplateRequest,
+            SchemaTemplateResponse,
+            crate::core::TemplateField,
+            SqlRequest,
+            SqlResponse,
+            crate::core::SqlColumn,
+            SqliteQueryRequest,
+            PostgresQueryRequest,
+            QueryResponse,
             ApiError,
             ErrorDetails,
         )
@@ -125,39 +266,316 @@ impl IntoResponse for ApiError {
 )]
 struct ApiDoc;
 
+/// The OpenAPI document, with example requests attached.
+pub fn api_doc() -> utoipa::openapi::OpenApi {
+    let mut doc = ApiDoc::openapi();
+    super::examples::annotate(&mut doc);
+    doc
+}
+
 /// Build the HTTP router.
 pub fn build_router() -> Router {
-    let state = Arc::new(AppState::default());
+    build_router_with_state(AppState::default())
+}
+
+/// Build the HTTP router around the given state.
+pub fn build_router_with_state(state: AppState) -> Router {
+    #[cfg(feature = "jwt")]
+    let auth = state.auth.clone();
+    let state = Arc::new(state);
 
     let cors = CorsLayer::new()
         .allow_origin(Any)
         .allow_methods(Any)
         .allow_headers(Any);
 
-    Router::new()
+    // Inspection endpoints (read role)
+    let read = Router::new()
+        .route(
+            "/capabilities",
+            get(capabilities).layer(from_fn(cache_hints)),
+        )
+        .route("/examples", get(examples))
+        .route("/demo", get(demo_list))
+        .route("/demo/{name}", get(demo_data))
+        .route("/validate", post(validate))
+        .route("/format/range", post(format_range))
+        .route("/stats", post(stats))
+        .route("/stats/decode", post(stats_decode))
+        .route("/assert_savings", post(assert_savings))
+        .route("/hash", post(hash))
+        .route("/cache_analysis", post(cache_analysis))
+        .route("/verify_roundtrip", post(verify_roundtrip))
+        .route("/diff_validate", post(diff_validate))
+        .route("/schema/example", post(example_from_schema))
+        .route("/schema/template", post(schema_template));
+
+    // Data-transforming endpoints (convert role)
+    let convert = Router::new()
+        .route("/encode", post(encode))
+        .route("/decode", post(decode))
+        .route("/decode/stream", post(decode_stream))
+        .route("/aggregate", post(aggregate))
+        .route("/join", post(join))
+        .route("/pivot", post(pivot))
+        .route("/anonymize", post(anonymize))
+        .route("/deanonymize", post(deanonymize))
+        .route("/sql", post(to_sql));
+
+    // Database access and administration (admin role)
+    let admin = Router::new()
+        .route("/sqlite/query", post(query_sqlite))
+        .route("/postgres/query", post(query_postgres))
+        .route("/admin/usage", get(usage))
+        .route("/admin/latency", get(latency));
+
+    // JSON-RPC mirror of the read and convert endpoints (convert role)
+    let rpc = Router::new().route("/rpc", post(super::rpc::rpc));
+
+    #[cfg(feature = "jwt")]
+    let (read, convert, admin, rpc) = match auth {
+        Some(_) => {
+            use super::auth::{require_role, Role};
+            use axum::middleware::from_fn_with_state;
+
+            (
+                read.route_layer(from_fn_with_state(Role::Read, require_role)),
+                convert.route_layer(from_fn_with_state(Role::Convert, require_role)),
+                admin.route_layer(from_fn_with_state(Role::Admin, require_role)),
+                rpc.route_layer(from_fn_with_state(Role::Convert, require_role)),
+            )
+        }
+        None => (read, convert, admin, rpc),
+    };
+
+    // Usage accounting, payload logging, authentication, chaos faults, and
+    // latency tracking cover every API route.
+    let guard = |router: Router<Arc<AppState>>| {
+        let mut router = router.route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            track_usage,
+        ));
+        if let Some(logger) = state.payload_log.clone() {
+            router = router.route_layer(axum::middleware::from_fn_with_state(logger, log_payloads));
+        }
+        #[cfg(feature = "jwt")]
+        if let Some(auth) = auth.clone() {
+            router = router.route_layer(axum::middleware::from_fn_with_state(
+                auth,
+                super::auth::require_jwt,
+            ));
+        }
+        if let Some(chaos) = state.chaos.clone() {
+            router = router.route_layer(axum::middleware::from_fn_with_state(chaos, inject_chaos));
+        }
+        // Outermost, so the latency measured includes everything above.
+        router.route_layer(axum::middleware::from_fn_with_state(
+            state.latency.clone(),
+            track_latency,
+        ))
+    };
+    let api = guard(read.merge(convert).merge(admin));
+    let rpc = guard(rpc);
+
+    // v1 keeps its bare response bodies and points clients at v2.
+    let v1 = api
+        .clone()
+        .route_layer(axum::middleware::from_fn(deprecate_v1));
+    let v2 = api.route_layer(axum::middleware::from_fn(envelope));
+
+    // API docs only change between builds.
+    let docs = Router::new()
+        .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", api_doc()))
+        .merge(Redoc::with_url("/docs", api_doc()))
+        .route("/api-docs/openapi.yaml", get(openapi_yaml))
+        .layer(from_fn(cache_hints));
+
+    let mut app = Router::new()
         .route("/health", get(health))
-        .route("/api/v1/encode", post(encode))
-        .route("/api/v1/decode", post(decode))
-        .route("/api/v1/validate", post(validate))
-        .route("/api/v1/stats", post(stats))
-        .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
-        .layer(cors)
-        .with_state(state)
+        .route("/version", get(version).layer(from_fn(cache_hints)))
+        .nest("/api/v1", v1)
+        .nest("/api/v2", v2)
+        .merge(rpc)
+        .merge(docs)
+        .layer(CatchPanicLayer::custom(panic_response));
+    // Record outside panic handling, so the 500s clients saw are replayed too.
+    if let Some(recorder) = state.recorder.clone() {
+        app = app.layer(axum::middleware::from_fn_with_state(recorder, record_http));
+    }
+    app.layer(cors).with_state(state)
 }
 
-/// Run the HTTP server.
-pub async fn run_http_server(addr: &str) -> anyhow::Result<()> {
-    let app = build_router();
+/// Mark v1 responses deprecated and link each to its v2 equivalent.
+async fn deprecate_v1(request: axum::extract::Request, next: axum::middleware::Next) -> Response {
+    let successor = format!(
+        "</api/v2{}>; rel=\"successor-version\"",
+        request.uri().path()
+    );
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+    headers.insert("deprecation", header::HeaderValue::from_static("true"));
+    if let Ok(link) = header::HeaderValue::from_str(&successor) {
+        headers.insert(header::LINK, link);
+    }
+    response
+}
+
+/// Wrap JSON responses in the v2 envelope: `{"data", "meta", "error"}`.
+///
+/// Successful bodies go in `data`, error bodies in `error`. Non-JSON
+/// responses such as NDJSON decode output pass through unchanged.
+async fn envelope(request: axum::extract::Request, next: axum::middleware::Next) -> Response {
+    let response = next.run(request).await;
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|ct| ct.as_bytes().starts_with(b"application/json"));
+    if !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+    let payload: serde_json::Value = serde_json::from_slice(&bytes).unwrap_or_default();
+    let meta = serde_json::json!({"api_version": "v2"});
+    let wrapped = if parts.status.is_success() {
+        serde_json::json!({"data": payload, "meta": meta, "error": null})
+    } else {
+        serde_json::json!({"data": null, "meta": meta, "error": payload})
+    };
 
-    eprintln!("toon-mcp HTTP server starting on http://{}", addr);
-    eprintln!("  API docs: http://{}/swagger-ui/", addr);
+    parts.headers.remove(header::CONTENT_LENGTH);
+    (parts, Json(wrapped)).into_response()
+}
 
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+/// Turn a handler panic into a 500 response with a reference to the log line.
+fn panic_response(panic: Box<dyn std::any::Any + Send + 'static>) -> Response {
+    let reference = core::error_reference();
+    eprintln!(
+        "toon-mcp: request handler panicked (reference {}): {}",
+        reference,
+        core::panic_message(&*panic)
+    );
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ApiError {
+            error: format!("internal error (reference {})", reference),
+            details: None,
+        }),
+    )
+        .into_response()
+}
+
+/// Run the HTTP server on every address in `listen`.
+pub async fn run_http_server(
+    listen: &[ListenAddr],
+    chaos: Option<ChaosConfig>,
+) -> anyhow::Result<()> {
+    let chaos = chaos.map(|config| Arc::new(Chaos::new(config)));
+    if let Some(chaos) = &chaos {
+        eprintln!("toon-mcp: chaos mode: {}", chaos.describe());
+    }
+    let recorder = Recorder::from_env()?.map(Arc::new);
+    if let Some(recorder) = &recorder {
+        eprintln!(
+            "toon-mcp: recording exchanges to {}",
+            recorder.path().display()
+        );
+    }
+    let slo = SloConfig::from_env()?;
+    let latency = Arc::new(LatencyTracker::new(slo.as_ref().map(|c| c.slo.clone())));
+    if let Some(config) = slo {
+        eprintln!(
+            "toon-mcp: latency SLO: p99 under {} ms, breached after {} minutes",
+            config.slo.p99_ms, config.slo.minutes
+        );
+        tokio::spawn(super::latency::watch(latency.clone(), config));
+    }
+    let base = AppState {
+        #[cfg(feature = "jwt")]
+        auth: super::auth::JwtConfig::from_env()?
+            .map(|config| Arc::new(super::auth::JwtAuth::new(config))),
+        usage: Arc::new(UsageTracker::from_env().await?),
+        latency,
+        workers: WorkerPool::from_env()?,
+        payload_log: PayloadLogConfig::from_env()?
+            .map(|config| Arc::new(PayloadLogger::new(config))),
+        chaos,
+        recorder,
+        ..AppState::default()
+    };
+    serve(listen, "HTTP server", |addr| {
+        if let ListenAddr::Tcp(_) = addr {
+            eprintln!("  API docs: {}/swagger-ui/ and {}/docs", addr, addr);
+        }
+        build_router_with_state(AppState {
+            listen_addr: Some(addr.to_string()),
+            ..base.clone()
+        })
+    })
+    .await
+}
+
+/// Serve the router `app` builds for each address in `listen`.
+///
+/// Each bound address is printed to stdout, which reports the actual port
+/// when binding to port 0. Serving stops as soon as any listener fails.
+pub(crate) async fn serve(
+    listen: &[ListenAddr],
+    name: &str,
+    app: impl Fn(&ListenAddr) -> Router,
+) -> anyhow::Result<()> {
+    let mut servers = tokio::task::JoinSet::new();
+
+    for addr in listen {
+        match addr {
+            ListenAddr::Tcp(addr) => {
+                let listener = tokio::net::TcpListener::bind(addr)
+                    .await
+                    .map_err(|e| anyhow::anyhow!("failed to bind {}: {}", addr, e))?;
+                let bound = ListenAddr::Tcp(listener.local_addr()?);
+                println!("toon-mcp {} listening on {}", name, bound);
+                let app = app(&bound);
+                servers.spawn(async move { axum::serve(listener, app).await });
+            }
+            #[cfg(unix)]
+            ListenAddr::Unix(path) => {
+                remove_stale_socket(path)?;
+                let listener = tokio::net::UnixListener::bind(path)
+                    .map_err(|e| anyhow::anyhow!("failed to bind {}: {}", addr, e))?;
+                println!("toon-mcp {} listening on {}", name, addr);
+                let app = app(addr);
+                servers.spawn(async move { axum::serve(listener, app).await });
+            }
+            #[cfg(not(unix))]
+            ListenAddr::Unix(_) => {
+                anyhow::bail!("Unix socket listeners are not supported on this platform")
+            }
+        }
+    }
+
+    while let Some(result) = servers.join_next().await {
+        result??;
+    }
 
     Ok(())
 }
 
+/// Remove a socket file left behind by a previous run; refuse to touch anything else.
+#[cfg(unix)]
+fn remove_stale_socket(path: &std::path::Path) -> anyhow::Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+
+    match std::fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_socket() => Ok(std::fs::remove_file(path)?),
+        Ok(_) => anyhow::bail!("{} exists and is not a socket", path.display()),
+        Err(_) => Ok(()),
+    }
+}
+
 /// Health check endpoint.
 #[utoipa::path(
     get,
@@ -171,9 +589,124 @@ async fn health(State(state): State<Arc<AppState>>) -> Json<HealthResponse> {
     Json(HealthResponse {
         status: "ok".to_string(),
         version: state.version.clone(),
+        address: state.listen_addr.clone(),
     })
 }
 
+/// Build and capability information.
+#[utoipa::path(
+    get,
+    path = "/version",
+    responses(
+        (status = 200, description = "Version, commit, build time, and features", body = VersionResponse)
+    ),
+    tag = "toon"
+)]
+async fn version() -> Json<VersionResponse> {
+    Json(core::build_info())
+}
+
+/// Supported formats, options, limits, and build features.
+#[utoipa::path(
+    get,
+    path = "/api/v1/capabilities",
+    responses(
+        (status = 200, description = "What this build supports", body = CapabilitiesResponse)
+    ),
+    tag = "toon"
+)]
+async fn capabilities() -> Json<CapabilitiesResponse> {
+    Json(core::capabilities())
+}
+
+/// Runnable request/response examples for every API route.
+#[utoipa::path(
+    get,
+    path = "/api/v1/examples",
+    responses(
+        (status = 200, description = "Example requests with this build's responses", body = ExamplesResponse)
+    ),
+    tag = "toon"
+)]
+async fn examples() -> Json<&'static ExamplesResponse> {
+    Json(super::examples::examples().await)
+}
+
+/// List the built-in demo datasets.
+#[utoipa::path(
+    get,
+    path = "/api/v1/demo",
+    responses(
+        (status = 200, description = "Names and descriptions of the sample datasets", body = DemoListResponse)
+    ),
+    tag = "toon"
+)]
+async fn demo_list() -> Json<DemoListResponse> {
+    Json(core::demo::list())
+}
+
+/// Fetch a built-in demo dataset as JSON and TOON.
+#[utoipa::path(
+    get,
+    path = "/api/v1/demo/{name}",
+    params(("name" = String, Path, description = "Dataset name: iris, countries, or orders")),
+    responses(
+        (status = 200, description = "The dataset, its JSON, and its TOON encoding", body = DemoDataResponse),
+        (status = 404, description = "Unknown dataset", body = ApiError)
+    ),
+    tag = "toon"
+)]
+async fn demo_data(Path(name): Path<String>) -> Result<Json<DemoDataResponse>, Response> {
+    let request = DemoDataRequest {
+        dataset: name,
+        encode_options: Default::default(),
+    };
+    core::demo::load(&request)
+        .map(Json)
+        .map_err(|e| (StatusCode::NOT_FOUND, Json(ApiError::from(e))).into_response())
+}
+
+/// OpenAPI document in YAML.
+async fn openapi_yaml() -> Response {
+    match api_doc().to_yaml() {
+        Ok(yaml) => ([(header::CONTENT_TYPE, "application/yaml")], yaml).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiError {
+                error: e.to_string(),
+                details: None,
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// Report today's usage for every API principal.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/usage",
+    responses(
+        (status = 200, description = "Per-principal usage for the current UTC day", body = UsageResponse)
+    ),
+    tag = "toon"
+)]
+async fn usage(State(state): State<Arc<AppState>>) -> Json<UsageResponse> {
+    Json(state.usage.report().await)
+}
+
+/// Report rolling latency percentiles for every API route.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/latency",
+    responses(
+        (status = 200, description = "Per-route p50 and p99 latency over the last minutes", body = LatencyResponse)
+    ),
+    tag = "toon"
+)]
+async fn latency(State(state): State<Arc<AppState>>) -> Json<LatencyResponse> {
+    Json(state.latency.report())
+}
+
 /// Encode JSON to TOON format.
 #[utoipa::path(
     post,
@@ -185,22 +718,17 @@ async fn health(State(state): State<Arc<AppState>>) -> Json<HealthResponse> {
     ),
     tag = "toon"
 )]
-async fn encode(Json(request): Json<EncodeRequest>) -> Result<Json<EncodeResponse>, ApiError> {
-    // Parse JSON input
-    let json_value = core::parse_json_input(&request.json)?;
-
-    // Build options
-    let options = EncodeOptionsInput {
-        delimiter: request.delimiter,
-        indent: request.indent,
-        fold_keys: request.fold_keys,
-        flatten_depth: request.flatten_depth,
-    };
-
-    // Encode
-    let toon = core::encode_json(&json_value, &options)?;
+async fn encode(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<EncodeRequest>,
+) -> Result<Json<EncodeResponse>, ApiError> {
+    let size = core::json_size_hint(&request.json);
+    let response = state
+        .workers
+        .run(size, move || core::encode_request(&request))
+        .await?;
 
-    Ok(Json(EncodeResponse { toon }))
+    Ok(Json(response))
 }
 
 /// Decode TOON to JSON format.
@@ -209,14 +737,125 @@ async fn encode(Json(request): Json<EncodeRequest>) -> Result<Json<EncodeRespons
     path = "/api/v1/decode",
     request_body = DecodeRequest,
     responses(
-        (status = 200, description = "Successfully decoded", body = DecodeResponse),
+        (status = 200, description = "Successfully decoded (NDJSON when output_format is ndjson)", content(
+            (DecodeResponse = "application/json"),
+            (String = "application/x-ndjson")
+        )),
         (status = 400, description = "Invalid TOON syntax", body = ApiError)
     ),
     tag = "toon"
 )]
-async fn decode(Json(request): Json<DecodeRequest>) -> Result<Json<DecodeResponse>, ApiError> {
-    let json = core::decode_toon(&request.toon, &request)?;
-    Ok(Json(DecodeResponse { json }))
+async fn decode(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<DecodeRequest>,
+) -> Result<Response, ApiError> {
+    let ndjson = request.output_format.as_deref() == Some("ndjson");
+    let size = request.toon.len();
+    let (json, page, coercions) = state
+        .workers
+        .run(size, move || {
+            let mut json = core::decode_toon(&request.toon, &request)?;
+            let coercions = core::coercion::report(&request, &json)?;
+            let page = core::paginate(&mut json, &request)?;
+            Ok::<_, ToonCoreError>((json, page, coercions))
+        })
+        .await?;
+
+    if ndjson {
+        let body = core::format_ndjson(&json)?;
+        return Ok(([(header::CONTENT_TYPE, "application/x-ndjson")], body).into_response());
+    }
+    Ok(Json(DecodeResponse {
+        json,
+        page,
+        coercions,
+    })
+    .into_response())
+}
+
+/// Rows buffered between the decoder and a slow client before decoding, and
+/// with it reading the upload, pauses.
+const STREAM_BUFFER: usize = 16;
+
+/// Paths whose request body is consumed as it arrives. Middleware that would
+/// buffer a body lets these through untouched.
+const STREAMING_PATHS: &[&str] = &["/decode/stream"];
+
+/// Whether the request at `path` streams its body.
+pub(crate) fn streams_body(path: &str) -> bool {
+    STREAMING_PATHS.iter().any(|suffix| path.ends_with(suffix))
+}
+
+/// Decode a TOON table while it uploads, answering with one NDJSON line per row.
+///
+/// Rows of a single top-level table are written as soon as their line
+/// arrives; any other document is decoded once the upload completes. A
+/// failure after rows have been sent ends the stream with an
+/// `{"error": "..."}` line.
+#[utoipa::path(
+    post,
+    path = "/api/v1/decode/stream",
+    params(DecodeStreamQuery),
+    request_body(content = String, content_type = "text/plain", description = "TOON document"),
+    responses(
+        (status = 200, description = "One JSON value per row, ending with an error line on failure", body = String, content_type = "application/x-ndjson")
+    ),
+    tag = "toon"
+)]
+async fn decode_stream(Query(query): Query<DecodeStreamQuery>, body: Body) -> Response {
+    let (sender, receiver) = tokio::sync::mpsc::channel(STREAM_BUFFER);
+    tokio::spawn(async move {
+        let strict_utf8 = query.strict_utf8.unwrap_or(false);
+        let decoder = core::stream::RowDecoder::new(query.into()).with_strict_utf8(strict_utf8);
+        if let Err(e) = stream_rows(decoder, body, &sender).await {
+            let line = format!("{}\n", serde_json::json!({ "error": e.to_string() }));
+            let _ = sender.send(Ok(line)).await;
+        }
+    });
+
+    (
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(tokio_stream::wrappers::ReceiverStream::new(receiver)),
+    )
+        .into_response()
+}
+
+/// Feed the upload through `decoder`, sending each batch of rows as it
+/// completes. Stops early, without error, once the client has gone away.
+async fn stream_rows(
+    mut decoder: core::stream::RowDecoder,
+    body: Body,
+    sender: &tokio::sync::mpsc::Sender<Result<String, std::convert::Infallible>>,
+) -> Result<(), ToonCoreError> {
+    use tokio_stream::StreamExt;
+
+    let mut chunks = body.into_data_stream();
+    while let Some(chunk) = chunks.next().await {
+        let chunk = chunk
+            .map_err(|e| ToonCoreError::DecodeError(format!("reading request body: {}", e)))?;
+        let mut rows = Vec::new();
+        let result = decoder.push(&chunk, &mut rows);
+        if !send_rows(sender, rows).await? {
+            return Ok(());
+        }
+        result?;
+    }
+    let mut rows = Vec::new();
+    let result = decoder.finish(&mut rows);
+    send_rows(sender, rows).await?;
+    result
+}
+
+/// Send `rows` as NDJSON; `false` once the client has gone away.
+async fn send_rows(
+    sender: &tokio::sync::mpsc::Sender<Result<String, std::convert::Infallible>>,
+    rows: Vec<serde_json::Value>,
+) -> Result<bool, ToonCoreError> {
+    if rows.is_empty() {
+        return Ok(true);
+    }
+    let lines = core::format_ndjson(&rows.into())?;
+    Ok(sender.send(Ok(lines)).await.is_ok())
 }
 
 /// Validate TOON syntax.
@@ -225,13 +864,32 @@ async fn decode(Json(request): Json<DecodeRequest>) -> Result<Json<DecodeRespons
     path = "/api/v1/validate",
     request_body = ValidateRequest,
     responses(
-        (status = 200, description = "Validation result", body = ValidateResponse)
+        (status = 200, description = "Validation result", body = ValidateResponse),
+        (status = 400, description = "Invalid contract", body = ApiError)
+    ),
+    tag = "toon"
+)]
+async fn validate(
+    Json(request): Json<ValidateRequest>,
+) -> Result<Json<ValidateResponse>, ApiError> {
+    Ok(Json(core::validate_request(&request)?))
+}
+
+/// Format a line range of a TOON document, returning line edits.
+#[utoipa::path(
+    post,
+    path = "/api/v1/format/range",
+    request_body = FormatRangeRequest,
+    responses(
+        (status = 200, description = "Edits formatting the range", body = FormatRangeResponse),
+        (status = 400, description = "Invalid TOON or line range", body = ApiError)
     ),
     tag = "toon"
 )]
-async fn validate(Json(request): Json<ValidateRequest>) -> Json<ValidateResponse> {
-    let result = core::validate_toon(&request.toon, request.strict);
-    Json(result)
+async fn format_range(
+    Json(request): Json<FormatRangeRequest>,
+) -> Result<Json<FormatRangeResponse>, ApiError> {
+    Ok(Json(core::format_range(&request)?))
 }
 
 /// Compare JSON and TOON statistics.
@@ -247,10 +905,271 @@ async fn validate(Json(request): Json<ValidateRequest>) -> Json<ValidateResponse
 )]
 async fn stats(Json(request): Json<StatsRequest>) -> Result<Json<StatsResponse>, ApiError> {
     let json_value = core::parse_json_input(&request.json)?;
-    let stats = core::compute_stats(&json_value, &request.encode_options)?;
+    let stats =
+        core::compute_stats(&json_value, &request.encode_options, &request.stats_options)?;
     Ok(Json(stats))
 }
 
+/// Compare a TOON document against the JSON it decodes to.
+#[utoipa::path(
+    post,
+    path = "/api/v1/stats/decode",
+    request_body = DecodeRequest,
+    responses(
+        (status = 200, description = "Format statistics", body = StatsResponse),
+        (status = 400, description = "Invalid TOON", body = ApiError)
+    ),
+    tag = "toon"
+)]
+async fn stats_decode(Json(request): Json<DecodeRequest>) -> Result<Json<StatsResponse>, ApiError> {
+    Ok(Json(core::compute_decode_stats(&request)?))
+}
+
+/// Check that encoding saves at least a minimum percentage.
+#[utoipa::path(
+    post,
+    path = "/api/v1/assert_savings",
+    request_body = AssertSavingsRequest,
+    responses(
+        (status = 200, description = "Whether the savings threshold was met", body = AssertSavingsResponse),
+        (status = 400, description = "Invalid input", body = ApiError)
+    ),
+    tag = "toon"
+)]
+async fn assert_savings(
+    Json(request): Json<AssertSavingsRequest>,
+) -> Result<Json<AssertSavingsResponse>, ApiError> {
+    Ok(Json(core::assert_savings(&request)?))
+}
+
+/// Group and aggregate an array of objects.
+#[utoipa::path(
+    post,
+    path = "/api/v1/aggregate",
+    request_body = AggregateRequest,
+    responses(
+        (status = 200, description = "Aggregated table", body = TableResponse),
+        (status = 400, description = "Invalid input", body = ApiError)
+    ),
+    tag = "toon"
+)]
+async fn aggregate(Json(request): Json<AggregateRequest>) -> Result<Json<TableResponse>, ApiError> {
+    Ok(Json(core::aggregate(&request)?))
+}
+
+/// Join two arrays of objects on key columns.
+#[utoipa::path(
+    post,
+    path = "/api/v1/join",
+    request_body = JoinRequest,
+    responses(
+        (status = 200, description = "Joined table", body = TableResponse),
+        (status = 400, description = "Invalid input or row limit exceeded", body = ApiError)
+    ),
+    tag = "toon"
+)]
+async fn join(Json(request): Json<JoinRequest>) -> Result<Json<TableResponse>, ApiError> {
+    Ok(Json(core::join(&request)?))
+}
+
+/// Reshape an array of objects between long and wide layouts.
+#[utoipa::path(
+    post,
+    path = "/api/v1/pivot",
+    request_body = PivotRequest,
+    responses(
+        (status = 200, description = "Reshaped table", body = TableResponse),
+        (status = 400, description = "Invalid input", body = ApiError)
+    ),
+    tag = "toon"
+)]
+async fn pivot(Json(request): Json<PivotRequest>) -> Result<Json<TableResponse>, ApiError> {
+    Ok(Json(core::pivot(&request)?))
+}
+
+/// Detect and replace PII before encoding.
+#[utoipa::path(
+    post,
+    path = "/api/v1/anonymize",
+    request_body = AnonymizeRequest,
+    responses(
+        (status = 200, description = "Anonymized TOON with report", body = AnonymizeResponse),
+        (status = 400, description = "Invalid input", body = ApiError)
+    ),
+    tag = "toon"
+)]
+async fn anonymize(
+    Json(request): Json<AnonymizeRequest>,
+) -> Result<Json<AnonymizeResponse>, ApiError> {
+    Ok(Json(core::anonymize(&request)?))
+}
+
+/// Restore original values using an encrypted anonymization mapping.
+#[utoipa::path(
+    post,
+    path = "/api/v1/deanonymize",
+    request_body = DeanonymizeRequest,
+    responses(
+        (status = 200, description = "Restored text", body = DeanonymizeResponse),
+        (status = 400, description = "Invalid mapping or key", body = ApiError)
+    ),
+    tag = "toon"
+)]
+async fn deanonymize(
+    Json(request): Json<DeanonymizeRequest>,
+) -> Result<Json<DeanonymizeResponse>, ApiError> {
+    Ok(Json(core::deanonymize(&request)?))
+}
+
+/// Compute integrity digests of a document.
+#[utoipa::path(
+    post,
+    path = "/api/v1/hash",
+    request_body = HashRequest,
+    responses(
+        (status = 200, description = "Canonical digests", body = HashResponse),
+        (status = 400, description = "Invalid input", body = ApiError)
+    ),
+    tag = "toon"
+)]
+async fn hash(Json(request): Json<HashRequest>) -> Result<Json<HashResponse>, ApiError> {
+    Ok(Json(core::hash_document(&request)?))
+}
+
+/// Estimate prompt-cache reuse between two payload versions.
+#[utoipa::path(
+    post,
+    path = "/api/v1/cache_analysis",
+    request_body = CacheAnalysisRequest,
+    responses(
+        (status = 200, description = "Shared prefix and cache reuse estimate", body = CacheAnalysisResponse),
+        (status = 400, description = "Invalid input", body = ApiError)
+    ),
+    tag = "toon"
+)]
+async fn cache_analysis(
+    Json(request): Json<CacheAnalysisRequest>,
+) -> Result<Json<CacheAnalysisResponse>, ApiError> {
+    Ok(Json(core::cache_analysis(&request)?))
+}
+
+/// Verify that encoding and decoding a document is lossless.
+#[utoipa::path(
+    post,
+    path = "/api/v1/verify_roundtrip",
+    request_body = RoundTripRequest,
+    responses(
+        (status = 200, description = "Round-trip report", body = RoundTripResponse),
+        (status = 400, description = "Invalid input", body = ApiError)
+    ),
+    tag = "toon"
+)]
+async fn verify_roundtrip(
+    Json(request): Json<RoundTripRequest>,
+) -> Result<Json<RoundTripResponse>, ApiError> {
+    Ok(Json(core::verify_roundtrip(&request)?))
+}
+
+/// Compare LLM-returned TOON against the original JSON.
+#[utoipa::path(
+    post,
+    path = "/api/v1/diff_validate",
+    request_body = DiffValidateRequest,
+    responses(
+        (status = 200, description = "Differential validation report", body = DiffValidateResponse),
+        (status = 400, description = "Invalid input", body = ApiError)
+    ),
+    tag = "toon"
+)]
+async fn diff_validate(
+    Json(request): Json<DiffValidateRequest>,
+) -> Result<Json<DiffValidateResponse>, ApiError> {
+    Ok(Json(core::diff_validate(&request)?))
+}
+
+/// Generate an example TOON document from a JSON Schema.
+#[utoipa::path(
+    post,
+    path = "/api/v1/schema/example",
+    request_body = SchemaExampleRequest,
+    responses(
+        (status = 200, description = "Synthetic example", body = SchemaExampleResponse),
+        (status = 400, description = "Invalid schema", body = ApiError)
+    ),
+    tag = "toon"
+)]
+async fn example_from_schema(
+    Json(request): Json<SchemaExampleRequest>,
+) -> Result<Json<SchemaExampleResponse>, ApiError> {
+    Ok(Json(core::example_from_schema(&request)?))
+}
+
+/// Generate a TOON tabular header template from a JSON Schema.
+#[utoipa::path(
+    post,
+    path = "/api/v1/schema/template",
+    request_body = SchemaTemplateRequest,
+    responses(
+        (status = 200, description = "Header template", body = SchemaTemplateResponse),
+        (status = 400, description = "Invalid schema", body = ApiError)
+    ),
+    tag = "toon"
+)]
+async fn schema_template(
+    Json(request): Json<SchemaTemplateRequest>,
+) -> Result<Json<SchemaTemplateResponse>, ApiError> {
+    Ok(Json(core::schema_template(&request)?))
+}
+
+/// Convert a tabular document into SQL statements.
+#[utoipa::path(
+    post,
+    path = "/api/v1/sql",
+    request_body = SqlRequest,
+    responses(
+        (status = 200, description = "CREATE TABLE and INSERT statements", body = SqlResponse),
+        (status = 400, description = "Invalid input", body = ApiError)
+    ),
+    tag = "toon"
+)]
+async fn to_sql(Json(request): Json<SqlRequest>) -> Result<Json<SqlResponse>, ApiError> {
+    Ok(Json(core::to_sql(&request)?))
+}
+
+/// Run a read-only SQLite query and return the rows as TOON.
+#[utoipa::path(
+    post,
+    path = "/api/v1/sqlite/query",
+    request_body = SqliteQueryRequest,
+    responses(
+        (status = 200, description = "Query result", body = QueryResponse),
+        (status = 400, description = "Invalid query, path, or disabled feature", body = ApiError)
+    ),
+    tag = "toon"
+)]
+async fn query_sqlite(
+    Json(request): Json<SqliteQueryRequest>,
+) -> Result<Json<QueryResponse>, ApiError> {
+    Ok(Json(core::query_sqlite(&request)?))
+}
+
+/// Run a read-only PostgreSQL query and return the rows as TOON.
+#[utoipa::path(
+    post,
+    path = "/api/v1/postgres/query",
+    request_body = PostgresQueryRequest,
+    responses(
+        (status = 200, description = "Query result", body = QueryResponse),
+        (status = 400, description = "Invalid query or disabled feature", body = ApiError)
+    ),
+    tag = "toon"
+)]
+async fn query_postgres(
+    Json(request): Json<PostgresQueryRequest>,
+) -> Result<Json<QueryResponse>, ApiError> {
+    Ok(Json(core::query_postgres(&request).await?))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,378 @@
+//! Rolling per-route latency and SLO alerts for the HTTP API.
+//!
+//! Every API route keeps a latency histogram per minute for the last
+//! [`WINDOW_MINUTES`] minutes, or for the SLO's breach period if longer.
+//! `GET /api/v1/admin/latency` reports p50 and p99 from them. With
+//! `TOON_SLO_P99_MS` set, a route whose p99 stays above it for
+//! `TOON_SLO_MINUTES` consecutive minutes is in breach. Each breach is logged
+//! once, and posted to `TOON_SLO_WEBHOOK` when the `alerts` feature is
+//! enabled, until the route recovers.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use serde::Serialize;
+
+use crate::core::{LatencyResponse, LatencySlo, RouteLatency};
+
+/// Minutes of history kept at least.
+pub const WINDOW_MINUTES: u32 = 5;
+
+/// Histogram buckets grow by 10%, so percentiles are read to within 10%.
+const GROWTH: f64 = 1.1;
+
+/// SLO settings.
+#[derive(Debug, Clone)]
+pub struct SloConfig {
+    pub slo: LatencySlo,
+    /// URL each breach is posted to
+    #[cfg(feature = "alerts")]
+    pub webhook: Option<String>,
+}
+
+impl SloConfig {
+    /// Read settings from `TOON_SLO_P99_MS` (unset disables the SLO),
+    /// `TOON_SLO_MINUTES` (default 5), and `TOON_SLO_WEBHOOK`.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+
+        let p99_ms = match var("TOON_SLO_P99_MS") {
+            Some(value) => match value.parse::<f64>() {
+                Ok(ms) if ms > 0.0 => ms,
+                _ => anyhow::bail!("TOON_SLO_P99_MS must be a positive number of milliseconds"),
+            },
+            None => return Ok(None),
+        };
+        let minutes = match var("TOON_SLO_MINUTES") {
+            Some(value) => match value.parse::<u32>() {
+                Ok(minutes) if minutes > 0 => minutes,
+                _ => anyhow::bail!("TOON_SLO_MINUTES must be a whole number of minutes above 0"),
+            },
+            None => 5,
+        };
+        let webhook = var("TOON_SLO_WEBHOOK");
+        #[cfg(not(feature = "alerts"))]
+        if webhook.is_some() {
+            anyhow::bail!("TOON_SLO_WEBHOOK requires a build with the alerts feature");
+        }
+
+        Ok(Some(Self {
+            slo: LatencySlo { p99_ms, minutes },
+            #[cfg(feature = "alerts")]
+            webhook,
+        }))
+    }
+}
+
+/// A route that has just gone into breach; the body posted to the webhook.
+#[derive(Debug, Clone, Serialize)]
+pub struct Breach {
+    /// Method and route, e.g. "POST /api/v1/encode"
+    pub route: String,
+    /// Highest p99 in milliseconds over the breach period
+    pub p99_ms: f64,
+    pub slo: LatencySlo,
+}
+
+/// Latency counts in buckets growing by [`GROWTH`].
+#[derive(Debug, Default, Clone)]
+struct Histogram {
+    buckets: BTreeMap<u16, u64>,
+    count: u64,
+}
+
+impl Histogram {
+    fn record(&mut self, latency: Duration) {
+        let micros = latency.as_micros().max(1) as f64;
+        let bucket = micros.log(GROWTH).ceil().min(f64::from(u16::MAX)) as u16;
+        *self.buckets.entry(bucket).or_default() += 1;
+        self.count += 1;
+    }
+
+    fn merge(&mut self, other: &Histogram) {
+        for (bucket, count) in &other.buckets {
+            *self.buckets.entry(*bucket).or_default() += count;
+        }
+        self.count += other.count;
+    }
+
+    /// Upper bound in milliseconds of the bucket holding the `q` quantile.
+    fn percentile_ms(&self, q: f64) -> f64 {
+        let target = ((q * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (bucket, count) in &self.buckets {
+            seen += count;
+            if seen >= target {
+                let ms = GROWTH.powi(i32::from(*bucket)) / 1000.0;
+                return (ms * 100.0).round() / 100.0;
+            }
+        }
+        0.0
+    }
+}
+
+#[derive(Default)]
+struct Route {
+    /// Histograms by minute since the Unix epoch, oldest first
+    minutes: VecDeque<(u64, Histogram)>,
+    /// Whether the current breach has been reported
+    alerted: bool,
+}
+
+/// Latency histograms shared by every listener.
+#[derive(Default)]
+pub struct LatencyTracker {
+    slo: Option<LatencySlo>,
+    routes: Mutex<HashMap<String, Route>>,
+}
+
+impl LatencyTracker {
+    pub fn new(slo: Option<LatencySlo>) -> Self {
+        Self {
+            slo,
+            ..Self::default()
+        }
+    }
+
+    fn window(&self) -> u64 {
+        let breach = self.slo.as_ref().map_or(0, |slo| slo.minutes);
+        u64::from(breach.max(WINDOW_MINUTES))
+    }
+
+    /// Record one request to `route`.
+    pub fn record(&self, route: &str, latency: Duration) {
+        self.record_at(minute(), route, latency)
+    }
+
+    fn record_at(&self, now: u64, route: &str, latency: Duration) {
+        let window = self.window();
+        let mut routes = self.routes.lock().unwrap_or_else(|e| e.into_inner());
+        let route = routes.entry(route.to_string()).or_default();
+        match route.minutes.back_mut() {
+            Some((minute, histogram)) if *minute == now => histogram.record(latency),
+            _ => {
+                let mut histogram = Histogram::default();
+                histogram.record(latency);
+                route.minutes.push_back((now, histogram));
+            }
+        }
+        while route
+            .minutes
+            .front()
+            .is_some_and(|(minute, _)| minute + window <= now)
+        {
+            route.minutes.pop_front();
+        }
+    }
+
+    /// Percentiles over the window for every route with recent requests.
+    pub fn report(&self) -> LatencyResponse {
+        self.report_at(minute())
+    }
+
+    fn report_at(&self, now: u64) -> LatencyResponse {
+        let window = self.window();
+        let routes = self.routes.lock().unwrap_or_else(|e| e.into_inner());
+        let mut report: Vec<RouteLatency> = routes
+            .iter()
+            .filter_map(|(name, route)| {
+                let mut total = Histogram::default();
+                for (_, histogram) in route
+                    .minutes
+                    .iter()
+                    .filter(|(minute, _)| minute + window > now)
+                {
+                    total.merge(histogram);
+                }
+                (total.count > 0).then(|| RouteLatency {
+                    route: name.clone(),
+                    requests: total.count,
+                    p50_ms: total.percentile_ms(0.5),
+                    p99_ms: total.percentile_ms(0.99),
+                    breached: self.breach_p99(route, now).is_some(),
+                })
+            })
+            .collect();
+        report.sort_by(|a, b| a.route.cmp(&b.route));
+
+        LatencyResponse {
+            window_minutes: window as u32,
+            slo: self.slo.clone(),
+            routes: report,
+        }
+    }
+
+    /// The highest p99 over the SLO's last complete minutes, if every one of
+    /// them had requests and a p99 above the objective.
+    fn breach_p99(&self, route: &Route, now: u64) -> Option<f64> {
+        let slo = self.slo.as_ref()?;
+        let first = now.checked_sub(u64::from(slo.minutes))?;
+        let mut worst: f64 = 0.0;
+        for minute in first..now {
+            let (_, histogram) = route.minutes.iter().find(|(m, _)| *m == minute)?;
+            let p99 = histogram.percentile_ms(0.99);
+            if p99 <= slo.p99_ms {
+                return None;
+            }
+            worst = worst.max(p99);
+        }
+        Some(worst)
+    }
+
+    /// Routes that went into breach since the last check.
+    pub fn check(&self) -> Vec<Breach> {
+        self.check_at(minute())
+    }
+
+    fn check_at(&self, now: u64) -> Vec<Breach> {
+        let Some(slo) = &self.slo else {
+            return Vec::new();
+        };
+        let mut routes = self.routes.lock().unwrap_or_else(|e| e.into_inner());
+        let mut breaches = Vec::new();
+        for (name, route) in routes.iter_mut() {
+            match self.breach_p99(route, now) {
+                Some(p99_ms) if !route.alerted => {
+                    route.alerted = true;
+                    breaches.push(Breach {
+                        route: name.clone(),
+                        p99_ms,
+                        slo: slo.clone(),
+                    });
+                }
+                Some(_) => {}
+                None => route.alerted = false,
+            }
+        }
+        breaches.sort_by(|a, b| a.route.cmp(&b.route));
+        breaches
+    }
+}
+
+/// Minutes since the Unix epoch.
+fn minute() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / 60)
+        .unwrap_or(0)
+}
+
+/// Check for breaches once a minute, logging each and posting it to the
+/// webhook when one is configured.
+pub async fn watch(tracker: Arc<LatencyTracker>, config: SloConfig) {
+    #[cfg(feature = "alerts")]
+    let client = reqwest::Client::new();
+    let mut interval = tokio::time::interval(Duration::from_secs(60));
+    loop {
+        interval.tick().await;
+        for breach in tracker.check() {
+            eprintln!(
+                "toon-mcp: SLO breached: {} p99 {} ms over {} ms for {} minutes",
+                breach.route, breach.p99_ms, config.slo.p99_ms, config.slo.minutes
+            );
+            #[cfg(feature = "alerts")]
+            if let Some(url) = &config.webhook {
+                let sent = client
+                    .post(url)
+                    .json(&breach)
+                    .timeout(Duration::from_secs(10))
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status());
+                if let Err(e) = sent {
+                    eprintln!("toon-mcp: SLO webhook failed: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Middleware timing each API request until its response headers are ready.
+pub async fn track_latency(
+    State(tracker): State<Arc<LatencyTracker>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(path) = request.extensions().get::<MatchedPath>() else {
+        return next.run(request).await;
+    };
+    let route = format!("{} {}", request.method(), path.as_str());
+    let started = Instant::now();
+    let response = next.run(request).await;
+    tracker.record(&route, started.elapsed());
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker() -> LatencyTracker {
+        LatencyTracker::new(Some(LatencySlo {
+            p99_ms: 100.0,
+            minutes: 2,
+        }))
+    }
+
+    #[test]
+    fn test_percentiles_within_bucket_error() {
+        let mut histogram = Histogram::default();
+        for ms in 1..=100 {
+            histogram.record(Duration::from_millis(ms));
+        }
+        let p50 = histogram.percentile_ms(0.5);
+        let p99 = histogram.percentile_ms(0.99);
+        assert!((50.0..=55.0).contains(&p50), "{}", p50);
+        assert!((99.0..=108.9).contains(&p99), "{}", p99);
+    }
+
+    #[test]
+    fn test_report_covers_window() {
+        let tracker = tracker();
+        tracker.record_at(10, "POST /api/v1/encode", Duration::from_millis(5));
+        tracker.record_at(16, "POST /api/v1/encode", Duration::from_millis(5));
+        tracker.record_at(16, "GET /api/v1/examples", Duration::from_millis(1));
+
+        let report = tracker.report_at(16);
+        assert_eq!(report.window_minutes, WINDOW_MINUTES);
+        let routes: Vec<(&str, u64)> = report
+            .routes
+            .iter()
+            .map(|r| (r.route.as_str(), r.requests))
+            .collect();
+        assert_eq!(
+            routes,
+            vec![("GET /api/v1/examples", 1), ("POST /api/v1/encode", 1)]
+        );
+    }
+
+    #[test]
+    fn test_breach_alerts_once_until_recovery() {
+        let tracker = tracker();
+        let slow = Duration::from_millis(250);
+        tracker.record_at(20, "POST /api/v1/encode", slow);
+        assert!(tracker.check_at(21).is_empty());
+
+        tracker.record_at(21, "POST /api/v1/encode", slow);
+        let breaches = tracker.check_at(22);
+        assert_eq!(breaches.len(), 1);
+        assert_eq!(breaches[0].route, "POST /api/v1/encode");
+        assert!(breaches[0].p99_ms > 100.0);
+        assert!(tracker.report_at(22).routes[0].breached);
+
+        tracker.record_at(22, "POST /api/v1/encode", slow);
+        assert!(tracker.check_at(23).is_empty());
+
+        tracker.record_at(23, "POST /api/v1/encode", Duration::from_millis(5));
+        assert!(tracker.check_at(24).is_empty());
+        tracker.record_at(24, "POST /api/v1/encode", slow);
+        tracker.record_at(25, "POST /api/v1/encode", slow);
+        assert_eq!(tracker.check_at(26).len(), 1);
+    }
+}
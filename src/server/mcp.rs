@@ -1,12 +1,116 @@
 //! MCP server implementation using stdio transport.
 
+use std::time::Duration;
+
 use crate::tools::ToonTools;
-use rmcp::{transport::stdio, ServiceExt};
+use rmcp::{
+    model::{PingRequest, ServerRequest},
+    transport::stdio,
+    ServiceExt,
+};
+
+/// How long a keepalive ping may wait for the client's reply.
+const PING_TIMEOUT: Duration = Duration::from_secs(30);
 
 /// Run the MCP server with stdio transport.
-pub async fn run_mcp_server() -> anyhow::Result<()> {
+///
+/// The server stops when stdin closes, when writing to stdout fails, or on
+/// SIGINT/SIGTERM. With `keepalive` set, the client is pinged at that
+/// interval and the server shuts down if a ping fails or goes unanswered,
+/// so the process does not outlive a crashed client.
+pub async fn run_mcp_server(keepalive: Option<Duration>) -> anyhow::Result<()> {
     eprintln!("toon-mcp server starting in MCP mode...");
     let service = ToonTools::new().serve(stdio()).await?;
-    service.waiting().await?;
+
+    if let Some(interval) = keepalive {
+        let peer = service.peer().clone();
+        let cancel = service.cancellation_token();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let ping = peer.send_request(ServerRequest::PingRequest(PingRequest::default()));
+                if !matches!(tokio::time::timeout(PING_TIMEOUT, ping).await, Ok(Ok(_))) {
+                    eprintln!("toon-mcp: client did not answer keepalive ping, shutting down");
+                    cancel.cancel();
+                    break;
+                }
+            }
+        });
+    }
+
+    let cancel = service.cancellation_token();
+    tokio::select! {
+        result = service.waiting() => {
+            result?;
+        }
+        _ = shutdown_signal() => {
+            eprintln!("toon-mcp: received shutdown signal");
+            cancel.cancel();
+        }
+    }
+    eprintln!("toon-mcp: MCP session closed");
     Ok(())
 }
+
+/// Serve MCP over a Windows named pipe (e.g. `\\.\pipe\toon-mcp`).
+///
+/// Each client connection gets its own session; the server keeps accepting
+/// connections until SIGINT.
+#[cfg(windows)]
+pub async fn run_mcp_pipe_server(name: &str) -> anyhow::Result<()> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    eprintln!("toon-mcp server listening on named pipe {}", name);
+    let mut server = ServerOptions::new()
+        .first_pipe_instance(true)
+        .create(name)?;
+    loop {
+        tokio::select! {
+            connected = server.connect() => connected?,
+            _ = shutdown_signal() => return Ok(()),
+        }
+        // Create the next instance before handing this one off, so clients never
+        // see the pipe missing between connections.
+        let client = std::mem::replace(&mut server, ServerOptions::new().create(name)?);
+        tokio::spawn(async move {
+            match ToonTools::new().serve(tokio::io::split(client)).await {
+                Ok(service) => {
+                    let _ = service.waiting().await;
+                }
+                Err(e) => eprintln!("toon-mcp: pipe session failed: {}", e),
+            }
+        });
+    }
+}
+
+/// Named pipes are only available on Windows.
+#[cfg(not(windows))]
+pub async fn run_mcp_pipe_server(_name: &str) -> anyhow::Result<()> {
+    anyhow::bail!("Named pipe transport is only available on Windows")
+}
+
+/// Resolve on SIGINT, or SIGTERM on Unix.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
@@ -0,0 +1,245 @@
+//! Mock HTTP server answering from canned fixtures (`--mode mock`).
+//!
+//! Frontend teams can develop against the API contract without running
+//! conversions: each request is reduced to a key (a SHA-256 of its method,
+//! path, and canonical body) and answered with the fixture response stored
+//! under that key. Fixtures come from a directory of `*.json` files, one
+//! exchange each, and `*.jsonl` recordings made with `TOON_RECORD`. Every
+//! response carries its key in an `x-toon-mock-key` header, so a fixture for
+//! an unmatched request can be written straight from the 404.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use axum::{
+    body::to_bytes,
+    extract::{Request, State},
+    http::{HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use tower_http::cors::{Any, CorsLayer};
+
+use super::http::ApiError;
+use super::record::{self, Exchange};
+use crate::cli::ListenAddr;
+use crate::core::{self, anonymize::hex_digest, transform::sort_keys};
+
+/// Header carrying the request key of a mock response.
+pub const MOCK_KEY_HEADER: &str = "x-toon-mock-key";
+
+/// A hand-written fixture file.
+#[derive(Deserialize)]
+struct FixtureFile {
+    method: String,
+    path: String,
+    /// Request body: JSON, or a string sent as is; omitted for no body
+    #[serde(default)]
+    request: Value,
+    #[serde(default = "default_status")]
+    status: u16,
+    response: Value,
+}
+
+fn default_status() -> u16 {
+    200
+}
+
+/// Canned responses by request key.
+#[derive(Debug, Default)]
+pub struct Fixtures {
+    responses: HashMap<String, (StatusCode, Value)>,
+}
+
+impl Fixtures {
+    /// Load every `*.json` fixture and `*.jsonl` recording in `dir`.
+    ///
+    /// When two fixtures share a key, the one loaded last (by file name, then
+    /// line) wins.
+    pub fn load(dir: &Path) -> anyhow::Result<Self> {
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(dir)
+            .map_err(|e| anyhow::anyhow!("cannot read fixtures {}: {}", dir.display(), e))?
+        {
+            paths.push(entry?.path());
+        }
+        paths.sort();
+
+        let mut fixtures = Self::default();
+        for path in paths {
+            match path.extension().and_then(|ext| ext.to_str()) {
+                Some("json") => {
+                    let file: FixtureFile = serde_json::from_str(&fs::read_to_string(&path)?)
+                        .map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+                    let body = match file.request {
+                        Value::Null => String::new(),
+                        Value::String(text) => text,
+                        json => json.to_string(),
+                    };
+                    fixtures.insert(&file.method, &file.path, &body, file.status, file.response)?;
+                }
+                Some("jsonl") => {
+                    for exchange in record::load(&path)? {
+                        if let Exchange::Http {
+                            method,
+                            path,
+                            request,
+                            status,
+                            response,
+                            ..
+                        } = exchange
+                        {
+                            let response =
+                                serde_json::from_str(&response).unwrap_or(Value::String(response));
+                            fixtures.insert(&method, &path, &request, status, response)?;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(fixtures)
+    }
+
+    fn insert(
+        &mut self,
+        method: &str,
+        path: &str,
+        body: &str,
+        status: u16,
+        response: Value,
+    ) -> anyhow::Result<()> {
+        let status = StatusCode::from_u16(status)
+            .map_err(|_| anyhow::anyhow!("invalid status {} for {} {}", status, method, path))?;
+        self.responses
+            .insert(request_key(method, path, body), (status, response));
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.responses.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.responses.is_empty()
+    }
+}
+
+/// Key identifying a request: the SHA-256 of its method, path with query,
+/// and body. JSON bodies are compared in canonical form, so key order and
+/// whitespace do not matter.
+pub fn request_key(method: &str, path: &str, body: &str) -> String {
+    let body = match serde_json::from_str::<Value>(body) {
+        Ok(json) => sort_keys(&json).to_string(),
+        Err(_) => body.to_string(),
+    };
+    hex_digest(format!("{} {}\n{}", method.to_ascii_uppercase(), path, body).as_bytes())
+}
+
+/// Build the mock router serving `fixtures` for every path.
+pub fn build_mock_router(fixtures: Fixtures) -> Router {
+    let cors = CorsLayer::new()
+        .allow_origin(Any)
+        .allow_methods(Any)
+        .allow_headers(Any);
+
+    Router::new()
+        .fallback(respond)
+        .layer(cors)
+        .with_state(Arc::new(fixtures))
+}
+
+async fn respond(State(fixtures): State<Arc<Fixtures>>, request: Request) -> Response {
+    let method = request.method().to_string();
+    let path = request
+        .uri()
+        .path_and_query()
+        .map_or_else(|| request.uri().path().to_string(), |pq| pq.to_string());
+    let body = to_bytes(request.into_body(), core::MAX_BODY_BYTES)
+        .await
+        .unwrap_or_default();
+    let key = request_key(&method, &path, &String::from_utf8_lossy(&body));
+
+    let mut response = match fixtures.responses.get(&key) {
+        Some((status, Value::String(text))) => (*status, text.clone()).into_response(),
+        Some((status, json)) => (*status, Json(json.clone())).into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(ApiError {
+                error: format!("No mock fixture for {} {} (key {})", method, path, key),
+                details: None,
+            }),
+        )
+            .into_response(),
+    };
+    if let Ok(value) = HeaderValue::from_str(&key) {
+        response.headers_mut().insert(MOCK_KEY_HEADER, value);
+    }
+    response
+}
+
+/// Run the mock server on every address in `listen`, answering from the
+/// fixtures in `dir`.
+pub async fn run_mock_server(listen: &[ListenAddr], dir: &Path) -> anyhow::Result<()> {
+    let fixtures = Fixtures::load(dir)?;
+    eprintln!(
+        "toon-mcp: mock mode: {} fixtures from {}",
+        fixtures.len(),
+        dir.display()
+    );
+    let router = build_mock_router(fixtures);
+    super::http::serve(listen, "mock server", |_| router.clone()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_key_ignores_json_key_order() {
+        assert_eq!(
+            request_key(
+                "post",
+                "/api/v2/encode",
+                r#"{"json": {"a": 1}, "indent": 2}"#
+            ),
+            request_key("POST", "/api/v2/encode", r#"{"indent":2,"json":{"a":1}}"#)
+        );
+        assert_ne!(
+            request_key("POST", "/api/v2/encode", "{}"),
+            request_key("POST", "/api/v2/decode", "{}")
+        );
+    }
+
+    #[test]
+    fn test_load_reads_fixtures_and_recordings() {
+        let dir = std::env::temp_dir().join(format!("toon-mock-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let fixture = serde_json::json!({
+            "method": "POST",
+            "path": "/api/v2/encode",
+            "request": {"json": {"a": 1}},
+            "response": {"data": {"toon": "a: 1"}}
+        });
+        fs::write(dir.join("encode.json"), fixture.to_string()).unwrap();
+        let recorder = record::Recorder::create(&dir.join("session.jsonl")).unwrap();
+        recorder.record(&Exchange::Http {
+            method: "GET".to_string(),
+            path: "/health".to_string(),
+            content_type: None,
+            request: String::new(),
+            status: 200,
+            response: r#"{"status":"ok"}"#.to_string(),
+        });
+
+        let fixtures = Fixtures::load(&dir).unwrap();
+        assert_eq!(fixtures.len(), 2);
+        let key = request_key("POST", "/api/v2/encode", r#"{"json":{"a":1}}"#);
+        assert_eq!(fixtures.responses[&key].0, StatusCode::OK);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -5,11 +5,46 @@
 #[cfg(feature = "mcp")]
 pub mod mcp;
 
+#[cfg(feature = "http")]
+pub mod cache;
+
+#[cfg(feature = "http")]
+pub mod chaos;
+
+#[cfg(feature = "http")]
+pub mod examples;
+
 #[cfg(feature = "http")]
 pub mod http;
 
+#[cfg(feature = "http")]
+pub mod latency;
+
+#[cfg(feature = "http")]
+pub mod mock;
+
+#[cfg(feature = "http")]
+pub mod payload_log;
+
+pub mod record;
+
+#[cfg(feature = "redis")]
+pub mod redis_store;
+
+#[cfg(feature = "http")]
+pub mod rpc;
+
+#[cfg(feature = "http")]
+pub mod usage;
+
+#[cfg(feature = "jwt")]
+pub mod auth;
+
 #[cfg(feature = "mcp")]
-pub use mcp::run_mcp_server;
+pub use mcp::{run_mcp_pipe_server, run_mcp_server};
 
 #[cfg(feature = "http")]
 pub use http::run_http_server;
+
+#[cfg(feature = "http")]
+pub use mock::run_mock_server;
@@ -0,0 +1,213 @@
+//! Opt-in, sampled logging of redacted HTTP payloads for debugging.
+//!
+//! Payloads are logged to stderr with every value masked: letters become
+//! `x`, digits become `0`, and punctuation, whitespace, and JSON keys are
+//! kept. That preserves the shape of a document (nesting, lengths,
+//! delimiters, quoting) without recording its contents.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::Value;
+
+use super::http::ApiError;
+use crate::core;
+
+/// Payload logging settings.
+#[derive(Debug, Clone)]
+pub struct PayloadLogConfig {
+    /// Percentage of requests logged (1-100)
+    pub sample_percent: u64,
+    /// Characters of each payload kept after redaction
+    pub max_chars: usize,
+}
+
+impl PayloadLogConfig {
+    /// Read settings from `TOON_DEBUG_PAYLOADS` (sample percentage; unset or
+    /// 0 disables logging) and `TOON_DEBUG_PAYLOAD_CHARS` (default 512).
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+
+        let sample_percent = match var("TOON_DEBUG_PAYLOADS") {
+            Some(value) => match value.parse::<u64>() {
+                Ok(0) => return Ok(None),
+                Ok(percent) if percent <= 100 => percent,
+                _ => anyhow::bail!("TOON_DEBUG_PAYLOADS must be a percentage from 0 to 100"),
+            },
+            None => return Ok(None),
+        };
+        let max_chars = match var("TOON_DEBUG_PAYLOAD_CHARS") {
+            Some(value) => value
+                .parse()
+                .map_err(|_| anyhow::anyhow!("TOON_DEBUG_PAYLOAD_CHARS must be a whole number"))?,
+            None => 512,
+        };
+
+        Ok(Some(Self {
+            sample_percent,
+            max_chars,
+        }))
+    }
+}
+
+/// Sampler and formatter for payload logs.
+#[derive(Debug)]
+pub struct PayloadLogger {
+    config: PayloadLogConfig,
+    seen: AtomicU64,
+}
+
+impl PayloadLogger {
+    pub fn new(config: PayloadLogConfig) -> Self {
+        Self {
+            config,
+            seen: AtomicU64::new(0),
+        }
+    }
+
+    /// Sequence number of this request if it falls in the sample.
+    ///
+    /// Sampling is evenly spaced rather than random: with 10%, every tenth
+    /// request is logged.
+    fn sample(&self) -> Option<u64> {
+        let n = self.seen.fetch_add(1, Ordering::Relaxed) + 1;
+        let percent = self.config.sample_percent;
+        (n * percent / 100 != (n - 1) * percent / 100).then_some(n)
+    }
+
+    /// Redact and truncate a payload for logging.
+    fn render(&self, body: &[u8]) -> String {
+        let redacted = match serde_json::from_slice::<Value>(body) {
+            Ok(value) => redact(&value).to_string(),
+            Err(_) => mask(&String::from_utf8_lossy(body)),
+        };
+        truncate(&redacted, self.config.max_chars)
+    }
+}
+
+/// Mask every value in a JSON document, keeping keys and structure.
+pub fn redact(value: &Value) -> Value {
+    match value {
+        Value::String(s) => Value::String(mask(s)),
+        Value::Number(n) if n.is_f64() => serde_json::json!(0.0),
+        Value::Number(_) => serde_json::json!(0),
+        Value::Array(items) => Value::Array(items.iter().map(redact).collect()),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| (key.clone(), redact(value)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Replace letters with `x` and digits with `0`, keeping everything else.
+fn mask(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            if c.is_alphabetic() {
+                'x'
+            } else if c.is_numeric() {
+                '0'
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}... ({} chars total)", &text[..end], text.chars().count()),
+        None => text.to_string(),
+    }
+}
+
+/// Middleware logging redacted request and response bodies for sampled requests.
+pub async fn log_payloads(
+    State(logger): State<Arc<PayloadLogger>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if super::http::streams_body(request.uri().path()) {
+        return next.run(request).await;
+    }
+    let Some(n) = logger.sample() else {
+        return next.run(request).await;
+    };
+
+    let label = format!("{} {}", request.method(), request.uri().path());
+    let (parts, body) = request.into_parts();
+    let Ok(body) = to_bytes(body, core::MAX_BODY_BYTES).await else {
+        let error = ApiError {
+            error: "request body too large".to_string(),
+            details: None,
+        };
+        return (StatusCode::PAYLOAD_TOO_LARGE, Json(error)).into_response();
+    };
+    eprintln!(
+        "toon-mcp: [payload #{}] {} request: {}",
+        n,
+        label,
+        logger.render(&body)
+    );
+
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+
+    let (parts, body) = response.into_parts();
+    let body = to_bytes(body, usize::MAX).await.unwrap_or_default();
+    eprintln!(
+        "toon-mcp: [payload #{}] {} response {}: {}",
+        n,
+        label,
+        parts.status.as_u16(),
+        logger.render(&body)
+    );
+
+    Response::from_parts(parts, Body::from(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn logger(sample_percent: u64, max_chars: usize) -> PayloadLogger {
+        PayloadLogger::new(PayloadLogConfig {
+            sample_percent,
+            max_chars,
+        })
+    }
+
+    #[test]
+    fn test_redact_keeps_keys_and_shape() {
+        let value = serde_json::json!({"name": "Ann Lee", "ids": [12, 3.5], "ok": true});
+        assert_eq!(
+            redact(&value),
+            serde_json::json!({"name": "xxx xxx", "ids": [0, 0.0], "ok": true})
+        );
+    }
+
+    #[test]
+    fn test_render_masks_text_and_truncates() {
+        let logger = logger(100, 12);
+        assert_eq!(
+            logger.render(b"users[2]{id,name}:\n  1,Ann"),
+            "xxxxx[0]{xx,... (26 chars total)"
+        );
+    }
+
+    #[test]
+    fn test_sample_is_evenly_spaced() {
+        let logger = logger(25, 512);
+        let sampled: Vec<u64> = (0..8).filter_map(|_| logger.sample()).collect();
+        assert_eq!(sampled, vec![4, 8]);
+    }
+}
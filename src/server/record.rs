@@ -0,0 +1,209 @@
+//! Opt-in recording of full request/response pairs for later replay.
+//!
+//! With `TOON_RECORD` set to a file path, every HTTP exchange and MCP tool
+//! call is appended to that file as one JSON line. `toon-mcp replay <file>`
+//! re-executes the recorded requests against the current build and reports
+//! responses that changed, so a report like "it worked yesterday" can be
+//! reproduced exactly. Unlike payload logging, nothing is redacted: treat
+//! recordings as containing the documents clients sent.
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Environment variable naming the recording file.
+pub const RECORD_ENV: &str = "TOON_RECORD";
+
+/// One recorded request and the response it got.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "transport", rename_all = "lowercase")]
+pub enum Exchange {
+    Http {
+        method: String,
+        /// Path and query string, e.g. `/api/v2/encode`
+        path: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        content_type: Option<String>,
+        request: String,
+        status: u16,
+        response: String,
+    },
+    Mcp {
+        /// Client name from the MCP handshake, which selects client defaults
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        client: Option<String>,
+        tool: String,
+        arguments: Value,
+        /// `{"result": ...}` or `{"error": ...}`, as in the JSON-RPC response
+        response: Value,
+    },
+}
+
+impl Exchange {
+    /// Short description such as `POST /api/v2/encode` or `mcp toon_encode`.
+    pub fn label(&self) -> String {
+        match self {
+            Exchange::Http { method, path, .. } => format!("{} {}", method, path),
+            Exchange::Mcp { tool, .. } => format!("mcp {}", tool),
+        }
+    }
+}
+
+/// Appends exchanges to a recording file.
+#[derive(Debug)]
+pub struct Recorder {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl Recorder {
+    /// Open `path` for appending, creating it if needed.
+    pub fn create(path: &Path) -> anyhow::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| anyhow::anyhow!("cannot open recording {}: {}", path.display(), e))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Open the file named by `TOON_RECORD`; unset or empty disables recording.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        match std::env::var_os(RECORD_ENV).filter(|v| !v.is_empty()) {
+            Some(path) => Self::create(Path::new(&path)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append one exchange. Failures are logged, never returned to clients.
+    pub fn record(&self, exchange: &Exchange) {
+        let mut line = match serde_json::to_string(exchange) {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("toon-mcp: cannot serialize recorded exchange: {}", e);
+                return;
+            }
+        };
+        line.push('\n');
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = file.write_all(line.as_bytes()) {
+            eprintln!(
+                "toon-mcp: cannot write recording {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+}
+
+/// Read every exchange from a recording file.
+pub fn load(path: &Path) -> anyhow::Result<Vec<Exchange>> {
+    let file = File::open(path)
+        .map_err(|e| anyhow::anyhow!("cannot open recording {}: {}", path.display(), e))?;
+    let mut exchanges = Vec::new();
+    for (n, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let exchange = serde_json::from_str(&line)
+            .map_err(|e| anyhow::anyhow!("{} line {}: {}", path.display(), n + 1, e))?;
+        exchanges.push(exchange);
+    }
+    Ok(exchanges)
+}
+
+/// Middleware recording every HTTP request and its response, except streamed
+/// uploads, which are passed through unrecorded.
+#[cfg(feature = "http")]
+pub async fn record_http(
+    axum::extract::State(recorder): axum::extract::State<std::sync::Arc<Recorder>>,
+    request: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> axum::response::Response {
+    use axum::{
+        body::{to_bytes, Body},
+        extract::Request,
+        http::header,
+        response::Response,
+    };
+
+    if super::http::streams_body(request.uri().path()) {
+        return next.run(request).await;
+    }
+    let method = request.method().to_string();
+    let path = request
+        .uri()
+        .path_and_query()
+        .map_or_else(|| request.uri().path().to_string(), |pq| pq.to_string());
+    let content_type = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|ct| ct.to_str().ok())
+        .map(str::to_string);
+    let (parts, body) = request.into_parts();
+    let body = to_bytes(body, crate::core::MAX_BODY_BYTES)
+        .await
+        .unwrap_or_default();
+    let request_text = String::from_utf8_lossy(&body).into_owned();
+
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+
+    let (parts, body) = response.into_parts();
+    let body = to_bytes(body, usize::MAX).await.unwrap_or_default();
+    recorder.record(&Exchange::Http {
+        method,
+        path,
+        content_type,
+        request: request_text,
+        status: parts.status.as_u16(),
+        response: String::from_utf8_lossy(&body).into_owned(),
+    });
+    Response::from_parts(parts, Body::from(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_load_round_trip() {
+        let path = std::env::temp_dir().join(format!("toon-record-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let exchanges = vec![
+            Exchange::Http {
+                method: "POST".to_string(),
+                path: "/api/v2/encode".to_string(),
+                content_type: Some("application/json".to_string()),
+                request: r#"{"json":{"a":1}}"#.to_string(),
+                status: 200,
+                response: r#"{"data":{"toon":"a: 1"}}"#.to_string(),
+            },
+            Exchange::Mcp {
+                client: Some("claude-ai".to_string()),
+                tool: "toon_ping".to_string(),
+                arguments: serde_json::json!({}),
+                response: serde_json::json!({"result": {"content": []}}),
+            },
+        ];
+
+        let recorder = Recorder::create(&path).unwrap();
+        for exchange in &exchanges {
+            recorder.record(exchange);
+        }
+        assert_eq!(load(&path).unwrap(), exchanges);
+        assert_eq!(exchanges[1].label(), "mcp toon_ping");
+        std::fs::remove_file(path).unwrap();
+    }
+}
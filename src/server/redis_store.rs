@@ -0,0 +1,168 @@
+//! Redis-backed usage counters for running several replicas (`redis` feature).
+//!
+//! With `TOON_REDIS_URL` set, every replica behind a load balancer counts
+//! requests against the same per-principal counters, so a daily quota holds
+//! across the deployment instead of per process. Each principal's counters
+//! for a day are a hash at `<prefix>:usage:<day>:key:<principal>`, and the
+//! principals seen that day are a set at `<prefix>:usage:<day>:keys`. Both
+//! expire two days after their last update.
+
+use redis::aio::ConnectionManager;
+use redis::{AsyncCommands, Script};
+
+use crate::core::{KeyUsage, UsageQuota};
+
+/// Seconds a day's counters outlive their last update.
+const EXPIRE_SECS: u64 = 2 * 86_400;
+
+/// Checks every limit before incrementing anything, so a rejected request
+/// is not counted, and returns the exceeded limit's name or an empty string.
+const RECORD_SCRIPT: &str = r"
+local names = {'requests', 'bytes', 'tokens'}
+local adding = {1, tonumber(ARGV[4]), tonumber(ARGV[5])}
+for i, name in ipairs(names) do
+  local limit = tonumber(ARGV[i])
+  local used = tonumber(redis.call('HGET', KEYS[1], name) or '0')
+  if limit >= 0 and used + adding[i] > limit then
+    return name
+  end
+end
+for i, name in ipairs(names) do
+  redis.call('HINCRBY', KEYS[1], name, adding[i])
+end
+redis.call('SADD', KEYS[2], ARGV[6])
+redis.call('EXPIRE', KEYS[1], ARGV[7])
+redis.call('EXPIRE', KEYS[2], ARGV[7])
+return ''
+";
+
+/// Connection settings read from the environment.
+#[derive(Debug, Clone)]
+pub struct RedisConfig {
+    pub url: String,
+    /// Prefix for every key, so deployments can share a Redis instance
+    pub prefix: String,
+}
+
+impl RedisConfig {
+    /// Read `TOON_REDIS_URL` and `TOON_REDIS_PREFIX` (default `toon-mcp`);
+    /// `None` when no URL is set.
+    pub fn from_env() -> Option<Self> {
+        let url = std::env::var("TOON_REDIS_URL")
+            .ok()
+            .filter(|v| !v.is_empty())?;
+        let prefix = std::env::var("TOON_REDIS_PREFIX")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| "toon-mcp".to_string());
+        Some(Self { url, prefix })
+    }
+}
+
+/// Usage counters stored in Redis.
+pub struct RedisUsage {
+    connection: ConnectionManager,
+    prefix: String,
+    script: Script,
+}
+
+impl RedisUsage {
+    /// Connect to the server in `config`; reconnects happen automatically.
+    pub async fn connect(config: &RedisConfig) -> anyhow::Result<Self> {
+        let client = redis::Client::open(config.url.as_str())
+            .map_err(|e| anyhow::anyhow!("invalid TOON_REDIS_URL: {}", e))?;
+        let connection = ConnectionManager::new(client)
+            .await
+            .map_err(|e| anyhow::anyhow!("cannot connect to Redis: {}", e))?;
+        Ok(Self {
+            connection,
+            prefix: config.prefix.clone(),
+            script: Script::new(RECORD_SCRIPT),
+        })
+    }
+
+    fn keys_key(&self, day: u64) -> String {
+        format!("{}:usage:{}:keys", self.prefix, day)
+    }
+
+    fn usage_key(&self, day: u64, key: &str) -> String {
+        format!("{}:usage:{}:key:{}", self.prefix, day, key)
+    }
+
+    /// Record a request for `key` on `day`, or return the exceeded limit's
+    /// name without recording anything.
+    pub async fn record(
+        &self,
+        day: u64,
+        key: &str,
+        bytes: u64,
+        tokens: u64,
+        quota: &UsageQuota,
+    ) -> redis::RedisResult<Result<(), &'static str>> {
+        // -1 stands for an unlimited dimension.
+        let limit = |limit: Option<u64>| limit.map_or_else(|| "-1".to_string(), |l| l.to_string());
+        let exceeded: String = self
+            .script
+            .key(self.usage_key(day, key))
+            .key(self.keys_key(day))
+            .arg(limit(quota.requests))
+            .arg(limit(quota.bytes))
+            .arg(limit(quota.tokens))
+            .arg(bytes)
+            .arg(tokens)
+            .arg(key)
+            .arg(EXPIRE_SECS)
+            .invoke_async(&mut self.connection.clone())
+            .await?;
+        Ok(match exceeded.as_str() {
+            "" => Ok(()),
+            "requests" => Err("requests"),
+            "bytes" => Err("bytes"),
+            _ => Err("tokens"),
+        })
+    }
+
+    /// Add `bytes` and `tokens` to a request already recorded for `key` on
+    /// `day`, without checking limits.
+    pub async fn charge(
+        &self,
+        day: u64,
+        key: &str,
+        bytes: u64,
+        tokens: u64,
+    ) -> redis::RedisResult<()> {
+        let usage_key = self.usage_key(day, key);
+        redis::pipe()
+            .atomic()
+            .hincr(&usage_key, "bytes", bytes)
+            .ignore()
+            .hincr(&usage_key, "tokens", tokens)
+            .ignore()
+            .expire(&usage_key, EXPIRE_SECS as i64)
+            .ignore()
+            .query_async(&mut self.connection.clone())
+            .await
+    }
+
+    /// Every principal's usage on `day`, in no particular order.
+    pub async fn report(&self, day: u64) -> redis::RedisResult<Vec<KeyUsage>> {
+        let mut connection = self.connection.clone();
+        let keys: Vec<String> = connection.smembers(self.keys_key(day)).await?;
+        let mut usage = Vec::with_capacity(keys.len());
+        for key in keys {
+            let (requests, bytes, tokens): (Option<u64>, Option<u64>, Option<u64>) =
+                redis::cmd("HMGET")
+                    .arg(self.usage_key(day, &key))
+                    .arg(&["requests", "bytes", "tokens"])
+                    .query_async(&mut connection)
+                    .await?;
+            usage.push(KeyUsage {
+                key,
+                requests: requests.unwrap_or(0),
+                bytes: bytes.unwrap_or(0),
+                tokens: tokens.unwrap_or(0),
+            });
+        }
+        Ok(usage)
+    }
+}
@@ -0,0 +1,246 @@
+//! JSON-RPC 2.0 endpoint mirroring the tool surface.
+//!
+//! Methods are named after the MCP tools (`toon.encode`, `toon.decode`, ...)
+//! and take the same parameters as the matching REST endpoint. Batches and
+//! notifications follow the JSON-RPC 2.0 specification.
+
+use std::sync::Arc;
+
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use super::http::{ApiError, AppState};
+use crate::core::{self, DecodeResponse, ToonCoreError};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+/// Server-defined code for operations that fail on their input.
+const OPERATION_FAILED: i64 = -32000;
+
+/// Methods served by [`rpc`].
+pub const METHODS: &[&str] = &[
+    "toon.encode",
+    "toon.decode",
+    "toon.validate",
+    "toon.stats",
+    "toon.stats_decode",
+    "toon.aggregate",
+    "toon.join",
+    "toon.pivot",
+    "toon.anonymize",
+    "toon.deanonymize",
+    "toon.hash",
+    "toon.cache_analysis",
+    "toon.verify_roundtrip",
+    "toon.diff_validate",
+    "toon.example_from_schema",
+    "toon.schema_template",
+    "toon.to_sql",
+    "toon.demo_data",
+];
+
+struct RpcError {
+    code: i64,
+    message: String,
+    data: Option<Value>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+}
+
+impl From<ToonCoreError> for RpcError {
+    fn from(e: ToonCoreError) -> Self {
+        let error = ApiError::from(e);
+        Self {
+            code: OPERATION_FAILED,
+            message: error.error,
+            data: error
+                .details
+                .and_then(|details| serde_json::to_value(details).ok()),
+        }
+    }
+}
+
+fn params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn result<T: Serialize>(value: T) -> Result<Value, RpcError> {
+    serde_json::to_value(value).map_err(|e| RpcError::new(INTERNAL_ERROR, e.to_string()))
+}
+
+/// Run one method call.
+fn call(method: &str, p: Value) -> Result<Value, RpcError> {
+    match method {
+        "toon.encode" => result(core::encode_request(&params(p)?)?),
+        "toon.decode" => {
+            let request: core::DecodeRequest = params(p)?;
+            let mut json = core::decode_toon(&request.toon, &request)?;
+            let coercions = core::coercion::report(&request, &json)?;
+            let page = core::paginate(&mut json, &request)?;
+            result(DecodeResponse {
+                json,
+                page,
+                coercions,
+            })
+        }
+        "toon.validate" => {
+            let request: core::ValidateRequest = params(p)?;
+            result(core::validate_request(&request)?)
+        }
+        "toon.stats" => {
+            let request: core::StatsRequest = params(p)?;
+            let json = core::parse_json_input(&request.json)?;
+            result(core::compute_stats(
+                &json,
+                &request.encode_options,
+                &request.stats_options,
+            )?)
+        }
+        "toon.stats_decode" => result(core::compute_decode_stats(&params(p)?)?),
+        "toon.aggregate" => result(core::aggregate(&params(p)?)?),
+        "toon.join" => result(core::join(&params(p)?)?),
+        "toon.pivot" => result(core::pivot(&params(p)?)?),
+        "toon.anonymize" => result(core::anonymize(&params(p)?)?),
+        "toon.deanonymize" => result(core::deanonymize(&params(p)?)?),
+        "toon.hash" => result(core::hash_document(&params(p)?)?),
+        "toon.cache_analysis" => result(core::cache_analysis(&params(p)?)?),
+        "toon.verify_roundtrip" => result(core::verify_roundtrip(&params(p)?)?),
+        "toon.diff_validate" => result(core::diff_validate(&params(p)?)?),
+        "toon.example_from_schema" => result(core::example_from_schema(&params(p)?)?),
+        "toon.schema_template" => result(core::schema_template(&params(p)?)?),
+        "toon.to_sql" => result(core::to_sql(&params(p)?)?),
+        "toon.demo_data" => result(core::demo::load(&params(p)?)?),
+        _ => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("method '{}' not found", method),
+        )),
+    }
+}
+
+fn error_response(id: Value, error: RpcError) -> Value {
+    let mut body = json!({"code": error.code, "message": error.message});
+    if let Some(data) = error.data {
+        body["data"] = data;
+    }
+    json!({"jsonrpc": "2.0", "id": id, "error": body})
+}
+
+/// Handle one request object; `None` for notifications.
+fn handle(request: Value) -> Option<Value> {
+    let Value::Object(mut request) = request else {
+        return Some(error_response(
+            Value::Null,
+            RpcError::new(INVALID_REQUEST, "request must be an object"),
+        ));
+    };
+
+    let id = request.remove("id");
+    let method = match request.remove("method") {
+        Some(Value::String(method)) if request.get("jsonrpc") == Some(&json!("2.0")) => method,
+        _ => {
+            return Some(error_response(
+                id.unwrap_or(Value::Null),
+                RpcError::new(INVALID_REQUEST, "expected jsonrpc \"2.0\" and a method"),
+            ))
+        }
+    };
+    let params = request.remove("params").unwrap_or(Value::Null);
+
+    let outcome = call(&method, params);
+    let id = id?;
+    Some(match outcome {
+        Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+        Err(error) => error_response(id, error),
+    })
+}
+
+/// Answer a request body: a single reply, a batch of replies, or `None`
+/// when only notifications were sent.
+fn reply(body: &[u8]) -> Option<Value> {
+    let payload: Value = match serde_json::from_slice(body) {
+        Ok(payload) => payload,
+        Err(e) => {
+            return Some(error_response(
+                Value::Null,
+                RpcError::new(PARSE_ERROR, e.to_string()),
+            ))
+        }
+    };
+
+    match payload {
+        Value::Array(batch) if batch.is_empty() => Some(error_response(
+            Value::Null,
+            RpcError::new(INVALID_REQUEST, "empty batch"),
+        )),
+        Value::Array(batch) => {
+            let replies: Vec<Value> = batch.into_iter().filter_map(handle).collect();
+            (!replies.is_empty()).then_some(Value::Array(replies))
+        }
+        single => handle(single),
+    }
+}
+
+/// JSON-RPC 2.0 endpoint accepting single requests and batches.
+///
+/// The whole body is answered on the worker pool, like the REST encode and
+/// decode handlers, so a large batch does not stall the async runtime.
+pub async fn rpc(State(state): State<Arc<AppState>>, body: Bytes) -> Response {
+    let reply = state.workers.run(body.len(), move || reply(&body)).await;
+    match reply {
+        Some(reply) => Json(reply).into_response(),
+        None => StatusCode::NO_CONTENT.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_method_dispatches() {
+        for method in METHODS {
+            let error = call(method, Value::Null).unwrap_err();
+            assert_eq!(error.code, INVALID_PARAMS, "{}", method);
+        }
+        assert_eq!(
+            call("toon.nope", Value::Null).unwrap_err().code,
+            METHOD_NOT_FOUND
+        );
+    }
+
+    #[test]
+    fn test_notifications_get_no_reply() {
+        let notification =
+            json!({"jsonrpc": "2.0", "method": "toon.validate", "params": {"toon": "a: 1"}});
+        assert!(handle(notification).is_none());
+
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 7,
+            "method": "toon.validate",
+            "params": {"toon": "a: 1"}
+        });
+        let reply = handle(request).unwrap();
+        assert_eq!(reply["id"], 7);
+        assert_eq!(reply["result"]["valid"], true);
+    }
+}
@@ -0,0 +1,392 @@
+//! Per-principal usage accounting and daily quotas for the HTTP API.
+//!
+//! Requests are attributed to the token's tenant when tenants are enabled,
+//! otherwise to the authenticated JWT subject, or to `anonymous` when
+//! authentication is disabled. Counters reset at midnight UTC. They live in
+//! memory, or in Redis when the `redis` feature is enabled and
+//! `TOON_REDIS_URL` is set, so replicas share one set of counters.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use tokio_stream::StreamExt;
+
+use super::http::{ApiError, AppState};
+use crate::core::{self, estimate_tokens, KeyUsage, UsageQuota, UsageResponse};
+
+/// Principal used when requests are not authenticated.
+pub const ANONYMOUS: &str = "anonymous";
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+impl UsageQuota {
+    /// Read limits from `TOON_QUOTA_REQUESTS`, `TOON_QUOTA_BYTES`, and
+    /// `TOON_QUOTA_TOKENS`; unset variables leave that dimension unlimited.
+    pub fn from_env() -> anyhow::Result<Self> {
+        let limit = |name: &str| -> anyhow::Result<Option<u64>> {
+            match std::env::var(name) {
+                Ok(value) if !value.is_empty() => value
+                    .parse()
+                    .map(Some)
+                    .map_err(|_| anyhow::anyhow!("{} must be a whole number", name)),
+                _ => Ok(None),
+            }
+        };
+        Ok(Self {
+            requests: limit("TOON_QUOTA_REQUESTS")?,
+            bytes: limit("TOON_QUOTA_BYTES")?,
+            tokens: limit("TOON_QUOTA_TOKENS")?,
+        })
+    }
+}
+
+/// Usage counters shared by every listener.
+#[derive(Default)]
+pub struct UsageTracker {
+    quota: UsageQuota,
+    state: Mutex<Counters>,
+    /// Shared counters; the in-memory ones are used while Redis is unreachable
+    #[cfg(feature = "redis")]
+    redis: Option<super::redis_store::RedisUsage>,
+}
+
+#[derive(Default)]
+struct Counters {
+    day: u64,
+    keys: HashMap<String, KeyUsage>,
+}
+
+impl UsageTracker {
+    pub fn new(quota: UsageQuota) -> Self {
+        Self {
+            quota,
+            ..Self::default()
+        }
+    }
+
+    /// Build a tracker with the quota from the environment, connecting to
+    /// `TOON_REDIS_URL` when it is set.
+    pub async fn from_env() -> anyhow::Result<Self> {
+        let tracker = Self::new(UsageQuota::from_env()?);
+        #[cfg(feature = "redis")]
+        if let Some(config) = super::redis_store::RedisConfig::from_env() {
+            let redis = super::redis_store::RedisUsage::connect(&config).await?;
+            eprintln!("toon-mcp: usage counters in Redis under {}:", config.prefix);
+            return Ok(Self {
+                redis: Some(redis),
+                ..tracker
+            });
+        }
+        #[cfg(not(feature = "redis"))]
+        if std::env::var("TOON_REDIS_URL").is_ok_and(|url| !url.is_empty()) {
+            anyhow::bail!("TOON_REDIS_URL requires a build with the redis feature");
+        }
+        Ok(tracker)
+    }
+
+    /// Record a request for `key` on `day`, or return the exceeded limit's
+    /// name without recording anything.
+    fn record_on(&self, day: u64, key: &str, bytes: u64, tokens: u64) -> Result<(), &'static str> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.day != day {
+            state.day = day;
+            state.keys.clear();
+        }
+
+        let usage = state
+            .keys
+            .entry(key.to_string())
+            .or_insert_with(|| KeyUsage {
+                key: key.to_string(),
+                ..KeyUsage::default()
+            });
+        let exceeds = |limit: Option<u64>, used: u64, adding: u64| {
+            limit.is_some_and(|limit| used.saturating_add(adding) > limit)
+        };
+        if exceeds(self.quota.requests, usage.requests, 1) {
+            return Err("requests");
+        }
+        if exceeds(self.quota.bytes, usage.bytes, bytes) {
+            return Err("bytes");
+        }
+        if exceeds(self.quota.tokens, usage.tokens, tokens) {
+            return Err("tokens");
+        }
+
+        usage.requests += 1;
+        usage.bytes += bytes;
+        usage.tokens += tokens;
+        Ok(())
+    }
+
+    /// Record a request for `key` against today's counters.
+    pub async fn record(&self, key: &str, bytes: u64, tokens: u64) -> Result<(), &'static str> {
+        #[cfg(feature = "redis")]
+        if let Some(redis) = &self.redis {
+            match redis.record(today(), key, bytes, tokens, &self.quota).await {
+                Ok(result) => return result,
+                Err(e) => eprintln!("toon-mcp: Redis unavailable, counting usage locally: {}", e),
+            }
+        }
+        self.record_on(today(), key, bytes, tokens)
+    }
+
+    /// Add `bytes` and `tokens` to a request already recorded for `key`,
+    /// without checking limits: by then the data has been accepted, and the
+    /// principal's next request is refused instead. Local counters change
+    /// before this returns; Redis is updated in the background.
+    pub fn charge(self: &Arc<Self>, key: String, bytes: u64, tokens: u64) {
+        #[cfg(feature = "redis")]
+        if self.redis.is_some() {
+            let tracker = Arc::clone(self);
+            tokio::spawn(async move {
+                if let Some(redis) = &tracker.redis {
+                    match redis.charge(today(), &key, bytes, tokens).await {
+                        Ok(()) => return,
+                        Err(e) => {
+                            eprintln!("toon-mcp: Redis unavailable, counting usage locally: {}", e)
+                        }
+                    }
+                }
+                tracker.charge_on(today(), &key, bytes, tokens);
+            });
+            return;
+        }
+        self.charge_on(today(), &key, bytes, tokens);
+    }
+
+    fn charge_on(&self, day: u64, key: &str, bytes: u64, tokens: u64) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.day != day {
+            return;
+        }
+        let usage = state
+            .keys
+            .entry(key.to_string())
+            .or_insert_with(|| KeyUsage {
+                key: key.to_string(),
+                ..KeyUsage::default()
+            });
+        usage.bytes += bytes;
+        usage.tokens += tokens;
+    }
+
+    /// Snapshot of today's usage for every principal.
+    pub async fn report(&self) -> UsageResponse {
+        let day = today();
+        let mut keys = self.local_usage(day);
+        #[cfg(feature = "redis")]
+        if let Some(redis) = &self.redis {
+            match redis.report(day).await {
+                Ok(shared) => keys = shared,
+                Err(e) => eprintln!("toon-mcp: Redis unavailable, reporting local usage: {}", e),
+            }
+        }
+        keys.sort_by(|a, b| a.key.cmp(&b.key));
+
+        UsageResponse {
+            resets_at: (day + 1) * SECONDS_PER_DAY,
+            quota: self.quota.clone(),
+            keys,
+        }
+    }
+
+    fn local_usage(&self, day: u64) -> Vec<KeyUsage> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.day == day {
+            state.keys.values().cloned().collect()
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+/// Days since the Unix epoch (UTC).
+fn today() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / SECONDS_PER_DAY)
+        .unwrap_or(0)
+}
+
+/// Principal a request is attributed to: its tenant, else its subject.
+fn principal(request: &Request) -> String {
+    #[cfg(feature = "jwt")]
+    if let Some(claims) = request.extensions().get::<super::auth::Claims>() {
+        return claims.tenant.clone().unwrap_or_else(|| claims.sub.clone());
+    }
+    let _ = request;
+    ANONYMOUS.to_string()
+}
+
+fn error_response(status: StatusCode, error: String) -> Response {
+    (
+        status,
+        Json(ApiError {
+            error,
+            details: None,
+        }),
+    )
+        .into_response()
+}
+
+/// Bytes of a streamed upload, charged beyond its declared length once the
+/// handler is done with the body, at roughly four bytes per token.
+struct StreamMeter {
+    usage: Arc<UsageTracker>,
+    key: String,
+    declared: u64,
+    read: u64,
+}
+
+impl StreamMeter {
+    fn add(&mut self, bytes: usize) {
+        self.read += bytes as u64;
+    }
+}
+
+impl Drop for StreamMeter {
+    fn drop(&mut self) {
+        let extra = self.read.saturating_sub(self.declared);
+        if extra > 0 {
+            self.usage
+                .charge(std::mem::take(&mut self.key), extra, extra / 4);
+        }
+    }
+}
+
+/// Whether requests to `path` count against quotas. Administration, such
+/// as reading the usage report itself, does not.
+fn metered(path: &str) -> bool {
+    !path.contains("/admin/")
+}
+
+/// Middleware counting each request against its principal's daily quota,
+/// answering 429 once a limit is reached.
+pub async fn track_usage(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !metered(request.uri().path()) {
+        return next.run(request).await;
+    }
+    let key = principal(&request);
+    if super::http::streams_body(request.uri().path()) {
+        // The body is not read ahead of the handler: count its declared
+        // length now, and whatever arrives beyond it as it is read.
+        let declared = request
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok()?.parse().ok())
+            .unwrap_or(0);
+        if let Err(limit) = state.usage.record(&key, declared, declared / 4).await {
+            return error_response(
+                StatusCode::TOO_MANY_REQUESTS,
+                format!("daily {} quota exceeded", limit),
+            );
+        }
+        let mut meter = StreamMeter {
+            usage: state.usage.clone(),
+            key,
+            declared,
+            read: 0,
+        };
+        let (parts, body) = request.into_parts();
+        let body = body.into_data_stream().map(move |chunk| {
+            if let Ok(bytes) = &chunk {
+                meter.add(bytes.len());
+            }
+            chunk
+        });
+        return next
+            .run(Request::from_parts(parts, Body::from_stream(body)))
+            .await;
+    }
+    let (parts, body) = request.into_parts();
+    let bytes = match to_bytes(body, core::MAX_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(_) => {
+            return error_response(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "request body too large".to_string(),
+            )
+        }
+    };
+    let tokens = estimate_tokens(&String::from_utf8_lossy(&bytes)) as u64;
+
+    if let Err(limit) = state.usage.record(&key, bytes.len() as u64, tokens).await {
+        return error_response(
+            StatusCode::TOO_MANY_REQUESTS,
+            format!("daily {} quota exceeded", limit),
+        );
+    }
+
+    next.run(Request::from_parts(parts, Body::from(bytes)))
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quota_rejects_without_recording() {
+        let tracker = UsageTracker::new(UsageQuota {
+            requests: Some(2),
+            bytes: Some(100),
+            tokens: None,
+        });
+
+        assert!(tracker.record_on(1, "alice", 40, 5).is_ok());
+        assert_eq!(tracker.record_on(1, "alice", 70, 5), Err("bytes"));
+        assert!(tracker.record_on(1, "alice", 60, 5).is_ok());
+        assert_eq!(tracker.record_on(1, "alice", 0, 0), Err("requests"));
+        assert!(tracker.record_on(1, "bob", 10, 1).is_ok());
+
+        let state = tracker.state.lock().unwrap();
+        let alice = &state.keys["alice"];
+        assert_eq!((alice.requests, alice.bytes, alice.tokens), (2, 100, 10));
+    }
+
+    #[test]
+    fn test_counters_reset_each_day() {
+        let tracker = UsageTracker::new(UsageQuota {
+            requests: Some(1),
+            ..UsageQuota::default()
+        });
+
+        assert!(tracker.record_on(1, "alice", 0, 0).is_ok());
+        assert!(tracker.record_on(1, "alice", 0, 0).is_err());
+        assert!(tracker.record_on(2, "alice", 0, 0).is_ok());
+    }
+
+    #[cfg(feature = "jwt")]
+    #[test]
+    fn test_principal_prefers_tenant() {
+        use crate::server::auth::{Claims, Role};
+
+        let request = |tenant: Option<&str>| {
+            let mut request = Request::new(Body::empty());
+            request.extensions_mut().insert(Claims {
+                sub: "alice".to_string(),
+                role: Role::Read,
+                tenant: tenant.map(str::to_string),
+                extra: Default::default(),
+            });
+            request
+        };
+        assert_eq!(principal(&request(Some("team-a"))), "team-a");
+        assert_eq!(principal(&request(None)), "alice");
+        assert_eq!(principal(&Request::new(Body::empty())), ANONYMOUS);
+    }
+}
@@ -0,0 +1,143 @@
+//! Per-client default tool arguments.
+//!
+//! `TOON_CLIENT_DEFAULTS` names a JSON file mapping MCP client names (the
+//! `clientInfo.name` sent at initialization) to default arguments, e.g.
+//! `{"claude-ai": {"delimiter": "tab"}}`. A `"*"` entry applies to every
+//! client. The file is re-read whenever its modification time changes, so
+//! defaults can be adjusted without restarting the server.
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use rmcp::model::JsonObject;
+use serde_json::Value;
+
+/// Entry applied to clients without one of their own.
+pub const ANY_CLIENT: &str = "*";
+
+#[derive(Default)]
+struct Loaded {
+    modified: Option<SystemTime>,
+    clients: JsonObject,
+}
+
+/// Default arguments per MCP client, reloaded from disk when changed.
+#[derive(Default)]
+pub struct ClientDefaults {
+    path: Option<PathBuf>,
+    loaded: Mutex<Loaded>,
+}
+
+impl ClientDefaults {
+    /// Read the file named by `TOON_CLIENT_DEFAULTS`; without it no defaults apply.
+    pub fn from_env() -> Self {
+        Self::new(std::env::var_os("TOON_CLIENT_DEFAULTS").map(PathBuf::from))
+    }
+
+    /// Defaults loaded from `path`.
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self {
+            path,
+            loaded: Mutex::default(),
+        }
+    }
+
+    /// Defaults for `client`, merged over the `"*"` entry.
+    ///
+    /// A file that cannot be read or parsed is reported on stderr and the
+    /// previously loaded defaults are kept.
+    pub fn for_client(&self, client: &str) -> JsonObject {
+        let mut loaded = self.loaded.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(path) = &self.path {
+            let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+            if modified != loaded.modified {
+                loaded.modified = modified;
+                match read(path) {
+                    Ok(clients) => loaded.clients = clients,
+                    Err(e) => eprintln!(
+                        "toon-mcp: ignoring client defaults in {}: {}",
+                        path.display(),
+                        e
+                    ),
+                }
+            }
+        }
+
+        let mut defaults = JsonObject::new();
+        for key in [ANY_CLIENT, client] {
+            if let Some(Value::Object(entry)) = loaded.clients.get(key) {
+                defaults.extend(entry.clone());
+            }
+        }
+        defaults
+    }
+}
+
+fn read(path: &std::path::Path) -> anyhow::Result<JsonObject> {
+    let text = std::fs::read_to_string(path)?;
+    match serde_json::from_str(&text)? {
+        Value::Object(clients) => Ok(clients),
+        _ => anyhow::bail!("expected an object keyed by client name"),
+    }
+}
+
+/// Fill in `defaults` for the arguments a call left out.
+///
+/// Only keys listed in `accepted` (the tool's input properties) are added,
+/// so one defaults entry can serve tools with different parameters.
+pub fn apply(arguments: &mut JsonObject, defaults: JsonObject, accepted: &JsonObject) {
+    for (key, value) in defaults {
+        if accepted.contains_key(&key) && !arguments.contains_key(&key) {
+            arguments.insert(key, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn object(value: Value) -> JsonObject {
+        match value {
+            Value::Object(map) => map,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_client_entry_overrides_wildcard() {
+        let path =
+            std::env::temp_dir().join(format!("toon-client-defaults-{}.json", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"{"*": {"delimiter": "pipe", "indent": 4}, "claude-ai": {"delimiter": "tab"}}"#,
+        )
+        .unwrap();
+        let defaults = ClientDefaults::new(Some(path.clone()));
+
+        assert_eq!(
+            Value::Object(defaults.for_client("claude-ai")),
+            json!({"delimiter": "tab", "indent": 4})
+        );
+        assert_eq!(
+            Value::Object(defaults.for_client("other")),
+            json!({"delimiter": "pipe", "indent": 4})
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_apply_keeps_explicit_arguments() {
+        let mut arguments = object(json!({"json": {}, "delimiter": "comma"}));
+        let defaults = object(json!({"delimiter": "tab", "indent": 4, "strict": false}));
+        let accepted = object(json!({"json": {}, "delimiter": {}, "indent": {}}));
+
+        apply(&mut arguments, defaults, &accepted);
+        assert_eq!(
+            Value::Object(arguments),
+            json!({"json": {}, "delimiter": "comma", "indent": 4})
+        );
+    }
+}
@@ -3,62 +3,106 @@
 //! These tools wrap the core business logic with MCP-specific
 //! error handling and response formatting.
 
+mod client_defaults;
+
+use std::sync::{Arc, Mutex};
+
 use rmcp::{
-    handler::server::{router::tool::ToolRouter, wrapper::Parameters},
+    handler::server::{router::tool::ToolRouter, tool::ToolCallContext, wrapper::Parameters},
     model::*,
-    tool, tool_handler, tool_router, ErrorData as McpError, Json, ServerHandler,
+    service::RequestContext,
+    tool, tool_router, ErrorData as McpError, Json, Peer, RoleServer,
+    ServerHandler,
 };
-use schemars::JsonSchema;
-use serde::{Deserialize, Serialize};
 
 use crate::core::{
-    self, DecodeRequest, EncodeOptionsInput, StatsRequest, ToonCoreError, ValidateRequest,
-    ValidateResponse,
+    self, AggregateRequest, AnonymizeRequest, AnonymizeResponse, CacheAnalysisRequest,
+    CacheAnalysisResponse, DeanonymizeRequest, DeanonymizeResponse, DecodeRequest, DecodeResponse,
+    DemoDataRequest, DemoDataResponse, DiffValidateRequest, DiffValidateResponse, EncodeRequest,
+    HashRequest, HashResponse, HowtoRequest, HowtoResponse, JoinRequest, PivotRequest,
+    PostgresQueryRequest, QueryResponse, RoundTripRequest, RoundTripResponse, SchemaExampleRequest,
+    SchemaExampleResponse, SchemaTemplateRequest, SchemaTemplateResponse, SqlRequest, SqlResponse,
+    SqliteQueryRequest, StatsRequest, ToonCoreError, ValidateRequest, ValidateResponse,
 };
 
-/// MCP-specific encode request (re-exported for schema generation).
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
-pub struct EncodeRequest {
-    /// JSON to encode (object, array, or JSON string)
-    pub json: serde_json::Value,
+use crate::core::snippets::{self, Snippet};
+use crate::server::record::{Exchange, Recorder};
+use crate::worker::WorkerPool;
+use client_defaults::ClientDefaults;
 
-    /// Delimiter: "comma" (default), "tab", or "pipe"
-    #[serde(default)]
-    pub delimiter: Option<String>,
+/// Stats response types (re-exported for MCP schema).
+pub use crate::core::{FormatStats, SavingsStats, StatsResponse, ValidationError};
 
-    /// Spaces for indentation (0-8, default: 2)
-    #[serde(default)]
-    pub indent: Option<u8>,
+#[derive(Clone)]
+pub struct ToonTools {
+    tool_router: ToolRouter<Self>,
+    snippets: Arc<Vec<Snippet>>,
+    log_level: Arc<Mutex<LoggingLevel>>,
+    workers: WorkerPool,
+    client_defaults: Arc<ClientDefaults>,
+    recorder: Option<Arc<Recorder>>,
+}
 
-    /// Enable v1.5 key folding
-    #[serde(default)]
-    pub fold_keys: Option<bool>,
+/// Protocol revisions this server can speak, oldest first.
+const SUPPORTED_PROTOCOL_VERSIONS: [ProtocolVersion; 3] = [
+    ProtocolVersion::V_2024_11_05,
+    ProtocolVersion::V_2025_03_26,
+    ProtocolVersion::V_2025_06_18,
+];
 
-    /// Max depth for key folding
-    #[serde(default)]
-    pub flatten_depth: Option<usize>,
-}
+/// Newest protocol revision, offered to clients requesting an unknown one.
+const LATEST_PROTOCOL_VERSION: ProtocolVersion = ProtocolVersion::V_2025_06_18;
 
-impl EncodeRequest {
-    fn to_options(&self) -> EncodeOptionsInput {
-        EncodeOptionsInput {
-            delimiter: self.delimiter.clone(),
-            indent: self.indent,
-            fold_keys: self.fold_keys,
-            flatten_depth: self.flatten_depth,
-        }
+/// Agree on a protocol revision: echo the client's if supported, otherwise offer the latest.
+///
+/// Clients that cannot speak the offered revision disconnect, as the spec requires.
+fn negotiate_protocol_version(requested: &ProtocolVersion) -> ProtocolVersion {
+    if SUPPORTED_PROTOCOL_VERSIONS.contains(requested) {
+        requested.clone()
+    } else {
+        LATEST_PROTOCOL_VERSION
     }
 }
 
-/// Stats response types (re-exported for MCP schema).
-pub use crate::core::{FormatStats, SavingsStats, StatsResponse, ValidationError};
-
-#[derive(Clone)]
-pub struct ToonTools {
-    tool_router: ToolRouter<Self>,
+/// Severity rank of a logging level, lowest first.
+fn level_rank(level: LoggingLevel) -> u8 {
+    match level {
+        LoggingLevel::Debug => 0,
+        LoggingLevel::Info => 1,
+        LoggingLevel::Notice => 2,
+        LoggingLevel::Warning => 3,
+        LoggingLevel::Error => 4,
+        LoggingLevel::Critical => 5,
+        LoggingLevel::Alert => 6,
+        LoggingLevel::Emergency => 7,
+    }
 }
 
 impl ToonTools {
+    /// Send a `notifications/message` log entry if `level` meets the client's threshold.
+    ///
+    /// Delivery failures are ignored; logging must never fail a tool call.
+    async fn log(&self, peer: &Peer<RoleServer>, level: LoggingLevel, message: String) {
+        let threshold = *self.log_level.lock().unwrap_or_else(|e| e.into_inner());
+        if level_rank(level) < level_rank(threshold) {
+            return;
+        }
+        let _ = peer
+            .notify_logging_message(LoggingMessageNotificationParam {
+                level,
+                logger: Some("toon-mcp".to_string()),
+                data: serde_json::Value::String(message),
+            })
+            .await;
+    }
+
+    async fn log_truncation(&self, peer: &Peer<RoleServer>, result: &QueryResponse) {
+        if result.truncated {
+            let message = format!("query result truncated to {} rows", result.rows);
+            self.log(peer, LoggingLevel::Warning, message).await;
+        }
+    }
+
     fn map_core_error(e: ToonCoreError) -> McpError {
         match e {
             ToonCoreError::ParseError {
@@ -66,6 +110,8 @@ impl ToonTools {
                 line,
                 column,
                 suggestion,
+                row,
+                snippet,
             } => {
                 let mut data = serde_json::json!({
                     "line": line,
@@ -74,6 +120,12 @@ impl ToonTools {
                 if let Some(s) = suggestion {
                     data["suggestion"] = serde_json::json!(s);
                 }
+                if let Some(row) = row {
+                    data["row"] = serde_json::json!(row);
+                }
+                if let Some(snippet) = snippet {
+                    data["snippet"] = serde_json::json!(snippet);
+                }
                 McpError {
                     code: ErrorCode::INVALID_PARAMS,
                     message: format!("Parse error at line {}, column {}: {}", line, column, message)
@@ -98,6 +150,11 @@ impl ToonTools {
                 message: format!("Invalid JSON: {}", msg).into(),
                 data: None,
             },
+            ToonCoreError::InvalidOption(msg) => McpError {
+                code: ErrorCode::INVALID_PARAMS,
+                message: format!("Invalid option: {}", msg).into(),
+                data: None,
+            },
             other => McpError {
                 code: ErrorCode::INTERNAL_ERROR,
                 message: other.to_string().into(),
@@ -110,12 +167,51 @@ impl ToonTools {
 #[tool_router]
 impl ToonTools {
     pub fn new() -> Self {
+        let snippets_dir = std::env::var_os("TOON_SNIPPETS_DIR").map(std::path::PathBuf::from);
         Self {
             tool_router: Self::tool_router(),
+            snippets: Arc::new(snippets::load(snippets_dir.as_deref())),
+            log_level: Arc::new(Mutex::new(LoggingLevel::Info)),
+            workers: WorkerPool::from_env().unwrap_or_else(|e| {
+                eprintln!("toon-mcp: {}; using default worker pool", e);
+                WorkerPool::default()
+            }),
+            client_defaults: Arc::new(ClientDefaults::from_env()),
+            recorder: Recorder::from_env()
+                .unwrap_or_else(|e| {
+                    eprintln!("toon-mcp: {}; not recording", e);
+                    None
+                })
+                .map(Arc::new),
+        }
+    }
+
+    /// Stop recording tool calls, e.g. while replaying a recording.
+    pub fn without_recording(mut self) -> Self {
+        self.recorder = None;
+        self
+    }
+
+    /// Fill in the calling client's configured defaults for arguments it left out.
+    fn apply_client_defaults(&self, client: &str, request: &mut CallToolRequestParam) {
+        let defaults = self.client_defaults.for_client(client);
+        if defaults.is_empty() {
+            return;
+        }
+        let tools = self.tool_router.list_all();
+        let Some(tool) = tools.iter().find(|tool| tool.name == request.name) else {
+            return;
+        };
+        if let Some(serde_json::Value::Object(accepted)) = tool.input_schema.get("properties") {
+            let arguments = request.arguments.get_or_insert_with(JsonObject::new);
+            client_defaults::apply(arguments, defaults, accepted);
         }
     }
 
-    #[tool(description = "Ping the TOON MCP server to verify connectivity")]
+    #[tool(
+        description = "Ping the TOON MCP server to verify connectivity",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
     async fn toon_ping(&self) -> Result<CallToolResult, McpError> {
         Ok(CallToolResult::success(vec![Content::text(
             "pong - toon-mcp server is running",
@@ -124,35 +220,97 @@ impl ToonTools {
 
     #[tool(
         name = "toon_encode",
-        description = "Convert JSON to TOON format for reduced token usage. Achieves 18-40% savings."
+        description = "Convert JSON to TOON format for reduced token usage. Achieves 18-40% savings.",
+        annotations(read_only_hint = true, open_world_hint = false)
     )]
     async fn toon_encode(
         &self,
+        peer: Peer<RoleServer>,
         Parameters(request): Parameters<EncodeRequest>,
     ) -> Result<CallToolResult, McpError> {
-        // Parse JSON input (handles string-wrapped JSON)
-        let json_value = core::parse_json_input(&request.json).map_err(Self::map_core_error)?;
+        let size = core::json_size_hint(&request.json);
+        let result = self
+            .workers
+            .run(size, move || core::encode_request(&request))
+            .await
+            .map_err(Self::map_core_error)?;
+
+        if let Some(dropped) = result.metadata.as_ref().and_then(|m| m.rows_dropped) {
+            let message = format!("dedupe_rows removed {} duplicate rows", dropped);
+            self.log(&peer, LoggingLevel::Info, message).await;
+        }
+        if let Some(flattened) = result.metadata.as_ref().and_then(|m| m.tables_flattened) {
+            let message = format!("nested_tables flattened {} arrays", flattened);
+            self.log(&peer, LoggingLevel::Info, message).await;
+        }
+        if let Some(filled) = result.metadata.as_ref().and_then(|m| m.cells_filled) {
+            let message = format!("fill_missing filled {} cells with null", filled);
+            self.log(&peer, LoggingLevel::Info, message).await;
+        }
+        if let Some(replaced) = result.metadata.as_ref().and_then(|m| m.blobs_replaced) {
+            let message = format!("blob_policy replaced {} blobs", replaced);
+            self.log(&peer, LoggingLevel::Info, message).await;
+        }
+        for decision in result.decisions.iter().flatten() {
+            let message = format!(
+                "auto strategy: {} = {} ({})",
+                decision.option, decision.value, decision.reason
+            );
+            self.log(&peer, LoggingLevel::Info, message).await;
+        }
 
-        // Encode to TOON
-        let options = request.to_options();
-        let result = core::encode_json(&json_value, &options).map_err(Self::map_core_error)?;
+        // Reversible transforms return their metadata as a second content block
+        let mut content = vec![Content::text(result.toon)];
+        if let Some(metadata) = result.metadata {
+            let metadata = serde_json::to_string(&metadata)
+                .map_err(|e| {
+                    Self::map_core_error(ToonCoreError::SerializationError(e.to_string()))
+                })?;
+            content.push(Content::text(metadata));
+        }
 
-        Ok(CallToolResult::success(vec![Content::text(result)]))
+        Ok(CallToolResult::success(content))
     }
 
     #[tool(
         name = "toon_decode",
-        description = "Convert TOON format back to JSON. Supports strict validation and type coercion."
+        description = "Convert TOON format back to JSON. Supports strict validation and type coercion; report_coercions lists every value coercion changed.",
+        annotations(read_only_hint = true, open_world_hint = false)
     )]
     async fn toon_decode(
         &self,
+        peer: Peer<RoleServer>,
         Parameters(request): Parameters<DecodeRequest>,
     ) -> Result<CallToolResult, McpError> {
-        // Decode TOON to JSON value
-        let json_value = core::decode_toon(&request.toon, &request).map_err(Self::map_core_error)?;
+        if request.strict == Some(false) {
+            let message = "lenient decode: length and structure checks are disabled".to_string();
+            self.log(&peer, LoggingLevel::Notice, message).await;
+        }
+
+        let size = request.toon.len();
+        let output = self
+            .workers
+            .run(size, move || {
+                // Decode TOON to JSON value
+                let mut json_value = core::decode_toon(&request.toon, &request)?;
+                let coercions = core::coercion::report(&request, &json_value)?;
+                let page = core::paginate(&mut json_value, &request)?;
 
-        // Format output
-        let output = core::format_json_output(&json_value, request.output_format.as_deref())
+                // Format output; a page is returned with its position in the
+                // table, and a coercion report alongside the value
+                let ndjson = request.output_format.as_deref() == Some("ndjson");
+                if ndjson || (page.is_none() && coercions.is_none()) {
+                    return core::format_json_output(&json_value, request.output_format.as_deref());
+                }
+                let response = serde_json::to_value(DecodeResponse {
+                    json: json_value,
+                    page,
+                    coercions,
+                })
+                .map_err(|e| ToonCoreError::SerializationError(e.to_string()))?;
+                core::format_json_output(&response, request.output_format.as_deref())
+            })
+            .await
             .map_err(Self::map_core_error)?;
 
         Ok(CallToolResult::success(vec![Content::text(output)]))
@@ -160,19 +318,21 @@ impl ToonTools {
 
     #[tool(
         name = "toon_validate",
-        description = "Validate TOON syntax without full decoding. Returns validity and error details."
+        description = "Validate TOON syntax without full decoding, optionally requiring a table with expected_rows rows and exactly expected_columns, or rows following a contract (required keys; per-column type, min/max, pattern, nullable). Returns validity, error details, and contract violations.",
+        annotations(read_only_hint = true, open_world_hint = false)
     )]
     async fn toon_validate(
         &self,
         Parameters(request): Parameters<ValidateRequest>,
     ) -> Result<Json<ValidateResponse>, McpError> {
-        let result = core::validate_toon(&request.toon, request.strict);
+        let result = core::validate_request(&request).map_err(Self::map_core_error)?;
         Ok(Json(result))
     }
 
     #[tool(
         name = "toon_stats",
-        description = "Compare token and byte counts between JSON and TOON. Estimates cost savings."
+        description = "Compare token and byte counts between JSON and TOON. Estimates cost savings.",
+        annotations(read_only_hint = true, open_world_hint = false)
     )]
     async fn toon_stats(
         &self,
@@ -183,18 +343,283 @@ impl ToonTools {
 
         // Compute stats
         let stats =
-            core::compute_stats(&json_value, &request.encode_options).map_err(Self::map_core_error)?;
+            core::compute_stats(&json_value, &request.encode_options, &request.stats_options)
+                .map_err(Self::map_core_error)?;
 
         Ok(Json(stats))
     }
+
+    #[tool(
+        name = "toon_stats_decode",
+        description = "Compare token and byte counts of a TOON document against the JSON it decodes to. Measures savings on model output.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    async fn toon_stats_decode(
+        &self,
+        Parameters(request): Parameters<DecodeRequest>,
+    ) -> Result<Json<StatsResponse>, McpError> {
+        let stats = core::compute_decode_stats(&request).map_err(Self::map_core_error)?;
+        Ok(Json(stats))
+    }
+
+    #[tool(
+        name = "toon_aggregate",
+        description = "Group an array of objects by columns and compute count/sum/avg/min/max per group. Returns the summary table as TOON.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    async fn toon_aggregate(
+        &self,
+        Parameters(request): Parameters<AggregateRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let result = core::aggregate(&request).map_err(Self::map_core_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result.toon)]))
+    }
+
+    #[tool(
+        name = "toon_join",
+        description = "Inner or left join two arrays of objects on key columns and return the result as TOON. Capped by max_rows.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    async fn toon_join(
+        &self,
+        Parameters(request): Parameters<JoinRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let result = core::join(&request).map_err(Self::map_core_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result.toon)]))
+    }
+
+    #[tool(
+        name = "toon_pivot",
+        description = "Reshape an array of objects from long to wide format (or back) and return it as TOON. Wide tables encode to far fewer tokens.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    async fn toon_pivot(
+        &self,
+        peer: Peer<RoleServer>,
+        Parameters(request): Parameters<PivotRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let result = core::pivot(&request).map_err(Self::map_core_error)?;
+        if let Some(skipped) = result.rows_skipped {
+            let message = format!(
+                "pivot skipped {} rows with no value in the pivot column",
+                skipped
+            );
+            self.log(&peer, LoggingLevel::Warning, message).await;
+        }
+        Ok(CallToolResult::success(vec![Content::text(result.toon)]))
+    }
+
+    #[tool(
+        name = "toon_anonymize",
+        description = "Mask, hash, or pseudonymize emails, phone numbers, credit cards, IPs, and custom patterns, then encode as TOON. Returns a report of what changed.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    async fn toon_anonymize(
+        &self,
+        peer: Peer<RoleServer>,
+        Parameters(request): Parameters<AnonymizeRequest>,
+    ) -> Result<Json<AnonymizeResponse>, McpError> {
+        let result = core::anonymize(&request).map_err(Self::map_core_error)?;
+        if result.report.total > 0 {
+            let message = format!("anonymized {} values", result.report.total);
+            self.log(&peer, LoggingLevel::Info, message).await;
+        }
+        Ok(Json(result))
+    }
+
+    #[tool(
+        name = "toon_deanonymize",
+        description = "Restore original values in text (e.g. LLM output) using the encrypted mapping returned by toon_anonymize.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    async fn toon_deanonymize(
+        &self,
+        Parameters(request): Parameters<DeanonymizeRequest>,
+    ) -> Result<Json<DeanonymizeResponse>, McpError> {
+        let result = core::deanonymize(&request).map_err(Self::map_core_error)?;
+        Ok(Json(result))
+    }
+
+    #[tool(
+        name = "toon_hash",
+        description = "Return SHA-256 digests of the canonical JSON and canonical TOON forms of a document for integrity checks.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    async fn toon_hash(
+        &self,
+        Parameters(request): Parameters<HashRequest>,
+    ) -> Result<Json<HashResponse>, McpError> {
+        let result = core::hash_document(&request).map_err(Self::map_core_error)?;
+        Ok(Json(result))
+    }
+
+    #[tool(
+        name = "toon_cache_analysis",
+        description = "Compare the TOON encodings of two successive payload versions: longest common prefix, estimated prompt-cache reuse, and how to restructure data for more cache hits.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    async fn toon_cache_analysis(
+        &self,
+        Parameters(request): Parameters<CacheAnalysisRequest>,
+    ) -> Result<Json<CacheAnalysisResponse>, McpError> {
+        let result = core::cache_analysis(&request).map_err(Self::map_core_error)?;
+        Ok(Json(result))
+    }
+
+    #[tool(
+        name = "toon_verify_roundtrip",
+        description = "Encode JSON to TOON, decode it back, and report whether the round trip is lossless, listing any paths that differ.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    async fn toon_verify_roundtrip(
+        &self,
+        Parameters(request): Parameters<RoundTripRequest>,
+    ) -> Result<Json<RoundTripResponse>, McpError> {
+        let result = core::verify_roundtrip(&request).map_err(Self::map_core_error)?;
+        Ok(Json(result))
+    }
+
+    #[tool(
+        name = "toon_diff_validate",
+        description = "Decode LLM-returned TOON and compare it with the original JSON. Reports changed, added, and removed fields plus type changes and invented or dropped keys.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    async fn toon_diff_validate(
+        &self,
+        peer: Peer<RoleServer>,
+        Parameters(request): Parameters<DiffValidateRequest>,
+    ) -> Result<Json<DiffValidateResponse>, McpError> {
+        let result = core::diff_validate(&request).map_err(Self::map_core_error)?;
+        if !result.violations.is_empty() {
+            let message = format!(
+                "modified document has {} shape violations",
+                result.violations.len()
+            );
+            self.log(&peer, LoggingLevel::Warning, message).await;
+        }
+        Ok(Json(result))
+    }
+
+    #[tool(
+        name = "toon_example_from_schema",
+        description = "Generate a small synthetic example document from a JSON Schema (types, enums, required fields) and render it as TOON.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    async fn toon_example_from_schema(
+        &self,
+        Parameters(request): Parameters<SchemaExampleRequest>,
+    ) -> Result<Json<SchemaExampleResponse>, McpError> {
+        let result = core::example_from_schema(&request).map_err(Self::map_core_error)?;
+        Ok(Json(result))
+    }
+
+    #[tool(
+        name = "toon_schema_template",
+        description = "Turn a JSON Schema for an array of objects into a TOON tabular header template (field order, types noted as # lines) to fill in when producing TOON output.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    async fn toon_schema_template(
+        &self,
+        Parameters(request): Parameters<SchemaTemplateRequest>,
+    ) -> Result<Json<SchemaTemplateResponse>, McpError> {
+        let result = core::schema_template(&request).map_err(Self::map_core_error)?;
+        Ok(Json(result))
+    }
+
+    #[tool(
+        name = "toon_to_sql",
+        description = "Convert a tabular TOON or JSON document into a CREATE TABLE statement with inferred PostgreSQL types plus batched INSERT statements.",
+        annotations(read_only_hint = true, open_world_hint = true)
+    )]
+    async fn toon_to_sql(
+        &self,
+        Parameters(request): Parameters<SqlRequest>,
+    ) -> Result<Json<SqlResponse>, McpError> {
+        let result = core::to_sql(&request).map_err(Self::map_core_error)?;
+        Ok(Json(result))
+    }
+
+    #[tool(
+        name = "toon_from_sqlite",
+        description = "Run a read-only SQL query against a SQLite file under the configured sandbox root and return the rows as tabular TOON. Requires the sqlite feature and TOON_SQLITE_ROOT.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    async fn toon_from_sqlite(
+        &self,
+        peer: Peer<RoleServer>,
+        Parameters(request): Parameters<SqliteQueryRequest>,
+    ) -> Result<Json<QueryResponse>, McpError> {
+        let result = core::query_sqlite(&request).map_err(Self::map_core_error)?;
+        self.log_truncation(&peer, &result).await;
+        Ok(Json(result))
+    }
+
+    #[tool(
+        name = "toon_from_postgres",
+        description = "Run a parameterized read-only SQL query against the configured PostgreSQL database and return the rows as tabular TOON. Requires the postgres feature and TOON_POSTGRES_URL.",
+        annotations(read_only_hint = true, open_world_hint = true)
+    )]
+    async fn toon_from_postgres(
+        &self,
+        peer: Peer<RoleServer>,
+        Parameters(request): Parameters<PostgresQueryRequest>,
+    ) -> Result<Json<QueryResponse>, McpError> {
+        let result = core::query_postgres(&request)
+            .await
+            .map_err(Self::map_core_error)?;
+        self.log_truncation(&peer, &result).await;
+        Ok(Json(result))
+    }
+
+    #[tool(
+        name = "toon_howto",
+        description = "Show how to call an operation over the HTTP API: given a tool name or route and its arguments, return the equivalent curl command plus Python (requests) and TypeScript (fetch) snippets. Omit arguments for a working example request.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    async fn toon_howto(
+        &self,
+        Parameters(request): Parameters<HowtoRequest>,
+    ) -> Result<Json<HowtoResponse>, McpError> {
+        let result = core::howto::howto(&request).map_err(Self::map_core_error)?;
+        Ok(Json(result))
+    }
+
+    #[tool(
+        name = "toon_demo_data",
+        description = "Load a built-in sample dataset to try conversions on: \"iris\" (a CSV table of flower measurements), \"countries\" (a table with list columns), or \"orders\" (nested records). Returns the embedded file, its JSON, and its TOON encoding under the given encode_options.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    async fn toon_demo_data(
+        &self,
+        Parameters(request): Parameters<DemoDataRequest>,
+    ) -> Result<Json<DemoDataResponse>, McpError> {
+        let result = core::demo::load(&request).map_err(Self::map_core_error)?;
+        Ok(Json(result))
+    }
 }
 
-#[tool_handler]
 impl ServerHandler for ToonTools {
     fn get_info(&self) -> ServerInfo {
+        let mut capabilities = ServerCapabilities::builder()
+            .enable_tools()
+            .enable_resources()
+            .enable_completions()
+            .enable_logging()
+            .build();
+        if let Ok(serde_json::Value::Object(toon)) = serde_json::to_value(core::capabilities()) {
+            capabilities.experimental = Some([("toon".to_string(), toon)].into());
+        }
+
+        let info = core::build_info();
+        let optional: Vec<String> = info
+            .features
+            .into_iter()
+            .filter(|f| f != "mcp" && f != "http")
+            .collect();
+
         ServerInfo {
-            protocol_version: ProtocolVersion::V_2024_11_05,
-            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            protocol_version: LATEST_PROTOCOL_VERSION,
+            capabilities,
             server_info: Implementation {
                 name: "toon-mcp".into(),
                 version: env!("CARGO_PKG_VERSION").into(),
@@ -202,9 +627,164 @@ impl ServerHandler for ToonTools {
                 website_url: None,
                 icons: None,
             },
-            instructions: Some(
-                "TOON format encoding/decoding server for LLM cost optimization".into(),
-            ),
+            instructions: Some(format!(
+                "TOON format encoding/decoding server for LLM cost optimization. \
+                 Uses toon-format {} (TOON spec {}). Defaults: comma delimiter, \
+                 2-space indent, strict decoding. Optional features: {}. \
+                 See capabilities.experimental.toon for details.",
+                info.toon_format_version,
+                info.spec_versions.join(", "),
+                if optional.is_empty() {
+                    "none".to_string()
+                } else {
+                    optional.join(", ")
+                }
+            )),
+        }
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, McpError> {
+        Ok(ListToolsResult::with_all_items(self.tool_router.list_all()))
+    }
+
+    /// Run each tool call on its own task so a panic fails only that call.
+    async fn call_tool(
+        &self,
+        mut request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        let client = context
+            .peer
+            .peer_info()
+            .map(|info| info.client_info.name.clone());
+        // Record the arguments as sent; replay re-applies the client's defaults.
+        let recorded = self.recorder.as_ref().map(|_| {
+            let arguments = request.arguments.clone().unwrap_or_default();
+            (request.name.to_string(), serde_json::Value::Object(arguments))
+        });
+        if let Some(client) = &client {
+            self.apply_client_defaults(client, &mut request);
         }
+
+        let tools = self.clone();
+        let name = request.name.clone();
+        let call = tokio::spawn(async move {
+            let context = ToolCallContext::new(&tools, request, context);
+            tools.tool_router.call(context).await
+        });
+
+        let result = match call.await {
+            Ok(result) => result,
+            Err(e) => {
+                let reference = core::error_reference();
+                let reason = match e.try_into_panic() {
+                    Ok(panic) => core::panic_message(&*panic).to_string(),
+                    Err(e) => e.to_string(),
+                };
+                eprintln!(
+                    "toon-mcp: tool {} failed (reference {}): {}",
+                    name, reference, reason
+                );
+                Err(McpError::internal_error(
+                    format!("internal error (reference {})", reference),
+                    None,
+                ))
+            }
+        };
+
+        if let (Some(recorder), Some((tool, arguments))) = (&self.recorder, recorded) {
+            let response = match &result {
+                Ok(result) => serde_json::json!({ "result": result }),
+                Err(error) => serde_json::json!({ "error": error }),
+            };
+            recorder.record(&Exchange::Mcp {
+                client,
+                tool,
+                arguments,
+                response,
+            });
+        }
+        result
+    }
+
+    async fn initialize(
+        &self,
+        request: InitializeRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<InitializeResult, McpError> {
+        let protocol_version = negotiate_protocol_version(&request.protocol_version);
+        if context.peer.peer_info().is_none() {
+            context.peer.set_peer_info(request);
+        }
+        Ok(ServerInfo {
+            protocol_version,
+            ..self.get_info()
+        })
+    }
+
+    async fn set_level(
+        &self,
+        request: SetLevelRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        *self.log_level.lock().unwrap_or_else(|e| e.into_inner()) = request.level;
+        Ok(())
+    }
+
+    async fn complete(
+        &self,
+        request: CompleteRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<CompleteResult, McpError> {
+        let values = core::complete_option(&request.argument.name, &request.argument.value);
+        Ok(CompleteResult {
+            completion: CompletionInfo {
+                total: Some(values.len() as u32),
+                has_more: Some(false),
+                values,
+            },
+        })
+    }
+
+    async fn list_resources(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, McpError> {
+        let resources = self
+            .snippets
+            .iter()
+            .map(|snippet| {
+                let mut resource = RawResource::new(snippet.uri(), snippet.name.clone());
+                resource.description = Some(snippet.description.clone());
+                resource.mime_type = Some("text/markdown".to_string());
+                resource.no_annotation()
+            })
+            .collect();
+        Ok(ListResourcesResult::with_all_items(resources))
+    }
+
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, McpError> {
+        let snippet = self
+            .snippets
+            .iter()
+            .find(|snippet| snippet.uri() == request.uri)
+            .ok_or_else(|| {
+                McpError::resource_not_found(
+                    format!("Unknown resource: {}", request.uri),
+                    None,
+                )
+            })?;
+        Ok(ReadResourceResult {
+            contents: vec![ResourceContents::text(snippet.text.clone(), request.uri)],
+        })
     }
 }
@@ -0,0 +1,113 @@
+//! Bounded pool for CPU-heavy conversions.
+//!
+//! Encoding or decoding a large document can take long enough to stall the
+//! async runtime that also answers health checks and small requests. Jobs
+//! whose input is at least the offload threshold run on tokio's blocking
+//! threads instead, with a semaphore capping how many run at once. Smaller
+//! jobs run inline, where a thread hop would cost more than it saves.
+
+use std::sync::Arc;
+
+use tokio::sync::Semaphore;
+
+/// Default input size (bytes) from which jobs are offloaded.
+pub const DEFAULT_OFFLOAD_BYTES: usize = 256 * 1024;
+
+/// Runs conversions inline or on a bounded set of blocking threads.
+#[derive(Debug, Clone)]
+pub struct WorkerPool {
+    permits: Arc<Semaphore>,
+    offload_bytes: usize,
+}
+
+impl Default for WorkerPool {
+    fn default() -> Self {
+        Self::new(default_workers(), DEFAULT_OFFLOAD_BYTES)
+    }
+}
+
+/// One worker per available CPU.
+pub fn default_workers() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4)
+}
+
+impl WorkerPool {
+    /// Pool running at most `workers` offloaded jobs at once.
+    pub fn new(workers: usize, offload_bytes: usize) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(workers.max(1))),
+            offload_bytes,
+        }
+    }
+
+    /// Read `TOON_WORKERS` (default: available CPUs) and `TOON_OFFLOAD_BYTES`
+    /// (default: 256 KiB).
+    pub fn from_env() -> anyhow::Result<Self> {
+        let var = |name: &str| -> anyhow::Result<Option<usize>> {
+            match std::env::var(name) {
+                Ok(value) if !value.is_empty() => value
+                    .parse()
+                    .map(Some)
+                    .map_err(|_| anyhow::anyhow!("{} must be a whole number", name)),
+                _ => Ok(None),
+            }
+        };
+        Ok(Self::new(
+            var("TOON_WORKERS")?.unwrap_or_else(default_workers),
+            var("TOON_OFFLOAD_BYTES")?.unwrap_or(DEFAULT_OFFLOAD_BYTES),
+        ))
+    }
+
+    /// Run `job`, offloading it when `input_bytes` reaches the threshold.
+    ///
+    /// A panic in an offloaded job is resumed on the caller, so the
+    /// surrounding panic isolation still reports it.
+    pub async fn run<T, F>(&self, input_bytes: usize, job: F) -> T
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        if input_bytes < self.offload_bytes {
+            return job();
+        }
+
+        let permit = self
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("worker pool semaphore is never closed");
+        let task = tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            job()
+        });
+        match task.await {
+            Ok(value) => value,
+            Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+            Err(e) => panic!("worker task failed: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_small_jobs_run_inline() {
+        let pool = WorkerPool::new(1, 100);
+        let caller = std::thread::current().id();
+        let ran_on = pool.run(10, || std::thread::current().id()).await;
+        assert_eq!(ran_on, caller);
+    }
+
+    #[tokio::test]
+    async fn test_large_jobs_are_offloaded() {
+        let pool = WorkerPool::new(1, 100);
+        let caller = std::thread::current().id();
+        let ran_on = pool.run(100, || std::thread::current().id()).await;
+        assert_ne!(ran_on, caller);
+    }
+}
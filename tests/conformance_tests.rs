@@ -0,0 +1,42 @@
+//! Official TOON specification test vectors.
+//!
+//! Set `TOON_SPEC_DIR` to the `tests/fixtures` directory of a checkout of
+//! https://github.com/toon-format/spec to run them; without it the test is
+//! skipped.
+
+use std::path::PathBuf;
+
+use toon_mcp::conformance::{self, Outcome};
+
+#[test]
+fn spec_conformance() {
+    let Some(dir) = std::env::var_os(conformance::SPEC_DIR_ENV).map(PathBuf::from) else {
+        eprintln!(
+            "skipping spec conformance: {} is not set",
+            conformance::SPEC_DIR_ENV
+        );
+        return;
+    };
+
+    let report = conformance::run(&dir).expect("failed to load spec fixtures");
+    let failures: Vec<String> = report
+        .cases
+        .iter()
+        .filter(|case| case.outcome == Outcome::Fail)
+        .map(|case| {
+            format!(
+                "{} / {}: {}",
+                case.file,
+                case.name,
+                case.message.as_deref().unwrap_or_default()
+            )
+        })
+        .collect();
+    assert!(
+        failures.is_empty(),
+        "{} of {} spec cases failed:\n{}",
+        failures.len(),
+        report.cases.len(),
+        failures.join("\n")
+    );
+}
@@ -3,8 +3,16 @@
 mod common;
 
 use toon_mcp::core::{
-    compute_stats, decode_toon, encode_json, estimate_tokens, parse_json_input, validate_toon,
-    DecodeRequest, EncodeOptionsInput,
+    aggregate, anonymize, build_info, cache_analysis, capabilities, coercion, complete_option,
+    compute_decode_stats, compute_stats, deanonymize, decode_toon, diff_validate, encode_json,
+    encode_request, encode_with_metadata, error_reference, estimate_tokens, example_from_schema,
+    format_json_output, format_range, hash_document, join, paginate, panic_message,
+    parse_json_input, pivot, query_postgres, schema_template, snippets, stream, to_sql,
+    validate_request, validate_toon, verify_roundtrip, AggregateRequest, Aggregation,
+    AnonymizeRequest, CacheAnalysisRequest, DeanonymizeRequest, DecodeRequest, DiffValidateRequest,
+    EncodeOptionsInput, EncodeRequest, FormatRangeRequest, HashRequest, JoinRequest, Literals,
+    PivotRequest, PostgresQueryRequest, RoundTripRequest, SchemaExampleRequest,
+    SchemaTemplateRequest, SqlRequest, StatsOptions, TextEdit, ValidateRequest, SPEC_VERSIONS,
 };
 
 #[test]
@@ -61,6 +69,142 @@ fn test_encode_with_indent() {
     assert!(result.is_ok());
 }
 
+#[test]
+fn test_encode_with_rename() {
+    let json = serde_json::json!([
+        {"customer_identifier": 1, "name": "Alice"},
+        {"customer_identifier": 2, "name": "Bob"}
+    ]);
+    let options = EncodeOptionsInput {
+        rename: Some([("customer_identifier".to_string(), "id".to_string())].into()),
+        ..Default::default()
+    };
+
+    let result = encode_with_metadata(&json, &options).expect("encode failed");
+    assert!(!result.toon.contains("customer_identifier"));
+    assert!(result.toon.contains("id"));
+
+    let metadata = result.metadata.expect("rename should emit metadata");
+    assert_eq!(
+        metadata.rename.unwrap().get("id").map(String::as_str),
+        Some("customer_identifier")
+    );
+}
+
+#[test]
+fn test_rename_roundtrip_with_metadata() {
+    let original =
+        serde_json::json!({"customer_identifier": 7, "nested": {"customer_identifier": 8}});
+    let options = EncodeOptionsInput {
+        rename: Some([("customer_identifier".to_string(), "id".to_string())].into()),
+        ..Default::default()
+    };
+
+    let encoded = encode_with_metadata(&original, &options).expect("encode failed");
+    let request = DecodeRequest {
+        toon: encoded.toon,
+        metadata: encoded.metadata,
+        ..Default::default()
+    };
+
+    let decoded = decode_toon(&request.toon, &request).expect("decode failed");
+    assert_eq!(original, decoded);
+}
+
+#[test]
+fn test_rename_collision_rejected() {
+    let json = serde_json::json!({"customer_identifier": 1, "id": 2});
+    let options = EncodeOptionsInput {
+        rename: Some([("customer_identifier".to_string(), "id".to_string())].into()),
+        ..Default::default()
+    };
+
+    assert!(encode_json(&json, &options).is_err());
+}
+
+#[test]
+fn test_rename_to_key_used_elsewhere_rejected() {
+    let json = serde_json::json!({"customer_identifier": 1, "nested": {"id": 2}});
+    let options = EncodeOptionsInput {
+        rename: Some([("customer_identifier".to_string(), "id".to_string())].into()),
+        ..Default::default()
+    };
+    assert!(encode_with_metadata(&json, &options).is_err());
+
+    // Swapping two keys renames every occurrence of both, so it round-trips.
+    let original = serde_json::json!({"a": 1, "b": 2, "nested": {"b": 3}});
+    let options = EncodeOptionsInput {
+        rename: Some(
+            [
+                ("a".to_string(), "b".to_string()),
+                ("b".to_string(), "a".to_string()),
+            ]
+            .into(),
+        ),
+        ..Default::default()
+    };
+    let encoded = encode_with_metadata(&original, &options).expect("encode failed");
+    let request = DecodeRequest {
+        toon: encoded.toon,
+        metadata: encoded.metadata,
+        ..Default::default()
+    };
+    let decoded = decode_toon(&request.toon, &request).expect("decode failed");
+    assert_eq!(original, decoded);
+}
+
+#[test]
+fn test_encode_dedupe_rows() {
+    let json = serde_json::json!({"events": [
+        {"service": "api", "level": "error"},
+        {"service": "api", "level": "error"},
+        {"service": "db", "level": "warn"},
+        {"service": "api", "level": "error"}
+    ]});
+    let options = EncodeOptionsInput {
+        dedupe_rows: Some(true),
+        ..Default::default()
+    };
+
+    let result = encode_with_metadata(&json, &options).expect("encode failed");
+    assert!(result.toon.contains("events[2]"));
+    assert_eq!(result.metadata.unwrap().rows_dropped, Some(2));
+}
+
+#[test]
+fn test_encode_dedupe_rows_ignores_key_order() {
+    let json = serde_json::json!({"events": [
+        {"service": "api", "level": "error"},
+        {"level": "error", "service": "api"}
+    ]});
+    let options = EncodeOptionsInput {
+        dedupe_rows: Some(true),
+        ..Default::default()
+    };
+
+    let result = encode_with_metadata(&json, &options).expect("encode failed");
+    assert!(result.toon.contains("events[1]"));
+    assert_eq!(result.metadata.unwrap().rows_dropped, Some(1));
+}
+
+#[test]
+fn test_keys_keep_input_order() {
+    let json: serde_json::Value =
+        serde_json::from_str(r#"{"zeta": 1, "alpha": {"mid": 2, "beta": 3}}"#).unwrap();
+    let toon = encode_json(&json, &EncodeOptionsInput::default()).unwrap();
+    assert_eq!(toon, "zeta: 1\nalpha:\n  mid: 2\n  beta: 3");
+
+    let request = DecodeRequest {
+        toon,
+        ..Default::default()
+    };
+    let decoded = decode_toon(&request.toon, &request).unwrap();
+    assert_eq!(
+        decoded.to_string(),
+        r#"{"zeta":1,"alpha":{"mid":2,"beta":3}}"#
+    );
+}
+
 #[test]
 fn test_decode_simple() {
     let toon = "name: Alice\nage: 30";
@@ -70,6 +214,7 @@ fn test_decode_simple() {
         coerce_types: None,
         expand_paths: None,
         output_format: None,
+        ..Default::default()
     };
 
     let result = decode_toon(&request.toon, &request);
@@ -89,6 +234,7 @@ fn test_decode_with_strict_mode() {
         coerce_types: None,
         expand_paths: None,
         output_format: None,
+        ..Default::default()
     };
 
     let result = decode_toon(&request.toon, &request);
@@ -108,6 +254,7 @@ fn test_roundtrip_simple() {
         coerce_types: None,
         expand_paths: None,
         output_format: None,
+        ..Default::default()
     };
 
     let decoded = decode_toon(&request.toon, &request).expect("decode failed");
@@ -127,6 +274,7 @@ fn test_roundtrip_tabular() {
         coerce_types: None,
         expand_paths: None,
         output_format: None,
+        ..Default::default()
     };
 
     let decoded = decode_toon(&request.toon, &request).expect("decode failed");
@@ -146,6 +294,7 @@ fn test_roundtrip_nested() {
         coerce_types: None,
         expand_paths: None,
         output_format: None,
+        ..Default::default()
     };
 
     let decoded = decode_toon(&request.toon, &request).expect("decode failed");
@@ -165,6 +314,7 @@ fn test_roundtrip_special_chars() {
         coerce_types: None,
         expand_paths: None,
         output_format: None,
+        ..Default::default()
     };
 
     let decoded = decode_toon(&request.toon, &request).expect("decode failed");
@@ -184,6 +334,7 @@ fn test_roundtrip_empty_object() {
         coerce_types: None,
         expand_paths: None,
         output_format: None,
+        ..Default::default()
     };
 
     let decoded = decode_toon(&request.toon, &request).expect("decode failed");
@@ -203,6 +354,7 @@ fn test_roundtrip_empty_array() {
         coerce_types: None,
         expand_paths: None,
         output_format: None,
+        ..Default::default()
     };
 
     let decoded = decode_toon(&request.toon, &request).expect("decode failed");
@@ -229,12 +381,62 @@ fn test_validate_invalid_toon() {
     assert!(result.valid || result.error.is_some());
 }
 
+#[test]
+fn test_validate_expected_table_shape() {
+    let validate = |expected_rows: Option<usize>, expected_columns: Option<&[&str]>| {
+        validate_request(&ValidateRequest {
+            toon: "users[2]{id,name}:\n  1,Alice\n  2,Bob".to_string(),
+            strict: None,
+            expected_rows,
+            expected_columns: expected_columns
+                .map(|names| names.iter().map(|name| name.to_string()).collect()),
+            contract: None,
+        })
+        .unwrap()
+    };
+
+    assert!(validate(Some(2), Some(&["name", "id"])).valid);
+    let result = validate(Some(3), None);
+    assert!(!result.valid);
+    assert_eq!(result.error.unwrap().message, "expected 3 rows, found 2");
+    let result = validate(None, Some(&["id", "nam"]));
+    assert!(!result.valid);
+    let error = result.error.unwrap();
+    assert_eq!(error.row, Some(1));
+    assert_eq!(error.suggestion.as_deref(), Some("did you mean `nam`?"));
+}
+
+#[test]
+fn test_validate_contract() {
+    let validate = |contract: serde_json::Value| {
+        validate_request(&ValidateRequest {
+            toon: "users[2]{id,email}:\n  1,alice@example.com\n  2,bob".to_string(),
+            strict: None,
+            expected_rows: None,
+            expected_columns: None,
+            contract: Some(serde_json::from_value(contract).unwrap()),
+        })
+    };
+
+    let contract = serde_json::json!({"required": ["id"], "columns": {"id": {"type": "integer"}}});
+    let result = validate(contract).unwrap();
+    assert!(result.valid);
+    assert!(result.violations.is_empty());
+
+    let result = validate(serde_json::json!({"columns": {"email": {"pattern": "@"}}})).unwrap();
+    assert!(!result.valid);
+    assert_eq!(result.violations.len(), 1);
+    assert_eq!(result.violations[0].path, "users[1].email");
+
+    assert!(validate(serde_json::json!({"columns": {"id": {"type": "int"}}})).is_err());
+}
+
 #[test]
 fn test_compute_stats() {
     let json = common::simple_json();
     let options = EncodeOptionsInput::default();
 
-    let result = compute_stats(&json, &options);
+    let result = compute_stats(&json, &options, &StatsOptions::default());
     assert!(result.is_ok());
 
     let stats = result.unwrap();
@@ -247,6 +449,82 @@ fn test_compute_stats() {
     assert!(stats.savings.bytes_percent >= 0.0);
 }
 
+#[test]
+fn test_compute_stats_precision_and_units() {
+    let json = common::simple_json();
+    let stats_options = StatsOptions {
+        precision: Some(0),
+        human_units: Some(true),
+        ..Default::default()
+    };
+
+    let stats = compute_stats(&json, &EncodeOptionsInput::default(), &stats_options).unwrap();
+
+    assert_eq!(stats.savings.bytes_percent.fract(), 0.0);
+    assert_eq!(
+        stats.savings.bytes_saved,
+        stats.json.bytes as i64 - stats.toon.bytes as i64
+    );
+    assert_eq!(
+        stats.json.bytes_human,
+        Some(format!("{} B", stats.json.bytes))
+    );
+    assert!(stats.toon.tokens_human.unwrap().ends_with(" tokens"));
+
+    let plain = compute_stats(
+        &json,
+        &EncodeOptionsInput::default(),
+        &StatsOptions::default(),
+    )
+    .unwrap();
+    assert!(plain.json.bytes_human.is_none());
+    assert!(plain.diagnostics.is_none());
+}
+
+#[test]
+fn test_compute_stats_sample_rows() {
+    let rows: Vec<serde_json::Value> = (0..5000)
+        .map(|i| {
+            let city = ["Oslo", "Lima", "Pune"][i % 3];
+            serde_json::json!({"id": i, "city": city})
+        })
+        .collect();
+    let json = serde_json::json!({"rows": rows});
+    let stats_options = StatsOptions {
+        sample_rows: Some(100),
+        ..Default::default()
+    };
+
+    let options = EncodeOptionsInput::default();
+    let sampled = compute_stats(&json, &options, &stats_options).unwrap();
+    let exact = compute_stats(&json, &options, &StatsOptions::default()).unwrap();
+    let sample = sampled.sample.unwrap();
+    assert_eq!((sample.path.as_str(), sample.rows_total), ("rows", 5000));
+    let (estimated, margin) = (sampled.toon.tokens_approx, sampled.toon.tokens_margin);
+    assert!(estimated.abs_diff(exact.toon.tokens_approx) <= margin.unwrap().max(50));
+    assert!(exact.sample.is_none() && exact.toon.tokens_margin.is_none());
+}
+
+#[test]
+fn test_compute_stats_diagnose() {
+    let json = serde_json::json!({
+        "rows": [
+            {"id": 1, "tags": ["a", "b"]},
+            {"id": 2, "tags": ["c"]}
+        ]
+    });
+    let stats_options = StatsOptions {
+        diagnose: Some(true),
+        ..Default::default()
+    };
+
+    let stats = compute_stats(&json, &EncodeOptionsInput::default(), &stats_options).unwrap();
+    let diagnostics = stats.diagnostics.unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].cause, "non_uniform_rows");
+    assert!(diagnostics[0].estimated_tokens > 0);
+}
+
 #[test]
 fn test_parse_json_input_object() {
     let value = serde_json::json!({"key": "value"});
@@ -299,3 +577,1310 @@ fn test_estimate_tokens_punctuation() {
     assert_eq!(estimate_tokens("..."), 3);
     assert_eq!(estimate_tokens("a.b.c"), 5); // a, ., b, ., c
 }
+
+#[test]
+fn test_aggregate_group_by() {
+    let request = AggregateRequest {
+        json: serde_json::json!([
+            {"service": "api", "latency": 10},
+            {"service": "db", "latency": 40},
+            {"service": "api", "latency": 30}
+        ]),
+        group_by: vec!["service".to_string()],
+        aggregations: vec![
+            Aggregation {
+                op: "count".to_string(),
+                column: None,
+                alias: None,
+            },
+            Aggregation {
+                op: "avg".to_string(),
+                column: Some("latency".to_string()),
+                alias: Some("avg_ms".to_string()),
+            },
+        ],
+        encode_options: EncodeOptionsInput::default(),
+    };
+
+    let result = aggregate(&request).expect("aggregate failed");
+    assert_eq!(result.rows, 2);
+    assert!(result.toon.contains("{service,count,avg_ms}"));
+    assert!(result.toon.contains("api,2,20"));
+    assert!(result.toon.contains("db,1,40"));
+}
+
+#[test]
+fn test_aggregate_rejects_unknown_op() {
+    let request = AggregateRequest {
+        json: serde_json::json!([{"a": 1}]),
+        group_by: vec!["a".to_string()],
+        aggregations: vec![Aggregation {
+            op: "median".to_string(),
+            column: Some("a".to_string()),
+            alias: None,
+        }],
+        encode_options: EncodeOptionsInput::default(),
+    };
+
+    assert!(aggregate(&request).is_err());
+}
+
+#[test]
+fn test_join_inner_and_left() {
+    let left = serde_json::json!([
+        {"user_id": 1, "event": "login"},
+        {"user_id": 2, "event": "logout"},
+        {"user_id": 3, "event": "login"}
+    ]);
+    let right = serde_json::json!([
+        {"user_id": 1, "name": "Alice"},
+        {"user_id": 2, "name": "Bob"}
+    ]);
+
+    let inner = join(&JoinRequest {
+        left: left.clone(),
+        right: right.clone(),
+        on: vec!["user_id".to_string()],
+        how: None,
+        max_rows: None,
+        encode_options: EncodeOptionsInput::default(),
+    })
+    .expect("inner join failed");
+    assert_eq!(inner.rows, 2);
+    assert!(inner.toon.contains("1,login,Alice"));
+
+    let outer = join(&JoinRequest {
+        left,
+        right,
+        on: vec!["user_id".to_string()],
+        how: Some("left".to_string()),
+        max_rows: None,
+        encode_options: EncodeOptionsInput::default(),
+    })
+    .expect("left join failed");
+    assert_eq!(outer.rows, 3);
+    assert!(outer.toon.contains("3,login,null"));
+}
+
+#[test]
+fn test_join_row_limit() {
+    let rows = serde_json::json!([{"k": 1}, {"k": 1}, {"k": 1}]);
+    let result = join(&JoinRequest {
+        left: rows.clone(),
+        right: rows,
+        on: vec!["k".to_string()],
+        how: None,
+        max_rows: Some(5),
+        encode_options: EncodeOptionsInput::default(),
+    });
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_join_rejects_taken_prefixed_column() {
+    let request = |left: serde_json::Value| JoinRequest {
+        left,
+        right: serde_json::json!([{"id": 1, "name": "Alice", "right_name": "A."}]),
+        on: vec!["id".to_string()],
+        how: None,
+        max_rows: None,
+        encode_options: EncodeOptionsInput::default(),
+    };
+
+    assert!(join(&request(serde_json::json!([{"id": 1, "name": "login"}]))).is_err());
+    assert!(join(&request(serde_json::json!([{"id": 1, "event": "login"}]))).is_ok());
+}
+
+#[test]
+fn test_pivot_wide_and_back() {
+    let long = serde_json::json!([
+        {"host": "a", "metric": "cpu", "v": 10},
+        {"host": "a", "metric": "mem", "v": 70},
+        {"host": "b", "metric": "cpu", "v": 20}
+    ]);
+
+    let wide = pivot(&PivotRequest {
+        json: long,
+        direction: None,
+        index: vec!["host".to_string()],
+        columns: Some("metric".to_string()),
+        values: Some("v".to_string()),
+        encode_options: EncodeOptionsInput::default(),
+    })
+    .expect("pivot failed");
+    assert_eq!(wide.rows, 2);
+    assert!(wide.toon.contains("{host,cpu,mem}"));
+    assert!(wide.toon.contains("b,20,null"));
+
+    let back = pivot(&PivotRequest {
+        json: serde_json::json!([{"host": "a", "cpu": 10, "mem": 70}]),
+        direction: Some("long".to_string()),
+        index: vec!["host".to_string()],
+        columns: None,
+        values: None,
+        encode_options: EncodeOptionsInput::default(),
+    })
+    .expect("unpivot failed");
+    assert_eq!(back.rows, 2);
+    assert!(back.toon.contains("{host,key,value}"));
+    assert!(back.toon.contains("a,mem,70"));
+}
+
+#[test]
+fn test_pivot_wide_reports_rows_without_a_column() {
+    let long = serde_json::json!([
+        {"host": "a", "metric": "cpu", "v": 10},
+        {"host": "a", "metric": null, "v": 70},
+        {"host": "b", "v": 20}
+    ]);
+
+    let wide = pivot(&PivotRequest {
+        json: long,
+        direction: None,
+        index: vec!["host".to_string()],
+        columns: Some("metric".to_string()),
+        values: Some("v".to_string()),
+        encode_options: EncodeOptionsInput::default(),
+    })
+    .expect("pivot failed");
+    assert_eq!(wide.rows, 1);
+    assert_eq!(wide.rows_skipped, Some(2));
+}
+
+#[test]
+fn test_anonymize_mask_and_report() {
+    let request = AnonymizeRequest {
+        json: serde_json::json!({"users": [
+            {"email": "alice@example.com", "note": "call 555-123-4567"},
+            {"email": "bob@example.com", "note": "from 10.0.0.1"}
+        ]}),
+        mode: None,
+        detectors: None,
+        patterns: vec![],
+        key: None,
+        return_mapping: None,
+        encode_options: EncodeOptionsInput::default(),
+    };
+
+    let result = anonymize(&request).expect("anonymize failed");
+    assert!(!result.toon.contains("alice@example.com"));
+    assert!(result.toon.contains("[EMAIL]"));
+    assert_eq!(result.report.by_type.get("email"), Some(&2));
+    assert_eq!(result.report.by_type.get("phone"), Some(&1));
+    assert_eq!(result.report.by_type.get("ip"), Some(&1));
+    assert!(result.report.paths.contains(&"users[0].email".to_string()));
+}
+
+#[test]
+fn test_anonymize_pseudonymize_is_consistent() {
+    let request = AnonymizeRequest {
+        json: serde_json::json!([
+            {"from": "alice@example.com"},
+            {"from": "bob@example.com"},
+            {"from": "alice@example.com"}
+        ]),
+        mode: Some("pseudonymize".to_string()),
+        detectors: Some(vec!["email".to_string()]),
+        patterns: vec![],
+        key: None,
+        return_mapping: None,
+        encode_options: EncodeOptionsInput::default(),
+    };
+
+    let result = anonymize(&request).expect("anonymize failed");
+    assert_eq!(result.toon.matches("email_1").count(), 2);
+    assert_eq!(result.toon.matches("email_2").count(), 1);
+}
+
+#[test]
+fn test_anonymize_hmac_stable_and_reversible() {
+    let request = |json: serde_json::Value| AnonymizeRequest {
+        json,
+        mode: Some("hmac".to_string()),
+        detectors: None,
+        patterns: vec![],
+        key: Some("test-secret".to_string()),
+        return_mapping: Some(true),
+        encode_options: EncodeOptionsInput::default(),
+    };
+
+    let first = anonymize(&request(serde_json::json!({"a": "alice@example.com"}))).unwrap();
+    let second = anonymize(&request(serde_json::json!({"b": "alice@example.com"}))).unwrap();
+    let token = first.toon.trim_start_matches("a: ").to_string();
+    assert!(token.starts_with("email_"));
+    assert!(second.toon.contains(&token));
+
+    let restored = deanonymize(&DeanonymizeRequest {
+        text: format!("Contact {} today", token),
+        mapping: first.mapping.expect("mapping requested"),
+        key: Some("test-secret".to_string()),
+    })
+    .unwrap();
+    assert_eq!(restored.text, "Contact alice@example.com today");
+    assert_eq!(restored.replaced, 1);
+}
+
+#[test]
+fn test_deanonymize_wrong_key_fails() {
+    let result = anonymize(&AnonymizeRequest {
+        json: serde_json::json!({"a": "alice@example.com"}),
+        mode: Some("pseudonymize".to_string()),
+        detectors: None,
+        patterns: vec![],
+        key: Some("right".to_string()),
+        return_mapping: Some(true),
+        encode_options: EncodeOptionsInput::default(),
+    })
+    .unwrap();
+
+    let restored = deanonymize(&DeanonymizeRequest {
+        text: "email_1".to_string(),
+        mapping: result.mapping.unwrap(),
+        key: Some("wrong".to_string()),
+    });
+    assert!(restored.is_err());
+}
+
+#[test]
+fn test_hash_is_key_order_independent() {
+    let a = hash_document(&HashRequest {
+        json: Some(serde_json::json!({"a": 1, "b": [1, 2]})),
+        toon: None,
+        encode_options: EncodeOptionsInput::default(),
+    })
+    .unwrap();
+    let b = hash_document(&HashRequest {
+        json: Some(serde_json::json!({"b": [1, 2], "a": 1})),
+        toon: None,
+        encode_options: EncodeOptionsInput::default(),
+    })
+    .unwrap();
+    let from_toon = hash_document(&HashRequest {
+        json: None,
+        toon: Some("a: 1\nb[2]: 1,2".to_string()),
+        encode_options: EncodeOptionsInput::default(),
+    })
+    .unwrap();
+
+    assert_eq!(a.json_sha256.len(), 64);
+    assert_eq!(a.json_sha256, b.json_sha256);
+    assert_eq!(a.toon_sha256, b.toon_sha256);
+    assert_eq!(a.json_sha256, from_toon.json_sha256);
+}
+
+#[test]
+fn test_hash_requires_one_input() {
+    let result = hash_document(&HashRequest {
+        json: None,
+        toon: None,
+        encode_options: EncodeOptionsInput::default(),
+    });
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_verify_roundtrip_lossless() {
+    let result = verify_roundtrip(&RoundTripRequest {
+        json: common::tabular_json(),
+        encode_options: EncodeOptionsInput::default(),
+    })
+    .unwrap();
+
+    assert!(result.lossless);
+    assert!(result.differences.is_empty());
+}
+
+#[test]
+fn test_verify_roundtrip_reports_dedupe_loss() {
+    let result = verify_roundtrip(&RoundTripRequest {
+        json: serde_json::json!({"rows": [{"a": 1}, {"a": 1}]}),
+        encode_options: EncodeOptionsInput {
+            dedupe_rows: Some(true),
+            ..Default::default()
+        },
+    })
+    .unwrap();
+
+    assert!(!result.lossless);
+    assert_eq!(result.differences.len(), 1);
+    assert_eq!(result.differences[0].path, "rows[1]");
+    assert_eq!(result.differences[0].kind, "removed");
+}
+
+#[test]
+fn test_diff_validate_reports_edits_and_violations() {
+    let original = serde_json::json!({"users": [
+        {"id": 1, "name": "Alice", "age": 30},
+        {"id": 2, "name": "Bob", "age": 25}
+    ]});
+    let toon = "users[2]{id,name,age,role}:\n  1,Alicia,30,admin\n  2,Bob,unknown,user";
+
+    let result = diff_validate(&DiffValidateRequest {
+        original,
+        toon: toon.to_string(),
+        strict: None,
+        metadata: None,
+    })
+    .unwrap();
+
+    assert!(!result.valid);
+    assert!(result.changed.iter().any(|d| d.path == "users[0].name"));
+    assert_eq!(result.added.len(), 2);
+    assert!(result
+        .violations
+        .iter()
+        .any(|v| v.path == "users[1].age" && v.message.contains("number to string")));
+    assert!(result
+        .violations
+        .iter()
+        .any(|v| v.path == "users[0].role" && v.message == "unexpected key"));
+}
+
+#[test]
+fn test_diff_validate_suggests_misspelled_keys() {
+    let result = diff_validate(&DiffValidateRequest {
+        original: serde_json::json!({"users": [{"user_id": 1, "name": "Alice"}]}),
+        toon: "users[1]{usr_id,name,role}:\n  1,Alice,admin".to_string(),
+        strict: None,
+        metadata: None,
+    })
+    .unwrap();
+
+    let suggestion = |path: &str| {
+        result
+            .violations
+            .iter()
+            .find(|v| v.path == path && v.message == "unexpected key")
+            .unwrap()
+            .suggestion
+            .clone()
+    };
+    assert_eq!(
+        suggestion("users[0].usr_id").as_deref(),
+        Some("did you mean `user_id`?")
+    );
+    assert_eq!(suggestion("users[0].role"), None);
+}
+
+#[test]
+fn test_diff_validate_decode_error_is_reported() {
+    let result = diff_validate(&DiffValidateRequest {
+        original: serde_json::json!({"items": [1, 2, 3]}),
+        toon: "items[3]: 1,2".to_string(),
+        strict: Some(true),
+        metadata: None,
+    })
+    .unwrap();
+
+    assert!(!result.valid);
+    assert!(result.error.is_some());
+}
+
+#[test]
+fn test_example_from_schema() {
+    let schema = serde_json::json!({
+        "type": "object",
+        "required": ["orders"],
+        "properties": {
+            "orders": {
+                "type": "array",
+                "items": {"$ref": "#/$defs/order"}
+            },
+            "note": {"type": "string"}
+        },
+        "$defs": {
+            "order": {
+                "type": "object",
+                "required": ["id", "status"],
+                "properties": {
+                    "id": {"type": "integer"},
+                    "status": {"enum": ["open", "shipped"]},
+                    "email": {"type": "string", "format": "email"}
+                }
+            }
+        }
+    });
+
+    let result = example_from_schema(&SchemaExampleRequest {
+        schema,
+        rows: Some(2),
+        include_optional: Some(false),
+        encode_options: EncodeOptionsInput::default(),
+    })
+    .unwrap();
+
+    assert!(result.json.get("note").is_none());
+    assert_eq!(
+        result.json["orders"][0],
+        serde_json::json!({"id": 1, "status": "open"})
+    );
+    assert!(result.toon.contains("orders[2]{id,status}:"));
+    assert!(result.toon.contains("2,shipped"));
+}
+
+#[test]
+fn test_schema_template() {
+    let schema = serde_json::json!({
+        "type": "array",
+        "items": {
+            "type": "object",
+            "required": ["id"],
+            "properties": {
+                "id": {"type": "integer"},
+                "status": {"type": "string", "enum": ["open", "closed"]},
+                "email": {"type": "string", "format": "email"}
+            }
+        }
+    });
+
+    let result = schema_template(&SchemaTemplateRequest {
+        schema,
+        name: Some("tickets".to_string()),
+        delimiter: Some("pipe".to_string()),
+    })
+    .unwrap();
+
+    assert_eq!(result.fields.len(), 3);
+    assert!(result.fields[0].required);
+    assert!(result.template.contains("# id: integer, required\n"));
+    assert!(result
+        .template
+        .contains("# status: string, optional, one of open|closed\n"));
+    assert!(result
+        .template
+        .contains("# email: string (email), optional\n"));
+    assert!(result
+        .template
+        .contains("tickets[N|]{id|status|email}:\n  <id>|<status>|<email>\n"));
+}
+
+#[test]
+fn test_schema_template_rejects_scalar_schema() {
+    let result = schema_template(&SchemaTemplateRequest {
+        schema: serde_json::json!({"type": "string"}),
+        name: None,
+        delimiter: None,
+    });
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_decode_stats_measures_expansion() {
+    let toon = "items[3]{id,name}:\n  1,alpha\n  2,beta\n  3,gamma";
+    let stats = compute_decode_stats(&DecodeRequest {
+        toon: toon.to_string(),
+        ..Default::default()
+    })
+    .unwrap();
+
+    assert_eq!(stats.toon.bytes, toon.len());
+    assert!(stats.json.bytes > stats.toon.bytes);
+    assert!(stats.savings.bytes_percent > 0.0);
+
+    let pretty = compute_decode_stats(&DecodeRequest {
+        toon: toon.to_string(),
+        output_format: Some("json_pretty".to_string()),
+        ..Default::default()
+    })
+    .unwrap();
+    assert!(pretty.json.bytes > stats.json.bytes);
+}
+
+#[test]
+fn test_format_ndjson_output() {
+    let table = serde_json::json!({"users": [{"id": 1}, {"id": 2}]});
+    assert_eq!(
+        format_json_output(&table, Some("ndjson")).unwrap(),
+        "{\"id\":1}\n{\"id\":2}\n"
+    );
+
+    let root = serde_json::json!([1, "two"]);
+    assert_eq!(
+        format_json_output(&root, Some("ndjson")).unwrap(),
+        "1\n\"two\"\n"
+    );
+
+    let scalar = serde_json::json!({"a": 1, "b": 2});
+    assert_eq!(
+        format_json_output(&scalar, Some("ndjson")).unwrap(),
+        "{\"a\":1,\"b\":2}\n"
+    );
+}
+
+#[test]
+fn test_to_sql_from_toon() {
+    let result = to_sql(&SqlRequest {
+        json: None,
+        toon: Some("users[3]{id,name,score}:\n  1,O'Brien,1.5\n  2,Bob,2\n  3,null,3".to_string()),
+        table: None,
+        batch_size: Some(2),
+    })
+    .unwrap();
+
+    assert_eq!(result.rows, 3);
+    assert_eq!(result.columns[0].sql_type, "BIGINT");
+    assert!(!result.columns[0].nullable);
+    assert!(result.columns[1].nullable);
+    assert_eq!(result.columns[2].sql_type, "DOUBLE PRECISION");
+    assert!(result
+        .sql
+        .starts_with("CREATE TABLE \"users\" (\n  \"id\" BIGINT NOT NULL,\n  \"name\" TEXT,\n"));
+    assert!(result
+        .sql
+        .contains("(1, 'O''Brien', 1.5),\n  (2, 'Bob', 2);"));
+    assert!(result.sql.contains("VALUES\n  (3, NULL, 3);"));
+}
+
+#[test]
+fn test_to_sql_requires_table() {
+    let result = to_sql(&SqlRequest {
+        json: Some(serde_json::json!({"a": 1, "b": 2})),
+        toon: None,
+        table: None,
+        batch_size: None,
+    });
+    assert!(result.is_err());
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn test_query_sqlite_sandboxed() {
+    use toon_mcp::core::{query_sqlite, SqliteQueryRequest};
+
+    let root = std::env::temp_dir().join(format!("toon-sqlite-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+    rusqlite::Connection::open(root.join("test.db"))
+        .unwrap()
+        .execute_batch(
+            "CREATE TABLE t (id INTEGER, name TEXT);
+             INSERT INTO t VALUES (1, 'a'), (2, 'b'), (3, 'c');",
+        )
+        .unwrap();
+    std::env::set_var("TOON_SQLITE_ROOT", &root);
+
+    let request = |path: &str, query: &str| SqliteQueryRequest {
+        path: path.to_string(),
+        query: query.to_string(),
+        params: vec![serde_json::json!(1)],
+        max_rows: Some(1),
+        encode_options: EncodeOptionsInput::default(),
+    };
+
+    let result = query_sqlite(&request("test.db", "SELECT id, name FROM t WHERE id > ?")).unwrap();
+    assert_eq!(result.rows, 1);
+    assert!(result.truncated);
+    assert!(result.toon.contains("[1]{id,name}:"));
+    assert!(result.toon.contains("2,b"));
+
+    assert!(query_sqlite(&request("test.db", "DELETE FROM t WHERE id = ?")).is_err());
+    assert!(query_sqlite(&request("../test.db", "SELECT ?")).is_err());
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[cfg(not(feature = "postgres"))]
+#[tokio::test]
+async fn test_query_postgres_requires_feature() {
+    let result = query_postgres(&PostgresQueryRequest {
+        query: "SELECT 1".to_string(),
+        params: vec![],
+        max_rows: None,
+        encode_options: EncodeOptionsInput::default(),
+    })
+    .await;
+
+    let message = result.unwrap_err().to_string();
+    assert!(message.contains("--features postgres"));
+}
+
+#[test]
+fn test_snippets_load_overrides_and_extends() {
+    let dir = std::env::temp_dir().join(format!("toon-snippets-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("instructions.md"), "custom instructions").unwrap();
+    std::fs::write(dir.join("house-style.txt"), "use tabs").unwrap();
+    std::fs::write(dir.join("ignored.json"), "{}").unwrap();
+
+    let loaded = snippets::load(Some(&dir));
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(loaded.len(), snippets::builtin().len() + 1);
+    let instructions = loaded.iter().find(|s| s.name == "instructions").unwrap();
+    assert_eq!(instructions.text, "custom instructions");
+    let custom = loaded.iter().find(|s| s.name == "house-style").unwrap();
+    assert_eq!(custom.uri(), "toon://snippets/house-style");
+}
+
+#[test]
+fn test_complete_option_values() {
+    assert_eq!(complete_option("delimiter", "t"), vec!["tab"]);
+    assert_eq!(complete_option("output_format", "json").len(), 2);
+    assert_eq!(complete_option("detectors", "").len(), 4);
+    assert!(complete_option("unknown", "").is_empty());
+}
+
+#[test]
+fn test_panic_message_and_error_reference() {
+    let panic = std::panic::catch_unwind(|| panic!("bad row {}", 3)).unwrap_err();
+    assert_eq!(panic_message(&*panic), "bad row 3");
+    let panic = std::panic::catch_unwind(|| panic!("static")).unwrap_err();
+    assert_eq!(panic_message(&*panic), "static");
+
+    assert_ne!(error_reference(), error_reference());
+}
+
+#[test]
+fn test_deterministic_encode_is_canonical() {
+    let options = EncodeOptionsInput {
+        deterministic: Some(true),
+        ..Default::default()
+    };
+    let a = encode_json(
+        &serde_json::json!({"b": 2.0, "a": {"y": -0.0, "x": 1.5}}),
+        &options,
+    )
+    .unwrap();
+    let b = encode_json(
+        &serde_json::json!({"a": {"x": 1.5, "y": 0}, "b": 2}),
+        &options,
+    )
+    .unwrap();
+
+    assert_eq!(a, b);
+    assert!(a.starts_with("a:"));
+    assert!(a.contains("x: 1.5"));
+}
+
+#[test]
+fn test_deterministic_decode_sorts_keys() {
+    let request = DecodeRequest {
+        toon: "b: 1\na: 2".to_string(),
+        deterministic: Some(true),
+        ..Default::default()
+    };
+    let value = decode_toon(&request.toon, &request).unwrap();
+
+    assert_eq!(serde_json::to_string(&value).unwrap(), r#"{"a":2,"b":1}"#);
+}
+
+#[test]
+fn test_capabilities_summary() {
+    let caps = capabilities();
+
+    assert_eq!(caps.spec_versions, SPEC_VERSIONS);
+    assert_eq!(caps.features, build_info().features);
+    assert!(caps.delimiters.contains(&"pipe".to_string()));
+    assert!(caps.output_formats.contains(&"ndjson".to_string()));
+    assert_eq!(caps.defaults.delimiter, "comma");
+    assert_eq!(caps.limits.query_max_rows, 1_000);
+}
+
+#[test]
+fn test_paginate_table_rows() {
+    let toon = "rows[5]{id}:\n  1\n  2\n  3\n  4\n  5";
+    let request = DecodeRequest {
+        toon: toon.to_string(),
+        page: Some(2),
+        page_size: Some(2),
+        ..Default::default()
+    };
+    let mut value = decode_toon(toon, &request).unwrap();
+    let page = paginate(&mut value, &request).unwrap().unwrap();
+
+    assert_eq!(value, serde_json::json!({"rows": [{"id": 3}, {"id": 4}]}));
+    assert_eq!((page.total_rows, page.offset, page.returned), (5, 2, 2));
+    assert_eq!(page.next_cursor.as_deref(), Some("4"));
+
+    let request = DecodeRequest {
+        cursor: page.next_cursor,
+        page_size: Some(2),
+        ..request
+    };
+    let mut value = decode_toon(toon, &request).unwrap();
+    let page = paginate(&mut value, &request).unwrap().unwrap();
+    assert_eq!(value, serde_json::json!({"rows": [{"id": 5}]}));
+    assert!(page.next_cursor.is_none());
+}
+
+#[test]
+fn test_paginate_rejects_non_tables_and_bad_cursors() {
+    let request = DecodeRequest {
+        page_size: Some(10),
+        ..Default::default()
+    };
+    assert!(paginate(&mut serde_json::json!({"a": 1, "b": 2}), &request).is_err());
+    assert!(
+        paginate(&mut serde_json::json!([1, 2]), &DecodeRequest::default())
+            .unwrap()
+            .is_none()
+    );
+
+    let request = DecodeRequest {
+        cursor: Some("abc".to_string()),
+        ..Default::default()
+    };
+    assert!(paginate(&mut serde_json::json!([1, 2]), &request).is_err());
+}
+
+#[test]
+fn test_stream_encode_matches_whole_document_encode() {
+    let input =
+        r#"[{"id": 1, "name": "Ada, L."}, {"id": 2, "name": "Bob"}, {"id": 3, "name": ""}]"#;
+    let options = EncodeOptionsInput::default();
+
+    let streamed = stream::encode_array(input.as_bytes(), &options)
+        .unwrap()
+        .unwrap();
+    let whole = encode_json(&serde_json::from_str(input).unwrap(), &options).unwrap();
+    assert_eq!(streamed, whole);
+
+    let request = EncodeRequest {
+        json: serde_json::json!(input),
+        options,
+    };
+    assert_eq!(encode_request(&request).unwrap().toon, whole);
+}
+
+#[test]
+fn test_stream_encode_falls_back_for_non_tables() {
+    let options = EncodeOptionsInput::default();
+    for input in [
+        r#"[{"a": 1}, {"b": 2}]"#,
+        r#"[{"a": {"b": 1}}]"#,
+        "[1, 2]",
+        "[]",
+    ] {
+        assert!(stream::encode_array(input.as_bytes(), &options)
+            .unwrap()
+            .is_none());
+    }
+    assert!(stream::encode_array(r#"[{"a": 1},"#.as_bytes(), &options).is_err());
+}
+
+#[test]
+fn test_format_range_returns_edits_inside_range() {
+    let request = |start_line, end_line| FormatRangeRequest {
+        toon: "a[1]{x}:\n  1\nb[1]{y}:\n  2\n".to_string(),
+        start_line,
+        end_line,
+        strict: None,
+        options: EncodeOptionsInput {
+            indent: Some(4),
+            ..Default::default()
+        },
+    };
+
+    let edits = format_range(&request(1, 2)).unwrap().edits;
+    assert_eq!(
+        edits,
+        vec![TextEdit {
+            start_line: 2,
+            end_line: 3,
+            new_text: "    1\n".to_string()
+        }]
+    );
+    assert_eq!(format_range(&request(1, 4)).unwrap().edits.len(), 2);
+    assert!(format_range(&request(3, 2)).is_err());
+}
+
+#[test]
+fn test_format_range_rejects_data_changing_options() {
+    let request = |options| FormatRangeRequest {
+        toon: "rows[2]{id,ok}:\n  1,true\n  2,false\n".to_string(),
+        start_line: 1,
+        end_line: 3,
+        strict: None,
+        options,
+    };
+
+    let rejected = [
+        EncodeOptionsInput {
+            fill_missing: Some(true),
+            ..Default::default()
+        },
+        EncodeOptionsInput {
+            literals: Some(Literals {
+                true_as: Some("Y".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        },
+        EncodeOptionsInput {
+            placeholders: Some([("rows[0].id".to_string(), "id".to_string())].into()),
+            ..Default::default()
+        },
+    ];
+    for options in rejected {
+        assert!(format_range(&request(options)).is_err());
+    }
+    let reordered = EncodeOptionsInput {
+        column_order: Some(vec!["ok".to_string()]),
+        ..Default::default()
+    };
+    assert_eq!(format_range(&request(reordered)).unwrap().edits.len(), 1);
+}
+
+#[test]
+fn test_encode_against_previous_keeps_unchanged_lines() {
+    let options = EncodeOptionsInput::default();
+    let before = serde_json::json!({
+        "users": [{"id": 1, "name": "Ada"}, {"id": 2, "name": "Bob"}],
+        "total": 2
+    });
+    let previous = encode_json(&before, &options).unwrap();
+
+    // Same data with keys reordered, plus one new row.
+    let after = serde_json::json!({
+        "total": 3,
+        "users": [
+            {"name": "Ada", "id": 1},
+            {"name": "Bob", "id": 2},
+            {"name": "Cy", "id": 3}
+        ]
+    });
+    let options = EncodeOptionsInput {
+        previous: Some(previous.clone()),
+        ..Default::default()
+    };
+    let toon = encode_json(&after, &options).unwrap();
+
+    assert_eq!(
+        toon,
+        "users[3]{id,name}:\n  1,Ada\n  2,Bob\n  3,Cy\ntotal: 3"
+    );
+    let unchanged = previous
+        .lines()
+        .filter(|line| toon.lines().any(|l| l == *line));
+    assert_eq!(unchanged.count(), 2);
+}
+
+#[test]
+fn test_cache_analysis_reports_shared_prefix() {
+    let request = |before, after| CacheAnalysisRequest {
+        before,
+        after,
+        encode_options: EncodeOptionsInput::default(),
+        min_cacheable_tokens: Some(1),
+    };
+
+    let report = cache_analysis(&request(
+        serde_json::json!({"title": "Report", "users": [{"id": 1}, {"id": 2}]}),
+        serde_json::json!({"title": "Report", "users": [{"id": 1}, {"id": 2}, {"id": 3}]}),
+    ))
+    .unwrap();
+    assert_eq!(report.prefix_lines, 1);
+    assert_eq!(report.first_difference_line, Some(2));
+    assert!(report.reusable_tokens > 0);
+    assert_eq!(report.reusable_tokens, report.prefix_tokens);
+    assert!(report.suggestions[0].contains("array header"));
+
+    let same = serde_json::json!({"a": 1});
+    let report = cache_analysis(&request(same.clone(), same)).unwrap();
+    assert_eq!(report.first_difference_line, None);
+    assert_eq!(report.reuse_ratio, 1.0);
+
+    let report = cache_analysis(&request(
+        serde_json::json!({"a": 1, "b": 2}),
+        serde_json::json!({"b": 2, "a": 1}),
+    ))
+    .unwrap();
+    assert_eq!(report.first_difference_line, Some(1));
+    assert!(report.suggestions.iter().any(|s| s.contains("`previous`")));
+}
+
+#[test]
+fn test_placeholders_emit_bare_template_variables() {
+    let json = serde_json::json!({
+        "greeting": "hi",
+        "user": {"name": "Ada", "age": 3},
+        "rows": [{"id": 1, "note": "x"}]
+    });
+    let options = |placeholders: &[(&str, &str)]| EncodeOptionsInput {
+        placeholders: Some(
+            placeholders
+                .iter()
+                .map(|(path, name)| (path.to_string(), name.to_string()))
+                .collect(),
+        ),
+        ..Default::default()
+    };
+
+    let toon = encode_json(
+        &json,
+        &options(&[("user.name", "user_name"), ("rows[0].note", "note")]),
+    )
+    .unwrap();
+    assert_eq!(
+        toon,
+        "greeting: hi\nuser:\n  name: {{user_name}}\n  age: 3\nrows[1]{id,note}:\n  1,{{note}}"
+    );
+
+    assert!(encode_json(&json, &options(&[("user.email", "email")])).is_err());
+    assert!(encode_json(&json, &options(&[("user.name", "a b")])).is_err());
+}
+
+#[test]
+fn test_column_order_leads_tables() {
+    let json = serde_json::json!({
+        "users": [
+            {"id": 1, "email": "a@x.io", "name": "Ada"},
+            {"id": 2, "email": "b@x.io", "name": "Bob"}
+        ]
+    });
+    let options = EncodeOptionsInput {
+        column_order: Some(vec!["name".to_string(), "missing".to_string()]),
+        ..Default::default()
+    };
+
+    let toon = encode_json(&json, &options).unwrap();
+    assert!(toon.starts_with("users[2]{name,id,email}:\n  Ada,1,a@x.io"));
+}
+
+#[test]
+fn test_decode_reports_coercions() {
+    let request = DecodeRequest {
+        toon: "users[2]{id,name,admin}:\n  1,Alice,true\n  2,Bob,\"false\"".to_string(),
+        report_coercions: Some(true),
+        ..Default::default()
+    };
+    let json = decode_toon(&request.toon, &request).unwrap();
+    let coercions = coercion::report(&request, &json).unwrap().unwrap();
+
+    let found: Vec<(&str, &str)> = coercions
+        .iter()
+        .map(|c| (c.path.as_str(), c.from.as_str()))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            ("users[0].id", "1"),
+            ("users[0].admin", "true"),
+            ("users[1].id", "2"),
+        ]
+    );
+    assert_eq!(coercions[1].to, serde_json::json!(true));
+}
+
+#[test]
+fn test_decode_locale_numbers() {
+    let mut request = DecodeRequest {
+        toon: "items[2|]{sku|price}:\n  A-1|1 234,56\n  B-2|\"30\"".to_string(),
+        report_coercions: Some(true),
+        ..Default::default()
+    };
+    let json = decode_toon(&request.toon, &request).unwrap();
+    assert_eq!(json["items"][0]["price"], "1 234,56");
+
+    request.locale_numbers = Some(true);
+    let json = decode_toon(&request.toon, &request).unwrap();
+    assert_eq!(json["items"][0]["price"], 1234.56);
+    assert_eq!(json["items"][1]["price"], "30");
+
+    let coercions = coercion::report(&request, &json).unwrap().unwrap();
+    assert_eq!(coercions.len(), 1);
+    assert_eq!(coercions[0].path, "items[0].price");
+    assert_eq!(coercions[0].from, "1 234,56");
+}
+
+#[test]
+fn test_encode_line_endings() {
+    let json = serde_json::json!({"users": [{"id": 1, "name": "Alice"}]});
+    let options = EncodeOptionsInput {
+        line_ending: Some("crlf".to_string()),
+        final_newline: Some(true),
+        ..Default::default()
+    };
+    let toon = encode_json(&json, &options).unwrap();
+    assert_eq!(toon, "users[1]{id,name}:\r\n  1,Alice\r\n");
+}
+
+#[test]
+fn test_decode_accepts_bom_and_crlf() {
+    let toon = "\u{feff}users[2]{id,name}:\r\n  1,Alice\r\n  2,Bob\r\n";
+    let json = decode_toon(toon, &DecodeRequest::default()).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({"users": [{"id": 1, "name": "Alice"}, {"id": 2, "name": "Bob"}]})
+    );
+    assert!(validate_toon(toon, None).valid);
+}
+
+#[test]
+fn test_encode_auto_strategy() {
+    let request = EncodeRequest {
+        json: serde_json::json!({"places": [
+            {"id": 1, "city": "Portland, OR"},
+            {"id": 2, "city": "Austin, TX"}
+        ]}),
+        options: EncodeOptionsInput {
+            strategy: Some("auto".to_string()),
+            ..Default::default()
+        },
+    };
+    let response = encode_request(&request).unwrap();
+    assert_eq!(
+        response.toon,
+        "places[2\t]{id\tcity}:\n  1\tPortland, OR\n  2\tAustin, TX"
+    );
+    let decisions = response.decisions.unwrap();
+    assert_eq!(decisions[0].option, "delimiter");
+    assert_eq!(decisions[0].value, "tab");
+
+    let request = EncodeRequest {
+        json: serde_json::json!({"a": 1}),
+        options: EncodeOptionsInput {
+            strategy: Some("best".to_string()),
+            ..Default::default()
+        },
+    };
+    assert!(encode_request(&request).is_err());
+}
+
+#[test]
+fn test_fill_missing_tabularizes_near_uniform_rows() {
+    let json = serde_json::json!({"users": [
+        {"id": 1, "name": "Alice"},
+        {"id": 2, "name": "Bob", "nickname": "Bobby"},
+        {"name": "Carol", "id": 3}
+    ]});
+    let options = EncodeOptionsInput {
+        fill_missing: Some(true),
+        ..Default::default()
+    };
+    let response = encode_with_metadata(&json, &options).unwrap();
+    assert_eq!(
+        response.toon,
+        "users[3]{id,name,nickname}:\n  1,Alice,null\n  2,Bob,Bobby\n  3,Carol,null"
+    );
+    assert_eq!(response.metadata.unwrap().cells_filled, Some(2));
+
+    // Mostly disjoint rows are left alone.
+    let json = serde_json::json!([{"a": 1}, {"b": 2}, {"c": 3}]);
+    let response = encode_with_metadata(&json, &options).unwrap();
+    assert_eq!(response.metadata.unwrap().cells_filled, Some(0));
+    assert!(!response.toon.contains('{'));
+}
+
+#[test]
+fn test_nested_tables_link_orders_to_items() {
+    let json = serde_json::json!({"orders": [
+        {"id": 7, "customer": "Alice", "items": [{"sku": "A1", "qty": 2}, {"sku": "B2", "qty": 1}]},
+        {"id": 8, "customer": "Bob", "items": [{"sku": "A1", "qty": 5}]}
+    ]});
+    let options = EncodeOptionsInput {
+        nested_tables: Some("link".to_string()),
+        ..Default::default()
+    };
+    let response = encode_with_metadata(&json, &options).unwrap();
+    assert_eq!(
+        response.toon,
+        "orders[2]{id,customer}:\n  7,Alice\n  8,Bob\n\
+         orders_items[3]{orders_id,sku,qty}:\n  7,A1,2\n  7,B2,1\n  8,A1,5"
+    );
+    assert_eq!(response.metadata.unwrap().tables_flattened, Some(1));
+
+    let options = EncodeOptionsInput {
+        nested_tables: Some("join".to_string()),
+        ..Default::default()
+    };
+    assert!(encode_with_metadata(&json, &options).is_err());
+}
+
+#[test]
+fn test_type_hints_round_trip_and_fix_model_output() {
+    let json = serde_json::json!({
+        "events": [
+            {"id": 1, "code": "007", "ok": true, "at": "2024-05-01T10:00:00Z"},
+            {"id": 2, "code": "12", "ok": false, "at": "2024-05-02T10:00:00Z"}
+        ]
+    });
+    let options = EncodeOptionsInput {
+        type_hints: Some(true),
+        ..Default::default()
+    };
+    let toon = encode_json(&json, &options).unwrap();
+    assert_eq!(toon.lines().nth(1), Some("  # int,str,bool,ts"));
+
+    let request = DecodeRequest {
+        type_hints: Some(true),
+        ..Default::default()
+    };
+    assert_eq!(decode_toon(&toon, &request).unwrap(), json);
+
+    // A model that quoted a number and left a numeric-looking string bare.
+    let model_output = "events[1]{id,code}:\n  # int,str\n  \"3\",42";
+    assert_eq!(
+        decode_toon(model_output, &request).unwrap(),
+        serde_json::json!({"events": [{"id": 3, "code": "42"}]})
+    );
+}
+
+#[test]
+fn test_enum_codes_round_trip() {
+    let statuses = [
+        "active", "archived", "pending", "active", "active", "pending",
+    ];
+    let rows: Vec<_> = statuses
+        .iter()
+        .enumerate()
+        .map(|(i, s)| serde_json::json!({"id": i, "status": s}))
+        .collect();
+    let json = serde_json::json!({"orders": rows});
+    let options = EncodeOptionsInput {
+        enum_codes: Some(true),
+        ..Default::default()
+    };
+
+    let encoded = encode_with_metadata(&json, &options).unwrap();
+    assert!(encoded.toon.contains("\n  0,ac\n"));
+    let metadata = encoded.metadata.unwrap();
+    let legend = &metadata.enums.as_ref().unwrap()["orders[].status"];
+    assert_eq!(legend["pe"], "pending");
+
+    let request = DecodeRequest {
+        metadata: Some(metadata),
+        ..Default::default()
+    };
+    assert_eq!(decode_toon(&encoded.toon, &request).unwrap(), json);
+}
+
+#[test]
+fn test_literals_round_trip() {
+    let json = serde_json::json!({
+        "flags": [
+            {"id": 1, "a": true, "b": false, "c": null},
+            {"id": 2, "a": false, "b": true, "c": "x"}
+        ]
+    });
+    let literals = Literals {
+        true_as: Some("T".to_string()),
+        false_as: Some("F".to_string()),
+        null_as: Some("~".to_string()),
+    };
+    let options = EncodeOptionsInput {
+        literals: Some(literals.clone()),
+        ..Default::default()
+    };
+
+    let encoded = encode_with_metadata(&json, &options).unwrap();
+    assert!(encoded.toon.contains("\n  1,T,F,~\n"));
+    let request = DecodeRequest {
+        metadata: encoded.metadata,
+        ..Default::default()
+    };
+    assert_eq!(decode_toon(&encoded.toon, &request).unwrap(), json);
+
+    // A string spelled like a literal could not be told apart on decode.
+    let clash = serde_json::json!({"grade": "F", "passed": false});
+    assert!(encode_json(&clash, &options).is_err());
+}
+
+#[test]
+fn test_blob_policy() {
+    let image = "iVBORw0KGgo".repeat(40);
+    let json = serde_json::json!({
+        "name": "logo",
+        "data": image.clone(),
+        "thumbnails": [image.clone(), "small"]
+    });
+    let encode = |policy: &str| {
+        let options = EncodeOptionsInput {
+            blob_policy: Some(policy.to_string()),
+            ..Default::default()
+        };
+        encode_with_metadata(&json, &options).unwrap()
+    };
+
+    let dropped = encode("drop");
+    assert_eq!(dropped.toon, "name: logo\nthumbnails[1]: small");
+    assert_eq!(dropped.metadata.unwrap().blobs_replaced, Some(2));
+
+    let truncated = encode("truncate");
+    assert!(truncated
+        .toon
+        .contains("iVBORw0KGgoiVBORw0KGgoiVBORw0KGg... [440 chars]"));
+
+    let hashed = encode("hash");
+    let digest = &toon_mcp::core::anonymize::hex_digest(image.as_bytes())[..16];
+    assert_eq!(
+        hashed.toon.matches(&format!("sha256:{}", digest)).count(),
+        2
+    );
+
+    assert_eq!(
+        encode("keep").toon,
+        encode_json(&json, &Default::default()).unwrap()
+    );
+
+    let options = EncodeOptionsInput {
+        blob_policy: Some("shrink".to_string()),
+        ..Default::default()
+    };
+    assert!(encode_json(&json, &options).is_err());
+}
+
+#[test]
+fn test_url_prefixes_round_trip() {
+    let json = serde_json::json!({
+        "logs": [
+            {"status": 200, "url": "https://api.example.com/v1/users/17"},
+            {"status": 404, "url": "https://api.example.com/v1/orders?page=2"},
+            {"status": 200, "url": null}
+        ]
+    });
+    let options = EncodeOptionsInput {
+        url_prefixes: Some(true),
+        ..Default::default()
+    };
+
+    let encoded = encode_with_metadata(&json, &options).unwrap();
+    assert!(encoded.toon.contains("\n  200,users/17\n"));
+    let metadata = encoded.metadata.unwrap();
+    assert_eq!(
+        metadata.prefixes.as_ref().unwrap()["logs[].url"],
+        "https://api.example.com/v1/"
+    );
+
+    let request = DecodeRequest {
+        metadata: Some(metadata),
+        ..Default::default()
+    };
+    assert_eq!(decode_toon(&encoded.toon, &request).unwrap(), json);
+}
+
+#[test]
+fn test_dictionary_round_trip() {
+    let rows: Vec<_> = (0..4)
+        .map(|i| {
+            serde_json::json!({
+                "id": i,
+                "customer": "Globex Corporation",
+                "region": "North America",
+                "note": "@0"
+            })
+        })
+        .collect();
+    let json = serde_json::json!({"orders": rows, "owner": "Globex Corporation"});
+    let options = EncodeOptionsInput {
+        dictionary: Some(true),
+        ..Default::default()
+    };
+
+    let encoded = encode_with_metadata(&json, &options).unwrap();
+    let metadata = encoded.metadata.unwrap();
+    let legend = metadata.dictionary.as_ref().unwrap();
+    // "@0" already occurs in the document, so tokens start at "@1".
+    assert_eq!(legend["@1"], "Globex Corporation");
+    assert_eq!(legend["@2"], "North America");
+    assert!(encoded.toon.contains("\n  0,@1,@2,@0\n"));
+    assert!(encoded.toon.ends_with("owner: @1"));
+
+    let request = DecodeRequest {
+        metadata: Some(metadata),
+        ..Default::default()
+    };
+    assert_eq!(decode_toon(&encoded.toon, &request).unwrap(), json);
+}
@@ -0,0 +1,28 @@
+//! Snapshot suite over the golden corpus in `tests/golden/`.
+//!
+//! A failure means encoder output changed. If the change is intended, run
+//! `toon-mcp regen-golden` (or `cargo insta review`) and commit the updated
+//! snapshots.
+
+use std::path::Path;
+
+use toon_mcp::golden;
+
+#[test]
+fn golden_corpus() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(golden::DEFAULT_DIR);
+    let cases = golden::cases(&dir).expect("failed to list golden cases");
+    assert!(!cases.is_empty(), "no payloads in {}", dir.display());
+
+    let mut settings = insta::Settings::clone_current();
+    settings.set_snapshot_path(&dir);
+    settings.set_prepend_module_to_snapshot(false);
+    settings.set_omit_expression(true);
+    settings.bind(|| {
+        for case in &cases {
+            let name = case.file_stem().unwrap().to_string_lossy().into_owned();
+            let toon = golden::encode_case(case).expect("encode failed");
+            insta::assert_snapshot!(name.as_str(), toon);
+        }
+    });
+}
@@ -12,6 +12,27 @@ use tower::util::ServiceExt;
 
 use toon_mcp::server::http::build_router;
 
+/// POST a JSON body to `uri` and return the status with the parsed JSON response.
+async fn post_json(uri: &str, body: serde_json::Value) -> (StatusCode, serde_json::Value) {
+    let response = build_router()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_string(&body).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null))
+}
+
 #[tokio::test]
 async fn test_health_endpoint() {
     let app = build_router();
@@ -90,6 +111,31 @@ async fn test_encode_endpoint_with_options() {
     assert_eq!(response.status(), StatusCode::OK);
 }
 
+#[tokio::test]
+async fn test_encode_endpoint_rename_metadata() {
+    let (status, json) = post_json(
+        "/api/v1/encode",
+        serde_json::json!({
+            "json": [{"customer_identifier": 1}, {"customer_identifier": 2}],
+            "rename": {"customer_identifier": "id"}
+        }),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert!(!json["toon"].as_str().unwrap().contains("customer_identifier"));
+    assert_eq!(json["metadata"]["rename"]["id"], "customer_identifier");
+
+    let (status, decoded) = post_json(
+        "/api/v1/decode",
+        serde_json::json!({"toon": json["toon"], "metadata": json["metadata"]}),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(decoded["json"][1]["customer_identifier"], 2);
+}
+
 #[tokio::test]
 async fn test_encode_endpoint_string_json() {
     let app = build_router();
@@ -417,3 +463,736 @@ async fn test_swagger_ui_accessible() {
             || response.status().is_redirection()
     );
 }
+
+#[tokio::test]
+async fn test_redoc_and_yaml_spec() {
+    let response = build_router()
+        .oneshot(Request::builder().uri("/docs").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = build_router()
+        .oneshot(
+            Request::builder()
+                .uri("/api-docs/openapi.yaml")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "application/yaml");
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let yaml = std::str::from_utf8(&body).unwrap();
+    assert!(yaml.starts_with("openapi:"));
+    assert!(yaml.contains("/api/v1/encode:"));
+}
+
+#[tokio::test]
+async fn test_aggregate_endpoint() {
+    let (status, json) = post_json(
+        "/api/v1/aggregate",
+        serde_json::json!({
+            "json": [{"level": "error"}, {"level": "warn"}, {"level": "error"}],
+            "group_by": ["level"]
+        }),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["rows"], 2);
+    assert!(json["toon"].as_str().unwrap().contains("error,2"));
+}
+
+#[tokio::test]
+async fn test_anonymize_endpoint_custom_pattern() {
+    let (status, json) = post_json(
+        "/api/v1/anonymize",
+        serde_json::json!({
+            "json": {"employee": "EMP-00412"},
+            "patterns": [{"name": "employee_id", "regex": "EMP-\\d{5}"}]
+        }),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert!(json["toon"].as_str().unwrap().contains("[EMPLOYEE_ID]"));
+    assert_eq!(json["report"]["total"], 1);
+}
+
+#[tokio::test]
+async fn test_decode_ndjson_output() {
+    let response = build_router()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/v1/decode")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({
+                        "toon": "rows[2]{id,name}:\n  1,a\n  2,b",
+                        "output_format": "ndjson"
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "application/x-ndjson");
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(
+        std::str::from_utf8(&body).unwrap(),
+        "{\"id\":1,\"name\":\"a\"}\n{\"id\":2,\"name\":\"b\"}\n"
+    );
+}
+
+#[tokio::test]
+async fn test_decode_stream_emits_rows_before_upload_ends() {
+    use tokio_stream::wrappers::ReceiverStream;
+    use tokio_stream::StreamExt;
+
+    let (upload, chunks) = tokio::sync::mpsc::channel::<Result<String, std::io::Error>>(4);
+    let response = build_router()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/v2/decode/stream?strict=true")
+                .header("content-type", "text/plain")
+                .body(Body::from_stream(ReceiverStream::new(chunks)))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "application/x-ndjson");
+    let mut rows = response.into_body().into_data_stream();
+
+    upload
+        .send(Ok("rows[2]{id,name}:\n  1,a\n  2,".to_string()))
+        .await
+        .unwrap();
+    let first = rows.next().await.unwrap().unwrap();
+    assert_eq!(&first[..], b"{\"id\":1,\"name\":\"a\"}\n");
+
+    upload.send(Ok("b\nextra: 1\n".to_string())).await.unwrap();
+    drop(upload);
+    let mut rest = Vec::new();
+    while let Some(chunk) = rows.next().await {
+        rest.extend_from_slice(&chunk.unwrap());
+    }
+    let rest = String::from_utf8(rest).unwrap();
+    let mut lines = rest.lines();
+    assert_eq!(lines.next(), Some(r#"{"id":2,"name":"b"}"#));
+    let error: serde_json::Value = serde_json::from_str(lines.next().unwrap()).unwrap();
+    assert!(error["error"].as_str().unwrap().contains("line 4"));
+}
+
+#[tokio::test]
+async fn test_daily_quota_and_usage_report() {
+    use std::sync::Arc;
+    use toon_mcp::core::UsageQuota;
+    use toon_mcp::server::http::{build_router_with_state, AppState};
+    use toon_mcp::server::usage::UsageTracker;
+
+    let app = build_router_with_state(AppState {
+        usage: Arc::new(UsageTracker::new(UsageQuota {
+            requests: Some(1),
+            ..UsageQuota::default()
+        })),
+        ..AppState::default()
+    });
+    let encode = || {
+        Request::builder()
+            .method("POST")
+            .uri("/api/v1/encode")
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"json": {"a": 1}}"#))
+            .unwrap()
+    };
+
+    let response = app.clone().oneshot(encode()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/v1/admin/usage")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["keys"][0]["key"], "anonymous");
+    assert_eq!(json["keys"][0]["requests"], 1);
+    assert_eq!(json["quota"]["requests"], 1);
+
+    let response = app.oneshot(encode()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn test_payload_logging_rejects_oversized_bodies() {
+    use std::sync::Arc;
+    use toon_mcp::server::http::{build_router_with_state, AppState};
+    use toon_mcp::server::payload_log::{PayloadLogConfig, PayloadLogger};
+
+    let app = build_router_with_state(AppState {
+        payload_log: Some(Arc::new(PayloadLogger::new(PayloadLogConfig {
+            sample_percent: 100,
+            max_chars: 16,
+        }))),
+        ..AppState::default()
+    });
+    let toon = "a".repeat(toon_mcp::core::MAX_BODY_BYTES);
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/v1/decode")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::json!({ "toon": toon }).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[tokio::test]
+async fn test_chunked_upload_counts_against_quota() {
+    use std::sync::Arc;
+    use tokio_stream::StreamExt;
+    use toon_mcp::core::UsageQuota;
+    use toon_mcp::server::http::{build_router_with_state, AppState};
+    use toon_mcp::server::usage::UsageTracker;
+
+    let app = build_router_with_state(AppState {
+        usage: Arc::new(UsageTracker::new(UsageQuota {
+            bytes: Some(16),
+            ..UsageQuota::default()
+        })),
+        ..AppState::default()
+    });
+    // No Content-Length: the body is sent in chunks.
+    let upload = || {
+        let chunks = ["rows[2]{id,name}:\n", "  1,a\n", "  2,b\n"].map(Ok::<_, std::io::Error>);
+        Request::builder()
+            .method("POST")
+            .uri("/api/v1/decode/stream")
+            .header("content-type", "text/plain")
+            .body(Body::from_stream(tokio_stream::iter(chunks)))
+            .unwrap()
+    };
+
+    let response = app.clone().oneshot(upload()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let mut rows = response.into_body().into_data_stream();
+    while let Some(chunk) = rows.next().await {
+        chunk.unwrap();
+    }
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/v1/admin/usage")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["keys"][0]["bytes"], 30);
+    assert_eq!(json["keys"][0]["tokens"], 7);
+
+    let response = app.oneshot(upload()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn test_latency_report() {
+    let app = build_router();
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/v1/encode")
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"json": {"a": 1}}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/v1/admin/latency")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["window_minutes"], 5);
+    assert!(json.get("slo").is_none());
+    assert_eq!(json["routes"][0]["route"], "POST /api/v1/encode");
+    assert_eq!(json["routes"][0]["requests"], 1);
+    assert_eq!(json["routes"][0]["breached"], false);
+}
+
+#[tokio::test]
+async fn test_version_endpoint() {
+    let response = build_router()
+        .oneshot(Request::builder().uri("/version").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(json["version"], env!("CARGO_PKG_VERSION"));
+    assert!(json["git_sha"].is_string());
+    assert!(json["features"]
+        .as_array()
+        .unwrap()
+        .contains(&serde_json::json!("http")));
+    assert!(!json["spec_versions"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_v2_envelope() {
+    let (status, json) = post_json("/api/v2/encode", serde_json::json!({"json": {"a": 1}})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["data"]["toon"], "a: 1");
+    assert_eq!(json["meta"]["api_version"], "v2");
+    assert!(json["error"].is_null());
+
+    let (status, json) = post_json(
+        "/api/v2/encode",
+        serde_json::json!({"json": "not valid json {"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(json["data"].is_null());
+    assert!(json["error"]["error"].is_string());
+}
+
+#[tokio::test]
+async fn test_v1_deprecation_headers() {
+    let response = build_router()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/v1/validate")
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"toon": "a: 1"}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["deprecation"], "true");
+    assert_eq!(
+        response.headers()["link"],
+        "</api/v2/validate>; rel=\"successor-version\""
+    );
+}
+
+#[tokio::test]
+async fn test_decode_pagination() {
+    let (status, json) = post_json(
+        "/api/v1/decode",
+        serde_json::json!({
+            "toon": "rows[3]{id}:\n  1\n  2\n  3",
+            "page_size": 2
+        }),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["json"]["rows"].as_array().unwrap().len(), 2);
+    assert_eq!(json["page"]["total_rows"], 3);
+    assert_eq!(json["page"]["next_cursor"], "2");
+}
+
+#[tokio::test]
+async fn test_json_rpc_batch() {
+    let (status, json) = post_json(
+        "/rpc",
+        serde_json::json!([
+            {"jsonrpc": "2.0", "id": 1, "method": "toon.encode", "params": {"json": {"a": 1}}},
+            {"jsonrpc": "2.0", "id": 2, "method": "toon.decode", "params": {"toon": "a: 1"}},
+            {"jsonrpc": "2.0", "method": "toon.validate", "params": {"toon": "a: 1"}},
+            {"jsonrpc": "2.0", "id": 3, "method": "toon.missing"}
+        ]),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK);
+    let replies = json.as_array().unwrap();
+    assert_eq!(replies.len(), 3);
+    assert_eq!(replies[0]["result"]["toon"], "a: 1");
+    assert_eq!(replies[1]["result"]["json"]["a"], 1);
+    assert_eq!(replies[2]["error"]["code"], -32601);
+}
+
+#[tokio::test]
+async fn test_capabilities_conditional_get() {
+    let app = build_router();
+    let get = |etag: Option<&str>| {
+        let mut request = Request::builder().uri("/api/v1/capabilities");
+        if let Some(etag) = etag {
+            request = request.header("if-none-match", etag);
+        }
+        request.body(Body::empty()).unwrap()
+    };
+
+    let response = app.clone().oneshot(get(None)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let headers = response.headers();
+    assert_eq!(headers["cache-control"], "public, max-age=300");
+    assert!(headers["vary"].to_str().unwrap().contains("Accept-Encoding"));
+    let etag = headers["etag"].to_str().unwrap().to_string();
+
+    let response = app.clone().oneshot(get(Some(&etag))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(response.headers()["etag"], etag.as_str());
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert!(body.is_empty());
+
+    let response = app.oneshot(get(Some("\"stale\""))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_format_range_endpoint() {
+    let (status, json) = post_json(
+        "/api/v1/format/range",
+        serde_json::json!({
+            "toon": "a[1]{x}:\n  1\nb[1]{y}:\n  2\n",
+            "start_line": 3,
+            "end_line": 4,
+            "indent": 4
+        }),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        json["edits"],
+        serde_json::json!([{"start_line": 4, "end_line": 5, "new_text": "    2\n"}])
+    );
+
+    let (status, _) = post_json(
+        "/api/v1/format/range",
+        serde_json::json!({"toon": "a: 1", "start_line": 0, "end_line": 1}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_assert_savings() {
+    let rows: Vec<_> = (0..20)
+        .map(|i| serde_json::json!({"id": i, "name": format!("user{}", i), "active": true}))
+        .collect();
+    let (status, json) = post_json(
+        "/api/v1/assert_savings",
+        serde_json::json!({"json": rows, "min_savings_percent": 20.0}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["passed"], true);
+    assert_eq!(json["metric"], "tokens");
+    assert_eq!(
+        json["actual_percent"],
+        json["stats"]["savings"]["tokens_percent"]
+    );
+
+    // A flat object has little to gain from tabular encoding.
+    let (status, json) = post_json(
+        "/api/v1/assert_savings",
+        serde_json::json!({"json": {"a": 1}, "min_savings_percent": 90.0, "metric": "bytes"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["passed"], false);
+
+    let (status, _) = post_json(
+        "/api/v1/assert_savings",
+        serde_json::json!({"json": {"a": 1}, "min_savings_percent": 10.0, "metric": "chars"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_chaos_mode_injects_faults() {
+    use std::sync::Arc;
+    use std::time::Duration;
+    use toon_mcp::server::chaos::{Chaos, ChaosConfig, CHAOS_HEADER};
+    use toon_mcp::server::http::{build_router_with_state, AppState};
+
+    let app = |config: ChaosConfig| {
+        build_router_with_state(AppState {
+            chaos: Some(Arc::new(Chaos::new(config))),
+            ..AppState::default()
+        })
+    };
+    let encode = || {
+        Request::builder()
+            .method("POST")
+            .uri("/api/v1/encode")
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"json": {"name": "Alice"}}"#))
+            .unwrap()
+    };
+    let none = ChaosConfig {
+        latency_percent: 0,
+        latency: Duration::ZERO,
+        error_percent: 0,
+        truncate_percent: 0,
+    };
+
+    let failing = app(ChaosConfig {
+        error_percent: 100,
+        ..none.clone()
+    });
+    let response = failing.clone().oneshot(encode()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(response.headers()[CHAOS_HEADER], "error");
+
+    // Health checks are not API routes and stay reliable.
+    let response = failing
+        .oneshot(Request::builder().uri("/health").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let truncating = app(ChaosConfig {
+        truncate_percent: 100,
+        ..none.clone()
+    });
+    let response = truncating.oneshot(encode()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[CHAOS_HEADER], "truncate");
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert!(serde_json::from_slice::<serde_json::Value>(&body).is_err());
+
+    let response = app(none).oneshot(encode()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get(CHAOS_HEADER).is_none());
+}
+
+#[tokio::test]
+async fn test_mock_mode_serves_fixtures() {
+    use toon_mcp::server::mock::{build_mock_router, request_key, Fixtures, MOCK_KEY_HEADER};
+
+    let dir = std::env::temp_dir().join(format!("toon-mock-http-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let fixture = serde_json::json!({
+        "method": "POST",
+        "path": "/api/v2/encode",
+        "request": {"json": {"name": "Alice"}},
+        "response": {"data": {"toon": "name: Alice"}, "meta": {"api_version": "v2"}, "error": null}
+    });
+    std::fs::write(dir.join("encode.json"), fixture.to_string()).unwrap();
+    let app = build_mock_router(Fixtures::load(&dir).unwrap());
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let encode = |body: &str| {
+        Request::builder()
+            .method("POST")
+            .uri("/api/v2/encode")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+
+    // Key order and whitespace do not affect matching.
+    let response = app
+        .clone()
+        .oneshot(encode(r#"{ "json": { "name": "Alice" } }"#))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["data"]["toon"], "name: Alice");
+
+    let body = r#"{"json": {"name": "Bob"}}"#;
+    let response = app.oneshot(encode(body)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        response.headers()[MOCK_KEY_HEADER],
+        request_key("POST", "/api/v2/encode", body).as_str()
+    );
+}
+
+#[tokio::test]
+async fn test_examples_endpoint_runs_every_example() {
+    let response = build_router()
+        .oneshot(
+            Request::builder()
+                .uri("/api/v1/examples")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    let examples = json["examples"].as_array().unwrap();
+    assert_eq!(examples.len(), toon_mcp::server::examples::requests().len());
+    for example in examples {
+        assert_eq!(
+            example["status"], 200,
+            "{} failed: {}",
+            example["path"], example["response"]
+        );
+    }
+    let encode = examples
+        .iter()
+        .find(|e| e["path"] == "/api/v1/encode")
+        .unwrap();
+    assert_eq!(
+        encode["response"]["toon"],
+        "users[2]{id,name}:\n  1,Alice\n  2,Bob"
+    );
+
+    // The same requests appear in the OpenAPI document.
+    let doc = serde_json::to_value(toon_mcp::server::http::api_doc()).unwrap();
+    let content = &doc["paths"]["/api/v1/encode"]["post"]["requestBody"]["content"];
+    assert_eq!(content["application/json"]["example"], encode["request"]);
+}
+
+#[test]
+fn test_every_route_has_an_example() {
+    // Routes without an example: the list itself, the plain-text stream,
+    // and the queries that need a database.
+    const NO_EXAMPLE: &[&str] = &[
+        "/api/v1/examples",
+        "/api/v1/decode/stream",
+        "/api/v1/sqlite/query",
+        "/api/v1/postgres/query",
+    ];
+    let matches = |template: &str, path: &str| {
+        let template: Vec<&str> = template.split('/').collect();
+        let path: Vec<&str> = path.split('/').collect();
+        template.len() == path.len()
+            && template
+                .iter()
+                .zip(&path)
+                .all(|(t, p)| t == p || t.starts_with('{'))
+    };
+
+    let examples = toon_mcp::server::examples::requests();
+    let doc = serde_json::to_value(toon_mcp::server::http::api_doc()).unwrap();
+    for (path, item) in doc["paths"].as_object().unwrap() {
+        if !path.starts_with("/api/v1/") || NO_EXAMPLE.contains(&path.as_str()) {
+            continue;
+        }
+        for method in item.as_object().unwrap().keys() {
+            let method = method.to_uppercase();
+            assert!(
+                examples
+                    .iter()
+                    .any(|e| e.method == method && matches(path, &e.path)),
+                "{} {} has no example",
+                method,
+                path
+            );
+        }
+    }
+    // /rpc is served outside the version prefix and the OpenAPI document.
+    assert!(examples.iter().any(|e| e.path == "/rpc"));
+}
+
+#[test]
+fn test_howto_routes_match_the_api() {
+    use toon_mcp::core::howto;
+
+    let routes: Vec<(String, String)> = howto::routes()
+        .map(|(path, method)| {
+            let path = howto::route_path("/api/v1", &format!("/{}", path));
+            (path, method.to_string())
+        })
+        .collect();
+    let doc = serde_json::to_value(toon_mcp::server::http::api_doc()).unwrap();
+    let mut documented = vec![];
+    for (path, item) in doc["paths"].as_object().unwrap() {
+        if path.starts_with("/api/v1/") {
+            for method in item.as_object().unwrap().keys() {
+                documented.push((path.clone(), method.to_uppercase()));
+            }
+        }
+    }
+
+    for route in &documented {
+        assert!(routes.contains(route), "{:?} is missing from howto", route);
+    }
+    // /rpc is the one route outside the OpenAPI document.
+    for route in &routes {
+        assert!(
+            route.0 == "/rpc" || documented.contains(route),
+            "howto route {:?} is not served",
+            route
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_demo_dataset() {
+    let app = build_router();
+    let get = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+
+    let response = app.clone().oneshot(get("/api/v1/demo/iris")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["dataset"]["name"], "iris");
+    assert!(json["toon"].as_str().unwrap().contains("sepal_length"));
+
+    let response = app.oneshot(get("/api/v1/demo/nope")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}